@@ -23,6 +23,7 @@ import {
   unregisterPyreDevtoolsClient,
 } from './devtools-registry';
 import type {
+  ClientOutdated,
   ElmApp,
   LiveSyncTransport,
   SchemaMetadata,
@@ -34,6 +35,7 @@ import type {
 } from './types';
 
 export type {
+  ClientOutdated,
   LiveSyncTransport,
   ServerConfig,
   ServerEndpoints,
@@ -109,7 +111,9 @@ export interface PyreDevtoolsTableSnapshot {
   sync?: TableSyncStatus;
   cursor?: {
     last_seen_updated_at: number | null;
+    last_seen_key?: Record<string, unknown> | null;
     permission_hash: string;
+    last_seen_tombstone?: number | null;
   };
 }
 
@@ -119,7 +123,9 @@ export interface DevtoolsTableSummary {
   sync?: TableSyncStatus;
   cursor?: {
     last_seen_updated_at: number | null;
+    last_seen_key?: Record<string, unknown> | null;
     permission_hash: string;
+    last_seen_tombstone?: number | null;
   };
 }
 
@@ -356,6 +362,7 @@ class SingleDatabasePyreClient {
   private syncStateCallbacks: Set<(state: SyncState) => void> = new Set();
   private syncProgressCallbacks: Set<(progress: SyncProgress) => void> = new Set();
   private connectionCallbacks: Set<(connectionId: string) => void> = new Set();
+  private clientOutdatedCallbacks: Set<(outdated: ClientOutdated) => void> = new Set();
   private lastSyncState: SyncState;
   private lastSyncProgress: SyncProgress | null = null;
   private pendingLiveState: SyncState | null = null;
@@ -451,6 +458,7 @@ class SingleDatabasePyreClient {
       baseUrl: config.server.baseUrl,
       eventsPath: this.endpoints.events,
       databaseId: config.databaseId,
+      manifestVersion: config.server.manifestVersion,
     }, undefined, this.logDebug);
     this.queryManager = new QueryManagerService(this.logDebug);
     this.queryClient = new QueryClientService(() => this.session, (payload) => {
@@ -563,6 +571,13 @@ class SingleDatabasePyreClient {
     };
   }
 
+  onClientOutdated(callback: (outdated: ClientOutdated) => void): () => void {
+    this.clientOutdatedCallbacks.add(callback);
+    return () => {
+      this.clientOutdatedCallbacks.delete(callback);
+    };
+  }
+
   async onEntityChanges(subscription: EntitySubscription, callback: (batch: EntityChangeBatch) => void): Promise<() => void> {
     validateEntitySubscription(subscription);
     const initialSequence = this.entityStream.reserveSequence();
@@ -747,7 +762,7 @@ class SingleDatabasePyreClient {
     if (message.type === 'delta' && this.shouldAcceptLiveDelta(message)) {
       this.noteAppliedServerRevision(message.serverRevision);
       this.entityStream.handleTableDelta(
-        message.data as ServerTableGroup[],
+        serverDeltaUpserts(message),
         this.lastSyncState.status === 'live' ? 'live' : 'catchup',
         this.databaseId
       );
@@ -863,7 +878,7 @@ class SingleDatabasePyreClient {
   }
 
   private shouldAcceptLiveDelta(message: LiveSyncMessage): boolean {
-    if (!Array.isArray(message.data)) {
+    if (message.data !== undefined && !Array.isArray(message.data)) {
       return false;
     }
 
@@ -1251,9 +1266,10 @@ class SingleDatabasePyreClient {
           this.noteAppliedServerRevision(extractServerRevision(mutationResultEnvelope(result)));
           const appResult = unwrapMutationResultEnvelope(result);
           this.emitDevtoolsEvent('mutation:result', { requestId, mutationId, result });
+          this.notifyClientOutdated(appResult);
           callback(appResult);
         },
-        await resolveServerHeaders(this.server),
+        await resolveMutationHeaders(this.server),
         getServerCredentials(this.server),
         this.server.withCredentials === true
       );
@@ -1295,6 +1311,7 @@ class SingleDatabasePyreClient {
             mutationName: message.mutationName,
             result: appResult,
           });
+          this.notifyClientOutdated(appResult);
           mutationResultPort?.send?.({
             type: 'mutation-result',
             requestId: message.requestId,
@@ -1303,13 +1320,25 @@ class SingleDatabasePyreClient {
             result: appResult as MutationResult,
           } satisfies ElmBridgeMutationResultMessage);
         },
-        await resolveServerHeaders(this.server),
+        await resolveMutationHeaders(this.server),
         getServerCredentials(this.server),
         this.server.withCredentials === true
       );
     })();
   }
 
+  private notifyClientOutdated(result: unknown): void {
+    const outdated = clientOutdatedFromResult(result);
+    if (!outdated) {
+      return;
+    }
+
+    this.emitDevtoolsEvent('client:outdated', outdated);
+    this.clientOutdatedCallbacks.forEach((callback) => {
+      callback(outdated);
+    });
+  }
+
   private emitDevtoolsEvent(type: string, payload?: unknown): void {
     if (this.devtoolsEventCallbacks.size === 0) {
       return;
@@ -1346,6 +1375,7 @@ export class PyreClient {
   private devtoolsEventCounter = 0;
   private devtoolsEvents: PyreDevtoolsEvent[] = [];
   private devtoolsEventCallbacks: Set<(event: PyreDevtoolsEvent) => void> = new Set();
+  private clientOutdatedCallbacks: Set<(outdated: ClientOutdated) => void> = new Set();
   private instanceId: string;
 
   private constructor(config: ResolvedPyreClientConfig) {
@@ -1478,6 +1508,13 @@ export class PyreClient {
     });
   }
 
+  onClientOutdated(callback: (outdated: ClientOutdated) => void): () => void {
+    this.clientOutdatedCallbacks.add(callback);
+    return () => {
+      this.clientOutdatedCallbacks.delete(callback);
+    };
+  }
+
   onDevtoolsEvent(callback: (event: PyreDevtoolsEvent) => void): () => void {
     this.devtoolsEventCallbacks.add(callback);
     return () => {
@@ -1896,6 +1933,12 @@ export class PyreClient {
           elapsedMs,
         });
       }
+      const outdated = clientOutdatedFromResult(result);
+      if (outdated) {
+        this.clientOutdatedCallbacks.forEach((outdatedCallback) => {
+          outdatedCallback(outdated);
+        });
+      }
       callback(result);
     });
   }
@@ -2146,6 +2189,23 @@ async function resolveServerHeaders(server: ServerConfig): Promise<Record<string
   return server.headers;
 }
 
+async function resolveMutationHeaders(server: ServerConfig): Promise<Record<string, string>> {
+  const headers = await resolveServerHeaders(server);
+  if (!server.manifestVersion) {
+    return headers;
+  }
+
+  return { ...headers, 'x-pyre-manifest': server.manifestVersion };
+}
+
+function clientOutdatedFromResult(result: unknown): ClientOutdated | null {
+  if (!isRecord(result) || result.ok !== false || result.code !== 'clientOutdated' || typeof result.manifestVersion !== 'string') {
+    return null;
+  }
+
+  return { manifestVersion: result.manifestVersion };
+}
+
 function serverHeadersToPairs(headers: Record<string, string>): Array<[string, string]> {
   return Object.entries(headers);
 }
@@ -2231,7 +2291,11 @@ function extractServerRevision(result: unknown): number | undefined {
 }
 
 function extractMutationSyncDelta(result: unknown): { databaseId?: string; data: ServerTableGroup[] } | null {
-  if (!isRecord(result) || !isRecord(result.sync) || result.sync.type !== 'delta' || !Array.isArray(result.sync.data)) {
+  if (!isRecord(result) || !isRecord(result.sync) || result.sync.type !== 'delta') {
+    return null;
+  }
+
+  if (result.sync.data !== undefined && !Array.isArray(result.sync.data)) {
     return null;
   }
 
@@ -2241,10 +2305,18 @@ function extractMutationSyncDelta(result: unknown): { databaseId?: string; data:
 
   return {
     databaseId,
-    data: result.sync.data.filter(isServerTableGroup),
+    data: serverDeltaUpserts(result.sync),
   };
 }
 
+// Conflicts are rows an `@ifVersion` update skipped, as they are now, so they
+// reach entity streams like any other row the server sent.
+function serverDeltaUpserts(message: { data?: unknown; conflicts?: unknown }): ServerTableGroup[] {
+  const data = Array.isArray(message.data) ? message.data : [];
+  const conflicts = Array.isArray(message.conflicts) ? message.conflicts : [];
+  return [...data, ...conflicts].filter(isServerTableGroup);
+}
+
 function isServerTableGroup(value: unknown): value is ServerTableGroup {
   return isRecord(value)
     && typeof value.table_name === 'string'
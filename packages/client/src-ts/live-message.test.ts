@@ -181,6 +181,41 @@ test('Elm live sync accepts matching delta databaseId', async () => {
   }
 });
 
+test('Elm live sync deletes rows the server removed', async () => {
+  const errors: string[] = [];
+  const deletes: unknown[] = [];
+  const { app, restore } = await startSyncedElmApp();
+
+  try {
+    app.ports.errorOut.subscribe((message) => {
+      errors.push(message);
+    });
+    app.ports.indexedDbOut.subscribe((message) => {
+      if (message?.type === 'deleteRows') {
+        deletes.push(message);
+      }
+    });
+
+    app.ports.receiveSSEMessage.send(deltaMessage('campaign:123'));
+    app.ports.receiveSSEMessage.send({
+      type: 'delta',
+      databaseId: 'campaign:123',
+      removed: [{ table_name: 'maps', keys: [{ id: 1 }] }],
+    });
+    await Bun.sleep(0);
+
+    expect(errors).toHaveLength(0);
+    expect(deletes).toEqual([
+      {
+        type: 'deleteRows',
+        removed: [{ table_name: 'maps', keys: [{ id: 1 }] }],
+      },
+    ]);
+  } finally {
+    restore();
+  }
+});
+
 test('Elm live syncRequired starts catchup from the current cursor', async () => {
   const { app, requests, restore } = await startSyncedElmApp();
 
@@ -501,3 +536,125 @@ test('Elm mutation response sync preserves newer rapid optimistic state', async
     globalThis.XMLHttpRequest = previousXmlHttpRequest;
   }
 });
+
+test('Elm mutation response conflicts replace the optimistic update', async () => {
+  const previousXmlHttpRequest = globalThis.XMLHttpRequest;
+  const pendingMutations: Array<(response: unknown) => void> = [];
+
+  class MockXMLHttpRequest {
+    listeners: Record<string, Array<() => void>> = {};
+    status = 200;
+    statusText = 'OK';
+    responseURL = '';
+    responseType = '';
+    response = '';
+    timeout = 0;
+    withCredentials = false;
+
+    addEventListener(type: string, callback: () => void) {
+      this.listeners[type] = this.listeners[type] ?? [];
+      this.listeners[type].push(callback);
+    }
+
+    open(_method: string, url: string) {
+      this.responseURL = url;
+    }
+
+    setRequestHeader() {}
+
+    send() {
+      if (this.responseURL.endsWith('/sync')) {
+        this.response = JSON.stringify({ databaseId: 'campaign:123', serverRevision: 0, tables: {}, has_more: false });
+        queueMicrotask(() => (this.listeners.load ?? []).forEach((listener) => listener()));
+        return;
+      }
+
+      pendingMutations.push((response: unknown) => {
+        this.response = JSON.stringify(response);
+        (this.listeners.load ?? []).forEach((listener) => listener());
+      });
+    }
+
+    abort() {}
+
+    getAllResponseHeaders() {
+      return '';
+    }
+  }
+
+  globalThis.XMLHttpRequest = MockXMLHttpRequest;
+
+  const Elm = loadElm(Object.create(globalThis));
+  const app = Elm.Main.init({
+    flags: {
+      schema,
+      server: {
+        baseUrl: 'http://example.test',
+        catchupPath: '/sync',
+        databaseId: 'campaign:123',
+      },
+      liveSync: { transport: 'sse' },
+    },
+  });
+  const queryResults: unknown[] = [];
+
+  try {
+    app.ports.queryClientOut.subscribe((message) => {
+      if (message?.type === 'full') {
+        queryResults.push(message.result);
+      }
+    });
+
+    app.ports.receiveIndexedDbMessage.send({
+      type: 'initialData',
+      data: {
+        tables: { maps: [{ id: 1, name: 'Initial', updatedAt: 0 }] },
+        cursor: { tables: {} },
+        lastAppliedServerRevision: null,
+      },
+    });
+    await Bun.sleep(0);
+    await Bun.sleep(0);
+
+    app.ports.receiveQueryManagerMessage.send({
+      type: 'sendMutation',
+      requestId: 'a',
+      mutationId: 'rename',
+      baseUrl: 'http://example.test/db',
+      input: { id: 1, name: 'Mine' },
+      optimistic: {
+        queryField: 'maps',
+        where: { field: 'id', input: 'id' },
+        set: [{ field: 'name', input: 'name' }],
+      },
+    });
+    await Bun.sleep(0);
+
+    expect(pendingMutations).toHaveLength(1);
+
+    pendingMutations[0]({
+      serverRevision: 1,
+      sync: {
+        type: 'delta',
+        serverRevision: 1,
+        databaseId: 'campaign:123',
+        conflicts: [{ table_name: 'maps', headers: ['id', 'name', 'updatedAt'], rows: [[1, 'Theirs', 1]] }],
+      },
+      result: {},
+    });
+    await Bun.sleep(0);
+
+    app.ports.receiveQueryClientMessage.send({
+      type: 'register',
+      queryId: 'maps-query',
+      querySource: { maps: { id: true, name: true } },
+      queryInput: {},
+    });
+    await Bun.sleep(0);
+
+    const latest = queryResults.at(-1) as { maps?: Array<{ name?: string }> };
+    expect(latest.maps?.[0]?.name).toBe('Theirs');
+  } finally {
+    globalThis.XMLHttpRequest = previousXmlHttpRequest;
+  }
+});
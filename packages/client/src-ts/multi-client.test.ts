@@ -792,3 +792,28 @@ test('devtools mutation events include database metadata and retain newest event
   });
   expect(snapshot?.events.some((event) => event.type === 'mutation.started' && (event.payload as any).mutationId === 'Mutation0')).toBe(false);
 });
+
+test('PyreClient reports mutations rejected with clientOutdated', async () => {
+  __resetPyreDevtoolsRegistryForTests();
+  const client = await PyreClient.create({
+    schema,
+    server: { ...server, manifestVersion: 'old-hash' },
+    cacheNamespace: 'user_42',
+    createInternalClient: async (config) => ({
+      ...fakeInternalClient([], config.databaseId),
+      run(_databaseId: string, _queryModule: any, _input: unknown, callback: (result: unknown) => void) {
+        callback({ ok: false, error: 'Bad Status: 409', code: 'clientOutdated', manifestVersion: 'new-hash' });
+      },
+    }),
+  });
+  const outdated: unknown[] = [];
+  client.onClientOutdated((event) => outdated.push(event));
+
+  let result: unknown;
+  await client.run('main', { operation: 'mutation', id: 'CreateNote' }, {}, (value) => {
+    result = value;
+  });
+
+  expect(outdated).toEqual([{ manifestVersion: 'new-hash' }]);
+  expect(result).toMatchObject({ ok: false, code: 'clientOutdated', manifestVersion: 'new-hash' });
+});
@@ -116,3 +116,45 @@ test('IndexedDbService forwards catchup entity deltas after cache writes', async
   expect(entityDeltas).toEqual([{ tableGroups, source: 'catchup' }]);
   expect(operations).toEqual(['write', 'write', 'notify']);
 });
+
+test('IndexedDbService deletes removed rows by id', async () => {
+  let handleIndexedDbOut: ((message: unknown) => void | Promise<void>) | null = null;
+  const deletedRows: unknown[] = [];
+
+  const storage = {
+    init: async () => undefined,
+    deleteRows: async (tableName: string, ids: unknown[]) => {
+      deletedRows.push({ tableName, ids });
+      return ids.length;
+    },
+  };
+
+  const service = new IndexedDbService(storage as never);
+  service.attachPorts({
+    ports: {
+      indexedDbOut: {
+        subscribe: (callback) => {
+          handleIndexedDbOut = callback;
+        },
+      },
+    },
+  });
+
+  if (!handleIndexedDbOut) {
+    throw new Error('indexedDbOut handler was not attached');
+  }
+
+  handleIndexedDbOut({
+    type: 'deleteRows',
+    removed: [
+      { table_name: 'maps', keys: [{ id: 1 }, { id: 3 }] },
+      { table_name: 'memberships', keys: [{ orgId: 1, userId: 2 }] },
+    ],
+  });
+  await Bun.sleep(0);
+
+  expect(deletedRows).toEqual([
+    { tableName: 'maps', ids: [1, 3] },
+    { tableName: 'memberships', ids: [] },
+  ]);
+});
@@ -7,9 +7,19 @@ export interface TableGroup {
   rows: unknown[][];
 }
 
+export interface RemovedRows {
+  table_name: string;
+  /** One object per row, keyed by primary key column. */
+  keys: Array<Record<string, unknown>>;
+}
+
 export interface SyncCursorEntry {
   last_seen_updated_at: number | null;
+  /** Primary key of the last row synced at `last_seen_updated_at`, keyed by column. */
+  last_seen_key?: Record<string, unknown> | null;
   permission_hash: string;
+  /** The newest server tombstone whose delete has been applied. */
+  last_seen_tombstone?: number | null;
 }
 
 export interface SyncCursor {
@@ -357,6 +367,30 @@ export class IndexedDBStorage {
     });
   }
 
+  async deleteRows(tableName: string, ids: IDBValidKey[]): Promise<number> {
+    if (ids.length === 0) {
+      return 0;
+    }
+
+    const db = await this.getDB();
+    return new Promise((resolve, reject) => {
+      const tx = db.transaction(['tables'], 'readwrite');
+      const store = tx.objectStore('tables');
+
+      tx.oncomplete = () => {
+        resolve(ids.length);
+      };
+
+      tx.onerror = () => {
+        reject(new Error(`Transaction failed: ${tx.error}`));
+      };
+
+      ids.forEach((id) => {
+        store.delete([tableName, id]);
+      });
+    });
+  }
+
   async deleteDatabase(): Promise<void> {
     if (this.db) {
       this.db.close();
@@ -402,7 +436,7 @@ export class IndexedDbService {
     }
   }
 
-  private async handleMessage(message: { type?: string; tableGroups?: TableGroup[]; cursor?: SyncCursor; serverRevision?: number; entityStreamSource?: string }): Promise<void> {
+  private async handleMessage(message: { type?: string; tableGroups?: TableGroup[]; removed?: RemovedRows[]; cursor?: SyncCursor; serverRevision?: number; entityStreamSource?: string }): Promise<void> {
     if (message.type === 'requestInitialData') {
       await this.sendInitialData();
       return;
@@ -413,6 +447,11 @@ export class IndexedDbService {
       return;
     }
 
+    if (message.type === 'deleteRows') {
+      await this.deleteRows(message.removed || []);
+      return;
+    }
+
     if (message.type === 'writeSyncCursor' && message.cursor) {
       await this.writeSyncCursor(message.cursor);
       return;
@@ -493,6 +532,28 @@ export class IndexedDbService {
     }
   }
 
+  private async deleteRows(removed: RemovedRows[]): Promise<void> {
+    try {
+      await this.storage.init();
+
+      for (const group of removed) {
+        // Rows are stored by their `id` column, so keys without one were never stored.
+        const ids = group.keys
+          .map((key) => key.id)
+          .filter((id): id is IDBValidKey => id !== null && id !== undefined);
+
+        try {
+          const deleted = await this.storage.deleteRows(group.table_name, ids);
+          this.debugLog('[PyreClient] IndexedDB rows deleted', { tableName: group.table_name, deleted });
+        } catch (error) {
+          console.error('[PyreClient] Failed to delete rows:', group.table_name, error);
+        }
+      }
+    } catch (error) {
+      console.error('[PyreClient] Failed to delete rows:', error);
+    }
+  }
+
   private async writeSyncCursor(cursor: SyncCursor): Promise<void> {
     try {
       await this.storage.init();
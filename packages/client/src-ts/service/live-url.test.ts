@@ -19,3 +19,12 @@ test('buildWebSocketUrl includes databaseId and switches protocol', () => {
     databaseId: 'campaign:123',
   })).toBe('wss://api.example.test/pyre/sync/events?databaseId=campaign%3A123');
 });
+
+test('buildWebSocketUrl includes the manifest version when configured', () => {
+  expect(buildWebSocketUrl({
+    baseUrl: 'https://api.example.test/pyre',
+    eventsPath: '/sync/events',
+    databaseId: 'campaign:123',
+    manifestVersion: 'abc123',
+  })).toBe('wss://api.example.test/pyre/sync/events?databaseId=campaign%3A123&manifestVersion=abc123');
+});
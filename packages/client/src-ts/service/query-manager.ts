@@ -12,6 +12,9 @@ export interface MutationResult {
   ok: boolean;
   value?: unknown;
   error?: string;
+  /** Set to `clientOutdated` when the server rejected this client's manifest version. */
+  code?: string;
+  manifestVersion?: string;
 }
 
 type QueryResultCallback = (result: unknown) => void;
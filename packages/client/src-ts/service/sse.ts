@@ -15,6 +15,8 @@ export interface LiveSyncMessage {
   connectionId?: string;
   serverRevision?: number;
   data?: unknown;
+  removed?: unknown;
+  conflicts?: unknown;
   error?: string;
 }
 
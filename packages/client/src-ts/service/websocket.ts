@@ -5,6 +5,7 @@ export interface WebSocketConfig {
   baseUrl: string;
   eventsPath: string;
   databaseId?: DatabaseId;
+  manifestVersion?: string;
   reconnectDelayMs?: number;
 }
 
@@ -112,11 +113,7 @@ export class WebSocketManager {
   }
 
   private buildWebSocketUrl(): string {
-    const url = new URL(resolveEndpointUrl(this.config.baseUrl, this.config.eventsPath, {
-      databaseId: this.config.databaseId,
-    }));
-    url.protocol = url.protocol === 'https:' ? 'wss:' : 'ws:';
-    return url.toString();
+    return buildWebSocketUrl(this.config);
   }
 
   disconnect(): void {
@@ -135,6 +132,7 @@ export class WebSocketManager {
 export function buildWebSocketUrl(config: WebSocketConfig): string {
   const url = new URL(resolveEndpointUrl(config.baseUrl, config.eventsPath, {
     databaseId: config.databaseId,
+    manifestVersion: config.manifestVersion,
   }));
   url.protocol = url.protocol === 'https:' ? 'wss:' : 'ws:';
   return url.toString();
@@ -28,6 +28,8 @@ export interface ServerConfig {
   credentials?: RequestCredentials;
   withCredentials?: boolean;
   liveSyncTransport?: LiveSyncTransport;
+  /** The `hash` from the manifest this client was built with, sent so the server can detect stale clients. */
+  manifestVersion?: string;
 }
 
 export interface SyncProgress {
@@ -38,6 +40,12 @@ export interface SyncProgress {
   error?: string;
 }
 
+/** Reported when the server no longer accepts this client's manifest version and the app should reload. */
+export interface ClientOutdated {
+  /** The manifest version the server is running now. */
+  manifestVersion: string;
+}
+
 export type SyncStatus = 'not_started' | 'catching_up' | 'live';
 
 export type TableSyncStatus = 'waiting' | 'catching_up' | 'live';
@@ -31,7 +31,9 @@ type Status
 
 type alias SyncCursorEntry =
     { lastSeenUpdatedAt : Maybe Float
+    , lastSeenKey : Maybe Decode.Value
     , permissionHash : String
+    , lastSeenTombstone : Maybe Int
     }
 
 
@@ -43,6 +45,10 @@ type alias CatchupTableResult =
     { rows : List (Dict String Data.Value.Value)
     , permissionHash : String
     , lastSeenUpdatedAt : Maybe Float
+    , lastSeenKey : Maybe Decode.Value
+    , deleted : List (Dict String Data.Value.Value)
+    , lastSeenTombstone : Maybe Int
+    , reset : Bool
     }
 
 
@@ -76,6 +82,7 @@ type alias UpdateResult =
     , cmd : Cmd Msg
     , dbCmds : List (Cmd Db.Msg)
     , delta : Maybe Data.Delta.Delta
+    , removed : List Data.Delta.RemovedRows
     , serverRevision : Maybe Int
     , touchedTables : List String
     , error : Maybe String
@@ -122,6 +129,7 @@ update msg model db =
             , cmd = cmd
             , dbCmds = [ Data.IndexedDb.writeSyncCursor updatedCursor ]
             , delta = Nothing
+            , removed = []
             , serverRevision = Nothing
             , touchedTables = []
             , error = Nothing
@@ -137,6 +145,7 @@ update msg model db =
             , cmd = cmd
             , dbCmds = []
             , delta = Nothing
+            , removed = []
             , serverRevision = Nothing
             , touchedTables = []
             , error = Nothing
@@ -178,6 +187,7 @@ handleCatchupResponse result model db =
                     , cmd = Cmd.none
                     , dbCmds = []
                     , delta = Nothing
+                    , removed = []
                     , serverRevision = response.serverRevision
                     , touchedTables = []
                     , error = Just message
@@ -185,7 +195,7 @@ handleCatchupResponse result model db =
 
                 Nothing ->
                     let
-                        ( maybeDelta, updatedDb, dbCmds ) =
+                        applied =
                             applyCatchupDelta response db
 
                         updatedCursor =
@@ -225,10 +235,11 @@ handleCatchupResponse result model db =
                                 ( { baseModel | inProgress = False }, Cmd.none )
                     in
                     { model = nextModel
-                    , db = updatedDb
+                    , db = applied.db
                     , cmd = cmd
-                    , dbCmds = Data.IndexedDb.writeSyncCursor updatedCursor :: dbCmds
-                    , delta = maybeDelta
+                    , dbCmds = Data.IndexedDb.writeSyncCursor updatedCursor :: applied.dbCmds
+                    , delta = applied.delta
+                    , removed = applied.removed
                     , serverRevision = response.serverRevision
                     , touchedTables = Dict.keys response.tables
                     , error = Nothing
@@ -244,6 +255,7 @@ handleCatchupResponse result model db =
             , cmd = Cmd.none
             , dbCmds = []
             , delta = Nothing
+            , removed = []
             , serverRevision = Nothing
             , touchedTables = []
             , error = Just message
@@ -306,9 +318,26 @@ httpHeaders headers =
     List.map (\( key, value ) -> Http.header key value) headers
 
 
-applyCatchupDelta : CatchupResponse -> Db.Db -> ( Maybe Data.Delta.Delta, Db.Db, List (Cmd Db.Msg) )
+applyCatchupDelta : CatchupResponse -> Db.Db -> { delta : Maybe Data.Delta.Delta, removed : List Data.Delta.RemovedRows, db : Db.Db, dbCmds : List (Cmd Db.Msg) }
 applyCatchupDelta response db =
     let
+        -- Deletes are applied before rows, so a key deleted and then reused keeps its new row.
+        removed =
+            response.tables
+                |> Dict.toList
+                |> List.filterMap
+                    (\( tableName, tableResult ) ->
+                        catchupTableRemovals db tableName tableResult
+                    )
+
+        ( dbWithoutRemoved, removeCmds ) =
+            if List.isEmpty removed then
+                ( db, [] )
+
+            else
+                Db.update (Db.RowsRemoved removed) db
+                    |> Tuple.mapSecond List.singleton
+
         tableGroups =
             response.tables
                 |> Dict.toList
@@ -318,10 +347,10 @@ applyCatchupDelta response db =
                     )
 
         dbWithKnownTables =
-            ensureTablesExist (Dict.keys response.tables) db
+            ensureTablesExist (Dict.keys response.tables) dbWithoutRemoved
     in
     if List.isEmpty tableGroups then
-        ( Nothing, dbWithKnownTables, [] )
+        { delta = Nothing, removed = removed, db = dbWithKnownTables, dbCmds = removeCmds }
 
     else
         let
@@ -331,7 +360,36 @@ applyCatchupDelta response db =
             ( updatedDb, _ ) =
                 Db.update (Db.LocalDeltaReceived delta) dbWithKnownTables
         in
-        ( Just delta, updatedDb, [ Data.IndexedDb.writeDeltaWithEntityNotification "catchup" delta.tableGroups ] )
+        { delta = Just delta
+        , removed = removed
+        , db = updatedDb
+        , dbCmds = removeCmds ++ [ Data.IndexedDb.writeDeltaWithEntityNotification "catchup" delta.tableGroups ]
+        }
+
+
+{-| Keys to drop before a table's rows are applied. A reset table drops every row
+it has, since deletes made while its cursor was stale can't be known.
+-}
+catchupTableRemovals : Db.Db -> String -> CatchupTableResult -> Maybe Data.Delta.RemovedRows
+catchupTableRemovals db tableName tableResult =
+    let
+        resetKeys =
+            if tableResult.reset then
+                Dict.get tableName db.tables
+                    |> Maybe.map (Dict.keys >> List.map (\rowId -> Dict.singleton "id" (Data.Value.IntValue rowId)))
+                    |> Maybe.withDefault []
+
+            else
+                []
+
+        keys =
+            resetKeys ++ tableResult.deleted
+    in
+    if List.isEmpty keys then
+        Nothing
+
+    else
+        Just { tableName = tableName, keys = keys }
 
 
 ensureTablesExist : List String -> Db.Db -> Db.Db
@@ -389,7 +447,9 @@ updateSyncCursor response cursor =
         (\tableName tableResult acc ->
             Dict.insert tableName
                 { lastSeenUpdatedAt = tableResult.lastSeenUpdatedAt
+                , lastSeenKey = tableResult.lastSeenKey
                 , permissionHash = tableResult.permissionHash
+                , lastSeenTombstone = tableResult.lastSeenTombstone
                 }
                 acc
         )
@@ -423,22 +483,38 @@ computeSyncCursor db cursor =
                 maxUpdatedAt =
                     computeMaxUpdatedAt tableData
 
-                existingPermission =
+                existingEntry =
                     Dict.get tableName cursor
+
+                existingPermission =
+                    existingEntry
                         |> Maybe.map .permissionHash
                         |> Maybe.withDefault ""
 
+                existingUpdatedAt =
+                    existingEntry
+                        |> Maybe.andThen .lastSeenUpdatedAt
+
+                lastSeenUpdatedAt =
+                    case maxUpdatedAt of
+                        Just _ ->
+                            maxUpdatedAt
+
+                        Nothing ->
+                            existingUpdatedAt
+
                 updatedEntry =
-                    { lastSeenUpdatedAt =
-                        case maxUpdatedAt of
-                            Just _ ->
-                                maxUpdatedAt
+                    { lastSeenUpdatedAt = lastSeenUpdatedAt
 
-                            Nothing ->
-                                Dict.get tableName cursor
-                                    |> Maybe.map .lastSeenUpdatedAt
-                                    |> Maybe.withDefault Nothing
+                    -- The key only continues the cursor at the timestamp it was sent with.
+                    , lastSeenKey =
+                        if lastSeenUpdatedAt == existingUpdatedAt then
+                            Maybe.andThen .lastSeenKey existingEntry
+
+                        else
+                            Nothing
                     , permissionHash = existingPermission
+                    , lastSeenTombstone = Maybe.andThen .lastSeenTombstone existingEntry
                     }
             in
             Dict.insert tableName updatedEntry acc
@@ -453,7 +529,9 @@ resetCursorIfRowsAreMissing tableName entry cursor =
         Just _ ->
             Dict.insert tableName
                 { lastSeenUpdatedAt = Nothing
+                , lastSeenKey = Nothing
                 , permissionHash = ""
+                , lastSeenTombstone = Nothing
                 }
                 cursor
 
@@ -521,7 +599,16 @@ encodeSyncCursorEntry entry =
                 Nothing ->
                     Encode.null
           )
+        , ( "last_seen_key", Maybe.withDefault Encode.null entry.lastSeenKey )
         , ( "permission_hash", Encode.string entry.permissionHash )
+        , ( "last_seen_tombstone"
+          , case entry.lastSeenTombstone of
+                Just value ->
+                    Encode.int value
+
+                Nothing ->
+                    Encode.null
+          )
         ]
 
 
@@ -555,10 +642,22 @@ validateResponseDatabaseId expected actual =
 
 decodeCatchupTable : Decode.Decoder CatchupTableResult
 decodeCatchupTable =
-    Decode.map3 CatchupTableResult
+    Decode.map7 CatchupTableResult
         (Decode.field "rows" (Decode.list (Decode.dict Data.Value.decodeValue)))
         (Decode.field "permission_hash" Decode.string)
         (Decode.field "last_seen_updated_at" decodeMaybeTimestamp)
+        (decodeOptionalField "last_seen_key" Decode.value)
+        (decodeOptionalField "deleted" (Decode.list (Decode.dict Data.Value.decodeValue)) |> Decode.map (Maybe.withDefault []))
+        (decodeOptionalField "last_seen_tombstone" Decode.int)
+        (decodeOptionalField "reset" Decode.bool |> Decode.map (Maybe.withDefault False))
+
+
+{-| Servers that predate a field leave it out.
+-}
+decodeOptionalField : String -> Decode.Decoder a -> Decode.Decoder (Maybe a)
+decodeOptionalField name decoder =
+    Decode.maybe (Decode.field name (Decode.nullable decoder))
+        |> Decode.map (Maybe.andThen identity)
 
 
 decodeMaybeTimestamp : Decode.Decoder (Maybe Float)
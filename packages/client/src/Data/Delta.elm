@@ -1,6 +1,7 @@
-module Data.Delta exposing (Delta, TableGroup, decodeDelta, decodeTableGroup, encodeDelta, encodeTableGroup)
+module Data.Delta exposing (Delta, RemovedRows, ServerDelta, TableGroup, decodeDelta, decodeRemovedRows, decodeServerDelta, decodeTableGroup, encodeDelta, encodeRemovedRows, encodeTableGroup, serverUpserts)
 
 import Data.Value exposing (Value)
+import Dict exposing (Dict)
 import Json.Decode as Decode
 import Json.Encode as Encode
 
@@ -24,6 +25,26 @@ type alias TableGroup =
     }
 
 
+{-| Rows of a single table the client should drop, such as rows deleted on the server.
+Each key maps primary key columns to their values.
+-}
+type alias RemovedRows =
+    { tableName : String
+    , keys : List (Dict String Value)
+    }
+
+
+{-| The rows of a server `delta` message: rows to upsert from `data`, rows the
+recipient can no longer see from `removed`, and rows its own `@ifVersion` update
+skipped from `conflicts`, as they are now. The server leaves out empty lists.
+-}
+type alias ServerDelta =
+    { delta : Delta
+    , removed : List RemovedRows
+    , conflicts : Delta
+    }
+
+
 decodeDelta : Decode.Decoder Delta
 decodeDelta =
     Decode.map Delta
@@ -38,6 +59,45 @@ decodeTableGroup =
         (Decode.field "rows" (Decode.list (Decode.list Data.Value.decodeValue)))
 
 
+decodeRemovedRows : Decode.Decoder RemovedRows
+decodeRemovedRows =
+    Decode.map2 RemovedRows
+        (Decode.field "table_name" Decode.string)
+        (Decode.field "keys" (Decode.list (Decode.dict Data.Value.decodeValue)))
+
+
+decodeServerDelta : Decode.Decoder ServerDelta
+decodeServerDelta =
+    Decode.map3 ServerDelta
+        (decodeOptionalList "data" decodeTableGroup |> Decode.map Delta)
+        (decodeOptionalList "removed" decodeRemovedRows)
+        (decodeOptionalList "conflicts" decodeTableGroup |> Decode.map Delta)
+
+
+{-| Every row the server sent as it is now. Conflicting rows replace whatever
+the client assumed the skipped update did.
+-}
+serverUpserts : ServerDelta -> Delta
+serverUpserts serverDelta =
+    { tableGroups = serverDelta.delta.tableGroups ++ serverDelta.conflicts.tableGroups }
+
+
+{-| A missing field is an empty list, but a malformed one still fails.
+-}
+decodeOptionalList : String -> Decode.Decoder a -> Decode.Decoder (List a)
+decodeOptionalList name decoder =
+    Decode.maybe (Decode.field name Decode.value)
+        |> Decode.andThen
+            (\field ->
+                case field of
+                    Just _ ->
+                        Decode.field name (Decode.list decoder)
+
+                    Nothing ->
+                        Decode.succeed []
+            )
+
+
 encodeDelta : Delta -> Encode.Value
 encodeDelta delta =
     Encode.list encodeTableGroup delta.tableGroups
@@ -51,3 +111,10 @@ encodeTableGroup group =
         , ( "rows", Encode.list (Encode.list Data.Value.encodeValue) group.rows )
         ]
 
+
+encodeRemovedRows : RemovedRows -> Encode.Value
+encodeRemovedRows removed =
+    Encode.object
+        [ ( "table_name", Encode.string removed.tableName )
+        , ( "keys", Encode.list (Encode.dict identity Data.Value.encodeValue) removed.keys )
+        ]
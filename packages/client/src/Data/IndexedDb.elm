@@ -2,6 +2,7 @@ port module Data.IndexedDb exposing
     ( Incoming(..)
     , InitialData
     , SyncCursor
+    , deleteRows
     , receiveIncoming
     , requestInitialData
     , writeDelta
@@ -10,7 +11,7 @@ port module Data.IndexedDb exposing
     , writeSyncCursor
     )
 
-import Data.Delta exposing (TableGroup)
+import Data.Delta exposing (RemovedRows, TableGroup)
 import Data.Value exposing (Value)
 import Dict exposing (Dict)
 import Json.Decode as Decode
@@ -34,7 +35,9 @@ type alias SyncCursor =
 
 type alias SyncCursorEntry =
     { lastSeenUpdatedAt : Maybe Float
+    , lastSeenKey : Maybe Decode.Value
     , permissionHash : String
+    , lastSeenTombstone : Maybe Int
     }
 
 
@@ -47,6 +50,7 @@ type Message
     = RequestInitialData
     | WriteDelta (List TableGroup)
     | WriteDeltaWithEntityNotification String (List TableGroup)
+    | DeleteRows (List RemovedRows)
     | WriteSyncCursor SyncCursor
     | WriteServerRevision Int
 
@@ -90,6 +94,12 @@ encodeMessage msg =
                 , ( "tableGroups", Encode.list Data.Delta.encodeTableGroup tableGroups )
                 ]
 
+        DeleteRows removed ->
+            Encode.object
+                [ ( "type", Encode.string "deleteRows" )
+                , ( "removed", Encode.list Data.Delta.encodeRemovedRows removed )
+                ]
+
         WriteSyncCursor cursor ->
             Encode.object
                 [ ( "type", Encode.string "writeSyncCursor" )
@@ -137,9 +147,19 @@ decodeSyncCursor =
 
 decodeSyncCursorEntry : Decode.Decoder SyncCursorEntry
 decodeSyncCursorEntry =
-    Decode.map2 SyncCursorEntry
+    Decode.map4 SyncCursorEntry
         (Decode.field "last_seen_updated_at" decodeMaybeTimestamp)
+        (decodeOptionalField "last_seen_key" Decode.value)
         (Decode.field "permission_hash" Decode.string)
+        (decodeOptionalField "last_seen_tombstone" Decode.int)
+
+
+{-| Cursors written before a field existed don't have it.
+-}
+decodeOptionalField : String -> Decode.Decoder a -> Decode.Decoder (Maybe a)
+decodeOptionalField name decoder =
+    Decode.maybe (Decode.field name (Decode.nullable decoder))
+        |> Decode.map (Maybe.andThen identity)
 
 
 decodeMaybeTimestamp : Decode.Decoder (Maybe Float)
@@ -168,7 +188,16 @@ encodeSyncCursorEntry entry =
                 Nothing ->
                     Encode.null
           )
+        , ( "last_seen_key", Maybe.withDefault Encode.null entry.lastSeenKey )
         , ( "permission_hash", Encode.string entry.permissionHash )
+        , ( "last_seen_tombstone"
+          , case entry.lastSeenTombstone of
+                Just value ->
+                    Encode.int value
+
+                Nothing ->
+                    Encode.null
+          )
         ]
 
 
@@ -196,6 +225,11 @@ writeDeltaWithEntityNotification source tableGroups =
     sendMessage (WriteDeltaWithEntityNotification source tableGroups)
 
 
+deleteRows : List RemovedRows -> Cmd msg
+deleteRows removed =
+    sendMessage (DeleteRows removed)
+
+
 writeSyncCursor : SyncCursor -> Cmd msg
 writeSyncCursor cursor =
     sendMessage (WriteSyncCursor cursor)
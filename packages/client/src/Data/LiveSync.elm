@@ -9,7 +9,7 @@ port module Data.LiveSync exposing
     , receiveIncoming
     )
 
-import Data.Delta exposing (Delta)
+import Data.Delta exposing (ServerDelta)
 import Json.Decode as Decode
 import Json.Encode as Encode
 
@@ -41,7 +41,7 @@ type Message
 
 
 type Incoming
-    = DeltaReceived (Maybe String) (Maybe Int) Delta
+    = DeltaReceived (Maybe String) (Maybe Int) ServerDelta
     | SyncProgressReceived (Maybe String) SyncProgress
     | LiveSyncConnected (Maybe String) String
     | LiveSyncError String
@@ -107,7 +107,7 @@ decodeIncoming =
                         Decode.map3 DeltaReceived
                             (Decode.maybe (Decode.field "databaseId" Decode.string))
                             (Decode.maybe (Decode.field "serverRevision" Decode.int))
-                            (Decode.field "data" Data.Delta.decodeDelta)
+                            Data.Delta.decodeServerDelta
 
                     "syncProgress" ->
                         Decode.map2 SyncProgressReceived
@@ -1,4 +1,4 @@
-port module Data.QueryManager exposing (Incoming(..), Model, Msg(..), OptimisticMutation, OptimisticSetField, OptimisticWhere, QueryClientIncoming(..), QueryDeltaOp(..), QuerySubscription, ReExecuteDecision(..), decodeIncoming, decodeQueryClientIncoming, doesChangeAffectWhereClause, extractChangedRowIds, extractWhereClauseFields, init, mutationResult, notifyTablesChanged, queryClientDelta, queryClientFull, receiveIncoming, receiveQueryClientIncoming, shouldReExecuteQuery, update)
+port module Data.QueryManager exposing (Incoming(..), Model, Msg(..), MutationError, OptimisticMutation, OptimisticSetField, OptimisticWhere, QueryClientIncoming(..), QueryDeltaOp(..), QuerySubscription, ReExecuteDecision(..), decodeIncoming, decodeQueryClientIncoming, doesChangeAffectWhereClause, extractChangedRowIds, extractWhereClauseFields, init, mutationError, mutationResult, notifyRowsRemoved, notifyTablesChanged, queryClientDelta, queryClientFull, receiveIncoming, receiveQueryClientIncoming, shouldReExecuteQuery, update, withServerErrorCode)
 
 import Data.Delta
 import Data.Schema
@@ -62,6 +62,16 @@ type alias OptimisticSetField =
     }
 
 
+{-| Why a mutation failed. `code` carries the server's error code, such as
+`clientOutdated` along with the `manifestVersion` the server is running.
+-}
+type alias MutationError =
+    { message : String
+    , code : Maybe String
+    , manifestVersion : Maybe String
+    }
+
+
 {-| Incoming messages from QueryClient (TypeScript side)
 -}
 type QueryClientIncoming
@@ -74,7 +84,7 @@ type Message
     = QueryResult String Encode.Value -- callbackPort, result
     | QueryFull String Int Encode.Value -- queryId, revision, result
     | QueryDelta String Int (List QueryDeltaOp) -- queryId, revision, delta ops
-    | MutationResult String String (Result String Encode.Value) -- requestId, mutationId, result
+    | MutationResult String String (Result MutationError Encode.Value) -- requestId, mutationId, result
 
 
 type QueryDeltaOp
@@ -120,14 +130,37 @@ handleIncoming incoming model =
 
 notifyTablesChanged : Data.Schema.SchemaMetadata -> Db.Db -> Model -> Data.Delta.Delta -> ( Model, List (Cmd msg) )
 notifyTablesChanged schema db model delta =
+    -- Use fine-grained reactivity to decide if re-execution is needed
+    reExecuteSubscriptions schema db model (\subscription -> shouldReExecuteQuery schema db subscription delta)
+
+
+
+-- Notify that rows were removed, re-executing every query that reads their tables
+
+
+notifyRowsRemoved : Data.Schema.SchemaMetadata -> Db.Db -> Model -> List Data.Delta.RemovedRows -> ( Model, List (Cmd msg) )
+notifyRowsRemoved schema db model removed =
+    let
+        removedTables =
+            List.map .tableName removed
+    in
+    reExecuteSubscriptions schema
+        db
+        model
+        (\subscription ->
+            if List.any (\tableName -> List.member tableName removedTables) (extractQueryTables schema subscription.query) then
+                ReExecuteFull
+
+            else
+                NoReExecute
+        )
+
+
+reExecuteSubscriptions : Data.Schema.SchemaMetadata -> Db.Db -> Model -> (QuerySubscription -> ReExecuteDecision) -> ( Model, List (Cmd msg) )
+reExecuteSubscriptions schema db model decide =
     Dict.foldl
         (\queryId subscription ( accModel, accCmds ) ->
-            let
-                -- Use fine-grained reactivity to decide if re-execution is needed
-                decision =
-                    shouldReExecuteQuery schema db subscription delta
-            in
-            case decision of
+            case decide subscription of
                 ReExecuteFull ->
                     let
                         executionResult =
@@ -1121,9 +1154,12 @@ encodeMessage msg =
 
                         Err error ->
                             Encode.object
-                                [ ( "ok", Encode.bool False )
-                                , ( "error", Encode.string error )
-                                ]
+                                ([ ( "ok", Encode.bool False )
+                                 , ( "error", Encode.string error.message )
+                                 ]
+                                    ++ optionalStringField "code" error.code
+                                    ++ optionalStringField "manifestVersion" error.manifestVersion
+                                )
                   )
                 ]
 
@@ -1339,11 +1375,51 @@ encodeQueryDeltaOp op =
                 ]
 
 
-mutationResult : String -> String -> Result String Encode.Value -> Cmd msg
+mutationResult : String -> String -> Result MutationError Encode.Value -> Cmd msg
 mutationResult requestId mutationId result =
     sendMessage (MutationResult requestId mutationId result)
 
 
+{-| A mutation failure with no server error code.
+-}
+mutationError : String -> MutationError
+mutationError message =
+    { message = message
+    , code = Nothing
+    , manifestVersion = Nothing
+    }
+
+
+{-| Keep the `code` from a failed mutation's response body, so a
+`clientOutdated` rejection reaches the app along with the manifest version
+it should reload to.
+-}
+withServerErrorCode : String -> MutationError -> MutationError
+withServerErrorCode body error =
+    let
+        decodeServerError =
+            Decode.map2 Tuple.pair
+                (Decode.field "code" Decode.string)
+                (Decode.maybe (Decode.field "manifestVersion" Decode.string))
+    in
+    case Decode.decodeString decodeServerError body of
+        Ok ( code, manifestVersion ) ->
+            { error | code = Just code, manifestVersion = manifestVersion }
+
+        Err _ ->
+            error
+
+
+optionalStringField : String -> Maybe String -> List ( String, Encode.Value )
+optionalStringField name maybeValue =
+    case maybeValue of
+        Just value ->
+            [ ( name, Encode.string value ) ]
+
+        Nothing ->
+            []
+
+
 receiveIncoming : (Result Decode.Error Incoming -> msg) -> Sub msg
 receiveIncoming toMsg =
     receiveQueryManagerMessage (\jsonValue -> toMsg (Decode.decodeValue decodeIncoming jsonValue))
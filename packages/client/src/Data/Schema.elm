@@ -1,4 +1,4 @@
-module Data.Schema exposing (IndexInfo, LinkInfo, LinkTarget, LinkType(..), SchemaMetadata, TableMetadata, decodeIndexInfo, decodeLinkInfo, decodeLinkTarget, decodeLinkType, decodeSchemaMetadata, decodeTableMetadata)
+module Data.Schema exposing (IndexInfo, LinkColumn, LinkInfo, LinkTarget, LinkType(..), SchemaMetadata, TableMetadata, decodeIndexInfo, decodeLinkInfo, decodeLinkTarget, decodeLinkType, decodeSchemaMetadata, decodeTableMetadata, linkColumns)
 
 import Dict exposing (Dict)
 import Json.Decode as Decode
@@ -8,6 +8,16 @@ type alias LinkInfo =
     { type_ : LinkType
     , from : String
     , to : LinkTarget
+    , columns : List LinkColumn
+    }
+
+
+{-| One column pair of a composite link. Single-column links leave `columns`
+empty and join `from` to `to.column`.
+-}
+type alias LinkColumn =
+    { from : String
+    , to : String
     }
 
 
@@ -65,10 +75,31 @@ decodeLinkType =
 
 decodeLinkInfo : Decode.Decoder LinkInfo
 decodeLinkInfo =
-    Decode.map3 LinkInfo
+    Decode.map4 LinkInfo
         (Decode.field "type" decodeLinkType)
         (Decode.field "from" Decode.string)
         (Decode.field "to" decodeLinkTarget)
+        (Decode.maybe (Decode.field "columns" (Decode.list decodeLinkColumn))
+            |> Decode.map (Maybe.withDefault [])
+        )
+
+
+decodeLinkColumn : Decode.Decoder LinkColumn
+decodeLinkColumn =
+    Decode.map2 LinkColumn
+        (Decode.field "from" Decode.string)
+        (Decode.field "to" Decode.string)
+
+
+{-| Every column pair a link joins on.
+-}
+linkColumns : LinkInfo -> List LinkColumn
+linkColumns link =
+    if List.isEmpty link.columns then
+        [ { from = link.from, to = link.to.column } ]
+
+    else
+        link.columns
 
 
 decodeLinkTarget : Decode.Decoder LinkTarget
@@ -1,7 +1,7 @@
 module Db exposing (Db, Msg(..), QueryExecutionResult, executeQuery, executeQueryWithTracking, extractAffectedTables, fromInitialData, init, rowMatchesWhere, update)
 
 import Basics exposing (Order(..))
-import Data.Delta exposing (Delta, TableGroup)
+import Data.Delta exposing (Delta, RemovedRows, TableGroup)
 import Data.IndexedDb
 import Data.Schema exposing (SchemaMetadata)
 import Data.Value exposing (Value)
@@ -33,6 +33,7 @@ type Msg
     = FromIndexedDb SchemaMetadata Data.IndexedDb.Incoming
     | DeltaReceived Delta
     | LocalDeltaReceived Delta
+    | RowsRemoved (List RemovedRows)
 
 
 
@@ -74,6 +75,11 @@ update msg db =
             , Cmd.none
             )
 
+        RowsRemoved removed ->
+            ( removeRows removed db
+            , Data.IndexedDb.deleteRows removed
+            )
+
 
 
 -- Convert initial data to database format
@@ -112,6 +118,49 @@ applyDelta delta db =
 
 
 
+-- Remove rows by primary key and drop them from indices
+
+
+removeRows : List RemovedRows -> Db -> Db
+removeRows removed db =
+    List.foldl
+        (\group accDb ->
+            case Dict.get group.tableName accDb.tables of
+                Just table ->
+                    let
+                        ( updatedTable, indexUpdates ) =
+                            List.foldl
+                                (\key ( accTable, accUpdates ) ->
+                                    let
+                                        existing =
+                                            getRowId key
+                                                |> Maybe.andThen (\rowId -> Dict.get rowId accTable |> Maybe.map (Tuple.pair rowId))
+                                    in
+                                    case existing of
+                                        Just ( rowId, existingRow ) ->
+                                            -- Comparing against an empty row removes every index entry
+                                            ( Dict.remove rowId accTable
+                                            , accUpdates ++ calculateIndexUpdates accDb.indices group.tableName rowId (Just existingRow) Dict.empty
+                                            )
+
+                                        Nothing ->
+                                            ( accTable, accUpdates )
+                                )
+                                ( table, [] )
+                                group.keys
+                    in
+                    { tables = Dict.insert group.tableName updatedTable accDb.tables
+                    , indices = applyIndexUpdates indexUpdates accDb.indices
+                    }
+
+                Nothing ->
+                    accDb
+        )
+        db
+        removed
+
+
+
 -- Query execution result with tracking
 
 
@@ -770,8 +819,16 @@ resolveRelationship schema tableName fieldName row data indices =
         Just tableMeta ->
             case Dict.get fieldName tableMeta.links of
                 Just linkInfo ->
-                    case linkInfo.type_ of
-                        Data.Schema.OneToMany ->
+                    case ( linkInfo.type_, linkInfo.columns ) of
+                        ( Data.Schema.OneToMany, _ :: _ :: _ ) ->
+                            Just (lookupRowsByLinkColumns data linkInfo row)
+
+                        ( _, _ :: _ :: _ ) ->
+                            lookupRowsByLinkColumns data linkInfo row
+                                |> List.head
+                                |> Maybe.map List.singleton
+
+                        ( Data.Schema.OneToMany, _ ) ->
                             case Dict.get "id" row of
                                 Just idValue ->
                                     lookupRowsByForeignKeyIndexed indices data linkInfo.to.table linkInfo.to.column idValue
@@ -781,7 +838,7 @@ resolveRelationship schema tableName fieldName row data indices =
                                 _ ->
                                     Just []
 
-                        Data.Schema.ManyToOne ->
+                        ( Data.Schema.ManyToOne, _ ) ->
                             case Dict.get linkInfo.from row of
                                 Just foreignKeyValue ->
                                     lookupRowByPrimaryKey data linkInfo.to.table linkInfo.to.column foreignKeyValue
@@ -790,7 +847,7 @@ resolveRelationship schema tableName fieldName row data indices =
                                 Nothing ->
                                     Nothing
 
-                        Data.Schema.OneToOne ->
+                        ( Data.Schema.OneToOne, _ ) ->
                             case Dict.get linkInfo.from row of
                                 Just foreignKeyValue ->
                                     lookupRowByPrimaryKey data linkInfo.to.table linkInfo.to.column foreignKeyValue
@@ -821,6 +878,39 @@ getRelatedTableName schema tableName fieldName =
             ""
 
 
+{-| Rows of a composite link's target table that match the row on every column
+pair. A row with a null or missing key column links to nothing.
+-}
+lookupRowsByLinkColumns : Dict String TableData -> Data.Schema.LinkInfo -> Dict String Value -> List (Dict String Value)
+lookupRowsByLinkColumns data linkInfo row =
+    let
+        keyValue pair =
+            case Dict.get pair.from row of
+                Just Data.Value.NullValue ->
+                    Nothing
+
+                Just value ->
+                    Just ( pair.to, value )
+
+                Nothing ->
+                    Nothing
+
+        expectedValues =
+            Data.Schema.linkColumns linkInfo
+                |> List.foldr (\pair acc -> Maybe.map2 (::) (keyValue pair) acc) (Just [])
+    in
+    case expectedValues of
+        Just expected ->
+            Dict.get linkInfo.to.table data
+                |> Maybe.map Dict.values
+                |> Maybe.withDefault []
+                |> List.filter
+                    (\target -> List.all (\( column, value ) -> Dict.get column target == Just value) expected)
+
+        Nothing ->
+            []
+
+
 lookupRowsByForeignKey : Dict String TableData -> String -> String -> Value -> Maybe (List (Dict String Value))
 lookupRowsByForeignKey data tableName foreignKeyColumn foreignKeyValue =
     case Dict.get tableName data of
@@ -66,7 +66,7 @@ type alias OptimisticInFlight =
 
 type alias MutationSyncMessage =
     { serverRevision : Maybe Int
-    , delta : Maybe Data.Delta.Delta
+    , delta : Maybe Data.Delta.ServerDelta
     , requiresCatchup : Bool
     }
 
@@ -80,7 +80,7 @@ type Msg
     | LiveSyncReceived LiveSync.Incoming
     | QueryManagerReceived QueryManager.Incoming
     | QueryClientReceived QueryManager.QueryClientIncoming
-    | MutationRequest String String String Encode.Value (Result Http.Error Encode.Value)
+    | MutationRequest String String String Encode.Value (Result QueryManager.MutationError Encode.Value)
     | DbMsg Db.Msg
     | Error String
     | CatchupMsg Catchup.Msg
@@ -194,7 +194,7 @@ update msg model =
                     settleSuccessfulMutation requestId mutationId response model
 
                 Err error ->
-                    rollbackOptimisticMutation requestId mutationId (httpErrorToString error) model
+                    rollbackOptimisticMutation requestId mutationId error model
 
         Error errorMessage ->
             ( model
@@ -271,7 +271,7 @@ handleLiveSyncIncoming incoming model =
                                 applyAuthoritativeDelta delta model
 
                             ( updatedQueryManager, triggerCmds ) =
-                                QueryManager.notifyTablesChanged model.schema updatedDb model.queryManager delta
+                                notifyAuthoritativeDelta delta updatedDb model
                         in
                         ( { model
                             | db = updatedDb
@@ -396,16 +396,16 @@ handleQueryManagerIncoming incoming model =
                             (\response ->
                                 case response of
                                     Http.BadUrl_ badUrl ->
-                                        Err (Http.BadUrl badUrl)
+                                        Err (httpMutationError (Http.BadUrl badUrl))
 
                                     Http.Timeout_ ->
-                                        Err Http.Timeout
+                                        Err (httpMutationError Http.Timeout)
 
                                     Http.NetworkError_ ->
-                                        Err Http.NetworkError
+                                        Err (httpMutationError Http.NetworkError)
 
                                     Http.BadStatus_ metadata body ->
-                                        Err (Http.BadStatus metadata.statusCode)
+                                        Err (QueryManager.withServerErrorCode body (httpMutationError (Http.BadStatus metadata.statusCode)))
 
                                     Http.GoodStatus_ _ body ->
                                         case Decode.decodeString Decode.value body of
@@ -413,7 +413,7 @@ handleQueryManagerIncoming incoming model =
                                                 Ok json
 
                                             Err err ->
-                                                Err (Http.BadBody (Decode.errorToString err))
+                                                Err (httpMutationError (Http.BadBody (Decode.errorToString err)))
                             )
                     , timeout = Nothing
                     , tracker = Nothing
@@ -549,6 +549,11 @@ httpErrorToString error =
             "Decode Error: " ++ message
 
 
+httpMutationError : Http.Error -> QueryManager.MutationError
+httpMutationError error =
+    QueryManager.mutationError (httpErrorToString error)
+
+
 buildMutationUrl : String -> String -> String
 buildMutationUrl baseUrl id =
     case String.split "?" baseUrl of
@@ -634,7 +639,7 @@ applyOptimisticMutation requestId maybeOptimistic input model =
                                 )
 
 
-rollbackOptimisticMutation : String -> String -> String -> Model -> ( Model, Cmd Msg )
+rollbackOptimisticMutation : String -> String -> QueryManager.MutationError -> Model -> ( Model, Cmd Msg )
 rollbackOptimisticMutation requestId mutationId error model =
     case Dict.get requestId model.inFlightOptimistic of
         Nothing ->
@@ -675,7 +680,7 @@ settleSuccessfulMutation requestId mutationId response model =
             extractMutationSyncMessage response
     in
     if Dict.member requestId model.inFlightOptimistic && missingAuthoritativeMutationEnvelope serverRevision maybeSyncMessage then
-        rollbackOptimisticMutation requestId mutationId "Optimistic mutation response missing authoritative sync envelope" model
+        rollbackOptimisticMutation requestId mutationId (QueryManager.mutationError "Optimistic mutation response missing authoritative sync envelope") model
 
     else
         settleSuccessfulMutationWithEnvelope requestId mutationId response serverRevision maybeSyncMessage model
@@ -704,13 +709,22 @@ settleSuccessfulMutationWithEnvelope requestId mutationId response serverRevisio
                         Just delta ->
                             if shouldApplyAuthoritative then
                                 let
+                                    -- A version conflict means the update never happened, so
+                                    -- its optimistic rows must not be replayed over the conflicts.
+                                    syncModel =
+                                        if List.isEmpty delta.conflicts.tableGroups then
+                                            model
+
+                                        else
+                                            removeOptimisticMutation requestId model
+
                                     ( updatedDb, dbCmds ) =
-                                        applyAuthoritativeDelta delta model
+                                        applyAuthoritativeDelta delta syncModel
 
                                     ( updatedQueryManager, triggerCmds ) =
-                                        QueryManager.notifyTablesChanged model.schema updatedDb model.queryManager delta
+                                        notifyAuthoritativeDelta delta updatedDb syncModel
                                 in
-                                ( { model | db = updatedDb, queryManager = updatedQueryManager }, dbCmds, triggerCmds )
+                                ( { syncModel | db = updatedDb, queryManager = updatedQueryManager }, dbCmds, triggerCmds )
 
                             else
                                 ( model, [], [] )
@@ -794,16 +808,43 @@ pruneAcknowledgedOptimisticPrefix model =
                     pruneAcknowledgedOptimisticPrefix { model | optimisticOrder = rest }
 
 
-applyAuthoritativeDelta : Data.Delta.Delta -> Model -> ( Db.Db, List (Cmd Db.Msg) )
-applyAuthoritativeDelta delta model =
+{-| Drop the rows the server removed, upsert the rows and conflicts it sent,
+then replay optimistic mutations on top.
+-}
+applyAuthoritativeDelta : Data.Delta.ServerDelta -> Model -> ( Db.Db, List (Cmd Db.Msg) )
+applyAuthoritativeDelta serverDelta model =
     let
+        ( removedDb, removedCmds ) =
+            if List.isEmpty serverDelta.removed then
+                ( model.db, [] )
+
+            else
+                Db.update (Db.RowsRemoved serverDelta.removed) model.db
+                    |> Tuple.mapSecond List.singleton
+
         ( authoritativeDb, authoritativeCmd ) =
-            Db.update (Db.DeltaReceived delta) model.db
+            Db.update (Db.DeltaReceived (Data.Delta.serverUpserts serverDelta)) removedDb
 
         ( replayedDb, replayCmds ) =
             replayOptimisticMutations model authoritativeDb
     in
-    ( replayedDb, authoritativeCmd :: replayCmds )
+    ( replayedDb, removedCmds ++ authoritativeCmd :: replayCmds )
+
+
+notifyAuthoritativeDelta : Data.Delta.ServerDelta -> Db.Db -> Model -> ( QueryManager.Model, List (Cmd Msg) )
+notifyAuthoritativeDelta serverDelta db model =
+    let
+        ( queryManagerAfterRemovals, removalTriggerCmds ) =
+            if List.isEmpty serverDelta.removed then
+                ( model.queryManager, [] )
+
+            else
+                QueryManager.notifyRowsRemoved model.schema db model.queryManager serverDelta.removed
+
+        ( updatedQueryManager, triggerCmds ) =
+            QueryManager.notifyTablesChanged model.schema db queryManagerAfterRemovals (Data.Delta.serverUpserts serverDelta)
+    in
+    ( updatedQueryManager, removalTriggerCmds ++ triggerCmds )
 
 
 replayOptimisticMutations : Model -> Db.Db -> ( Db.Db, List (Cmd Db.Msg) )
@@ -894,7 +935,7 @@ decodeMutationSyncMessage =
                                 }
                             )
                             (Decode.maybe (Decode.field "serverRevision" Decode.int))
-                            (Decode.field "data" Data.Delta.decodeDelta)
+                            Data.Delta.decodeServerDelta
 
                     "syncRequired" ->
                         Decode.map
@@ -1031,13 +1072,20 @@ applyCatchupUpdate result model =
         ( liveSyncModel, liveSyncCmd ) =
             startLiveSyncIfReady updatedModel
 
+        ( queryManagerAfterRemovals, removalTriggerCmds ) =
+            if List.isEmpty result.removed then
+                ( model.queryManager, [] )
+
+            else
+                QueryManager.notifyRowsRemoved model.schema replayedDb model.queryManager result.removed
+
         ( updatedQueryManager, triggerCmds ) =
             case result.delta of
                 Just delta ->
-                    QueryManager.notifyTablesChanged model.schema replayedDb model.queryManager delta
+                    QueryManager.notifyTablesChanged model.schema replayedDb queryManagerAfterRemovals delta
 
                 Nothing ->
-                    ( model.queryManager, [] )
+                    ( queryManagerAfterRemovals, [] )
 
         errorCmd =
             case result.error of
@@ -1055,6 +1103,7 @@ applyCatchupUpdate result model =
         cmds =
             [ Cmd.map CatchupMsg result.cmd
             , errorCmd
+            , Cmd.batch removalTriggerCmds
             , Cmd.batch triggerCmds
             , liveSyncCmd
             , writeServerRevisionCmd result.serverRevision
@@ -0,0 +1,144 @@
+module CatchupTest exposing (suite)
+
+import Data.Catchup as Catchup
+import Data.Value exposing (Value)
+import Db
+import Dict exposing (Dict)
+import Expect
+import Json.Decode as Decode
+import Test exposing (Test, describe, test)
+
+
+suite : Test
+suite =
+    describe "Catchup"
+        [ test "a row deleted on the server disappears after catchup" <|
+            \_ ->
+                let
+                    result =
+                        catchup { usersTable | deleted = [ key 1 ] }
+                in
+                Expect.equal (Just [ 2 ]) (userIds result.db)
+        , test "deleted rows are reported so queries re-run" <|
+            \_ ->
+                let
+                    result =
+                        catchup { usersTable | deleted = [ key 1 ] }
+                in
+                Expect.equal [ "users" ] (List.map .tableName result.removed)
+        , test "a deleted key that comes back keeps its new row" <|
+            \_ ->
+                let
+                    result =
+                        catchup { usersTable | rows = [ user 1 "Returned" ], deleted = [ key 1 ] }
+                in
+                Expect.equal
+                    (Just (Data.Value.StringValue "Returned"))
+                    (Dict.get "users" result.db.tables
+                        |> Maybe.andThen (Dict.get 1)
+                        |> Maybe.andThen (Dict.get "name")
+                    )
+        , test "a reset table drops rows the server did not resend" <|
+            \_ ->
+                let
+                    result =
+                        catchup { usersTable | rows = [ user 2 "Bea" ], reset = True }
+                in
+                Expect.equal (Just [ 2 ]) (userIds result.db)
+        , test "the cursor records the newest tombstone" <|
+            \_ ->
+                let
+                    result =
+                        catchup usersTable
+                in
+                Expect.equal (Just 7)
+                    (Dict.get "users" result.model.cursor
+                        |> Maybe.andThen .lastSeenTombstone
+                    )
+        ]
+
+
+type alias TableResult =
+    { rows : List (Dict String Value)
+    , permissionHash : String
+    , lastSeenUpdatedAt : Maybe Float
+    , lastSeenKey : Maybe Decode.Value
+    , deleted : List (Dict String Value)
+    , lastSeenTombstone : Maybe Int
+    , reset : Bool
+    }
+
+
+usersTable : TableResult
+usersTable =
+    { rows = []
+    , permissionHash = "perm"
+    , lastSeenUpdatedAt = Just 1700000000
+    , lastSeenKey = Nothing
+    , deleted = []
+    , lastSeenTombstone = Just 7
+    , reset = False
+    }
+
+
+catchup : TableResult -> Catchup.UpdateResult
+catchup table =
+    let
+        response =
+            { databaseId = Nothing
+            , serverRevision = Nothing
+            , tables = Dict.singleton "users" table
+            , hasMore = False
+            }
+    in
+    Catchup.update (Catchup.CatchupResponseReceived (Ok response)) (Catchup.init server) dbWithUsers
+
+
+server : Catchup.ServerConfig
+server =
+    { baseUrl = "http://localhost"
+    , catchupPath = "/sync"
+    , databaseId = Nothing
+    , headers = []
+    , credentials = "same-origin"
+    , withCredentials = False
+    }
+
+
+dbWithUsers : Db.Db
+dbWithUsers =
+    Db.update
+        (Db.LocalDeltaReceived
+            { tableGroups =
+                [ { tableName = "users"
+                  , headers = [ "id", "name", "updatedAt" ]
+                  , rows =
+                        [ [ Data.Value.IntValue 1, Data.Value.StringValue "Ada", Data.Value.IntValue 1700000000 ]
+                        , [ Data.Value.IntValue 2, Data.Value.StringValue "Bea", Data.Value.IntValue 1700000000 ]
+                        ]
+                  }
+                ]
+            }
+        )
+        Db.init
+        |> Tuple.first
+
+
+key : Int -> Dict String Value
+key id =
+    Dict.singleton "id" (Data.Value.IntValue id)
+
+
+user : Int -> String -> Dict String Value
+user id name =
+    Dict.fromList
+        [ ( "id", Data.Value.IntValue id )
+        , ( "name", Data.Value.StringValue name )
+        , ( "updatedAt", Data.Value.IntValue 1700000001 )
+        ]
+
+
+userIds : Db.Db -> Maybe (List Int)
+userIds db =
+    Dict.get "users" db.tables
+        |> Maybe.map Dict.keys
@@ -0,0 +1,44 @@
+module DeltaTest exposing (suite)
+
+import Data.Delta
+import Data.Value
+import Dict
+import Expect
+import Json.Decode as Decode
+import Test exposing (Test, describe, test)
+
+
+suite : Test
+suite =
+    describe "Server deltas"
+        [ test "removed rows decode alongside upserted rows" <|
+            \_ ->
+                Decode.decodeString Data.Delta.decodeServerDelta
+                    """{"type":"delta","data":[{"table_name":"users","headers":["id","name"],"rows":[[2,"Bea"]]}],"removed":[{"table_name":"users","keys":[{"id":1}]}]}"""
+                    |> Result.map .removed
+                    |> Expect.equal
+                        (Ok [ { tableName = "users", keys = [ Dict.singleton "id" (Data.Value.IntValue 1) ] } ])
+        , test "a delta with only removed rows has no table groups" <|
+            \_ ->
+                Decode.decodeString Data.Delta.decodeServerDelta
+                    """{"type":"delta","removed":[{"table_name":"users","keys":[{"id":1}]}]}"""
+                    |> Result.map (.delta >> .tableGroups)
+                    |> Expect.equal (Ok [])
+        , test "conflicting rows are upserted after the delta's rows" <|
+            \_ ->
+                Decode.decodeString Data.Delta.decodeServerDelta
+                    """{"type":"delta","data":[{"table_name":"users","headers":["id","name"],"rows":[[2,"Bea"]]}],"conflicts":[{"table_name":"users","headers":["id","name"],"rows":[[1,"Ada"]]}]}"""
+                    |> Result.map (Data.Delta.serverUpserts >> .tableGroups >> List.concatMap .rows)
+                    |> Expect.equal
+                        (Ok
+                            [ [ Data.Value.IntValue 2, Data.Value.StringValue "Bea" ]
+                            , [ Data.Value.IntValue 1, Data.Value.StringValue "Ada" ]
+                            ]
+                        )
+        , test "a malformed removed list fails to decode" <|
+            \_ ->
+                Decode.decodeString Data.Delta.decodeServerDelta
+                    """{"type":"delta","data":[],"removed":[{"keys":[{"id":1}]}]}"""
+                    |> Result.toMaybe
+                    |> Expect.equal Nothing
+        ]
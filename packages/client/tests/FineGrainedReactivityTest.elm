@@ -455,6 +455,7 @@ shouldReExecuteQueryTests =
                                                     { table = "game_members"
                                                     , column = "gameId"
                                                     }
+                                                , columns = []
                                                 }
                                               )
                                             ]
@@ -0,0 +1,33 @@
+module MutationErrorTest exposing (suite)
+
+import Data.QueryManager as QueryManager
+import Expect
+import Test exposing (Test, describe, test)
+
+
+suite : Test
+suite =
+    describe "Mutation errors"
+        [ test "a clientOutdated response keeps its code and manifest version" <|
+            \_ ->
+                QueryManager.withServerErrorCode
+                    """{"error":"client is outdated","code":"clientOutdated","manifestVersion":"new-hash"}"""
+                    (QueryManager.mutationError "Bad Status: 409")
+                    |> Expect.equal
+                        { message = "Bad Status: 409"
+                        , code = Just "clientOutdated"
+                        , manifestVersion = Just "new-hash"
+                        }
+        , test "a body without a code leaves the error unchanged" <|
+            \_ ->
+                QueryManager.withServerErrorCode
+                    """{"error":"boom"}"""
+                    (QueryManager.mutationError "Bad Status: 500")
+                    |> Expect.equal (QueryManager.mutationError "Bad Status: 500")
+        , test "a body that is not JSON leaves the error unchanged" <|
+            \_ ->
+                QueryManager.withServerErrorCode
+                    "Internal Server Error"
+                    (QueryManager.mutationError "Bad Status: 500")
+                    |> Expect.equal (QueryManager.mutationError "Bad Status: 500")
+        ]
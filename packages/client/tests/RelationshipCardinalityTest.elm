@@ -62,6 +62,20 @@ suite =
                         ]
                     )
                     result
+        , test "composite many-to-one joins on every column pair" <|
+            \_ ->
+                Expect.equal
+                    (Dict.fromList
+                        [ ( "game"
+                          , [ Dict.fromList
+                                [ ( "id", Data.Value.IntValue 1 )
+                                , ( "owner", Data.Value.ObjectValue (Dict.singleton "id" (Data.Value.IntValue 3)) )
+                                ]
+                            ]
+                          )
+                        ]
+                    )
+                    (Db.executeQuery compositeSchema dbWithCompositeOwner gameOwnerQuery)
         , test "aliased one-to-many resolves by source link and projects alias" <|
             \_ ->
                 let
@@ -107,6 +121,7 @@ schema =
                                 { table = "game_members"
                                 , column = "gameId"
                                 }
+                            , columns = []
                             }
                           )
                         ]
@@ -253,3 +268,87 @@ expectedResult =
             ]
           )
         ]
+
+
+compositeSchema : Data.Schema.SchemaMetadata
+compositeSchema =
+    { tables =
+        Dict.fromList
+            [ ( "games"
+              , { name = "games"
+                , links =
+                    Dict.fromList
+                        [ ( "owner"
+                          , { type_ = Data.Schema.ManyToOne
+                            , from = "orgId"
+                            , to =
+                                { table = "org_members"
+                                , column = "orgId"
+                                }
+                            , columns =
+                                [ { from = "orgId", to = "orgId" }
+                                , { from = "ownerId", to = "userId" }
+                                ]
+                            }
+                          )
+                        ]
+                , indices = []
+                }
+              )
+            , ( "org_members"
+              , { name = "org_members"
+                , links = Dict.empty
+                , indices = []
+                }
+              )
+            ]
+    , queryFieldToTable = Dict.fromList [ ( "game", "games" ) ]
+    }
+
+
+gameOwnerQuery : Db.Query.Query
+gameOwnerQuery =
+    Dict.fromList
+        [ ( "game"
+          , { selections =
+                Dict.fromList
+                    [ ( "id", Db.Query.SelectField Nothing )
+                    , ( "owner"
+                      , Db.Query.SelectNested Nothing
+                            { selections = Dict.singleton "id" (Db.Query.SelectField Nothing)
+                            , where_ = Nothing
+                            , sort = Nothing
+                            , limit = Nothing
+                            }
+                      )
+                    ]
+            , where_ = Nothing
+            , sort = Nothing
+            , limit = Nothing
+            }
+          )
+        ]
+
+
+dbWithCompositeOwner : Db.Db
+dbWithCompositeOwner =
+    { tables =
+        Dict.fromList
+            [ ( "games"
+              , Dict.singleton 1 (intRow [ ( "id", 1 ), ( "orgId", 5 ), ( "ownerId", 7 ) ])
+              )
+            , ( "org_members"
+              , Dict.fromList
+                    [ ( 1, intRow [ ( "id", 1 ), ( "orgId", 5 ), ( "userId", 8 ) ] )
+                    , ( 2, intRow [ ( "id", 2 ), ( "orgId", 6 ), ( "userId", 7 ) ] )
+                    , ( 3, intRow [ ( "id", 3 ), ( "orgId", 5 ), ( "userId", 7 ) ] )
+                    ]
+              )
+            ]
+    , indices = Dict.empty
+    }
+
+
+intRow : List ( String, Int ) -> Dict String Value
+intRow columns =
+    Dict.fromList (List.map (Tuple.mapSecond Data.Value.IntValue) columns)
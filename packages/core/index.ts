@@ -1,6 +1,8 @@
 export interface LinkInfo {
   type: 'many-to-one' | 'one-to-many' | 'one-to-one';
   from: string;
+  /** Every column pair of a composite link. `from` and `to.column` hold the first. */
+  columns?: Array<{ from: string; to: string }>;
   to: {
     table: string;
     column: string;
@@ -425,14 +425,16 @@ async function insertSeedRow(
             throw new SeedInputError(`seed link '${path}.${nested.key}' must be a single object because '${nested.link.from}' is set on '${table.name}'`);
         }
         const linkedRow = await insertSeedRow(context, linkedTable, nested.value, `${path}.${nested.key}`);
-        const linkedValue = linkedRow[nested.link.to.column];
-        if (!isSeedValue(linkedValue)) {
-            throw new SeedInputError(`seed link '${path}.${nested.key}' did not return '${nested.link.to.column}'`);
-        }
-        if (nested.link.from in scalarValues && !sameSeedValue(scalarValues[nested.link.from], linkedValue)) {
-            throw new SeedInputError(`seed field '${path}.${nested.link.from}' conflicts with nested link '${nested.key}'`);
+        for (const pair of linkColumns(nested.link)) {
+            const linkedValue = linkedRow[pair.to];
+            if (!isSeedValue(linkedValue)) {
+                throw new SeedInputError(`seed link '${path}.${nested.key}' did not return '${pair.to}'`);
+            }
+            if (pair.from in scalarValues && !sameSeedValue(scalarValues[pair.from], linkedValue)) {
+                throw new SeedInputError(`seed field '${path}.${pair.from}' conflicts with nested link '${nested.key}'`);
+            }
+            scalarValues[pair.from] = linkedValue;
         }
-        scalarValues[nested.link.from] = linkedValue;
     }
 
     const inserted = await insertScalarRow(context, table, scalarValues, path);
@@ -442,9 +444,13 @@ async function insertSeedRow(
         if (!linkedTable) {
             throw new SeedInputError(`seed link '${path}.${nested.key}' points to unknown table '${nested.link.to.table}'`);
         }
-        const parentValue = inserted[nested.link.from];
-        if (!isSeedValue(parentValue)) {
-            throw new SeedInputError(`seed link '${path}.${nested.key}' cannot derive '${nested.link.from}' from inserted parent row`);
+        const parentValues: Record<string, SeedValue> = {};
+        for (const pair of linkColumns(nested.link)) {
+            const parentValue = inserted[pair.from];
+            if (!isSeedValue(parentValue)) {
+                throw new SeedInputError(`seed link '${path}.${nested.key}' cannot derive '${pair.from}' from inserted parent row`);
+            }
+            parentValues[pair.to] = parentValue;
         }
 
         const childRows = Array.isArray(nested.value) ? nested.value : [nested.value];
@@ -455,7 +461,7 @@ async function insertSeedRow(
                 linkedTable,
                 childRows[index],
                 `${path}.${nested.key}[${index}]`,
-                { [nested.link.to.column]: parentValue },
+                parentValues,
             ));
         }
         inserted[nested.key] = Array.isArray(nested.value) ? nestedResult : nestedResult[0];
@@ -785,7 +791,11 @@ function parseJsonSqlValue(value: unknown): unknown {
 }
 
 function isParentToChildLink(table: TableMetadata, link: LinkInfo): boolean {
-    return (table.columns ?? []).some((column) => column.name === link.from && column.primary);
+    return linkColumns(link).every((pair) => (table.columns ?? []).some((column) => column.name === pair.from && column.primary));
+}
+
+function linkColumns(link: LinkInfo): Array<{ from: string; to: string }> {
+    return link.columns ?? [{ from: link.from, to: link.to.column }];
 }
 
 function quoteIdentifier(identifier: string): string {
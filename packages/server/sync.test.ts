@@ -218,6 +218,102 @@ test("catchup normalizes bigint row values before reshaping", async () => {
   expect(result.tables.maps.last_seen_updated_at).toBe(1700000000);
 });
 
+test("catchup advances the cursor key to the last returned row", async () => {
+  getSyncSqlMock = () => ({
+    tables: [
+      {
+        ...defaultSyncSql().tables[0],
+        headers: ["id", "name", "updatedAt"],
+        primary_key: ["id"],
+      },
+    ],
+  });
+  reshapeSyncTableGroupsMock = (groups: any) => groups;
+  const db = {
+    execute: mock(async () => ({ rows: [{ table_name: "maps", needs_sync: 1 }] })),
+    batch: mock(async () => ([
+      {
+        columns: ["id", "name", "updatedAt"],
+        rows: [
+          { id: 1, name: "World", updatedAt: 1700000000 },
+          { id: 2, name: "Moon", updatedAt: 1700000000 },
+          { id: 3, name: "Mars", updatedAt: 1700000000 },
+        ],
+      },
+    ])),
+  };
+
+  const result = await catchup(db as any, { tables: {} }, {}, 2);
+
+  expect(result.has_more).toBe(true);
+  expect(result.tables.maps.last_seen_updated_at).toBe(1700000000);
+  expect(result.tables.maps.last_seen_key).toEqual({ id: 2 });
+});
+
+test("catchup reports keys deleted since the cursor's tombstone", async () => {
+  getSyncSqlMock = () => ({ tables: [] });
+  const db = {
+    execute: mock(async (statement: any) => {
+      const sql = typeof statement === "string" ? statement : statement.sql;
+      if (sql.includes("pruned_through")) {
+        return { rows: [{ pruned_through: 2, latest: 7 }] };
+      }
+      if (sql.includes("from _pyre_tombstones where table_name")) {
+        expect(statement.args).toEqual(["maps", 4, 7, 1001]);
+        return { rows: [{ id: 6, row_key: "{\"id\":1}" }] };
+      }
+      return { rows: [{ table_name: "maps", permission_hash: "perm" }] };
+    }),
+    batch: mock(async () => ([])),
+  };
+
+  const result = await catchup(
+    db as any,
+    { tables: { maps: { last_seen_updated_at: 1700000000, permission_hash: "perm", last_seen_tombstone: 4 } } },
+    {},
+    1000,
+  );
+
+  expect(result.tables.maps).toEqual({
+    rows: [],
+    permission_hash: "perm",
+    last_seen_updated_at: 1700000000,
+    deleted: [{ id: 1 }],
+    last_seen_tombstone: 7,
+  });
+  expect(result.has_more).toBe(false);
+});
+
+test("catchup resets tables whose cursor is behind the pruned tombstones", async () => {
+  const db = {
+    execute: mock(async (statement: any) => {
+      const sql = typeof statement === "string" ? statement : statement.sql;
+      if (sql.includes("pruned_through")) {
+        return { rows: [{ pruned_through: 5, latest: 9 }] };
+      }
+      return { rows: [{ table_name: "maps", permission_hash: "perm" }] };
+    }),
+    batch: mock(async () => ([{ columns: ["id", "name", "updatedAt"], rows: [] }])),
+  };
+  const getSyncSqlCursors: unknown[] = [];
+  getSyncSqlMock = (_rows: unknown, cursor: unknown) => {
+    getSyncSqlCursors.push(cursor);
+    return { tables: [{ ...defaultSyncSql().tables[0], headers: ["id", "name", "updatedAt"] }] };
+  };
+  reshapeSyncTableGroupsMock = (groups: any) => groups;
+
+  const result = await catchup(
+    db as any,
+    { tables: { maps: { last_seen_updated_at: 1700000000, permission_hash: "perm", last_seen_tombstone: 3 } } },
+    {},
+    1000,
+  );
+
+  expect(getSyncSqlCursors).toEqual([{ tables: {} }]);
+  expect(result.tables.maps.reset).toBe(true);
+  expect(result.tables.maps.last_seen_tombstone).toBe(9);
+});
+
 test("catchup unwraps double-encoded json objects for json columns", async () => {
   getSyncSqlMock = () => ({
     tables: [
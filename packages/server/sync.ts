@@ -50,6 +50,16 @@ function validateSyncCursor(syncCursor: SyncCursor): void {
         if (new TextEncoder().encode(entry.permission_hash).byteLength > MAX_SYNC_CURSOR_PERMISSION_HASH_BYTES) {
             throw new Error(`syncCursor permission_hash for ${tableName} is too large`);
         }
+
+        const lastSeenKey = entry.last_seen_key;
+        if (lastSeenKey !== undefined && lastSeenKey !== null && (typeof lastSeenKey !== "object" || Array.isArray(lastSeenKey))) {
+            throw new Error(`syncCursor last_seen_key for ${tableName} must be an object`);
+        }
+
+        const lastSeenTombstone = entry.last_seen_tombstone;
+        if (lastSeenTombstone !== undefined && lastSeenTombstone !== null && !Number.isSafeInteger(lastSeenTombstone)) {
+            throw new Error(`syncCursor last_seen_tombstone for ${tableName} must be an integer`);
+        }
     }
 }
 
@@ -59,7 +69,9 @@ function validateSyncCursor(syncCursor: SyncCursor): void {
 export interface SyncCursor {
     tables: Record<string, {
         last_seen_updated_at: number | null;
+        last_seen_key?: Record<string, unknown> | null;
         permission_hash: string;
+        last_seen_tombstone?: number | null;
     }>;
 }
 
@@ -75,11 +87,131 @@ export interface SyncPageResult {
             rows: any[];
             permission_hash: string;
             last_seen_updated_at: number | null;
+            last_seen_key?: Record<string, unknown> | null;
+            deleted?: Record<string, unknown>[];
+            last_seen_tombstone?: number;
+            reset?: boolean;
         }
     >;
     has_more: boolean;
 }
 
+/**
+ * The cursor advances to the last row's primary key, so rows sharing its
+ * updatedAt are not skipped on the next page. An empty page keeps the
+ * cursor's key.
+ */
+function lastSeenKey(
+    tableSql: { primary_key?: string[] },
+    rows: Record<string, any>[],
+    cursor: SyncCursor["tables"][string] | undefined,
+): Record<string, unknown> | null {
+    const lastRow = rows[rows.length - 1];
+    if (!lastRow) {
+        return cursor?.last_seen_key ?? null;
+    }
+
+    const primaryKey = tableSql.primary_key ?? [];
+    if (primaryKey.length === 0 || lastRow.updatedAt === null || lastRow.updatedAt === undefined) {
+        return null;
+    }
+
+    const key: Record<string, unknown> = {};
+    for (const column of primaryKey) {
+        key[column] = lastRow[column] ?? null;
+    }
+    return key;
+}
+
+/**
+ * Bounds of `_pyre_tombstones` ids. Tombstones at or below `pruned_through` have
+ * been pruned; `latest` is the newest id ever recorded.
+ */
+interface TombstoneRange {
+    prunedThrough: number;
+    latest: number;
+}
+
+interface DeletedKeys {
+    keys: Record<string, unknown>[];
+    lastSeenTombstone: number;
+    hasMore: boolean;
+}
+
+async function tombstoneRange(db: Client): Promise<TombstoneRange | null> {
+    // Ids come from autoincrement and only pruning deletes tombstones, so every id
+    // below the oldest remaining one, or every id when none remain, was pruned.
+    try {
+        const result = await db.execute(
+            "select coalesce((select min(id) - 1 from _pyre_tombstones), seq, 0) as pruned_through, coalesce(seq, 0) as latest "
+            + "from (select (select seq from sqlite_sequence where name = '_pyre_tombstones') as seq)",
+        );
+        const row = result.rows[0];
+        const prunedThrough = row?.pruned_through;
+        const latest = row?.latest;
+
+        if ((typeof prunedThrough === "number" || typeof prunedThrough === "bigint")
+            && (typeof latest === "number" || typeof latest === "bigint")) {
+            return { prunedThrough: Number(prunedThrough), latest: Number(latest) };
+        }
+    } catch {
+        // Databases migrated before tombstones existed have nothing to report.
+        return null;
+    }
+
+    return null;
+}
+
+function cursorWithinTombstones(syncCursor: SyncCursor, tombstones: TombstoneRange): SyncCursor {
+    const tables: SyncCursor["tables"] = {};
+    for (const [tableName, entry] of Object.entries(syncCursor.tables)) {
+        if (entry.last_seen_tombstone == null || entry.last_seen_tombstone >= tombstones.prunedThrough) {
+            tables[tableName] = entry;
+        }
+    }
+    return { tables };
+}
+
+function matchingCursor(
+    syncCursor: SyncCursor,
+    tableName: string,
+    permissionHash: unknown,
+): SyncCursor["tables"][string] | undefined {
+    const entry = syncCursor.tables[tableName];
+    return entry?.permission_hash === permissionHash ? entry : undefined;
+}
+
+/**
+ * Keys deleted from `tableName` after the cursor's tombstone, at most `pageSize` of them.
+ *
+ * Cursors from clients that predate tombstones get no keys, only the position to
+ * continue from.
+ */
+async function readDeletedKeys(
+    db: Client,
+    tableName: string,
+    lastSeenTombstone: number | null | undefined,
+    tombstones: TombstoneRange,
+    pageSize: number,
+): Promise<DeletedKeys> {
+    if (lastSeenTombstone == null) {
+        return { keys: [], lastSeenTombstone: tombstones.latest, hasMore: false };
+    }
+
+    const result = await db.execute({
+        sql: "select id, row_key from _pyre_tombstones where table_name = ? and id > ? and id <= ? order by id limit ?",
+        args: [tableName, lastSeenTombstone, tombstones.latest, pageSize + 1],
+    });
+
+    const rows = result.rows.slice(0, pageSize);
+    const hasMore = result.rows.length > pageSize;
+    return {
+        keys: rows.map((row) => JSON.parse(String(row.row_key))),
+        lastSeenTombstone: hasMore ? Number(rows[rows.length - 1].id) : tombstones.latest,
+        hasMore,
+    };
+}
+
 async function currentServerRevision(db: Client): Promise<number | null> {
     try {
         const result = await db.execute("select value from _pyre_sync where key = 'server_revision'");
@@ -202,8 +334,13 @@ export async function catchup(
     const effectivePageSize = normalizePageSize(pageSize);
     validateSyncCursor(syncCursor);
 
+    // A cursor behind the pruned tombstones may have missed deletes, so its table
+    // is resent from the start.
+    const tombstones = await tombstoneRange(db);
+    const resumableCursor = tombstones ? cursorWithinTombstones(syncCursor, tombstones) : syncCursor;
+
     // Step 1: Get sync status SQL
-    const statusStatement = wasm.get_sync_status_sql(syncCursor, session);
+    const statusStatement = wasm.get_sync_status_sql(resumableCursor, session);
     if (typeof statusStatement === "string" && statusStatement.startsWith("Error:")) {
         throw new Error(statusStatement);
     }
@@ -214,7 +351,7 @@ export async function catchup(
     const statusResult = await db.execute(statusParams.length > 0 ? { sql: statusSql, args: statusParams } : statusSql);
 
     // Step 3: Get sync SQL for tables that need syncing
-    const syncSqlResult = wasm.get_sync_sql(statusResult.rows, syncCursor, session, effectivePageSize);
+    const syncSqlResult = wasm.get_sync_sql(statusResult.rows, resumableCursor, session, effectivePageSize);
     if (typeof syncSqlResult === "string" && syncSqlResult.startsWith("Error:")) {
         throw new Error(syncSqlResult);
     }
@@ -230,17 +367,11 @@ export async function catchup(
         has_more: false,
     };
 
-    if (!Array.isArray(sqlResult.tables) || sqlResult.tables.length === 0) {
-        const serverRevision = await currentServerRevision(db);
-        if (serverRevision !== null) {
-            result.serverRevision = serverRevision;
-        }
-        return result;
-    }
+    const tableSqls: any[] = Array.isArray(sqlResult.tables) ? sqlResult.tables : [];
 
     // Collect all SQL statements for batch execution
     const allSqlStatements: any[] = [];
-    for (const tableSql of sqlResult.tables) {
+    for (const tableSql of tableSqls) {
         for (let index = 0; index < tableSql.sql.length; index += 1) {
             const params = normalizeParams(tableSql.params?.[index]);
             allSqlStatements.push(params.length > 0
@@ -251,11 +382,11 @@ export async function catchup(
     }
 
     // Execute all SQL statements in a single batch
-    const allQueryResults = await db.batch(allSqlStatements);
+    const allQueryResults = allSqlStatements.length > 0 ? await db.batch(allSqlStatements) : [];
 
     // Process results for each table
     let resultIndex = 0;
-    for (const tableSql of sqlResult.tables) {
+    for (const tableSql of tableSqls) {
         const updatedAtIndex = tableSql.headers.indexOf("updatedAt");
         const jsonColumns = new Set<string>(tableSql.json_columns ?? []);
         const tableRows: any[] = [];
@@ -317,17 +448,71 @@ export async function catchup(
             }
         }
 
+        const tableCursor = matchingCursor(resumableCursor, tableSql.table_name, tableSql.permission_hash);
+        const tableLastSeenKey = lastSeenKey(tableSql, finalRows, tableCursor);
         result.tables[tableSql.table_name] = {
             rows: reshapedRows,
             permission_hash: tableSql.permission_hash,
             last_seen_updated_at: maxUpdatedAt,
+            ...(tableLastSeenKey ? { last_seen_key: tableLastSeenKey } : {}),
         };
 
+        if (tombstones) {
+            const tableResult = result.tables[tableSql.table_name];
+            if (tableCursor) {
+                const deleted = await readDeletedKeys(db, tableSql.table_name, tableCursor.last_seen_tombstone, tombstones, effectivePageSize);
+                if (deleted.keys.length > 0) {
+                    tableResult.deleted = deleted.keys;
+                }
+                tableResult.last_seen_tombstone = deleted.lastSeenTombstone;
+                if (deleted.hasMore) {
+                    result.has_more = true;
+                }
+            } else {
+                tableResult.last_seen_tombstone = tombstones.latest;
+                if (syncCursor.tables[tableSql.table_name]) {
+                    tableResult.reset = true;
+                }
+            }
+        }
+
         if (hasMoreForTable) {
             result.has_more = true;
         }
     }
 
+    // Tables without new rows still need the keys deleted since their cursor.
+    if (tombstones) {
+        for (const status of statusResult.rows) {
+            const tableName = status.table_name;
+            if (typeof tableName !== "string" || result.tables[tableName]) {
+                continue;
+            }
+
+            const tableCursor = matchingCursor(resumableCursor, tableName, status.permission_hash);
+            if (!tableCursor) {
+                continue;
+            }
+
+            const deleted = await readDeletedKeys(db, tableName, tableCursor.last_seen_tombstone, tombstones, effectivePageSize);
+            if (deleted.keys.length === 0) {
+                continue;
+            }
+
+            if (deleted.hasMore) {
+                result.has_more = true;
+            }
+            result.tables[tableName] = {
+                rows: [],
+                permission_hash: tableCursor.permission_hash,
+                last_seen_updated_at: tableCursor.last_seen_updated_at,
+                ...(tableCursor.last_seen_key ? { last_seen_key: tableCursor.last_seen_key } : {}),
+                deleted: deleted.keys,
+                last_seen_tombstone: deleted.lastSeenTombstone,
+            };
+        }
+    }
+
     const serverRevision = await currentServerRevision(db);
     if (serverRevision !== null) {
         result.serverRevision = serverRevision;
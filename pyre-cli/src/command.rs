@@ -9,6 +9,8 @@ mod mcp;
 mod migrate;
 mod serve;
 mod shared;
+mod squash_migrations;
+mod sync;
 
 pub use check::check;
 pub use docs::docs;
@@ -20,5 +22,12 @@ pub use introspect::introspect;
 pub use mcp::mcp;
 pub use migrate::migrate;
 pub use migrate::push;
-pub use serve::{serve, ServeOptions};
+pub use migrate::verify;
+pub use serve::{
+    serve, ServeOptions, DEFAULT_CHANGE_LOG_POLL_MS, DEFAULT_CHANGE_LOG_RETENTION_SECONDS,
+    DEFAULT_CONNECTION_QUEUE_SIZE, DEFAULT_IDEMPOTENCY_RETENTION_SECONDS, DEFAULT_MANIFEST_HISTORY,
+    DEFAULT_MAX_OPEN_DATABASES, DEFAULT_TOMBSTONE_RETENTION_SECONDS,
+};
 pub use shared::Options;
+pub use squash_migrations::squash_migrations;
+pub use sync::{sync_inspect, InspectOptions};
@@ -41,18 +41,23 @@ pub async fn generate_migration<'a>(
             let introspection_result = db::introspect::introspect(&conn).await;
             match introspection_result {
                 Ok(introspection) => {
-                    let existing_migrations =
-                        db::read_migration_items(target_namespace_dir).unwrap_or(vec![]);
+                    let existing_migrations = db::read_migration_folder(target_namespace_dir)
+                        .map(|migrations| migrations.file_contents)
+                        .unwrap_or(vec![]);
+
+                    let applied_names: Vec<String> = match &introspection.migration_state {
+                        MigrationState::NoMigrationTable => vec![],
+                        MigrationState::MigrationTable { migrations } => {
+                            migrations.iter().map(|m| m.name.clone()).collect()
+                        }
+                    };
 
                     let not_applied: Vec<String> = existing_migrations
                         .iter()
-                        .filter(|migration| match &introspection.migration_state {
-                            MigrationState::NoMigrationTable => true,
-                            MigrationState::MigrationTable { migrations } => {
-                                !migrations.iter().any(|m| m.name == **migration)
-                            }
+                        .filter(|(name, sql)| {
+                            !pyre::db::migrate::is_migration_satisfied(name, sql, &applied_names)
                         })
-                        .map(|m| m.to_string())
+                        .map(|(name, _)| name.to_string())
                         .collect();
 
                     if !not_applied.is_empty() {
@@ -6,7 +6,7 @@ use std::path::{Component, Path, PathBuf};
 use std::process::Command;
 
 use super::docs::{find_doc, DocResource, DOC_RESOURCES};
-use super::shared::Options;
+use super::shared::{session_schema, Options};
 use crate::db;
 use pyre::server::manifest::{FieldSchema, Manifest, PyreSession, QueryManifest, SqlInfo};
 use pyre::{ast, format, generate, parser, typecheck};
@@ -507,7 +507,9 @@ async fn db_status(options: &Options<'_>, arguments: &JsonValue) -> Result<JsonV
         .await
         .map_err(|error| error.to_string())?;
 
-    let migration_files = db::read_migration_items(&namespace_migration_dir).unwrap_or_default();
+    let migration_files = db::read_migration_folder(&namespace_migration_dir)
+        .map(|migrations| migrations.file_contents)
+        .unwrap_or_default();
     let applied_migrations = match &introspection.migration_state {
         pyre::db::introspect::MigrationState::NoMigrationTable => Vec::new(),
         pyre::db::introspect::MigrationState::MigrationTable { migrations } => migrations
@@ -517,10 +519,32 @@ async fn db_status(options: &Options<'_>, arguments: &JsonValue) -> Result<JsonV
     };
     let pending_migrations = migration_files
         .iter()
-        .filter(|name| !applied_migrations.contains(name))
-        .cloned()
+        .filter(|(name, sql)| {
+            !pyre::db::migrate::is_migration_satisfied(name, sql, &applied_migrations)
+        })
+        .map(|(name, _)| name.clone())
         .collect::<Vec<_>>();
 
+    if let pyre::db::introspect::MigrationState::MigrationTable { .. } =
+        &introspection.migration_state
+    {
+        let applied = db::get_applied_migrations(&conn)
+            .await
+            .map_err(|error| error.format_error())?;
+        let changed = pyre::db::migrate::changed_migrations(&migration_files, &applied);
+        if !changed.is_empty() {
+            return Ok(json!({
+                "ok": false,
+                "accessible": true,
+                "status": "applied_migration_changed",
+                "namespace": namespace,
+                "appliedMigrations": applied_migrations,
+                "changedMigrations": changed,
+                "error": "Applied migration files no longer match the checksums recorded in the database. Create a new migration instead of editing an applied migration."
+            }));
+        }
+    }
+
     let mut schema_status = json!({ "checked": false });
     let status = if !pending_migrations.is_empty() {
         "pending_migrations"
@@ -758,7 +782,12 @@ fn dynamic_manifest(
 
     Ok(Manifest {
         version: 1,
-        session_schema: session_schema(database),
+        session_schema: session_schema(
+            database
+                .schemas
+                .iter()
+                .find_map(|schema| schema.session.clone()),
+        ),
         queries,
     })
 }
@@ -806,6 +835,7 @@ fn dynamic_query_manifest(
         json_input_args: Vec::new(),
         sql,
         sync_sql: None,
+        shapes: Vec::new(),
     })
 }
 
@@ -836,28 +866,6 @@ fn input_schema(query_info: &pyre::typecheck::QueryInfo) -> HashMap<String, Fiel
     schema
 }
 
-fn session_schema(database: &ast::Database) -> HashMap<String, FieldSchema> {
-    let session = database
-        .schemas
-        .iter()
-        .find_map(|schema| schema.session.clone())
-        .unwrap_or_else(ast::default_session_details);
-    let mut schema = HashMap::new();
-    for field in session.fields {
-        if let ast::Field::Column(column) = field {
-            schema.insert(
-                column.name,
-                FieldSchema {
-                    type_: column.type_.to_string(),
-                    nullable: column.nullable,
-                    omittable: false,
-                },
-            );
-        }
-    }
-    schema
-}
-
 fn query_param_names(query: &ast::Query, query_info: &pyre::typecheck::QueryInfo) -> Vec<String> {
     let mut names = Vec::new();
     for param in query_info.variables.values() {
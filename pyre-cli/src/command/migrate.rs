@@ -85,6 +85,37 @@ pub async fn migrate<'a>(
     Ok(())
 }
 
+pub async fn verify<'a>(
+    options: &'a Options<'a>,
+    database: &str,
+    auth: &Option<String>,
+    migration_dir: &str,
+    namespace: &Option<String>,
+) -> io::Result<()> {
+    check_namespace_requirements(&namespace, &options);
+    let namespace_migration_dir = match namespace {
+        Some(ns) => Path::new(migration_dir).join(ns),
+        None => Path::new(migration_dir).to_path_buf(),
+    };
+
+    match db::connect(&database.to_string(), auth).await {
+        Ok(conn) => match db::verify(&conn, &namespace_migration_dir).await {
+            Ok(outcome) => {
+                println!("{}", outcome.status_line());
+            }
+            Err(migration_error) => {
+                println!("{}", migration_error.format_error());
+                std::process::exit(1);
+            }
+        },
+        Err(err) => {
+            println!("{}", err.format_error());
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
 /**
  * This is the new "dynamic" migration approach
  *
@@ -1,37 +1,30 @@
-use axum::extract::{Path as AxumPath, Query, State};
-use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
-use axum::response::sse::{Event, KeepAlive, Sse};
-use axum::response::{IntoResponse, Response};
-use axum::routing::{get, post};
-use axum::{Json, Router};
-use base64::engine::general_purpose::URL_SAFE_NO_PAD;
-use base64::Engine;
-use hmac::{Hmac, Mac};
+use pyre::server::database_id::{DatabaseId, DatabaseResolver};
+use pyre::server::http::{
+    ChangeLogConfig, HttpConfig, HttpServer, OpenDatabase, SessionSource, DEFAULT_SESSION_HEADER,
+};
+use pyre::server::jwt::{parse_jwks, JwtKey, JwtVerifier};
 use pyre::server::manifest::{Manifest, PyreSession};
-use pyre::server::schema::{load_schema_from_database, LoadedSchema};
-use pyre::server::sync::{ConnectedSessions, SyncServer};
-use pyre::sync::SyncCursor;
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value as JsonValue};
-use sha2::Sha256;
+use serde_json::Value as JsonValue;
 use std::collections::HashMap;
-use std::convert::Infallible;
 use std::io;
 use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::{mpsc, Mutex};
+use std::time::Duration;
 
 use super::shared::Options;
 use crate::db;
 
-type HmacSha256 = Hmac<Sha256>;
-
-const DEFAULT_SESSION_HEADER: &str = "x-pyre-session";
+pub use pyre::server::http::{
+    DEFAULT_CHANGE_LOG_POLL_MS, DEFAULT_CHANGE_LOG_RETENTION_SECONDS,
+    DEFAULT_CONNECTION_QUEUE_SIZE, DEFAULT_MANIFEST_HISTORY, DEFAULT_MAX_OPEN_DATABASES,
+    DEFAULT_TOMBSTONE_RETENTION_SECONDS,
+};
+pub use pyre::server::idempotency::DEFAULT_IDEMPOTENCY_RETENTION_SECONDS;
 
 pub struct ServeOptions<'a> {
-    pub database: &'a str,
+    pub database: &'a Option<String>,
+    pub database_map: &'a Option<String>,
     pub auth: &'a Option<String>,
     pub host: &'a str,
     pub port: u16,
@@ -39,102 +32,57 @@ pub struct ServeOptions<'a> {
     pub database_id: &'a str,
     pub session_header: &'a Option<String>,
     pub session_secret: &'a Option<String>,
+    pub jwt_secret: &'a Option<String>,
+    pub jwks: &'a Option<String>,
+    pub jwt_issuer: &'a Option<String>,
+    pub jwt_audience: &'a Option<String>,
+    pub jwt_claims: &'a Vec<String>,
     pub dev_session: &'a Option<String>,
     pub cors_origins: &'a Vec<String>,
+    pub admin_token: &'a Option<String>,
     pub page_size: usize,
+    pub queue_size: usize,
+    pub change_log: bool,
+    pub change_log_poll_ms: u64,
+    pub change_log_retention: u64,
+    pub tombstone_retention: u64,
+    pub idempotency_retention: u64,
+    pub max_open_databases: usize,
+    pub previous_manifests: &'a Vec<String>,
+    pub manifest_history: usize,
     pub allow_unsafe_dev_session: bool,
     pub allow_unsafe_unsigned_session: bool,
 }
 
-#[derive(Clone, Debug)]
-enum SessionSource {
-    Empty,
-    Dev(JsonValue),
-    Header {
-        name: String,
-        secret: Option<String>,
-    },
-}
-
-struct AppState {
-    db: libsql::Database,
-    manifest: Manifest,
-    loaded_schema: LoadedSchema,
-    database_id: String,
-    session_source: SessionSource,
-    page_size: usize,
-    connections: Mutex<HashMap<String, Connection>>,
-    cors_origins: Vec<String>,
-}
-
-struct Connection {
-    session: HashMap<String, pyre::sync::SessionValue>,
-    sender: mpsc::UnboundedSender<JsonValue>,
-}
-
-#[derive(Deserialize)]
-struct SyncRequest {
-    #[serde(rename = "databaseId")]
-    database_id: Option<String>,
-    #[serde(rename = "syncCursor")]
-    sync_cursor: SyncCursor,
-}
-
-#[derive(Deserialize)]
-struct RequestQuery {
-    #[serde(rename = "databaseId")]
-    database_id: Option<String>,
-    #[serde(rename = "connectionId")]
-    connection_id: Option<String>,
-    sync: Option<String>,
-}
-
-#[derive(Serialize)]
-struct HealthResponse<'a> {
-    ok: bool,
-    #[serde(rename = "databaseId")]
-    database_id: &'a str,
-}
-
-#[derive(Deserialize)]
-struct SignedSessionPayload {
-    session: JsonValue,
-    exp: i64,
-}
+/// Prints the server's log messages: info to stdout, warnings and errors to stderr.
+struct ServeLogger;
 
-#[derive(Debug)]
-enum ServeError {
-    BadRequest(String),
-    Unauthorized(String),
-    Internal(String),
-}
-
-impl ServeError {
-    fn status(&self) -> StatusCode {
-        match self {
-            ServeError::BadRequest(_) => StatusCode::BAD_REQUEST,
-            ServeError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            ServeError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        }
+impl log::Log for ServeLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info && metadata.target().starts_with("pyre")
     }
 
-    fn message(&self) -> &str {
-        match self {
-            ServeError::BadRequest(message)
-            | ServeError::Unauthorized(message)
-            | ServeError::Internal(message) => message,
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            log::Level::Info => println!("{}", record.args()),
+            _ => eprintln!("{}", record.args()),
         }
     }
-}
 
-impl IntoResponse for ServeError {
-    fn into_response(self) -> Response {
-        let status = self.status();
-        (status, Json(json!({ "error": self.message() }))).into_response()
-    }
+    fn flush(&self) {}
 }
 
+static LOGGER: ServeLogger = ServeLogger;
+
 pub async fn serve<'a>(_: &'a Options<'a>, options: ServeOptions<'a>) -> io::Result<()> {
+    // Only fails when a logger is already set, which is then used instead.
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+
     let host: IpAddr = options.host.parse().map_err(|error| {
         io::Error::new(
             io::ErrorKind::InvalidInput,
@@ -144,15 +92,7 @@ pub async fn serve<'a>(_: &'a Options<'a>, options: ServeOptions<'a>) -> io::Res
     let addr = SocketAddr::from((host, options.port));
     let loopback = host.is_loopback();
 
-    let db = db::connect(&options.database.to_string(), options.auth)
-        .await
-        .map_err(|error| io::Error::new(io::ErrorKind::Other, error.format_error()))?;
-    let conn = db.connect().map_err(|error| {
-        io::Error::new(io::ErrorKind::Other, format!("database error: {}", error))
-    })?;
-    let loaded_schema = load_schema_from_database(&conn)
-        .await
-        .map_err(|error| io::Error::new(io::ErrorKind::Other, error.to_string()))?;
+    let resolver = database_resolver(&options)?;
     let manifest_path = PathBuf::from(options.generated).join("manifest.json");
     let manifest = Manifest::load(&manifest_path).map_err(|error| {
         io::Error::new(
@@ -166,26 +106,69 @@ pub async fn serve<'a>(_: &'a Options<'a>, options: ServeOptions<'a>) -> io::Res
     })?;
 
     let session_source = session_source(&manifest, &options, loopback)?;
-    let state = Arc::new(AppState {
-        db,
-        manifest,
-        loaded_schema,
-        database_id: require_non_empty(options.database_id, "databaseId")?,
-        session_source,
-        page_size: options.page_size,
-        connections: Mutex::new(HashMap::new()),
-        cors_origins: options.cors_origins.clone(),
+    let mut previous_manifests = Vec::new();
+    for path in options.previous_manifests {
+        let previous = Manifest::load(path).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("failed to load --previous-manifest {}: {}", path, error),
+            )
+        })?;
+        previous_manifests.push(previous);
+    }
+
+    let auth = options.auth.clone();
+    let open_database: OpenDatabase = Arc::new(move |database: String| {
+        let auth = auth.clone();
+        Box::pin(async move {
+            db::connect(&database, &auth)
+                .await
+                .map_err(|error| error.format_error())
+        })
     });
+    let default_database_id = resolver.default_database_id().map(str::to_string);
+    let mut config = HttpConfig::new(manifest, resolver.clone()).with_session(session_source);
+    config.open_database = open_database;
+    config.manifest_path = Some(manifest_path);
+    config.previous_manifests = previous_manifests;
+    config.manifest_history = options.manifest_history;
+    config.cors_origins = options.cors_origins.clone();
+    config.admin_token = options.admin_token.clone();
+    config.page_size = options.page_size;
+    config.queue_size = options.queue_size;
+    config.change_log = options.change_log.then(|| ChangeLogConfig {
+        poll_interval: Duration::from_millis(options.change_log_poll_ms),
+        retention_seconds: options.change_log_retention,
+    });
+    config.tombstone_retention_seconds = options.tombstone_retention;
+    config.idempotency_retention_seconds = options.idempotency_retention;
+    config.max_open_databases = options.max_open_databases;
+    let server = HttpServer::new(config);
+
+    // A single database is opened up front so startup fails fast, routed ones open on demand.
+    if let Some(database_id) = &default_database_id {
+        server
+            .open_database(database_id)
+            .await
+            .map_err(|error| io::Error::new(io::ErrorKind::Other, error.message().to_string()))?;
+    }
 
-    let app = Router::new()
-        .route("/health", get(health).options(cors_preflight))
-        .route("/sync", post(sync).options(cors_preflight))
-        .route("/sync/events", get(sync_events).options(cors_preflight))
-        .route("/db/:query_id", post(run_query).options(cors_preflight))
-        .with_state(state);
+    tokio::spawn(server.watch_for_changes());
+    let app = server.router::<()>();
 
     println!("Pyre server listening on http://{}", addr);
-    println!("Database ID: {}", options.database_id);
+    match &resolver {
+        DatabaseResolver::Fixed { database_id, .. } => println!("Database ID: {}", database_id),
+        DatabaseResolver::Template(template) => println!("Databases: {}", template),
+        DatabaseResolver::Directory(directory) => {
+            println!("Databases: {}/{{databaseId}}.db", directory.display())
+        }
+        DatabaseResolver::Map(databases) => {
+            let mut database_ids = databases.keys().cloned().collect::<Vec<_>>();
+            database_ids.sort();
+            println!("Database IDs: {}", database_ids.join(", "))
+        }
+    }
     println!("SSE endpoint: http://{}/sync/events", addr);
 
     axum::Server::bind(&addr)
@@ -194,11 +177,55 @@ pub async fn serve<'a>(_: &'a Options<'a>, options: ServeOptions<'a>) -> io::Res
         .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
 }
 
+fn database_resolver(options: &ServeOptions<'_>) -> io::Result<DatabaseResolver> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    match (options.database, options.database_map) {
+        (Some(_), Some(_)) => Err(invalid(
+            "pass either a database or --database-map, not both".to_string(),
+        )),
+        (None, None) => Err(invalid(
+            "a database or --database-map is required".to_string(),
+        )),
+        (Some(database), None) => {
+            DatabaseResolver::from_database_arg(database, options.database_id)
+                .map_err(|error| invalid(error.to_string()))
+        }
+        (None, Some(path)) => {
+            let raw = std::fs::read_to_string(path)
+                .map_err(|error| invalid(format!("failed to read {}: {}", path, error)))?;
+            let databases: HashMap<DatabaseId, String> =
+                serde_json::from_str(&raw).map_err(|error| {
+                    invalid(format!(
+                        "{} should be a JSON object of databaseId to database: {}",
+                        path, error
+                    ))
+                })?;
+            if databases.is_empty() {
+                return Err(invalid(format!("{} doesn't list any databases", path)));
+            }
+            Ok(DatabaseResolver::Map(databases))
+        }
+    }
+}
+
 fn session_source(
     manifest: &Manifest,
     options: &ServeOptions<'_>,
     loopback: bool,
 ) -> io::Result<SessionSource> {
+    if options.jwt_secret.is_some() || options.jwks.is_some() {
+        if options.dev_session.is_some()
+            || options.session_header.is_some()
+            || options.session_secret.is_some()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "JWT sessions can't be combined with --dev-session, --session-header or --session-secret",
+            ));
+        }
+        return jwt_verifier(manifest, options).map(SessionSource::Jwt);
+    }
+
     if let Some(raw_session) = options.dev_session {
         if !loopback && !options.allow_unsafe_dev_session {
             return Err(io::Error::new(
@@ -250,313 +277,52 @@ fn session_source(
         Err(io::Error::new(
             io::ErrorKind::InvalidInput,
             format!(
-                "This Pyre schema requires session data.\n\nFor local development:\n  pyre serve {} --dev-session '{{...}}'\n\nFor production, run behind authenticated upstream infrastructure and pass:\n  --session-header {} --session-secret <secret>",
-                options.database, DEFAULT_SESSION_HEADER
+                "This Pyre schema requires session data.\n\nFor local development:\n  pyre serve {} --dev-session '{{...}}'\n\nFor production, run behind authenticated upstream infrastructure and pass:\n  --session-header {} --session-secret <secret>\n\nOr verify bearer JWTs from your identity provider with:\n  --jwks <file> or --jwt-secret <secret>",
+                options.database.as_deref().unwrap_or("<database>"),
+                DEFAULT_SESSION_HEADER
             ),
         ))
     }
 }
 
-async fn health(State(state): State<Arc<AppState>>, headers: HeaderMap) -> impl IntoResponse {
-    with_cors(
-        &state,
-        &headers,
-        Json(HealthResponse {
-            ok: true,
-            database_id: &state.database_id,
-        })
-        .into_response(),
-    )
-}
-
-async fn cors_preflight(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
-    with_cors(&state, &headers, StatusCode::NO_CONTENT.into_response())
-}
-
-async fn sync(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-    Json(body): Json<SyncRequest>,
-) -> Result<Response, ServeError> {
-    ensure_database_id(&state, body.database_id.as_deref())?;
-    let session = pyre_session_from_request(&state, &headers)?;
-    let conn = state
-        .db
-        .connect()
-        .map_err(|error| ServeError::Internal(format!("database error: {}", error)))?;
-    let context = state
-        .loaded_schema
-        .context()
-        .map_err(|error| ServeError::Internal(error.to_string()))?;
-    let server = SyncServer::new(context);
-    let result = server
-        .catchup(
-            &conn,
-            &body.sync_cursor,
-            session.logical(),
-            state.page_size,
-            &state.database_id,
-        )
-        .await
-        .map_err(|error| ServeError::Internal(error.to_string()))?;
-
-    Ok(with_cors(&state, &headers, Json(result).into_response()))
-}
-
-async fn sync_events(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-    Query(query): Query<RequestQuery>,
-) -> Result<Response, ServeError> {
-    ensure_database_id(&state, query.database_id.as_deref())?;
-    let session = pyre_session_from_request(&state, &headers)?;
-    let session_id = new_connection_id();
-    let (sender, mut receiver) = mpsc::unbounded_channel();
-    state.connections.lock().await.insert(
-        session_id.clone(),
-        Connection {
-            session: session.logical().clone(),
-            sender,
-        },
-    );
-
-    let connected = json!({
-        "type": "connected",
-        "sessionId": session_id,
-        "connectionId": session_id,
-        "databaseId": state.database_id,
-    });
-    let cleanup = ConnectionCleanup {
-        state: Arc::clone(&state),
-        session_id: session_id.clone(),
-    };
-
-    let stream = async_stream::stream! {
-        let _cleanup = cleanup;
-        yield Ok::<_, Infallible>(Event::default().json_data(connected).unwrap_or_else(|_| Event::default()));
-        while let Some(message) = receiver.recv().await {
-            yield Ok::<_, Infallible>(Event::default().json_data(message).unwrap_or_else(|_| Event::default()));
-        }
-    };
-
-    let response = Sse::new(stream)
-        .keep_alive(KeepAlive::default())
-        .into_response();
-    Ok(with_cors(&state, &headers, response))
-}
-
-async fn run_query(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-    Query(query): Query<RequestQuery>,
-    AxumPath(query_id): AxumPath<String>,
-    Json(input): Json<JsonValue>,
-) -> Result<Response, ServeError> {
-    ensure_database_id(&state, query.database_id.as_deref())?;
-    let session = pyre_session_from_request(&state, &headers)?;
-    let conn = state
-        .db
-        .connect()
-        .map_err(|error| ServeError::Internal(format!("database error: {}", error)))?;
-    let mut result = if query.sync.as_deref() == Some("true") {
-        pyre::server::query::run_sync(&conn, &state.manifest, &query_id, input, &session).await
-    } else {
-        pyre::server::query::run(&conn, &state.manifest, &query_id, input, &session).await
-    }
-    .map_err(|error| ServeError::BadRequest(error.to_string()))?;
-
-    if query.sync.as_deref() == Some("true") {
-        let connected_sessions = connected_sessions(&state).await;
-        let context = state
-            .loaded_schema
-            .context()
-            .map_err(|error| ServeError::Internal(error.to_string()))?;
-        let server = SyncServer::new(context);
-        let messages = server
-            .calculate_deltas(
-                &conn,
-                &mut result,
-                &connected_sessions,
-                &state.database_id,
-                query.connection_id.as_deref(),
-            )
-            .await
-            .map_err(|error| ServeError::Internal(error.to_string()))?;
-        send_messages(&state, messages).await;
-    }
-
-    Ok(with_cors(
-        &state,
-        &headers,
-        Json(result.response).into_response(),
-    ))
-}
-
-async fn connected_sessions(state: &AppState) -> ConnectedSessions {
-    state
-        .connections
-        .lock()
-        .await
-        .iter()
-        .map(|(id, connection)| (id.clone(), connection.session.clone()))
-        .collect()
-}
-
-async fn send_messages(state: &AppState, messages: Vec<pyre::server::sync::SessionDeltaMessage>) {
-    let connections = state.connections.lock().await;
-    for message in messages {
-        if let Some(connection) = connections.get(&message.session_id) {
-            if let Ok(value) = serde_json::to_value(message.message) {
-                let _ = connection.sender.send(value);
-            }
-        }
-    }
-}
-
-struct ConnectionCleanup {
-    state: Arc<AppState>,
-    session_id: String,
-}
-
-impl Drop for ConnectionCleanup {
-    fn drop(&mut self) {
-        let state = Arc::clone(&self.state);
-        let session_id = self.session_id.clone();
-        tokio::spawn(async move {
-            state.connections.lock().await.remove(&session_id);
+fn jwt_verifier(manifest: &Manifest, options: &ServeOptions<'_>) -> io::Result<JwtVerifier> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let mut keys = Vec::new();
+    if let Some(secret) = options.jwt_secret {
+        keys.push(JwtKey::Hmac {
+            kid: None,
+            secret: secret.as_bytes().to_vec(),
         });
     }
-}
-
-fn pyre_session_from_request(
-    state: &AppState,
-    headers: &HeaderMap,
-) -> Result<PyreSession, ServeError> {
-    let value = match &state.session_source {
-        SessionSource::Empty => JsonValue::Object(serde_json::Map::new()),
-        SessionSource::Dev(value) => value.clone(),
-        SessionSource::Header { name, secret } => {
-            let raw = headers
-                .get(name)
-                .ok_or_else(|| ServeError::Unauthorized(format!("missing {} header", name)))?
-                .to_str()
-                .map_err(|_| ServeError::Unauthorized(format!("invalid {} header", name)))?;
-            if let Some(secret) = secret {
-                decode_signed_session(raw, secret)?
-            } else {
-                decode_unsigned_session(raw)?
-            }
-        }
-    };
-
-    PyreSession::new(value, &state.manifest.session_schema)
-        .map_err(|error| ServeError::Unauthorized(format!("invalid Pyre session: {}", error)))
-}
-
-fn decode_unsigned_session(raw: &str) -> Result<JsonValue, ServeError> {
-    let bytes = URL_SAFE_NO_PAD
-        .decode(raw)
-        .map_err(|_| ServeError::Unauthorized("invalid session header encoding".to_string()))?;
-    serde_json::from_slice(&bytes)
-        .map_err(|_| ServeError::Unauthorized("invalid session header JSON".to_string()))
-}
-
-fn decode_signed_session(raw: &str, secret: &str) -> Result<JsonValue, ServeError> {
-    let Some((payload, signature)) = raw.split_once('.') else {
-        return Err(ServeError::Unauthorized(
-            "signed session header must contain payload and signature".to_string(),
-        ));
-    };
-    let signature = URL_SAFE_NO_PAD
-        .decode(signature)
-        .map_err(|_| ServeError::Unauthorized("invalid session signature encoding".to_string()))?;
-    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-        .map_err(|_| ServeError::Internal("invalid session secret".to_string()))?;
-    mac.update(payload.as_bytes());
-    mac.verify_slice(&signature)
-        .map_err(|_| ServeError::Unauthorized("invalid session signature".to_string()))?;
-
-    let payload_bytes = URL_SAFE_NO_PAD
-        .decode(payload)
-        .map_err(|_| ServeError::Unauthorized("invalid session payload encoding".to_string()))?;
-    let payload: SignedSessionPayload = serde_json::from_slice(&payload_bytes)
-        .map_err(|_| ServeError::Unauthorized("invalid session payload JSON".to_string()))?;
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|_| ServeError::Internal("system clock is before unix epoch".to_string()))?
-        .as_secs() as i64;
-    if payload.exp <= now {
-        return Err(ServeError::Unauthorized(
-            "session header is expired".to_string(),
-        ));
+    if let Some(path) = options.jwks {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|error| invalid(format!("failed to read {}: {}", path, error)))?;
+        keys.extend(parse_jwks(&raw).map_err(|error| invalid(format!("{}: {}", path, error)))?);
     }
-    Ok(payload.session)
-}
 
-fn ensure_database_id(state: &AppState, value: Option<&str>) -> Result<(), ServeError> {
-    if let Some(value) = value {
-        if value != state.database_id {
-            return Err(ServeError::BadRequest(format!(
-                "databaseId '{}' does not match this server's databaseId '{}'",
-                value, state.database_id
+    let mut claim_paths = HashMap::new();
+    for mapping in options.jwt_claims {
+        let Some((field, path)) = mapping.split_once('=') else {
+            return Err(invalid(format!(
+                "invalid --jwt-claim '{}', expected <sessionField>=<claim.path>",
+                mapping
+            )));
+        };
+        if !manifest.session_schema.contains_key(field) {
+            return Err(invalid(format!(
+                "--jwt-claim '{}' doesn't name a session field",
+                mapping
             )));
         }
+        claim_paths.insert(field.to_string(), path.to_string());
     }
-    Ok(())
-}
-
-fn require_non_empty(value: &str, label: &str) -> io::Result<String> {
-    if value.trim().is_empty() {
-        Err(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            format!("{} is required", label),
-        ))
-    } else {
-        Ok(value.to_string())
-    }
-}
 
-fn new_connection_id() -> String {
-    static NEXT_CONNECTION_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
-    let id = NEXT_CONNECTION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-    format!("conn_{}", id)
-}
-
-fn allowed_cors_origin<'a>(state: &'a AppState, headers: &HeaderMap) -> Option<&'a str> {
-    allowed_cors_origin_for(&state.cors_origins, headers)
-}
-
-fn allowed_cors_origin_for<'a>(cors_origins: &'a [String], headers: &HeaderMap) -> Option<&'a str> {
-    if cors_origins.is_empty() {
-        return None;
-    }
-    let request_origin = headers.get(header::ORIGIN)?.to_str().ok()?;
-
-    cors_origins
-        .iter()
-        .find(|origin| origin.as_str() == request_origin)
-        .map(String::as_str)
-}
-
-fn with_cors(state: &AppState, request_headers: &HeaderMap, mut response: Response) -> Response {
-    let Some(origin) = allowed_cors_origin(state, request_headers) else {
-        return response;
-    };
-
-    let headers = response.headers_mut();
-    if let Ok(value) = HeaderValue::from_str(&origin) {
-        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
-    }
-    headers.insert(header::VARY, HeaderValue::from_static("Origin"));
-    headers.insert(
-        header::ACCESS_CONTROL_ALLOW_METHODS,
-        HeaderValue::from_static("GET, POST, OPTIONS"),
-    );
-    headers.insert(
-        header::ACCESS_CONTROL_ALLOW_HEADERS,
-        HeaderValue::from_static("content-type, x-pyre-session"),
-    );
-    response
+    Ok(JwtVerifier {
+        keys,
+        issuer: options.jwt_issuer.clone(),
+        audience: options.jwt_audience.clone(),
+        claim_paths,
+    })
 }
 
 #[cfg(test)]
@@ -579,11 +345,16 @@ mod tests {
         }
     }
 
+    static NO_JWT_CLAIMS: Vec<String> = Vec::new();
+
+    static NO_PREVIOUS_MANIFESTS: Vec<String> = Vec::new();
+
     fn empty_auth() -> Option<String> {
         None
     }
 
     fn serve_options<'a>(
+        database: &'a Option<String>,
         auth: &'a Option<String>,
         session_header: &'a Option<String>,
         session_secret: &'a Option<String>,
@@ -591,7 +362,8 @@ mod tests {
         cors_origins: &'a Vec<String>,
     ) -> ServeOptions<'a> {
         ServeOptions {
-            database: "db.sqlite",
+            database,
+            database_map: &None,
             auth,
             host: "127.0.0.1",
             port: 3000,
@@ -599,44 +371,39 @@ mod tests {
             database_id: "default",
             session_header,
             session_secret,
+            jwt_secret: &None,
+            jwks: &None,
+            jwt_issuer: &None,
+            jwt_audience: &None,
+            jwt_claims: &NO_JWT_CLAIMS,
             dev_session,
             cors_origins,
+            admin_token: &None,
             page_size: 1000,
+            queue_size: DEFAULT_CONNECTION_QUEUE_SIZE,
+            change_log: false,
+            change_log_poll_ms: DEFAULT_CHANGE_LOG_POLL_MS,
+            change_log_retention: DEFAULT_CHANGE_LOG_RETENTION_SECONDS,
+            tombstone_retention: DEFAULT_TOMBSTONE_RETENTION_SECONDS,
+            idempotency_retention: DEFAULT_IDEMPOTENCY_RETENTION_SECONDS,
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            previous_manifests: &NO_PREVIOUS_MANIFESTS,
+            manifest_history: DEFAULT_MANIFEST_HISTORY,
             allow_unsafe_dev_session: false,
             allow_unsafe_unsigned_session: false,
         }
     }
 
-    #[test]
-    fn unsigned_session_header_contains_full_session_json() {
-        let encoded = URL_SAFE_NO_PAD.encode(r#"{"userId":123}"#);
-        let session = decode_unsigned_session(&encoded).expect("decoded session");
-
-        assert_eq!(session["userId"], json!(123));
-    }
-
-    #[test]
-    fn signed_session_header_verifies_signature_and_expiration() {
-        let payload = URL_SAFE_NO_PAD.encode(r#"{"session":{"userId":123},"exp":4102444800}"#);
-        let mut mac = HmacSha256::new_from_slice(b"secret").expect("hmac");
-        mac.update(payload.as_bytes());
-        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
-        let raw = format!("{}.{}", payload, signature);
-
-        let session = decode_signed_session(&raw, "secret").expect("decoded session");
-
-        assert_eq!(session["userId"], json!(123));
-        assert!(decode_signed_session(&raw, "wrong-secret").is_err());
-    }
-
     #[test]
     fn non_loopback_dev_session_requires_explicit_unsafe_flag() {
+        let database = Some("db.sqlite".to_string());
         let auth = empty_auth();
         let session_header = None;
         let session_secret = None;
         let dev_session = Some(r#"{"userId":1}"#.to_string());
         let cors_origins = Vec::new();
         let options = serve_options(
+            &database,
             &auth,
             &session_header,
             &session_secret,
@@ -652,12 +419,14 @@ mod tests {
 
     #[test]
     fn schema_with_session_requires_session_source() {
+        let database = Some("db.sqlite".to_string());
         let auth = empty_auth();
         let session_header = None;
         let session_secret = None;
         let dev_session = None;
         let cors_origins = Vec::new();
         let options = serve_options(
+            &database,
             &auth,
             &session_header,
             &session_secret,
@@ -672,32 +441,45 @@ mod tests {
     }
 
     #[test]
-    fn cors_echoes_only_matching_request_origin() {
-        let cors_origins = vec![
-            "http://localhost:5173".to_string(),
-            "http://localhost:3001".to_string(),
-        ];
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::ORIGIN,
-            HeaderValue::from_static("http://localhost:5173"),
-        );
-
-        assert_eq!(
-            allowed_cors_origin_for(&cors_origins, &headers),
-            Some("http://localhost:5173")
+    fn jwt_sessions_reject_other_session_sources_and_unknown_claims() {
+        let database = Some("db.sqlite".to_string());
+        let auth = empty_auth();
+        let session_header = None;
+        let session_secret = None;
+        let dev_session = Some(r#"{"userId":1}"#.to_string());
+        let cors_origins = Vec::new();
+        let jwt_secret = Some("secret".to_string());
+        let mut options = serve_options(
+            &database,
+            &auth,
+            &session_header,
+            &session_secret,
+            &dev_session,
+            &cors_origins,
         );
-    }
+        options.jwt_secret = &jwt_secret;
 
-    #[test]
-    fn cors_ignores_unlisted_request_origin() {
-        let cors_origins = vec!["http://localhost:5173".to_string()];
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            header::ORIGIN,
-            HeaderValue::from_static("http://evil.example"),
-        );
+        let error = session_source(&manifest_with_session(), &options, true)
+            .expect_err("expected conflicting session sources");
+        assert!(error.to_string().contains("can't be combined"));
 
-        assert_eq!(allowed_cors_origin_for(&cors_origins, &headers), None);
+        let no_dev_session = None;
+        let claims = vec!["userId=sub".to_string(), "tenant=org_id".to_string()];
+        options.dev_session = &no_dev_session;
+        options.jwt_claims = &claims;
+        let error = session_source(&manifest_with_session(), &options, false)
+            .expect_err("expected unknown session field");
+        assert!(error.to_string().contains("tenant=org_id"));
+
+        let claims = vec!["userId=sub".to_string()];
+        options.jwt_claims = &claims;
+        let source = session_source(&manifest_with_session(), &options, false)
+            .expect("JWT sessions are allowed on non-loopback addresses");
+        match source {
+            SessionSource::Jwt(verifier) => {
+                assert_eq!(verifier.claim_paths.get("userId").unwrap(), "sub")
+            }
+            other => panic!("expected a JWT session source, got {:?}", other),
+        }
     }
 }
@@ -4,6 +4,8 @@ use pyre::error;
 use pyre::filesystem;
 use pyre::generate;
 use pyre::parser;
+use pyre::server::manifest::FieldSchema;
+use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::Path;
@@ -225,3 +227,22 @@ pub fn get_stdin() -> io::Result<Option<String>> {
         }
     }
 }
+
+/// The session fields a `PyreSession` is validated against, as `manifest.json` lists them.
+pub fn session_schema(session: Option<ast::SessionDetails>) -> HashMap<String, FieldSchema> {
+    let session = session.unwrap_or_else(ast::default_session_details);
+    let mut schema = HashMap::new();
+    for field in session.fields {
+        if let ast::Field::Column(column) = field {
+            schema.insert(
+                column.name,
+                FieldSchema {
+                    type_: column.type_.to_string(),
+                    nullable: column.nullable,
+                    omittable: false,
+                },
+            );
+        }
+    }
+    schema
+}
@@ -0,0 +1,30 @@
+use std::io;
+use std::path::Path;
+
+use super::shared::{check_namespace_requirements, Options};
+use crate::db;
+
+pub async fn squash_migrations<'a>(
+    options: &'a Options<'a>,
+    until: &str,
+    migration_dir: &Path,
+    namespace: &Option<String>,
+) -> io::Result<()> {
+    check_namespace_requirements(&namespace, &options);
+
+    let namespace_migration_dir = match namespace {
+        Some(ns) => migration_dir.join(ns),
+        None => migration_dir.to_path_buf(),
+    };
+
+    match db::squash(&namespace_migration_dir, until).await {
+        Ok(outcome) => {
+            println!("{}", outcome.status_line());
+        }
+        Err(migration_error) => {
+            println!("{}", migration_error.format_error());
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::io;
+
+use super::shared::{session_schema, Options};
+use crate::db;
+use pyre::server::manifest::PyreSession;
+use pyre::server::schema::load_schema_from_database;
+use pyre::server::sync_inspect::{self, ConditionOutcome, RowInspection, TableInspection};
+use pyre::sync::{self, SessionValue};
+
+pub struct InspectOptions<'a> {
+    pub database: &'a str,
+    pub auth: &'a Option<String>,
+    pub session: &'a str,
+    pub row: &'a Option<String>,
+    pub sql: bool,
+    pub page_size: usize,
+}
+
+/// Print what `--session` receives from catchup for each synced table, or why one
+/// `--row` is or isn't visible to it.
+pub async fn sync_inspect<'a>(
+    options: &'a Options<'a>,
+    inspect: InspectOptions<'a>,
+) -> io::Result<()> {
+    let conn = match db::connect(&inspect.database.to_string(), inspect.auth).await {
+        Ok(db) => db.connect().map_err(io::Error::other)?,
+        Err(err) => {
+            println!("{}", err.format_error());
+            std::process::exit(1);
+        }
+    };
+    if let Err(message) = run_inspect(options, &conn, &inspect).await {
+        println!(
+            "{}",
+            pyre::error::format_custom_error("Sync Inspect Error", &message)
+        );
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn run_inspect(
+    options: &Options<'_>,
+    conn: &libsql::Connection,
+    inspect: &InspectOptions<'_>,
+) -> Result<(), String> {
+    let loaded = load_schema_from_database(conn)
+        .await
+        .map_err(|error| format!("Could not load the schema: {}", error))?;
+    let context = loaded
+        .context()
+        .map_err(|error| format!("Could not load the schema: {}", error))?;
+
+    let session_value: serde_json::Value = serde_json::from_str(inspect.session)
+        .map_err(|error| format!("Invalid --session JSON: {}", error))?;
+    let session = PyreSession::new(session_value, &session_schema(context.session.clone()))
+        .map_err(|error| format!("Invalid --session: {}", error))?;
+
+    match inspect.row {
+        Some(row) => {
+            let (table, id) = row
+                .split_once(':')
+                .ok_or_else(|| "--row should look like Table:id".to_string())?;
+            let inspection = sync_inspect::inspect_row(conn, context, session.logical(), table, id)
+                .await
+                .map_err(|error| error.to_string())?;
+            print_row(options, &inspection);
+        }
+        None => {
+            let tables =
+                sync_inspect::inspect_tables(conn, context, session.logical(), inspect.page_size)
+                    .await
+                    .map_err(|error| error.to_string())?;
+            if tables.is_empty() {
+                println!("No tables are synced.");
+            }
+            for table in &tables {
+                print_table(options, table, inspect.sql);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_table(options: &Options, table: &TableInspection, sql: bool) {
+    println!(
+        "{} {}",
+        pyre::color::cyan(options.enable_color, &table.table_name),
+        pyre::color::gray(
+            options.enable_color,
+            &format!("(sync layer {})", table.sync_layer)
+        )
+    );
+    println!("  permission hash  {}", table.permission_hash);
+    match &table.permission {
+        Some(permission) => {
+            println!("  permission       {}", permission.sql);
+            if !permission.params.is_empty() {
+                println!("  params           {}", format_params(&permission.params));
+            }
+        }
+        None => println!("  permission       none, every row is visible"),
+    }
+    println!(
+        "  rows             {} of {} visible",
+        table.visible_rows, table.total_rows
+    );
+    if sql {
+        for statement in &table.catchup {
+            println!("  catchup          {}", statement.sql);
+            if !statement.params.is_empty() {
+                println!("  params           {}", format_params(&statement.params));
+            }
+        }
+    }
+    println!();
+}
+
+fn print_row(options: &Options, row: &RowInspection) {
+    let name = format!(
+        "{} {} = {}",
+        row.table_name,
+        row.primary_key,
+        sync::session_value_to_json(&row.id)
+    );
+    let verdict = if row.visible {
+        pyre::color::cyan(options.enable_color, "is visible")
+    } else {
+        pyre::color::red(options.enable_color, "is not visible")
+    };
+    println!("{} {}", name, verdict);
+
+    if !row.found {
+        println!("  the row does not exist");
+    } else if !row.synced {
+        println!("  {} is not synced", row.table_name);
+    } else if row.permission.is_none() {
+        println!(
+            "  {} has no permission, so every row is visible",
+            row.table_name
+        );
+    }
+    if let Some(permission) = &row.permission {
+        print_condition(options, permission, 1);
+    }
+}
+
+fn print_condition(options: &Options, condition: &ConditionOutcome, depth: usize) {
+    let outcome = match condition.passed {
+        Some(true) => pyre::color::cyan(options.enable_color, "pass"),
+        Some(false) => pyre::color::red(options.enable_color, "fail"),
+        None => pyre::color::red(options.enable_color, "null"),
+    };
+    let params = if condition.params.is_empty() {
+        String::new()
+    } else {
+        format!("  {}", format_params(&condition.params))
+    };
+    println!(
+        "{}{} {}{}",
+        "  ".repeat(depth),
+        outcome,
+        condition.sql,
+        pyre::color::gray(options.enable_color, &params)
+    );
+    for child in &condition.children {
+        print_condition(options, child, depth + 1);
+    }
+}
+
+fn format_params(params: &[SessionValue]) -> String {
+    serde_json::Value::Array(params.iter().map(sync::session_value_to_json).collect()).to_string()
+}
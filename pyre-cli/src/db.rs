@@ -4,7 +4,6 @@ use pyre::generate::sql::to_sql::SqlAndParams;
 use pyre::typecheck;
 
 use libsql;
-use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env;
 use std::fs;
@@ -117,6 +116,7 @@ pub async fn connect(
 pub enum MigrationError {
     SqlError(libsql::Error),
     MigrationReadIoError(std::io::Error, PathBuf),
+    MigrationWriteIoError(std::io::Error, PathBuf),
     NoMigrationsFound(PathBuf),
     IncompatibleDatabase {
         db_path: String,
@@ -129,6 +129,20 @@ pub enum MigrationError {
     },
     AppliedMigrationChanged {
         name: String,
+        recorded_checksum: String,
+        file_checksum: String,
+    },
+    SchemaDriftDetected {
+        changes: Vec<String>,
+    },
+    SquashTargetNotFound {
+        until: String,
+        available: Vec<String>,
+    },
+    PartiallyAppliedSquash {
+        name: String,
+        applied: Vec<String>,
+        missing: Vec<String>,
     },
     MigrationValidationFailed {
         changes: Vec<String>,
@@ -150,6 +164,16 @@ impl MigrationError {
                 ),
                 )
             }
+            MigrationError::MigrationWriteIoError(io_error, path) => {
+                pyre::error::format_custom_error(
+                    "Migration Write Error",
+                    &format!(
+                        "I was updating migrations in {},\nbut ran into the following issue:\n\n{}",
+                        pyre::error::yellow_if(true, &path.display().to_string()),
+                        io_error
+                    ),
+                )
+            }
             MigrationError::NoMigrationsFound(path) => pyre::error::format_custom_error(
                 "No Migrations Found",
                 &format!(
@@ -190,11 +214,28 @@ impl MigrationError {
                     db_path
                 ),
             ),
-            MigrationError::AppliedMigrationChanged { name } => pyre::error::format_custom_error(
+            MigrationError::AppliedMigrationChanged {
+                name,
+                recorded_checksum,
+                file_checksum,
+            } => pyre::error::format_custom_error(
                 "Applied Migration Changed",
                 &format!(
-                    "Migration {} has already been applied, but its migration.sql no longer matches the SQL recorded in the database.\n\nCreate a new migration instead of editing an applied migration.",
-                    pyre::error::yellow_if(true, name)
+                    "Migration {} has already been applied, but its migration.sql no longer matches the checksum recorded in the database.\n\n  recorded: {}\n  on disk:  {}\n\nCreate a new migration instead of editing an applied migration.",
+                    pyre::error::yellow_if(true, name),
+                    recorded_checksum,
+                    file_checksum
+                ),
+            ),
+            MigrationError::SchemaDriftDetected { changes } => pyre::error::format_custom_error(
+                "Schema Drift Detected",
+                &format!(
+                    "The live database does not match the schema produced by replaying every migration file:\n\n{}\n\nIf migrations are pending, run `pyre migrate` first. Otherwise the database was changed outside of Pyre's migrations.",
+                    changes
+                        .iter()
+                        .map(|change| format!("  - {}", change))
+                        .collect::<Vec<_>>()
+                        .join("\n")
                 ),
             ),
             MigrationError::MigrationValidationFailed { changes } => pyre::error::format_custom_error(
@@ -208,6 +249,43 @@ impl MigrationError {
                         .join("\n")
                 ),
             ),
+            MigrationError::SquashTargetNotFound { until, available } => pyre::error::format_custom_error(
+                "Unknown Migration",
+                &format!(
+                    "I couldn't find a migration named {} to squash until.\n\nMigrations found:\n{}",
+                    pyre::error::yellow_if(true, until),
+                    if available.is_empty() {
+                        "  (none)".to_string()
+                    } else {
+                        available
+                            .iter()
+                            .map(|name| format!("  - {}", name))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    }
+                ),
+            ),
+            MigrationError::PartiallyAppliedSquash {
+                name,
+                applied,
+                missing,
+            } => pyre::error::format_custom_error(
+                "Partially Applied Baseline",
+                &format!(
+                    "Baseline {} replaces migrations this database has only partly applied.\n\nApplied:\n{}\n\nMissing:\n{}\n\nApply the missing migrations from a checkout before the squash, then run `pyre migrate` again.",
+                    pyre::error::yellow_if(true, name),
+                    applied
+                        .iter()
+                        .map(|name| format!("  - {}", name))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    missing
+                        .iter()
+                        .map(|name| format!("  - {}", name))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            ),
             MigrationError::SchemaTypecheckFailed => pyre::error::format_custom_error(
                 "Schema Typecheck Failed",
                 "The schema could not be typechecked while validating migrations.",
@@ -216,43 +294,92 @@ impl MigrationError {
     }
 }
 
-async fn get_applied_migration_sql(
+async fn has_migration_checksum_column(conn: &libsql::Connection) -> Result<bool, MigrationError> {
+    let mut rows = conn
+        .query(pyre::db::migrate::HAS_MIGRATION_CHECKSUM_COLUMN, ())
+        .await
+        .map_err(MigrationError::SqlError)?;
+
+    match rows.next().await.map_err(MigrationError::SqlError)? {
+        Some(row) => Ok(row.get::<i64>(0).map_err(MigrationError::SqlError)? > 0),
+        None => Ok(false),
+    }
+}
+
+async fn ensure_migration_checksum_column(conn: &libsql::Connection) -> Result<(), MigrationError> {
+    if !has_migration_checksum_column(conn).await? {
+        conn.execute(pyre::db::migrate::ADD_MIGRATION_CHECKSUM_COLUMN, ())
+            .await
+            .map_err(MigrationError::SqlError)?;
+    }
+
+    Ok(())
+}
+
+/// Read successfully applied migrations, including their recorded checksums.
+/// Expects the migration table to exist.
+pub async fn get_applied_migrations(
     conn: &libsql::Connection,
-) -> Result<HashMap<String, String>, MigrationError> {
+) -> Result<Vec<pyre::db::migrate::AppliedMigration>, MigrationError> {
+    let list_sql = if has_migration_checksum_column(conn).await? {
+        pyre::db::migrate::LIST_APPLIED_MIGRATIONS
+    } else {
+        pyre::db::migrate::LIST_APPLIED_MIGRATIONS_LEGACY
+    };
+
     let mut rows = conn
-        .query(
-            &format!(
-                "select name, sql from {}",
-                pyre::ext::string::quote(pyre::db::migrate::MIGRATION_TABLE)
-            ),
-            (),
-        )
+        .query(list_sql, ())
         .await
         .map_err(MigrationError::SqlError)?;
 
-    let mut applied = HashMap::new();
+    let mut applied = Vec::new();
     while let Some(row) = rows.next().await.map_err(MigrationError::SqlError)? {
-        let name: String = row.get(0).map_err(MigrationError::SqlError)?;
-        let sql: String = row.get(1).map_err(MigrationError::SqlError)?;
-        applied.insert(name, sql);
+        applied.push(pyre::db::migrate::AppliedMigration {
+            name: row.get(0).map_err(MigrationError::SqlError)?,
+            sql: row.get(1).map_err(MigrationError::SqlError)?,
+            checksum: row.get(2).map_err(MigrationError::SqlError)?,
+        });
     }
 
     Ok(applied)
 }
 
-fn verify_applied_migration_sql_unchanged(
+fn verify_applied_migrations_unchanged(
     migration_files: &[(String, String)],
-    applied_sql: &HashMap<String, String>,
+    applied: &[pyre::db::migrate::AppliedMigration],
 ) -> Result<(), MigrationError> {
-    for (name, sql) in migration_files {
-        if let Some(recorded_sql) = applied_sql.get(name) {
-            if recorded_sql != sql {
-                return Err(MigrationError::AppliedMigrationChanged { name: name.clone() });
-            }
-        }
+    match pyre::db::migrate::changed_migrations(migration_files, applied)
+        .into_iter()
+        .next()
+    {
+        Some(changed) => Err(MigrationError::AppliedMigrationChanged {
+            name: changed.name,
+            recorded_checksum: changed.recorded_checksum,
+            file_checksum: changed.file_checksum,
+        }),
+        None => Ok(()),
     }
+}
 
-    Ok(())
+fn verify_squashes_fully_applied(
+    migration_files: &[(String, String)],
+    applied: &[pyre::db::migrate::AppliedMigration],
+) -> Result<(), MigrationError> {
+    let applied_names: Vec<String> = applied
+        .iter()
+        .map(|migration| migration.name.clone())
+        .collect();
+    match pyre::db::migrate::partially_applied_squashes(migration_files, &applied_names)
+        .into_iter()
+        .next()
+    {
+        Some(partial) => Err(MigrationError::PartiallyAppliedSquash {
+            name: partial.name,
+            applied: partial.applied,
+            missing: partial.missing,
+        }),
+        None => Ok(()),
+    }
 }
 
 fn migration_validation_changes(db_diff: &diff::Diff) -> Vec<String> {
@@ -290,6 +417,15 @@ fn migration_validation_changes(db_diff: &diff::Diff) -> Vec<String> {
                 diff::RecordChange::RemovedIndex(index) => {
                     changes.push(format!("unexpected index {}", index.name));
                 }
+                diff::RecordChange::ModifiedTableOptions(_) => {
+                    changes.push(format!("modified table options {}", record_diff.name));
+                }
+                diff::RecordChange::ModifiedPrimaryKey { .. } => {
+                    changes.push(format!("modified primary key {}", record_diff.name));
+                }
+                diff::RecordChange::AddedTombstoneTrigger { .. } => {
+                    changes.push(format!("missing tombstone trigger {}", record_diff.name));
+                }
             }
         }
     }
@@ -344,14 +480,16 @@ fn detect_namespaces_from_migrations(
             continue;
         };
 
-        let Ok(migration_names) = read_migration_items(&path) else {
+        let Ok(migrations) = read_migration_folder(&path) else {
             continue;
         };
 
-        if migration_names
-            .iter()
-            .any(|name| applied_migrations.contains(name))
-        {
+        if migrations.file_contents.iter().any(|(name, sql)| {
+            applied_migrations.contains(name)
+                || pyre::db::migrate::squashed_migration_names(sql)
+                    .iter()
+                    .any(|squashed| applied_migrations.contains(squashed))
+        }) {
             namespaces.push(namespace.to_string());
         }
     }
@@ -495,8 +633,10 @@ pub async fn migrate(
         .await
         .map_err(MigrationError::SqlError)?;
 
-    let applied_sql = get_applied_migration_sql(&conn).await?;
-    verify_applied_migration_sql_unchanged(&migration_files.file_contents, &applied_sql)?;
+    ensure_migration_checksum_column(&conn).await?;
+    let applied = get_applied_migrations(&conn).await?;
+    verify_applied_migrations_unchanged(&migration_files.file_contents, &applied)?;
+    verify_squashes_fully_applied(&migration_files.file_contents, &applied)?;
 
     // Use centralized migration planning logic
     let migration_plan = pyre::db::migrate::plan_file_based_migrations(
@@ -518,22 +658,55 @@ pub async fn migrate(
         .await
         .map_err(MigrationError::SqlError)?;
 
+    // Record migration using centralized constant
+    // INSERT_MIGRATION_SUCCESS_WITH_CHECKSUM requires (name, sql, checksum, finished_at)
+    // where finished_at is set to unixepoch() automatically
+    let insert_sql = pyre::db::migrate::INSERT_MIGRATION_SUCCESS_WITH_CHECKSUM.replace(
+        pyre::db::migrate::MIGRATION_TABLE,
+        &pyre::ext::string::quote(pyre::db::migrate::MIGRATION_TABLE),
+    );
+
+    // Baselines whose squashed migrations already ran are recorded without executing them
+    for (migration_filename, migration_contents) in &migration_plan.migrations_to_record {
+        tx.execute(
+            &insert_sql,
+            libsql::params![
+                migration_filename.clone(),
+                migration_contents.clone(),
+                pyre::db::migrate::migration_checksum(migration_contents)
+            ],
+        )
+        .await
+        .map_err(MigrationError::SqlError)?;
+    }
+
     // Execute migrations that need to be run
     for (migration_filename, migration_contents) in &migration_plan.migrations_to_run {
         tx.execute_batch(migration_contents)
             .await
             .map_err(MigrationError::SqlError)?;
 
-        // Record migration using centralized constant
-        // INSERT_MIGRATION_SUCCESS requires (name, sql, finished_at)
-        // where finished_at is set to unixepoch() automatically
-        let insert_sql = pyre::db::migrate::INSERT_MIGRATION_SUCCESS.replace(
-            pyre::db::migrate::MIGRATION_TABLE,
-            &pyre::ext::string::quote(pyre::db::migrate::MIGRATION_TABLE),
-        );
         tx.execute(
             &insert_sql,
-            libsql::params![migration_filename.clone(), migration_contents.clone()],
+            libsql::params![
+                migration_filename.clone(),
+                migration_contents.clone(),
+                pyre::db::migrate::migration_checksum(migration_contents)
+            ],
+        )
+        .await
+        .map_err(MigrationError::SqlError)?;
+    }
+
+    // Migrations applied before checksums were recorded get one now that
+    // their contents have been verified against the recorded SQL.
+    for migration in applied
+        .iter()
+        .filter(|migration| migration.checksum.is_none())
+    {
+        tx.execute(
+            pyre::db::migrate::BACKFILL_MIGRATION_CHECKSUM,
+            libsql::params![migration.recorded_checksum(), migration.name.clone()],
         )
         .await
         .map_err(MigrationError::SqlError)?;
@@ -566,50 +739,228 @@ pub async fn migrate(
     })
 }
 
-pub fn read_migration_items(migration_folder: &Path) -> Result<Vec<String>, std::io::Error> {
-    let mut migration_items: Vec<String> = Vec::new();
+/// Replay migration files into a fresh in-memory database.
+/// The returned database must be kept alive for as long as the connection is used.
+async fn replay_migrations(
+    migration_files: &[(String, String)],
+) -> Result<(libsql::Database, libsql::Connection), MigrationError> {
+    let scratch = libsql::Builder::new_local(":memory:")
+        .build()
+        .await
+        .map_err(MigrationError::SqlError)?;
+    let conn = scratch.connect().map_err(MigrationError::SqlError)?;
 
-    for entry in fs::read_dir(migration_folder)? {
-        let entry = entry?;
-        let path = entry.path();
+    for statement in pyre::db::migrate::quoted_internal_setup_sql() {
+        match statement {
+            SqlAndParams::Sql(sql) => {
+                conn.execute_batch(&sql)
+                    .await
+                    .map_err(MigrationError::SqlError)?;
+            }
+            SqlAndParams::SqlWithParams { sql, args } => {
+                conn.execute(&sql, libsql::params_from_iter(args))
+                    .await
+                    .map_err(MigrationError::SqlError)?;
+            }
+        }
+    }
 
-        if path.is_dir() {
-            if let Some(folder_name) = path.file_name().and_then(|name| name.to_str()) {
-                migration_items.push(folder_name.to_string());
+    for (_, migration_contents) in migration_files {
+        conn.execute_batch(migration_contents)
+            .await
+            .map_err(MigrationError::SqlError)?;
+    }
+
+    Ok((scratch, conn))
+}
+
+#[derive(Debug)]
+pub struct VerifyOutcome {
+    pub migrations_replayed: usize,
+}
+
+impl VerifyOutcome {
+    pub fn status_line(&self) -> String {
+        match self.migrations_replayed {
+            1 => "Database matches 1 migration file.".to_string(),
+            count => format!("Database matches {} migration files.", count),
+        }
+    }
+}
+
+/// Check that applied migration files are unchanged and that the live schema
+/// matches the schema produced by replaying every migration file into an
+/// in-memory database.
+pub async fn verify(
+    db: &libsql::Database,
+    migration_folder: &Path,
+) -> Result<VerifyOutcome, MigrationError> {
+    let migration_files = match read_migration_folder(migration_folder) {
+        Err(err) => {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                return Err(MigrationError::NoMigrationsFound(
+                    migration_folder.to_path_buf(),
+                ));
             }
+
+            return Err(MigrationError::MigrationReadIoError(
+                err,
+                migration_folder.to_path_buf(),
+            ));
         }
+        Ok(files) => files,
+    };
+
+    if migration_files.file_contents.is_empty() {
+        return Err(MigrationError::NoMigrationsFound(
+            migration_folder.to_path_buf(),
+        ));
     }
 
-    migration_items.sort();
+    let conn = db.connect().map_err(MigrationError::SqlError)?;
+    let migration_state = introspect::get_migration_state(&conn)
+        .await
+        .map_err(MigrationError::SqlError)?;
+    if let pyre::db::introspect::MigrationState::MigrationTable { .. } = migration_state {
+        let applied = get_applied_migrations(&conn).await?;
+        verify_applied_migrations_unchanged(&migration_files.file_contents, &applied)?;
+        verify_squashes_fully_applied(&migration_files.file_contents, &applied)?;
+    }
+    let live = introspect::introspect_connection(&conn)
+        .await
+        .map_err(MigrationError::SqlError)?;
 
-    Ok(migration_items)
+    let (_scratch, scratch_conn) = replay_migrations(&migration_files.file_contents).await?;
+    let expected = introspect::introspect_connection(&scratch_conn)
+        .await
+        .map_err(MigrationError::SqlError)?;
+
+    let changes = pyre::db::migrate::schema_drift(&expected.tables, &live.tables);
+    if !changes.is_empty() {
+        return Err(MigrationError::SchemaDriftDetected { changes });
+    }
+
+    Ok(VerifyOutcome {
+        migrations_replayed: migration_files.file_contents.len(),
+    })
+}
+
+#[derive(Debug)]
+pub struct SquashOutcome {
+    pub baseline_name: String,
+    pub squashed: Vec<String>,
+}
+
+impl SquashOutcome {
+    pub fn status_line(&self) -> String {
+        match self.squashed.len() {
+            1 => format!("Squashed 1 migration into {}.", self.baseline_name),
+            count => format!("Squashed {} migrations into {}.", count, self.baseline_name),
+        }
+    }
+}
+
+fn baseline_migration_name(until: &str) -> String {
+    match until.split_once('_') {
+        Some((timestamp, _)) => format!("{}_baseline", timestamp),
+        None => format!("{}_baseline", until),
+    }
+}
+
+/// Replace every migration up to and including `until` with a single baseline
+/// migration that recreates the schema those migrations produce.
+pub async fn squash(migration_folder: &Path, until: &str) -> Result<SquashOutcome, MigrationError> {
+    let migration_files = match read_migration_folder(migration_folder) {
+        Err(err) => {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                return Err(MigrationError::NoMigrationsFound(
+                    migration_folder.to_path_buf(),
+                ));
+            }
+
+            return Err(MigrationError::MigrationReadIoError(
+                err,
+                migration_folder.to_path_buf(),
+            ));
+        }
+        Ok(files) => files.file_contents,
+    };
+
+    let Some(until_index) = migration_files.iter().position(|(name, _)| name == until) else {
+        return Err(MigrationError::SquashTargetNotFound {
+            until: until.to_string(),
+            available: migration_files
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect(),
+        });
+    };
+    let squashed = &migration_files[..=until_index];
+
+    let (_scratch, conn) = replay_migrations(squashed).await?;
+    let mut rows = conn
+        .query(pyre::db::migrate::LIST_SCHEMA_OBJECTS_SQL, ())
+        .await
+        .map_err(MigrationError::SqlError)?;
+    let mut schema_statements = Vec::new();
+    while let Some(row) = rows.next().await.map_err(MigrationError::SqlError)? {
+        let sql: String = row.get(0).map_err(MigrationError::SqlError)?;
+        schema_statements.push(sql);
+    }
+
+    let baseline_sql = pyre::db::migrate::squash_baseline_sql(squashed, &schema_statements);
+    let baseline_name = baseline_migration_name(until);
+
+    for (name, _) in squashed {
+        let path = migration_folder.join(name);
+        fs::remove_dir_all(&path)
+            .map_err(|err| MigrationError::MigrationWriteIoError(err, path))?;
+    }
+
+    let baseline_folder = migration_folder.join(&baseline_name);
+    fs::create_dir_all(&baseline_folder)
+        .map_err(|err| MigrationError::MigrationWriteIoError(err, baseline_folder.clone()))?;
+    let baseline_file = baseline_folder.join("migration.sql");
+    fs::write(&baseline_file, baseline_sql)
+        .map_err(|err| MigrationError::MigrationWriteIoError(err, baseline_file))?;
+
+    Ok(SquashOutcome {
+        baseline_name,
+        squashed: squashed.iter().map(|(name, _)| name.clone()).collect(),
+    })
 }
 
 pub struct Migrations {
     pub file_contents: Vec<(String, String)>,
 }
 
-pub fn read_migration_folder(migration_folder: &Path) -> Result<Migrations, std::io::Error> {
-    let mut migration_dirs: Vec<(String, PathBuf)> = Vec::new();
+pub fn read_migration_items(migration_folder: &Path) -> Result<Vec<String>, std::io::Error> {
+    let mut migration_items: Vec<String> = Vec::new();
 
     for entry in fs::read_dir(migration_folder)? {
         let entry = entry?;
         let path = entry.path();
 
         if path.is_dir() {
-            let migrate_file_path = path.join("migration.sql");
-            if migrate_file_path.is_file() {
-                if let Some(folder_name) = path.file_name().and_then(|name| name.to_str()) {
-                    migration_dirs.push((folder_name.to_string(), migrate_file_path));
-                }
+            if let Some(folder_name) = path.file_name().and_then(|name| name.to_str()) {
+                migration_items.push(folder_name.to_string());
             }
         }
     }
 
-    migration_dirs.sort_by(|(left, _), (right, _)| left.cmp(right));
+    migration_items.sort();
 
+    Ok(migration_items)
+}
+
+pub fn read_migration_folder(migration_folder: &Path) -> Result<Migrations, std::io::Error> {
     let mut file_contents: Vec<(String, String)> = Vec::new();
-    for (folder_name, migrate_file_path) in migration_dirs {
+    for folder_name in read_migration_items(migration_folder)? {
+        let migrate_file_path = migration_folder.join(&folder_name).join("migration.sql");
+        if !migrate_file_path.is_file() {
+            continue;
+        }
+
         let mut file = fs::File::open(&migrate_file_path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
@@ -85,6 +85,11 @@ enum Commands {
         #[arg(long, default_value_t = false)]
         push: bool,
 
+        /// Check applied migrations and the live schema against the migration files
+        /// without applying anything.
+        #[arg(long, default_value_t = false, conflicts_with = "push")]
+        verify: bool,
+
         /// Directory where migration files are stored.
         #[arg(long, default_value = "pyre/migrations")]
         migration_dir: String,
@@ -110,10 +115,31 @@ enum Commands {
         migration_dir: String,
     },
 
-    /// Start the built-in single-database Pyre server.
+    /// Replace every migration up to and including `--until` with a single baseline migration.
+    Squash {
+        /// The last migration to include in the baseline.
+        #[arg(long)]
+        until: String,
+
+        /// The Pyre namespace whose migrations should be squashed.
+        #[arg(long)]
+        namespace: Option<String>,
+
+        /// Directory where migration files are stored.
+        #[arg(long, default_value = "pyre/migrations")]
+        migration_dir: String,
+    },
+
+    /// Start the built-in Pyre server.
     Serve {
         /// A local filename, or a url, or an environment variable if prefixed with a $.
-        database: String,
+        /// A path containing {databaseId}, or a directory of <databaseId>.db files, serves many databases.
+        #[arg(required_unless_present = "database_map")]
+        database: Option<String>,
+
+        /// JSON file mapping each served databaseId to a database.
+        #[arg(long, conflicts_with = "database")]
+        database_map: Option<String>,
 
         /// Database auth token for remote libSQL/Turso databases.
         #[arg(long)]
@@ -131,7 +157,7 @@ enum Commands {
         #[arg(long, default_value = "pyre/generated")]
         generated: String,
 
-        /// Fixed database id exposed to clients.
+        /// Database id exposed to clients when serving a single database.
         #[arg(long, default_value = "default")]
         database_id: String,
 
@@ -143,6 +169,26 @@ enum Commands {
         #[arg(long)]
         session_secret: Option<String>,
 
+        /// Shared secret for HS256 bearer JWT sessions.
+        #[arg(long)]
+        jwt_secret: Option<String>,
+
+        /// Local JWKS file with RS256/ES256 keys for bearer JWT sessions.
+        #[arg(long)]
+        jwks: Option<String>,
+
+        /// Required `iss` claim for bearer JWTs.
+        #[arg(long)]
+        jwt_issuer: Option<String>,
+
+        /// Required `aud` claim for bearer JWTs.
+        #[arg(long)]
+        jwt_audience: Option<String>,
+
+        /// Map a session field to a JWT claim path, as <field>=<claim.path>. May be passed multiple times.
+        #[arg(long)]
+        jwt_claim: Vec<String>,
+
         /// Static JSON session used for local development.
         #[arg(long)]
         dev_session: Option<String>,
@@ -151,10 +197,55 @@ enum Commands {
         #[arg(long)]
         cors_origin: Vec<String>,
 
+        /// Bearer token that enables `POST /sync/sessions` for updating live sessions.
+        #[arg(long)]
+        admin_token: Option<String>,
+
         /// Sync catchup page size.
         #[arg(long, default_value_t = pyre::sync::DEFAULT_SYNC_PAGE_SIZE)]
         page_size: usize,
 
+        /// Live messages buffered per connection before it is told to resync.
+        #[arg(long, default_value_t = command::DEFAULT_CONNECTION_QUEUE_SIZE)]
+        queue_size: usize,
+
+        /// Write live changes to `_pyre_changes` and fan out changes made by other
+        /// `pyre serve` processes using the same database.
+        #[arg(long, default_value_t = false)]
+        change_log: bool,
+
+        /// How often to read `_pyre_changes`, in milliseconds.
+        #[arg(long, default_value_t = command::DEFAULT_CHANGE_LOG_POLL_MS)]
+        change_log_poll_ms: u64,
+
+        /// Seconds to keep `_pyre_changes` rows before pruning them.
+        #[arg(long, default_value_t = command::DEFAULT_CHANGE_LOG_RETENTION_SECONDS)]
+        change_log_retention: u64,
+
+        /// Seconds to keep deleted row keys for offline clients. Clients whose
+        /// cursor is older resync the affected tables from scratch.
+        #[arg(long, default_value_t = command::DEFAULT_TOMBSTONE_RETENTION_SECONDS)]
+        tombstone_retention: u64,
+
+        /// Seconds to remember the responses of requests sent with an idempotency key.
+        /// Retries after that run the mutation again.
+        #[arg(long, default_value_t = command::DEFAULT_IDEMPOTENCY_RETENTION_SECONDS)]
+        idempotency_retention: u64,
+
+        /// Routed databases to keep open at once. Requests for another database
+        /// are refused with 503 once this many are open.
+        #[arg(long, default_value_t = command::DEFAULT_MAX_OPEN_DATABASES)]
+        max_open_databases: usize,
+
+        /// An older `manifest.json` to keep serving to clients built against it.
+        /// May be passed multiple times.
+        #[arg(long)]
+        previous_manifest: Vec<String>,
+
+        /// Previous manifests to keep, including ones replaced while serving.
+        #[arg(long, default_value_t = command::DEFAULT_MANIFEST_HISTORY)]
+        manifest_history: usize,
+
         /// Allow --dev-session on non-loopback bind addresses.
         #[arg(long, default_value_t = false)]
         allow_unsafe_dev_session: bool,
@@ -164,6 +255,12 @@ enum Commands {
         allow_unsafe_unsigned_session: bool,
     },
 
+    /// Debug what sync sends to a session.
+    Sync {
+        #[command(subcommand)]
+        action: SyncCommands,
+    },
+
     /// Start the Pyre MCP server over stdio.
     Mcp,
 
@@ -174,6 +271,34 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum SyncCommands {
+    /// Show, per synced table, the permission a session gets and the rows it can see.
+    Inspect {
+        /// A local filename, or a url, or an environment variable if prefixed with a $.
+        database: String,
+
+        #[arg(long)]
+        auth: Option<String>,
+
+        /// The session to inspect, as JSON.
+        #[arg(long)]
+        session: String,
+
+        /// Explain why one row is or isn't visible, as <Table>:<id>.
+        #[arg(long)]
+        row: Option<String>,
+
+        /// Also print the SQL of a first catchup page for each table.
+        #[arg(long, default_value_t = false)]
+        sql: bool,
+
+        /// Sync catchup page size used for --sql.
+        #[arg(long, default_value_t = pyre::sync::DEFAULT_SYNC_PAGE_SIZE)]
+        page_size: usize,
+    },
+}
+
 #[tokio::main]
 async fn main() -> io::Result<()> {
     let cli = Cli::parse();
@@ -211,11 +336,14 @@ async fn main() -> io::Result<()> {
             database,
             auth,
             push,
+            verify,
             migration_dir,
             namespace,
         } => {
             if *push {
                 command::push(&options, database, auth, namespace).await?;
+            } else if *verify {
+                command::verify(&options, database, auth, migration_dir, namespace).await?;
             } else {
                 command::migrate(&options, database, auth, migration_dir, namespace).await?;
             }
@@ -237,8 +365,17 @@ async fn main() -> io::Result<()> {
             )
             .await?;
         }
+        Commands::Squash {
+            until,
+            namespace,
+            migration_dir,
+        } => {
+            command::squash_migrations(&options, until, Path::new(migration_dir), namespace)
+                .await?;
+        }
         Commands::Serve {
             database,
+            database_map,
             auth,
             host,
             port,
@@ -246,9 +383,24 @@ async fn main() -> io::Result<()> {
             database_id,
             session_header,
             session_secret,
+            jwt_secret,
+            jwks,
+            jwt_issuer,
+            jwt_audience,
+            jwt_claim,
             dev_session,
             cors_origin,
+            admin_token,
             page_size,
+            queue_size,
+            change_log,
+            change_log_poll_ms,
+            change_log_retention,
+            tombstone_retention,
+            idempotency_retention,
+            max_open_databases,
+            previous_manifest,
+            manifest_history,
             allow_unsafe_dev_session,
             allow_unsafe_unsigned_session,
         } => {
@@ -256,6 +408,7 @@ async fn main() -> io::Result<()> {
                 &options,
                 command::ServeOptions {
                     database,
+                    database_map,
                     auth,
                     host,
                     port: *port,
@@ -263,15 +416,54 @@ async fn main() -> io::Result<()> {
                     database_id,
                     session_header,
                     session_secret,
+                    jwt_secret,
+                    jwks,
+                    jwt_issuer,
+                    jwt_audience,
+                    jwt_claims: jwt_claim,
                     dev_session,
                     cors_origins: cors_origin,
+                    admin_token,
                     page_size: *page_size,
+                    queue_size: *queue_size,
+                    change_log: *change_log,
+                    change_log_poll_ms: *change_log_poll_ms,
+                    change_log_retention: *change_log_retention,
+                    tombstone_retention: *tombstone_retention,
+                    idempotency_retention: *idempotency_retention,
+                    max_open_databases: *max_open_databases,
+                    previous_manifests: previous_manifest,
+                    manifest_history: *manifest_history,
                     allow_unsafe_dev_session: *allow_unsafe_dev_session,
                     allow_unsafe_unsigned_session: *allow_unsafe_unsigned_session,
                 },
             )
             .await?;
         }
+        Commands::Sync {
+            action:
+                SyncCommands::Inspect {
+                    database,
+                    auth,
+                    session,
+                    row,
+                    sql,
+                    page_size,
+                },
+        } => {
+            command::sync_inspect(
+                &options,
+                command::InspectOptions {
+                    database,
+                    auth,
+                    session,
+                    row,
+                    sql: *sql,
+                    page_size: *page_size,
+                },
+            )
+            .await?;
+        }
         Commands::Mcp => {
             command::mcp(&options).await?;
         }
@@ -192,25 +192,74 @@ pub fn has_default_value(col: &Column) -> bool {
     })
 }
 
+/// The name of the primary key column, if the record has a single-column primary key.
+///
+/// Records with a composite `@id(a, b)` return `None`; use
+/// `get_primary_key_field_names` when every key column is needed.
 pub fn get_primary_id_field_name(fields: &Vec<Field>) -> Option<String> {
+    match get_primary_key_field_names(fields).as_slice() {
+        [name] => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// All primary key columns of a record, in key order.
+///
+/// A record-level `@id(a, b)` takes precedence; otherwise this is the single
+/// column marked with `@id`.
+pub fn get_primary_key_field_names(fields: &[Field]) -> Vec<String> {
+    for field in fields.iter() {
+        if let Field::FieldDirective(FieldDirective::PrimaryKey(names)) = field {
+            return names.clone();
+        }
+    }
     for field in fields.iter() {
         match field {
             Field::Column(col) => {
                 if is_primary_key(col) {
-                    return Some(col.name.clone());
+                    return vec![col.name.clone()];
                 }
             }
             _ => {}
         }
     }
-    None
+    vec![]
+}
+
+pub fn has_composite_primary_key(fields: &[Field]) -> bool {
+    get_primary_key_field_names(fields).len() > 1
+}
+
+/// The columns of a record-level `@id(a, b)`, in key order.
+/// Returns `None` for records keyed by a single column.
+pub fn composite_primary_key_columns(fields: &[Field]) -> Option<Vec<&Column>> {
+    let names = get_primary_key_field_names(fields);
+    if names.len() < 2 {
+        return None;
+    }
+    names
+        .iter()
+        .map(|name| {
+            fields.iter().find_map(|field| match field {
+                Field::Column(column) if &column.name == name => Some(column),
+                _ => None,
+            })
+        })
+        .collect()
 }
 
 pub fn is_field_primary_key(field_names: &Vec<String>, field: &Vec<Field>) -> bool {
-    field.iter().any(|f| match f {
-        Field::Column(col) => is_primary_key(col) && field_names.contains(&col.name),
-        _ => false,
-    })
+    let primary_key = get_primary_key_field_names(field);
+    field_names.iter().any(|name| primary_key.contains(name))
+}
+
+/// True if `local_ids` are exactly the record's primary key columns,
+/// meaning a link declared with them points from a parent to its children.
+pub fn ids_are_primary_key(local_ids: &[String], fields: &[Field]) -> bool {
+    let primary_key = get_primary_key_field_names(fields);
+    !primary_key.is_empty()
+        && local_ids.len() == primary_key.len()
+        && local_ids.iter().all(|id| primary_key.contains(id))
 }
 
 pub fn is_primary_key(col: &Column) -> bool {
@@ -336,8 +385,11 @@ pub enum FieldDirective {
     Link(LinkDetails),
     Index(IndexDirective),
     Unique(IndexDirective),
+    PrimaryKey(Vec<String>),
     Permissions(PermissionDetails),
     Timestamps,
+    Strict,
+    WithoutRowid,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -416,6 +468,10 @@ pub fn linked_to_unique_field_with_record(
     link: &LinkDetails,
     foreign_record: &RecordDetails,
 ) -> bool {
+    if ids_are_primary_key(&link.foreign.fields, &foreign_record.fields) {
+        return true;
+    }
+
     // If linking to a single field, check if that field has UNIQUE or PRIMARY KEY constraint
     if link.foreign.fields.len() == 1 {
         let field_name = &link.foreign.fields[0];
@@ -434,14 +490,47 @@ pub fn linked_to_unique_field_with_record(
         }
     }
 
-    // For multi-field links, we'd need to check for composite UNIQUE constraints
-    // For now, fall back to checking if all fields are "id" (which is a common pattern)
-    // TODO: Support composite UNIQUE constraints
-    link.foreign.fields.iter().all(|f| f == "id")
+    // Multi-field links are unique when they match a record-level
+    // @unique(...) over exactly the same columns.
+    let matches_composite_unique = matches_record_unique(&link.foreign.fields, foreign_record);
+
+    // Fall back to the "id" convention for fields the record doesn't declare.
+    matches_composite_unique || link.foreign.fields.iter().all(|f| f == "id")
+}
+
+/// Check if a set of fields is unique together in a record: a single unique
+/// column, the whole primary key, or a record-level @unique(...) over exactly
+/// those columns.
+pub fn fields_are_unique(field_names: &[String], record: &RecordDetails) -> bool {
+    match field_names {
+        [] => false,
+        [field_name] => field_is_unique(field_name, record),
+        _ => {
+            ids_are_primary_key(field_names, &record.fields)
+                || matches_record_unique(field_names, record)
+        }
+    }
+}
+
+fn matches_record_unique(field_names: &[String], record: &RecordDetails) -> bool {
+    record.fields.iter().any(|field| match field {
+        Field::FieldDirective(FieldDirective::Unique(details)) => {
+            details.where_.is_none()
+                && details.columns.len() == field_names.len()
+                && details
+                    .columns
+                    .iter()
+                    .all(|column| field_names.contains(&column.name))
+        }
+        _ => false,
+    })
 }
 
 /// Check if a field in a record has a UNIQUE or PRIMARY KEY constraint.
 pub fn field_is_unique(field_name: &str, record: &RecordDetails) -> bool {
+    if get_primary_key_field_names(&record.fields) == [field_name] {
+        return true;
+    }
     for field in &record.fields {
         match field {
             Field::Column(column) => {
@@ -958,15 +1047,189 @@ pub enum ColumnDirective {
     Index,
     CreatedAt,
     UpdatedAt,
+    /// An `Int` Pyre increments on every update, see `@ifVersion`.
+    Version,
     Default {
         id: String,
         value: DefaultValue,
         start: Option<Location>,
         end: Option<Location>,
     },
+    Collate(Collation),
+    Computed(ComputedExpr),
     // Check(String),
 }
 
+/// The expression behind a `@computed(...)` column.
+///
+/// These are stored as SQLite generated columns, so they can only refer to
+/// other columns of the same record, literals, and deterministic builtins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ComputedExpr {
+    Column(String),
+    String(String),
+    Int(i64),
+    Float(f64),
+    Null,
+    Call {
+        name: String,
+        args: Vec<ComputedExpr>,
+    },
+    Binary {
+        op: ComputedOperator,
+        left: Box<ComputedExpr>,
+        right: Box<ComputedExpr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComputedOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    /// `++`, string concatenation.
+    Concat,
+}
+
+impl ComputedOperator {
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            ComputedOperator::Add => "+",
+            ComputedOperator::Subtract => "-",
+            ComputedOperator::Multiply => "*",
+            ComputedOperator::Divide => "/",
+            ComputedOperator::Concat => "||",
+        }
+    }
+
+    pub fn to_pyre(&self) -> &'static str {
+        match self {
+            ComputedOperator::Concat => "++",
+            _ => self.to_sql(),
+        }
+    }
+
+    /// Binding strength, higher binds tighter.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            ComputedOperator::Add | ComputedOperator::Subtract | ComputedOperator::Concat => 1,
+            ComputedOperator::Multiply | ComputedOperator::Divide => 2,
+        }
+    }
+}
+
+impl ComputedExpr {
+    /// Render as SQL, suitable for `generated always as (...)`.
+    pub fn to_sql(&self) -> String {
+        match self {
+            ComputedExpr::Column(name) => format!("\"{}\"", name),
+            ComputedExpr::String(value) => format!("'{}'", value.replace('\'', "''")),
+            ComputedExpr::Int(value) => value.to_string(),
+            ComputedExpr::Float(value) => format!("{:?}", value),
+            ComputedExpr::Null => "null".to_string(),
+            ComputedExpr::Call { name, args } => format!(
+                "{}({})",
+                name,
+                args.iter()
+                    .map(|arg| arg.to_sql())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ComputedExpr::Binary { op, left, right } => {
+                format!("({} {} {})", left.to_sql(), op.to_sql(), right.to_sql())
+            }
+        }
+    }
+
+    /// Every column the expression reads.
+    pub fn referenced_columns(&self) -> Vec<String> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    fn collect_columns(&self, columns: &mut Vec<String>) {
+        match self {
+            ComputedExpr::Column(name) => {
+                if !columns.contains(name) {
+                    columns.push(name.clone());
+                }
+            }
+            ComputedExpr::Call { args, .. } => {
+                for arg in args {
+                    arg.collect_columns(columns);
+                }
+            }
+            ComputedExpr::Binary { left, right, .. } => {
+                left.collect_columns(columns);
+                right.collect_columns(columns);
+            }
+            ComputedExpr::String(_)
+            | ComputedExpr::Int(_)
+            | ComputedExpr::Float(_)
+            | ComputedExpr::Null => {}
+        }
+    }
+}
+
+pub fn get_computed(col: &Column) -> Option<&ComputedExpr> {
+    col.directives.iter().find_map(|directive| match directive {
+        ColumnDirective::Computed(expr) => Some(expr),
+        _ => None,
+    })
+}
+
+pub fn is_computed(col: &Column) -> bool {
+    get_computed(col).is_some()
+}
+
+/// SQLite's built-in collating sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Collation {
+    Binary,
+    NoCase,
+    Rtrim,
+}
+
+impl Collation {
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            Collation::Binary => "binary",
+            Collation::NoCase => "nocase",
+            Collation::Rtrim => "rtrim",
+        }
+    }
+
+    pub fn from_sql(name: &str) -> Option<Collation> {
+        match name.to_ascii_lowercase().as_str() {
+            "binary" => Some(Collation::Binary),
+            "nocase" => Some(Collation::NoCase),
+            "rtrim" => Some(Collation::Rtrim),
+            _ => None,
+        }
+    }
+}
+
+pub fn get_collation(col: &Column) -> Option<Collation> {
+    col.directives.iter().find_map(|directive| match directive {
+        ColumnDirective::Collate(collation) => Some(*collation),
+        _ => None,
+    })
+}
+
+pub fn is_strict(fields: &[Field]) -> bool {
+    fields
+        .iter()
+        .any(|field| matches!(field, Field::FieldDirective(FieldDirective::Strict)))
+}
+
+pub fn is_without_rowid(fields: &[Field]) -> bool {
+    fields
+        .iter()
+        .any(|field| matches!(field, Field::FieldDirective(FieldDirective::WithoutRowid)))
+}
+
 pub fn is_created_at(col: &Column) -> bool {
     col.directives
         .iter()
@@ -983,6 +1246,20 @@ pub fn is_managed_timestamp(col: &Column) -> bool {
     is_created_at(col) || is_updated_at(col)
 }
 
+pub fn is_version(col: &Column) -> bool {
+    col.directives
+        .iter()
+        .any(|directive| matches!(directive, ColumnDirective::Version))
+}
+
+/// The record's `@version` column, if it has one.
+pub fn get_version_column(fields: &[Field]) -> Option<&Column> {
+    fields.iter().find_map(|field| match field {
+        Field::Column(column) if is_version(column) => Some(column),
+        _ => None,
+    })
+}
+
 pub fn is_integer_primary_key(col: &Column) -> bool {
     is_primary_key(col)
         && matches!(
@@ -1211,6 +1488,23 @@ pub enum Arg {
     Limit(QueryValue),
     OrderBy(Direction, String),
     Where(WhereArg),
+    /// `@ifVersion($version)`: only update rows whose `@version` column equals the value.
+    IfVersion(QueryValue),
+}
+
+pub fn get_if_version(fields: &[ArgField]) -> Option<&QueryValue> {
+    fields.iter().find_map(|field| match field {
+        ArgField::Arg(LocatedArg {
+            arg: Arg::IfVersion(value),
+            ..
+        }) => Some(value),
+        _ => None,
+    })
+}
+
+/// The response field listing the rows an `@ifVersion` update skipped, as they are now.
+pub fn version_conflicts_name(query_field: &QueryField) -> String {
+    format!("{}Conflicts", get_aliased_name(query_field))
 }
 
 #[derive(Debug, Clone)]
@@ -316,7 +316,12 @@ fn diff_column(old: &crate::ast::Column, new: &crate::ast::Column) -> Option<Col
             crate::ast::ColumnDirective::Index => "_idx".to_string(),
             crate::ast::ColumnDirective::CreatedAt => "_createdAt".to_string(),
             crate::ast::ColumnDirective::UpdatedAt => "_updatedAt".to_string(),
+            crate::ast::ColumnDirective::Version => "_version".to_string(),
             crate::ast::ColumnDirective::Default { id, .. } => id.clone(),
+            crate::ast::ColumnDirective::Collate(collation) => {
+                format!("_collate_{}", collation.to_sql())
+            }
+            crate::ast::ColumnDirective::Computed(expr) => format!("_computed_{}", expr.to_sql()),
         }
     };
 
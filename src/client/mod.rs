@@ -0,0 +1,2 @@
+#[cfg(feature = "client")]
+pub mod sync;
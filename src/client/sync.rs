@@ -0,0 +1,778 @@
+//! A sync client that keeps a local SQLite replica of the rows a session can see,
+//! using the same `/sync` and `/sync/events` protocol as `@pyre/client`.
+//!
+//! ```rust,ignore
+//! let manifest = Manifest::load("pyre/generated/manifest.json")?;
+//! let client = SyncClient::open(
+//!     "replica.db",
+//!     &schema_source,
+//!     SyncClientConfig::new("http://127.0.0.1:3000", manifest)
+//!         .with_header("authorization", format!("Bearer {}", token))
+//!         .with_session(json!({ "userId": 1 })),
+//! )
+//! .await?;
+//!
+//! client.catchup().await?;
+//! let mut live = client.subscribe().await?;
+//! while let Some(event) = live.next().await? {
+//!     let notes = client.query(&get_notes, json!({})).await?;
+//! }
+//! ```
+//!
+//! The replica is created from the server's schema source, so generated read queries
+//! run against it unchanged. Mutations are sent to the server.
+use crate::ast;
+use crate::db::migrate;
+use crate::generate::sql::to_sql::SqlAndParams;
+use crate::server::manifest::{self, Manifest, PyreSession};
+use crate::server::query;
+use crate::server::schema::{self, introspect_database, load_schema_from_database};
+use crate::server::sync::DeltaMessage;
+use crate::sync::{SyncCursor, SyncPageResult, TableCursor};
+use crate::sync_deltas::AffectedRowTableGroup;
+use crate::sync_encoding::{self, SyncFormat};
+use crate::typecheck;
+use hyper::body::HttpBody;
+use hyper::client::HttpConnector;
+use hyper::{Body, Method, Request};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Sent with the `hash` of the manifest the client was built against.
+const MANIFEST_VERSION_HEADER: &str = "x-pyre-manifest";
+
+/// Name of the migration that creates the replica's tables.
+const REPLICA_MIGRATION: &str = "replica";
+
+const CREATE_REPLICA_TABLE: &str = "create table if not exists _pyre_replica (
+    key text not null primary key,
+    value text not null
+)";
+
+const READ_REPLICA_VALUE: &str = "select value from _pyre_replica where key = ?";
+
+const WRITE_REPLICA_VALUE: &str = "insert into _pyre_replica (key, value) values (?, ?)
+    on conflict(key) do update set value = excluded.value";
+
+/// The `SyncCursor` catchup continues from.
+const CURSOR_KEY: &str = "cursor";
+
+/// The newest server revision applied, sent back as `Last-Event-ID`.
+const SERVER_REVISION_KEY: &str = "server_revision";
+
+/// Where a `SyncClient` syncs from, and as whom.
+pub struct SyncClientConfig {
+    /// The server's base URL, such as `http://127.0.0.1:3000`, including the path
+    /// the Pyre router is nested under.
+    pub url: String,
+    /// Sent with every request. Omit to use the server's default database.
+    pub database_id: Option<String>,
+    pub manifest: Manifest,
+    /// Sent with every request, such as `authorization` or a session header.
+    pub headers: Vec<(String, String)>,
+    /// The session local queries run with. The replica only holds the rows the
+    /// server's session for `headers` can see, so the two should match.
+    pub session: JsonValue,
+}
+
+impl SyncClientConfig {
+    pub fn new(url: impl Into<String>, manifest: Manifest) -> Self {
+        SyncClientConfig {
+            url: url.into(),
+            database_id: None,
+            manifest,
+            headers: Vec::new(),
+            session: json!({}),
+        }
+    }
+
+    pub fn with_database_id(mut self, database_id: impl Into<String>) -> Self {
+        self.database_id = Some(database_id.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_session(mut self, session: JsonValue) -> Self {
+        self.session = session;
+        self
+    }
+}
+
+/// Syncs a server's rows into a local SQLite file and runs read queries against it.
+pub struct SyncClient {
+    db: libsql::Database,
+    http: hyper::Client<HttpConnector>,
+    url: String,
+    database_id: Option<String>,
+    headers: Vec<(String, String)>,
+    manifest: Manifest,
+    session: PyreSession,
+    /// Replicated tables by SQL table name.
+    tables: HashMap<String, ReplicaTable>,
+    /// Set by the `connected` event, so the server returns this connection's own
+    /// deltas in mutation responses.
+    connection_id: Mutex<Option<String>>,
+}
+
+struct ReplicaTable {
+    /// Columns rows are written to. Computed columns are filled in by SQLite.
+    columns: Vec<String>,
+    primary_key: Vec<String>,
+}
+
+/// What a live sync event did to the replica.
+#[derive(Debug)]
+pub enum SyncEvent {
+    Connected {
+        connection_id: String,
+    },
+    /// A delta was applied.
+    Delta(DeltaMessage),
+    /// The server asked for a catchup, which has been run.
+    CaughtUp,
+}
+
+impl SyncClient {
+    /// Open the replica at `path`, creating or migrating its tables from
+    /// `schema_source`. The sync cursor is kept from earlier runs.
+    pub async fn open(
+        path: impl AsRef<Path>,
+        schema_source: &str,
+        config: SyncClientConfig,
+    ) -> Result<Self, Error> {
+        let session = PyreSession::new(config.session, &config.manifest.session_schema)
+            .map_err(Error::Session)?;
+        let db = libsql::Builder::new_local(path.as_ref())
+            .build()
+            .await
+            .map_err(Error::Database)?;
+        let conn = db.connect().map_err(Error::Database)?;
+        migrate_replica(&conn, schema_source).await?;
+        conn.execute(CREATE_REPLICA_TABLE, ())
+            .await
+            .map_err(Error::Database)?;
+
+        let loaded_schema = load_schema_from_database(&conn)
+            .await
+            .map_err(Error::Schema)?;
+        let context = loaded_schema.context().map_err(Error::Schema)?;
+
+        Ok(SyncClient {
+            db,
+            http: hyper::Client::new(),
+            url: config.url.trim_end_matches('/').to_string(),
+            database_id: config.database_id,
+            headers: config.headers,
+            manifest: config.manifest,
+            session,
+            tables: replica_tables(context),
+            connection_id: Mutex::new(None),
+        })
+    }
+
+    /// The replica, for reads generated queries don't cover.
+    pub fn database(&self) -> &libsql::Database {
+        &self.db
+    }
+
+    /// The cursor the next catchup continues from.
+    pub async fn cursor(&self) -> Result<SyncCursor, Error> {
+        let conn = self.db.connect().map_err(Error::Database)?;
+        Ok(read_replica_value(&conn, CURSOR_KEY)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Fetch catchup pages until the replica has every row changed since its cursor.
+    pub async fn catchup(&self) -> Result<(), Error> {
+        loop {
+            let sync_cursor = self.cursor().await?;
+            // Pages are large, so ask for the compact encoding.
+            let request = self
+                .request(Method::POST, "/sync")?
+                .header("accept", sync_encoding::MESSAGEPACK_CONTENT_TYPE)
+                .header("accept-encoding", "gzip");
+            let (content_type, bytes) = self
+                .send(
+                    request,
+                    json!({
+                        "databaseId": self.database_id,
+                        "syncCursor": sync_cursor,
+                    }),
+                )
+                .await?;
+            let format = if content_type.as_deref() == Some(sync_encoding::MESSAGEPACK_CONTENT_TYPE)
+            {
+                SyncFormat::MessagePack
+            } else {
+                SyncFormat::Json
+            };
+            let page = sync_encoding::decode_sync_page(&bytes, format).map_err(Error::Encoding)?;
+            self.apply_page(sync_cursor, &page).await?;
+            if !page.has_more {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Apply a live delta, such as one returned by `mutate`.
+    pub async fn apply_delta(&self, message: &DeltaMessage) -> Result<(), Error> {
+        let conn = self.db.connect().map_err(Error::Database)?;
+        let transaction = conn.transaction().await.map_err(Error::Database)?;
+        // Conflicts carry rows as they are now, so they are written like any other.
+        for group in message.data.iter().chain(&message.conflicts) {
+            self.write_group(&transaction, group).await?;
+        }
+        for removed in &message.removed {
+            if let Some(table) = self.tables.get(&removed.table_name) {
+                for key in &removed.keys {
+                    delete_row(&transaction, &removed.table_name, table, key).await?;
+                }
+            }
+        }
+        if let Some(server_revision) = message.server_revision {
+            write_replica_value(&transaction, SERVER_REVISION_KEY, &server_revision).await?;
+        }
+        transaction.commit().await.map_err(Error::Database)
+    }
+
+    /// Open `/sync/events`. Deltas missed since the last applied revision are replayed,
+    /// or the server asks for a catchup.
+    pub async fn subscribe(&self) -> Result<LiveSync<'_>, Error> {
+        let conn = self.db.connect().map_err(Error::Database)?;
+        let server_revision: Option<i64> = read_replica_value(&conn, SERVER_REVISION_KEY).await?;
+        let path = match &self.database_id {
+            Some(database_id) => format!(
+                "/sync/events?{}",
+                form_urlencoded::Serializer::new(String::new())
+                    .append_pair("databaseId", database_id)
+                    .finish()
+            ),
+            None => "/sync/events".to_string(),
+        };
+        let mut request = self.request(Method::GET, &path)?;
+        if let Some(server_revision) = server_revision {
+            request = request.header("last-event-id", server_revision.to_string());
+        }
+        let response = self
+            .http
+            .request(request.body(Body::empty()).map_err(Error::Request)?)
+            .await
+            .map_err(Error::Http)?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+
+        Ok(LiveSync {
+            client: self,
+            body: response.into_body(),
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Run a generated read query against the replica.
+    pub async fn query(&self, query_id: &str, input: JsonValue) -> Result<JsonValue, Error> {
+        let query = self
+            .manifest
+            .queries
+            .get(query_id)
+            .ok_or_else(|| Error::Query(query::Error::UnknownQuery(query_id.to_string())))?;
+        if query.operation != "query" {
+            return Err(Error::NotAQuery(query_id.to_string()));
+        }
+        let conn = self.db.connect().map_err(Error::Database)?;
+        query::run(&conn, &self.manifest, query_id, input, &self.session)
+            .await
+            .map(|result| result.response)
+            .map_err(Error::Query)
+    }
+
+    /// Run a query or mutation on the server and return its result. When subscribed,
+    /// the mutation's own delta comes back with the result and is applied to the replica.
+    pub async fn mutate(&self, query_id: &str, input: JsonValue) -> Result<JsonValue, Error> {
+        let mut params = form_urlencoded::Serializer::new(String::new());
+        params.append_pair("sync", "true");
+        if let Some(database_id) = &self.database_id {
+            params.append_pair("databaseId", database_id);
+        }
+        if let Some(connection_id) = self.connection_id() {
+            params.append_pair("connectionId", &connection_id);
+        }
+        let path = format!("/db/{}?{}", query_id, params.finish());
+        let mut response = self.post(path, input).await?;
+        // The origin's response is wrapped as `{ serverRevision, sync, result }`.
+        let Some(sync) = response.get_mut("sync").map(JsonValue::take) else {
+            return Ok(response);
+        };
+        let message: DeltaMessage = serde_json::from_value(sync).map_err(Error::Json)?;
+        self.apply_delta(&message).await?;
+        Ok(response
+            .get_mut("result")
+            .map(JsonValue::take)
+            .unwrap_or(JsonValue::Null))
+    }
+
+    fn connection_id(&self) -> Option<String> {
+        self.connection_id
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .clone()
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<hyper::http::request::Builder, Error> {
+        let uri: hyper::Uri = format!("{}{}", self.url, path)
+            .parse()
+            .map_err(|_| Error::InvalidUrl(format!("{}{}", self.url, path)))?;
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(MANIFEST_VERSION_HEADER, self.manifest.version_hash());
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        Ok(request)
+    }
+
+    async fn post(&self, path: String, body: JsonValue) -> Result<JsonValue, Error> {
+        let request = self.request(Method::POST, &path)?;
+        let (_, bytes) = self.send(request, body).await?;
+        serde_json::from_slice(&bytes).map_err(Error::Json)
+    }
+
+    /// Send a JSON body, returning the response's content type and body.
+    async fn send(
+        &self,
+        request: hyper::http::request::Builder,
+        body: JsonValue,
+    ) -> Result<(Option<String>, hyper::body::Bytes), Error> {
+        let request = request
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .map_err(Error::Request)?;
+        let response = self.http.request(request).await.map_err(Error::Http)?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let bytes = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(Error::Http)?;
+        Ok((content_type, bytes))
+    }
+
+    /// Apply a catchup page and move the cursor past it in one transaction.
+    async fn apply_page(
+        &self,
+        mut sync_cursor: SyncCursor,
+        page: &SyncPageResult,
+    ) -> Result<(), Error> {
+        let conn = self.db.connect().map_err(Error::Database)?;
+        let transaction = conn.transaction().await.map_err(Error::Database)?;
+        for (table_name, data) in &page.tables {
+            if let Some(table) = self.tables.get(table_name) {
+                if data.reset {
+                    transaction
+                        .execute(&format!("delete from {}", quote(table_name)), ())
+                        .await
+                        .map_err(Error::Database)?;
+                }
+                for key in &data.deleted {
+                    delete_row(&transaction, table_name, table, key).await?;
+                }
+                for row in &data.rows {
+                    if let JsonValue::Object(row) = row {
+                        write_row(&transaction, table_name, table, row).await?;
+                    }
+                }
+            }
+
+            let previous = sync_cursor.remove(table_name);
+            sync_cursor.insert(
+                table_name.clone(),
+                TableCursor {
+                    last_seen_updated_at: data.last_seen_updated_at.or(previous
+                        .as_ref()
+                        .and_then(|cursor| cursor.last_seen_updated_at)),
+                    last_seen_key: data.last_seen_key.clone().or(previous
+                        .as_ref()
+                        .and_then(|cursor| cursor.last_seen_key.clone())),
+                    permission_hash: data.permission_hash.clone(),
+                    last_seen_tombstone: data
+                        .last_seen_tombstone
+                        .or(previous.and_then(|cursor| cursor.last_seen_tombstone)),
+                },
+            );
+        }
+        write_replica_value(&transaction, CURSOR_KEY, &sync_cursor).await?;
+        if let Some(server_revision) = page.server_revision {
+            write_replica_value(&transaction, SERVER_REVISION_KEY, &server_revision).await?;
+        }
+        transaction.commit().await.map_err(Error::Database)
+    }
+
+    async fn write_group(
+        &self,
+        conn: &libsql::Connection,
+        group: &AffectedRowTableGroup,
+    ) -> Result<(), Error> {
+        let Some(table) = self.tables.get(&group.table_name) else {
+            return Ok(());
+        };
+        for values in &group.rows {
+            let row = group
+                .headers
+                .iter()
+                .cloned()
+                .zip(values.iter().cloned())
+                .collect::<serde_json::Map<String, JsonValue>>();
+            write_row(conn, &group.table_name, table, &row).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The events of an open `/sync/events` stream.
+pub struct LiveSync<'a> {
+    client: &'a SyncClient,
+    body: Body,
+    buffer: Vec<u8>,
+}
+
+impl LiveSync<'_> {
+    /// Wait for the next event and apply it to the replica. `None` once the server
+    /// closes the stream; subscribe again to resume from the last applied revision.
+    pub async fn next(&mut self) -> Result<Option<SyncEvent>, Error> {
+        loop {
+            let Some(data) = self.next_data().await? else {
+                return Ok(None);
+            };
+            let message: JsonValue = serde_json::from_str(&data).map_err(Error::Json)?;
+            match message.get("type").and_then(JsonValue::as_str) {
+                Some("connected") => {
+                    let connection_id = message
+                        .get("connectionId")
+                        .and_then(JsonValue::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    *self
+                        .client
+                        .connection_id
+                        .lock()
+                        .unwrap_or_else(|error| error.into_inner()) = Some(connection_id.clone());
+                    return Ok(Some(SyncEvent::Connected { connection_id }));
+                }
+                Some("delta") => {
+                    let message: DeltaMessage =
+                        serde_json::from_value(message).map_err(Error::Json)?;
+                    self.client.apply_delta(&message).await?;
+                    return Ok(Some(SyncEvent::Delta(message)));
+                }
+                Some("syncRequired") => {
+                    self.client.catchup().await?;
+                    return Ok(Some(SyncEvent::CaughtUp));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The `data` of the next SSE event, skipping keep-alive comments.
+    async fn next_data(&mut self) -> Result<Option<String>, Error> {
+        loop {
+            if let Some(end) = self.buffer.windows(2).position(|window| window == b"\n\n") {
+                let event = self.buffer.drain(..end + 2).collect::<Vec<u8>>();
+                let data = String::from_utf8_lossy(&event)
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                    .collect::<Vec<&str>>()
+                    .join("\n");
+                if !data.is_empty() {
+                    return Ok(Some(data));
+                }
+                continue;
+            }
+
+            match self.body.data().await {
+                Some(chunk) => {
+                    let chunk = chunk.map_err(Error::Http)?;
+                    self.buffer
+                        .extend(chunk.iter().filter(|byte| **byte != b'\r'));
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Bring the replica's tables in line with `schema_source`.
+async fn migrate_replica(conn: &libsql::Connection, schema_source: &str) -> Result<(), Error> {
+    let introspection = introspect_database(conn).await.map_err(Error::Schema)?;
+    let migration = migrate::migrate_dynamic(
+        REPLICA_MIGRATION.to_string(),
+        &introspection,
+        schema_source,
+        "schema.pyre",
+    )
+    .map_err(Error::Migration)?;
+    if migration.sql.is_empty() {
+        return Ok(());
+    }
+
+    let transaction = conn.transaction().await.map_err(Error::Database)?;
+    for statement in migration.sql.iter().chain([&migration.mark_success]) {
+        match statement {
+            SqlAndParams::Sql(sql) => transaction
+                .execute_batch(sql)
+                .await
+                .map(|_| ())
+                .map_err(Error::Database)?,
+            SqlAndParams::SqlWithParams { sql, args } => transaction
+                .execute(sql, libsql::params_from_iter(args.clone()))
+                .await
+                .map(|_| ())
+                .map_err(Error::Database)?,
+        }
+    }
+    transaction.commit().await.map_err(Error::Database)
+}
+
+fn replica_tables(context: &typecheck::Context) -> HashMap<String, ReplicaTable> {
+    context
+        .tables
+        .values()
+        .map(|table| {
+            let columns = typecheck::to_sql_column_info(context, &table.record.fields)
+                .into_iter()
+                .filter(|column| {
+                    !column
+                        .directives
+                        .iter()
+                        .any(|directive| matches!(directive, ast::ColumnDirective::Computed(_)))
+                })
+                .map(|column| column.name)
+                .collect();
+            (
+                ast::get_tablename(&table.record.name, &table.record.fields),
+                ReplicaTable {
+                    columns,
+                    primary_key: ast::get_primary_key_field_names(&table.record.fields),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Insert a row, or update the row with its primary key.
+async fn write_row(
+    conn: &libsql::Connection,
+    table_name: &str,
+    table: &ReplicaTable,
+    row: &serde_json::Map<String, JsonValue>,
+) -> Result<(), Error> {
+    let columns = table
+        .columns
+        .iter()
+        .filter(|column| row.contains_key(column.as_str()))
+        .collect::<Vec<&String>>();
+    if columns.is_empty() {
+        return Ok(());
+    }
+    let values = columns
+        .iter()
+        .map(|column| to_libsql(row[column.as_str()].clone()))
+        .collect::<Vec<libsql::Value>>();
+    let updates = columns
+        .iter()
+        .filter(|column| !table.primary_key.contains(column))
+        .map(|column| format!("{} = excluded.{}", quote(column), quote(column)))
+        .collect::<Vec<String>>();
+    let on_conflict = if table.primary_key.is_empty() {
+        String::new()
+    } else if updates.is_empty() {
+        format!(
+            " on conflict ({}) do nothing",
+            quoted_list(&table.primary_key)
+        )
+    } else {
+        format!(
+            " on conflict ({}) do update set {}",
+            quoted_list(&table.primary_key),
+            updates.join(", ")
+        )
+    };
+    let sql = format!(
+        "insert into {} ({}) values ({}){}",
+        quote(table_name),
+        columns
+            .iter()
+            .map(|column| quote(column))
+            .collect::<Vec<String>>()
+            .join(", "),
+        vec!["?"; columns.len()].join(", "),
+        on_conflict
+    );
+    conn.execute(&sql, libsql::params_from_iter(values))
+        .await
+        .map(|_| ())
+        .map_err(Error::Database)
+}
+
+/// Delete the row with `key`, an object keyed by primary key column.
+async fn delete_row(
+    conn: &libsql::Connection,
+    table_name: &str,
+    table: &ReplicaTable,
+    key: &JsonValue,
+) -> Result<(), Error> {
+    let Some(key) = key.as_object() else {
+        return Ok(());
+    };
+    if table.primary_key.is_empty()
+        || !table
+            .primary_key
+            .iter()
+            .all(|column| key.contains_key(column))
+    {
+        return Ok(());
+    }
+    let conditions = table
+        .primary_key
+        .iter()
+        .map(|column| format!("{} = ?", quote(column)))
+        .collect::<Vec<String>>();
+    let values = table
+        .primary_key
+        .iter()
+        .map(|column| to_libsql(key[column].clone()))
+        .collect::<Vec<libsql::Value>>();
+    conn.execute(
+        &format!(
+            "delete from {} where {}",
+            quote(table_name),
+            conditions.join(" and ")
+        ),
+        libsql::params_from_iter(values),
+    )
+    .await
+    .map(|_| ())
+    .map_err(Error::Database)
+}
+
+async fn read_replica_value<T: serde::de::DeserializeOwned>(
+    conn: &libsql::Connection,
+    key: &str,
+) -> Result<Option<T>, Error> {
+    let mut rows = conn
+        .query(READ_REPLICA_VALUE, [key])
+        .await
+        .map_err(Error::Database)?;
+    let Some(row) = rows.next().await.map_err(Error::Database)? else {
+        return Ok(None);
+    };
+    let value: String = row.get(0).map_err(Error::Database)?;
+    serde_json::from_str(&value).map(Some).map_err(Error::Json)
+}
+
+async fn write_replica_value<T: serde::Serialize>(
+    conn: &libsql::Connection,
+    key: &str,
+    value: &T,
+) -> Result<(), Error> {
+    let value = serde_json::to_string(value).map_err(Error::Json)?;
+    conn.execute(WRITE_REPLICA_VALUE, [key, value.as_str()])
+        .await
+        .map(|_| ())
+        .map_err(Error::Database)
+}
+
+fn to_libsql(value: JsonValue) -> libsql::Value {
+    match value {
+        JsonValue::Null => libsql::Value::Null,
+        JsonValue::Bool(value) => libsql::Value::Integer(value as i64),
+        JsonValue::Number(number) => match number.as_i64() {
+            Some(value) => libsql::Value::Integer(value),
+            None => libsql::Value::Real(number.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(value) => libsql::Value::Text(value),
+        JsonValue::Array(_) | JsonValue::Object(_) => libsql::Value::Text(value.to_string()),
+    }
+}
+
+fn quote(name: &str) -> String {
+    crate::ext::string::quote(name)
+}
+
+fn quoted_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| quote(name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+async fn status_error(response: hyper::Response<Body>) -> Error {
+    let status = response.status().as_u16();
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+        .unwrap_or_default();
+    Error::Status { status, body }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Database(libsql::Error),
+    /// A catchup page could not be decoded.
+    Encoding(sync_encoding::Error),
+    Http(hyper::Error),
+    InvalidUrl(String),
+    Json(serde_json::Error),
+    Migration(Vec<crate::error::Error>),
+    /// Only read queries run against the replica. Send mutations with `mutate`.
+    NotAQuery(String),
+    Query(query::Error),
+    Request(hyper::http::Error),
+    Schema(schema::Error),
+    Session(manifest::Error),
+    /// The server answered with an error status.
+    Status {
+        status: u16,
+        body: String,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Database(error) => write!(f, "database error: {}", error),
+            Error::Encoding(error) => write!(f, "{}", error),
+            Error::Http(error) => write!(f, "http error: {}", error),
+            Error::InvalidUrl(url) => write!(f, "invalid url: {}", url),
+            Error::Json(error) => write!(f, "json error: {}", error),
+            Error::Migration(errors) => {
+                write!(f, "the replica schema failed with {} errors", errors.len())
+            }
+            Error::NotAQuery(query_id) => {
+                write!(f, "only read queries run on the replica: {}", query_id)
+            }
+            Error::Query(error) => write!(f, "{}", error),
+            Error::Request(error) => write!(f, "invalid request: {}", error),
+            Error::Schema(error) => write!(f, "{}", error),
+            Error::Session(error) => write!(f, "invalid session: {}", error),
+            Error::Status { status, body } => write!(f, "server returned {}: {}", status, body),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -18,6 +18,17 @@ pub fn is_empty(diff: &Diff) -> bool {
 pub struct DetailedRecordDiff {
     pub name: String,
     pub changes: Vec<RecordChange>,
+    /// Set when a change can't be made with `alter table`, so the table has to be recreated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rebuild: Option<TableRebuild>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableRebuild {
+    /// The table as it should exist after the migration.
+    pub table: crate::db::introspect::Table,
+    /// Columns that exist both before and after, whose data is copied across.
+    pub copied_columns: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,12 +38,25 @@ pub enum RecordChange {
     ModifiedField { name: String, changes: ColumnDiff },
     AddedIndex(crate::db::introspect::IndexInfo),
     RemovedIndex(crate::db::introspect::IndexInfo),
+    ModifiedTableOptions(TableOptionsDiff),
+    ModifiedPrimaryKey { old: Vec<String>, new: Vec<String> },
+    AddedTombstoneTrigger { primary_key: Vec<String> },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ColumnDiff {
     pub type_changed: Option<(String, String)>, // (old_type, new_type)
     pub nullable_changed: Option<(bool, bool)>, // (old_nullable, new_nullable)
+    #[serde(default)]
+    pub collation_changed: Option<(Option<crate::ast::Collation>, Option<crate::ast::Collation>)>, // (old_collation, new_collation)
+    #[serde(default)]
+    pub generated_changed: Option<(Option<String>, Option<String>)>, // (old_expression, new_expression)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableOptionsDiff {
+    pub strict_changed: Option<(bool, bool)>, // (old_strict, new_strict)
+    pub without_rowid_changed: Option<(bool, bool)>, // (old_without_rowid, new_without_rowid)
 }
 
 pub fn diff(
@@ -153,6 +177,8 @@ fn add_fields(
 
             let default_value = if crate::ast::is_managed_timestamp(col) {
                 Some("(unixepoch())".to_string())
+            } else if crate::ast::is_version(col) {
+                Some("(1)".to_string())
             } else {
                 col.directives.iter().find_map(|d| match d {
                     crate::ast::ColumnDirective::Default { value, .. } => {
@@ -178,6 +204,8 @@ fn add_fields(
                             .directives
                             .iter()
                             .any(|d| matches!(d, crate::ast::ColumnDirective::Index)),
+                        collation: crate::ast::get_collation(col),
+                        generated: crate::ast::get_computed(col).map(|expr| expr.to_sql()),
                     });
                 }
                 crate::ast::SerializationType::FromType(typename) => {
@@ -196,6 +224,8 @@ fn add_fields(
                                         .directives
                                         .iter()
                                         .any(|d| matches!(d, crate::ast::ColumnDirective::Index)),
+                                    collation: None,
+                                    generated: None,
                                 });
 
                                 for variant in variants {
@@ -226,6 +256,9 @@ fn add_fields(
                                         .directives
                                         .iter()
                                         .any(|d| matches!(d, crate::ast::ColumnDirective::Index)),
+                                    collation: crate::ast::get_collation(col),
+                                    generated: crate::ast::get_computed(col)
+                                        .map(|expr| expr.to_sql()),
                                 });
                             }
                         }
@@ -366,6 +399,13 @@ fn create_table_from_fields(
         false,
     );
 
+    let primary_key = crate::ast::get_primary_key_field_names(fields);
+    for column in columns.iter_mut() {
+        if primary_key.contains(&column.name) {
+            column.pk = true;
+        }
+    }
+
     let table_name = crate::ast::get_tablename(name, fields);
 
     let mut indexes: Vec<crate::db::introspect::IndexInfo> = crate::ast::collect_indexes(fields)
@@ -405,11 +445,16 @@ fn create_table_from_fields(
         }
     }
 
+    let tombstone_trigger = !primary_key.is_empty();
     crate::db::introspect::Table {
         name: table_name,
         columns,
         foreign_keys: vec![],
         indexes,
+        primary_key,
+        strict: crate::ast::is_strict(fields),
+        without_rowid: crate::ast::is_without_rowid(fields),
+        tombstone_trigger,
     }
 }
 
@@ -556,12 +601,30 @@ fn compare_record(
                     None
                 };
 
-                if type_changed.is_some() || nullable_changed.is_some() {
+                let collation_changed = if schema_col.collation != intro_col.collation {
+                    Some((intro_col.collation, schema_col.collation))
+                } else {
+                    None
+                };
+
+                let generated_changed = if schema_col.generated != intro_col.generated {
+                    Some((intro_col.generated.clone(), schema_col.generated.clone()))
+                } else {
+                    None
+                };
+
+                if type_changed.is_some()
+                    || nullable_changed.is_some()
+                    || collation_changed.is_some()
+                    || generated_changed.is_some()
+                {
                     changes.push(RecordChange::ModifiedField {
                         name: name.to_string(),
                         changes: ColumnDiff {
                             type_changed,
                             nullable_changed,
+                            collation_changed,
+                            generated_changed,
                         },
                     });
                 }
@@ -569,6 +632,36 @@ fn compare_record(
         }
     }
 
+    let strict_changed = if schema_table.strict != intro_table.strict {
+        Some((intro_table.strict, schema_table.strict))
+    } else {
+        None
+    };
+    let without_rowid_changed = if schema_table.without_rowid != intro_table.without_rowid {
+        Some((intro_table.without_rowid, schema_table.without_rowid))
+    } else {
+        None
+    };
+    if strict_changed.is_some() || without_rowid_changed.is_some() {
+        changes.push(RecordChange::ModifiedTableOptions(TableOptionsDiff {
+            strict_changed,
+            without_rowid_changed,
+        }));
+    }
+
+    if schema_table.primary_key != intro_table.primary_key {
+        changes.push(RecordChange::ModifiedPrimaryKey {
+            old: intro_table.primary_key.clone(),
+            new: schema_table.primary_key.clone(),
+        });
+    }
+
+    if schema_table.tombstone_trigger && !intro_table.tombstone_trigger {
+        changes.push(RecordChange::AddedTombstoneTrigger {
+            primary_key: schema_table.primary_key.clone(),
+        });
+    }
+
     // Find removed columns
     for name in intro_columns.keys() {
         if !schema_columns.contains_key(name) {
@@ -678,6 +771,8 @@ fn compare_record(
                     default_value: None,
                     pk: false,
                     indexed: false,
+                    collation: None,
+                    generated: None,
                 },
             ));
         }
@@ -688,11 +783,47 @@ fn compare_record(
     } else {
         Some(DetailedRecordDiff {
             name: schema_table.name.clone(),
+            rebuild: table_rebuild(schema_table, intro_table, &changes),
             changes,
         })
     }
 }
 
+/// SQLite can't change table options, primary keys, column collations or generated
+/// column expressions in place, so those changes recreate the table and copy its rows across.
+fn table_rebuild(
+    schema_table: &crate::db::introspect::Table,
+    intro_table: &crate::db::introspect::Table,
+    changes: &[RecordChange],
+) -> Option<TableRebuild> {
+    let needs_rebuild = changes.iter().any(|change| match change {
+        RecordChange::ModifiedTableOptions(_) | RecordChange::ModifiedPrimaryKey { .. } => true,
+        RecordChange::ModifiedField { changes, .. } => {
+            changes.collation_changed.is_some() || changes.generated_changed.is_some()
+        }
+        _ => false,
+    });
+    if !needs_rebuild {
+        return None;
+    }
+
+    Some(TableRebuild {
+        table: schema_table.clone(),
+        copied_columns: schema_table
+            .columns
+            .iter()
+            .filter(|column| {
+                // Generated columns can't be written to, SQLite fills them in.
+                column.generated.is_none()
+                    && intro_table.columns.iter().any(|existing| {
+                        existing.name == column.name && existing.generated.is_none()
+                    })
+            })
+            .map(|column| column.name.clone())
+            .collect(),
+    })
+}
+
 fn index_signature(index: &crate::db::introspect::IndexInfo) -> String {
     let columns = index
         .columns
@@ -13,45 +13,18 @@ pub fn to_sql(diff: &Diff) -> Vec<SqlAndParams> {
 
     // Handle added tables
     for table in &diff.added {
-        let columns: Vec<String> = table.columns.iter().map(column_definition).collect();
-
-        let mut create_stmt = format!(
-            "create table \"{}\" (\n  {}\n)",
-            table.name,
-            columns.join(",\n  ")
-        );
-
-        // Add foreign key constraints if any exist
-        if !table.foreign_keys.is_empty() {
-            create_stmt = create_stmt.trim_end_matches(')').to_string();
-            for fk in &table.foreign_keys {
-                create_stmt.push_str(&format!(
-                    ",\n  foreign key ({}) references {}({})",
-                    fk.from, fk.table, fk.to
-                ));
-            }
-            create_stmt.push(')');
-        }
-
-        sql_statements.push(SqlAndParams::Sql(create_stmt));
-
-        for index in &table.indexes {
-            sql_statements.push(SqlAndParams::Sql(render_index_sql(&table.name, index)));
-        }
-
-        // Legacy support for column-level @index directives.
-        for column in &table.columns {
-            if column.indexed && !has_column_index(table.indexes.iter(), &column.name) {
-                sql_statements.push(SqlAndParams::Sql(format!(
-                    "create index if not exists \"idx_{}_{}\" on \"{}\" (\"{}\")",
-                    table.name, column.name, table.name, column.name
-                )));
-            }
-        }
+        sql_statements.push(SqlAndParams::Sql(create_table_sql(&table.name, table)));
+        push_index_statements(&table.name, table, &mut sql_statements);
+        push_tombstone_trigger(&table.name, table, &mut sql_statements);
     }
 
     // Handle modified tables
     for record_diff in &diff.modified_records {
+        if let Some(rebuild) = &record_diff.rebuild {
+            push_rebuild_statements(&record_diff.name, rebuild, &mut sql_statements);
+            continue;
+        }
+
         let added_indexes: Vec<&crate::db::introspect::IndexInfo> = record_diff
             .changes
             .iter()
@@ -105,6 +78,16 @@ pub fn to_sql(diff: &Diff) -> Vec<SqlAndParams> {
                         index.name
                     )));
                 }
+                RecordChange::ModifiedTableOptions(_) | RecordChange::ModifiedPrimaryKey { .. } => {
+                    // Table options and primary keys always come with a rebuild, handled above.
+                }
+                RecordChange::AddedTombstoneTrigger { primary_key } => {
+                    if let Some(sql) =
+                        crate::db::migrate::tombstone_trigger_sql(&record_diff.name, primary_key)
+                    {
+                        sql_statements.push(SqlAndParams::Sql(sql));
+                    }
+                }
             }
         }
     }
@@ -112,9 +95,145 @@ pub fn to_sql(diff: &Diff) -> Vec<SqlAndParams> {
     sql_statements
 }
 
+fn create_table_sql(name: &str, table: &crate::db::introspect::Table) -> String {
+    // Composite keys, and every key of a `without rowid` table, are declared as a
+    // table constraint. `without rowid` tables can't use autoincrement.
+    let key_constraint = table.primary_key.len() > 1 || table.without_rowid;
+
+    let mut definitions: Vec<String> = table
+        .columns
+        .iter()
+        .map(|column| {
+            if key_constraint {
+                column_definition(&crate::db::introspect::ColumnInfo {
+                    pk: false,
+                    ..column.clone()
+                })
+            } else {
+                column_definition(column)
+            }
+        })
+        .collect();
+
+    if key_constraint && !table.primary_key.is_empty() {
+        definitions.push(format!(
+            "primary key ({})",
+            table
+                .primary_key
+                .iter()
+                .map(|name| format!("\"{}\"", name))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+    }
+
+    for fk in &table.foreign_keys {
+        definitions.push(format!(
+            "foreign key ({}) references {}({})",
+            fk.from, fk.table, fk.to
+        ));
+    }
+
+    let mut options = Vec::new();
+    if table.strict {
+        options.push("strict");
+    }
+    if table.without_rowid {
+        options.push("without rowid");
+    }
+
+    let mut create_stmt = format!(
+        "create table \"{}\" (\n  {}\n)",
+        name,
+        definitions.join(",\n  ")
+    );
+    if !options.is_empty() {
+        create_stmt.push(' ');
+        create_stmt.push_str(&options.join(", "));
+    }
+    create_stmt
+}
+
+fn push_index_statements(
+    name: &str,
+    table: &crate::db::introspect::Table,
+    sql_statements: &mut Vec<SqlAndParams>,
+) {
+    for index in &table.indexes {
+        sql_statements.push(SqlAndParams::Sql(render_index_sql(name, index)));
+    }
+
+    // Legacy support for column-level @index directives.
+    for column in &table.columns {
+        if column.indexed && !has_column_index(table.indexes.iter(), &column.name) {
+            sql_statements.push(SqlAndParams::Sql(format!(
+                "create index if not exists \"idx_{}_{}\" on \"{}\" (\"{}\")",
+                name, column.name, name, column.name
+            )));
+        }
+    }
+}
+
+/// Recreate a table using SQLite's copy-and-rename procedure.
+///
+/// Dropping the old table also drops its indexes and triggers, so they are
+/// created again once the new table has been renamed into place.
+fn push_rebuild_statements(
+    name: &str,
+    rebuild: &TableRebuild,
+    sql_statements: &mut Vec<SqlAndParams>,
+) {
+    let temporary_name = format!("_pyre_rebuild_{}", name);
+
+    sql_statements.push(SqlAndParams::Sql(create_table_sql(
+        &temporary_name,
+        &rebuild.table,
+    )));
+
+    if !rebuild.copied_columns.is_empty() {
+        let columns = rebuild
+            .copied_columns
+            .iter()
+            .map(|column| format!("\"{}\"", column))
+            .collect::<Vec<String>>()
+            .join(", ");
+        sql_statements.push(SqlAndParams::Sql(format!(
+            "insert into \"{}\" ({}) select {} from \"{}\"",
+            temporary_name, columns, columns, name
+        )));
+    }
+
+    sql_statements.push(SqlAndParams::Sql(format!("drop table \"{}\"", name)));
+    sql_statements.push(SqlAndParams::Sql(format!(
+        "alter table \"{}\" rename to \"{}\"",
+        temporary_name, name
+    )));
+
+    push_index_statements(name, &rebuild.table, sql_statements);
+    push_tombstone_trigger(name, &rebuild.table, sql_statements);
+}
+
+fn push_tombstone_trigger(
+    name: &str,
+    table: &crate::db::introspect::Table,
+    sql_statements: &mut Vec<SqlAndParams>,
+) {
+    if let Some(sql) = crate::db::migrate::tombstone_trigger_sql(name, &table.primary_key) {
+        sql_statements.push(SqlAndParams::Sql(sql));
+    }
+}
+
 fn column_definition(column: &crate::db::introspect::ColumnInfo) -> String {
     let mut def = format!("`{}` {}", column.name, column.column_type);
 
+    if let Some(collation) = &column.collation {
+        def.push_str(&format!(" collate {}", collation.to_sql()));
+    }
+
+    if let Some(expression) = &column.generated {
+        def.push_str(&format!(" generated always as ({})", expression));
+    }
+
     if column.pk {
         if column.column_type.eq_ignore_ascii_case("INTEGER") {
             def.push_str(" primary key autoincrement");
@@ -193,6 +312,8 @@ mod tests {
             default_value: None,
             pk: true,
             indexed: false,
+            collation: None,
+            generated: None,
         };
 
         let sql = column_definition(&col);
@@ -209,6 +330,8 @@ mod tests {
             default_value: None,
             pk: true,
             indexed: false,
+            collation: None,
+            generated: None,
         };
 
         let sql = column_definition(&col);
@@ -216,6 +339,188 @@ mod tests {
         assert!(!sql.contains("autoincrement"));
     }
 
+    #[test]
+    fn composite_primary_keys_use_a_table_constraint() {
+        let key_column = |name: &str| crate::db::introspect::ColumnInfo {
+            cid: 0,
+            name: name.to_string(),
+            column_type: "INTEGER".to_string(),
+            notnull: true,
+            default_value: None,
+            pk: true,
+            indexed: false,
+            collation: None,
+            generated: None,
+        };
+        let diff = Diff {
+            added: vec![crate::db::introspect::Table {
+                name: "memberships".to_string(),
+                columns: vec![key_column("orgId"), key_column("userId")],
+                foreign_keys: vec![],
+                indexes: vec![],
+                primary_key: vec!["orgId".to_string(), "userId".to_string()],
+                strict: false,
+                without_rowid: false,
+                tombstone_trigger: true,
+            }],
+            removed: vec![],
+            modified_records: vec![],
+        };
+
+        let sql = to_sql(&diff);
+        let SqlAndParams::Sql(create) = &sql[0] else {
+            panic!("expected a plain create table statement");
+        };
+
+        assert!(create.contains("primary key (\"orgId\", \"userId\")"));
+        assert!(!create.contains("autoincrement"));
+    }
+
+    #[test]
+    fn added_tables_get_a_tombstone_trigger_keyed_by_primary_key() {
+        let key_column = |name: &str| crate::db::introspect::ColumnInfo {
+            cid: 0,
+            name: name.to_string(),
+            column_type: "INTEGER".to_string(),
+            notnull: true,
+            default_value: None,
+            pk: true,
+            indexed: false,
+            collation: None,
+            generated: None,
+        };
+        let diff = Diff {
+            added: vec![crate::db::introspect::Table {
+                name: "memberships".to_string(),
+                columns: vec![key_column("orgId"), key_column("userId")],
+                foreign_keys: vec![],
+                indexes: vec![],
+                primary_key: vec!["orgId".to_string(), "userId".to_string()],
+                strict: false,
+                without_rowid: false,
+                tombstone_trigger: true,
+            }],
+            removed: vec![],
+            modified_records: vec![],
+        };
+
+        let sql = to_sql(&diff);
+        let Some(SqlAndParams::Sql(trigger)) = sql.last() else {
+            panic!("expected a plain create trigger statement");
+        };
+
+        assert_eq!(
+            trigger,
+            "create trigger if not exists \"_pyre_tombstone_memberships\" after delete on \"memberships\" begin insert into _pyre_tombstones (table_name, row_key) values ('memberships', json_object('orgId', old.\"orgId\", 'userId', old.\"userId\")); end"
+        );
+    }
+
+    #[test]
+    fn table_options_and_collations_are_rendered() {
+        let diff = Diff {
+            added: vec![crate::db::introspect::Table {
+                name: "accounts".to_string(),
+                columns: vec![crate::db::introspect::ColumnInfo {
+                    cid: 0,
+                    name: "email".to_string(),
+                    column_type: "TEXT".to_string(),
+                    notnull: true,
+                    default_value: None,
+                    pk: true,
+                    indexed: false,
+                    collation: Some(crate::ast::Collation::NoCase),
+                    generated: None,
+                }],
+                foreign_keys: vec![],
+                indexes: vec![],
+                primary_key: vec!["email".to_string()],
+                strict: true,
+                without_rowid: true,
+                tombstone_trigger: true,
+            }],
+            removed: vec![],
+            modified_records: vec![],
+        };
+
+        let sql = to_sql(&diff);
+        let SqlAndParams::Sql(create) = &sql[0] else {
+            panic!("expected a plain create table statement");
+        };
+
+        assert!(create.contains("`email` TEXT collate nocase not null"));
+        assert!(create.contains("primary key (\"email\")"));
+        assert!(create.ends_with(") strict, without rowid"));
+    }
+
+    #[test]
+    fn rebuilds_replace_the_table_and_recreate_its_indexes() {
+        let column = |name: &str| crate::db::introspect::ColumnInfo {
+            cid: 0,
+            name: name.to_string(),
+            column_type: "TEXT".to_string(),
+            notnull: true,
+            default_value: None,
+            pk: false,
+            indexed: false,
+            collation: None,
+            generated: None,
+        };
+        let diff = Diff {
+            added: vec![],
+            removed: vec![],
+            modified_records: vec![DetailedRecordDiff {
+                name: "users".to_string(),
+                changes: vec![RecordChange::ModifiedTableOptions(TableOptionsDiff {
+                    strict_changed: Some((false, true)),
+                    without_rowid_changed: None,
+                })],
+                rebuild: Some(TableRebuild {
+                    table: crate::db::introspect::Table {
+                        name: "users".to_string(),
+                        columns: vec![column("email"), column("nickname")],
+                        foreign_keys: vec![],
+                        indexes: vec![crate::db::introspect::IndexInfo {
+                            name: "uniq_users_email".to_string(),
+                            unique: true,
+                            columns: vec![crate::db::introspect::IndexedColumnInfo {
+                                name: "email".to_string(),
+                                desc: false,
+                            }],
+                            where_clause: None,
+                        }],
+                        primary_key: vec![],
+                        strict: true,
+                        without_rowid: false,
+                        tombstone_trigger: true,
+                    },
+                    copied_columns: vec!["email".to_string()],
+                }),
+            }],
+        };
+
+        let sql: Vec<String> = to_sql(&diff)
+            .into_iter()
+            .map(|statement| match statement {
+                SqlAndParams::Sql(sql) => sql,
+                SqlAndParams::SqlWithParams { sql, .. } => sql,
+            })
+            .collect();
+
+        assert_eq!(sql.len(), 5);
+        assert!(sql[0].starts_with("create table \"_pyre_rebuild_users\""));
+        assert!(sql[0].ends_with(") strict"));
+        assert_eq!(
+            sql[1],
+            "insert into \"_pyre_rebuild_users\" (\"email\") select \"email\" from \"users\""
+        );
+        assert_eq!(sql[2], "drop table \"users\"");
+        assert_eq!(
+            sql[3],
+            "alter table \"_pyre_rebuild_users\" rename to \"users\""
+        );
+        assert!(sql[4].contains("\"uniq_users_email\" on \"users\""));
+    }
+
     #[test]
     fn added_table_does_not_duplicate_column_level_index() {
         let diff = Diff {
@@ -229,6 +534,8 @@ mod tests {
                     default_value: None,
                     pk: false,
                     indexed: true,
+                    collation: None,
+                    generated: None,
                 }],
                 foreign_keys: vec![],
                 indexes: vec![crate::db::introspect::IndexInfo {
@@ -240,6 +547,10 @@ mod tests {
                     }],
                     where_clause: None,
                 }],
+                primary_key: vec![],
+                strict: false,
+                without_rowid: false,
+                tombstone_trigger: true,
             }],
             removed: vec![],
             modified_records: vec![],
@@ -273,6 +584,7 @@ mod tests {
             removed: vec![],
             modified_records: vec![DetailedRecordDiff {
                 name: "events".to_string(),
+                rebuild: None,
                 changes: vec![
                     RecordChange::AddedField(crate::db::introspect::ColumnInfo {
                         cid: 0,
@@ -282,6 +594,8 @@ mod tests {
                         default_value: None,
                         pk: false,
                         indexed: true,
+                        collation: None,
+                        generated: None,
                     }),
                     RecordChange::AddedIndex(index),
                 ],
@@ -38,7 +38,9 @@ WITH RECURSIVE
     SELECT name 
     FROM sqlite_master 
     WHERE type='table' 
-    AND name NOT IN ('sqlite_sequence', '_pyre_migrations', '_pyre_schema', '_pyre_sync')
+    AND name != 'sqlite_sequence'
+    -- Every table Pyre manages itself is prefixed with `_pyre_`
+    AND name NOT LIKE '\_pyre\_%' ESCAPE '\'
   ),
   -- Get table info for each table
   table_info AS (
@@ -55,7 +57,7 @@ WITH RECURSIVE
         )
       ) as columns_json
     FROM all_tables t
-    CROSS JOIN pragma_table_info(t.name) c
+    CROSS JOIN pragma_table_xinfo(t.name) c
     GROUP BY t.name
   ),
   -- Get foreign key info for each table
@@ -155,12 +157,24 @@ SELECT json_object(
       'name', ti.table_name,
       'columns', jsonb(ti.columns_json),
       'foreign_keys', COALESCE(jsonb(fk.fks_json), jsonb('[]')),
-      'indexes', COALESCE(jsonb(ix.indexes_json), jsonb('[]'))
+      'indexes', COALESCE(jsonb(ix.indexes_json), jsonb('[]')),
+      'primary_key', (
+        SELECT jsonb_group_array(pk.name)
+        FROM (
+          SELECT p.name FROM pragma_table_info(ti.table_name) p WHERE p.pk > 0 ORDER BY p.pk
+        ) pk
+      ),
+      'strict', (SELECT tl.strict FROM pragma_table_list(ti.table_name) tl WHERE tl.schema = 'main'),
+      'without_rowid', (SELECT tl.wr FROM pragma_table_list(ti.table_name) tl WHERE tl.schema = 'main'),
+      'sql', (SELECT sm.sql FROM sqlite_master sm WHERE sm.type = 'table' AND sm.name = ti.table_name),
+      'tombstone_trigger', EXISTS (
+        SELECT 1 FROM sqlite_master sm WHERE sm.type = 'trigger' AND sm.name = '_pyre_tombstone_' || ti.table_name
+      )
     )
   ),
   'migration_state', json((SELECT state_json FROM migration_state)),
   'schema_source', COALESCE(
-    (SELECT schema FROM _pyre_schema ORDER BY created_at DESC LIMIT 1),
+    (SELECT schema FROM _pyre_schema ORDER BY created_at DESC, id DESC LIMIT 1),
     ''
   ),
   'links', jsonb('[]')
@@ -180,7 +194,9 @@ WITH RECURSIVE
     SELECT name 
     FROM sqlite_master 
     WHERE type='table' 
-    AND name NOT IN ('sqlite_sequence', '_pyre_migrations', '_pyre_schema', '_pyre_sync')
+    AND name != 'sqlite_sequence'
+    -- Every table Pyre manages itself is prefixed with `_pyre_`
+    AND name NOT LIKE '\_pyre\_%' ESCAPE '\'
   ),
   -- Get table info for each table
   table_info AS (
@@ -197,7 +213,7 @@ WITH RECURSIVE
         )
       ) as columns_json
     FROM all_tables t
-    CROSS JOIN pragma_table_info(t.name) c
+    CROSS JOIN pragma_table_xinfo(t.name) c
     GROUP BY t.name
   ),
   -- Get foreign key info for each table
@@ -279,7 +295,19 @@ SELECT json_object(
       'name', ti.table_name,
       'columns', json(ti.columns_json),
       'foreign_keys', COALESCE(json(fk.fks_json), json('[]')),
-      'indexes', COALESCE(json(ix.indexes_json), json('[]'))
+      'indexes', COALESCE(json(ix.indexes_json), json('[]')),
+      'primary_key', (
+        SELECT json_group_array(pk.name)
+        FROM (
+          SELECT p.name FROM pragma_table_info(ti.table_name) p WHERE p.pk > 0 ORDER BY p.pk
+        ) pk
+      ),
+      'strict', (SELECT tl.strict FROM pragma_table_list(ti.table_name) tl WHERE tl.schema = 'main'),
+      'without_rowid', (SELECT tl.wr FROM pragma_table_list(ti.table_name) tl WHERE tl.schema = 'main'),
+      'sql', (SELECT sm.sql FROM sqlite_master sm WHERE sm.type = 'table' AND sm.name = ti.table_name),
+      'tombstone_trigger', EXISTS (
+        SELECT 1 FROM sqlite_master sm WHERE sm.type = 'trigger' AND sm.name = '_pyre_tombstone_' || ti.table_name
+      )
     )
   ),
   'migration_state', json('{"NoMigrationTable": null}'),
@@ -355,12 +383,70 @@ pub struct Introspection {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TableJson")]
 pub struct Table {
     pub name: String,
     pub columns: Vec<ColumnInfo>,
     pub foreign_keys: Vec<ForeignKey>,
     #[serde(default)]
     pub indexes: Vec<IndexInfo>,
+    /// Primary key columns in key order. Composite keys list more than one column.
+    #[serde(default)]
+    pub primary_key: Vec<String>,
+    pub strict: bool,
+    pub without_rowid: bool,
+    /// Whether the `_pyre_tombstone_<table>` trigger exists.
+    #[serde(default)]
+    pub tombstone_trigger: bool,
+}
+
+/// `Table` as produced by the introspection SQL.
+///
+/// SQLite has no pragma for column collations or generated column expressions,
+/// so they're read from the table's `create table` statement while decoding.
+#[derive(Deserialize)]
+struct TableJson {
+    name: String,
+    columns: Vec<ColumnInfo>,
+    foreign_keys: Vec<ForeignKey>,
+    #[serde(default)]
+    indexes: Vec<IndexInfo>,
+    #[serde(default)]
+    primary_key: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_boolish")]
+    strict: bool,
+    #[serde(default, deserialize_with = "deserialize_boolish")]
+    without_rowid: bool,
+    #[serde(default)]
+    sql: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_boolish")]
+    tombstone_trigger: bool,
+}
+
+impl From<TableJson> for Table {
+    fn from(json: TableJson) -> Self {
+        let mut columns = json.columns;
+        if let Some(sql) = &json.sql {
+            let constraints = column_constraints(sql);
+            for column in columns.iter_mut() {
+                if let Some(constraint) = constraints.iter().find(|c| c.name == column.name) {
+                    column.collation = constraint.collation;
+                    column.generated = constraint.generated.clone();
+                }
+            }
+        }
+
+        Table {
+            name: json.name,
+            columns,
+            foreign_keys: json.foreign_keys,
+            indexes: json.indexes,
+            primary_key: json.primary_key,
+            strict: json.strict,
+            without_rowid: json.without_rowid,
+            tombstone_trigger: json.tombstone_trigger,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -525,11 +611,19 @@ pub struct ColumnInfo {
     #[serde(rename = "dflt_value")]
     pub default_value: Option<String>,
 
-    #[serde(deserialize_with = "deserialize_notnull")]
+    // `pk` is the column's 1-based position within the primary key, so composite keys report values above 1.
+    #[serde(deserialize_with = "deserialize_boolish")]
     pub pk: bool,
 
     #[serde(default)]
     pub indexed: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collation: Option<ast::Collation>,
+
+    /// The SQL expression of a generated (`@computed`) column.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -648,6 +742,147 @@ pub fn from_raw(mut raw: IntrospectionRaw) -> Introspection {
     }
 }
 
+/// Column constraints SQLite doesn't report through a pragma.
+struct ColumnConstraints {
+    name: String,
+    collation: Option<ast::Collation>,
+    /// The expression of a generated column, without its outer parentheses.
+    generated: Option<String>,
+}
+
+/// Read the `collate` and `generated always as (...)` clauses of each column
+/// definition in a `create table` statement.
+fn column_constraints(create_sql: &str) -> Vec<ColumnConstraints> {
+    let Some(open) = create_sql.find('(') else {
+        return vec![];
+    };
+
+    // Split the body into definitions on top-level commas, skipping quoted text.
+    let mut definitions = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    for c in create_sql[open + 1..].chars() {
+        match quote {
+            Some(close) => {
+                if c == close {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '`' | '\'' => quote = Some(c),
+                '[' => quote = Some(']'),
+                '(' => depth += 1,
+                ')' if depth == 0 => break,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    definitions.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        current.push(c);
+    }
+    definitions.push(current);
+
+    let mut constraints = Vec::new();
+    for definition in definitions {
+        let definition = definition.trim();
+        let (name, rest) = match definition.chars().next() {
+            Some(open @ ('"' | '`' | '[')) => {
+                let close = if open == '[' { ']' } else { open };
+                match definition[1..].find(close) {
+                    Some(end) => (&definition[1..end + 1], &definition[end + 2..]),
+                    None => continue,
+                }
+            }
+            _ => match definition.split_once(char::is_whitespace) {
+                Some((name, rest)) => (name, rest),
+                None => continue,
+            },
+        };
+
+        let lowered = name.to_ascii_lowercase();
+        if matches!(
+            lowered.as_str(),
+            "constraint" | "primary" | "foreign" | "unique" | "check"
+        ) {
+            continue;
+        }
+
+        let generated = generated_expression(rest);
+
+        // Look for `collate` outside of the generated expression.
+        let mut collation = None;
+        let outside_expression = match &generated {
+            Some(expression) => rest.replacen(expression.as_str(), "", 1),
+            None => rest.to_string(),
+        };
+        let mut words = outside_expression.split_whitespace();
+        while let Some(word) = words.next() {
+            if word.eq_ignore_ascii_case("collate") {
+                collation = words
+                    .next()
+                    .map(|word| word.trim_matches(|c| c == '"' || c == '`' || c == '\''))
+                    .and_then(ast::Collation::from_sql);
+                break;
+            }
+        }
+
+        constraints.push(ColumnConstraints {
+            name: name.to_string(),
+            collation,
+            generated,
+        });
+    }
+
+    constraints
+}
+
+/// Find `as (...)` in a column definition and return the text between the parentheses.
+fn generated_expression(definition: &str) -> Option<String> {
+    let lowered = definition.to_ascii_lowercase();
+    let bytes = lowered.as_bytes();
+    let mut search_from = 0;
+    while let Some(found) = lowered[search_from..].find("as") {
+        let at = search_from + found;
+        search_from = at + 2;
+
+        let starts_word = at == 0 || bytes[at - 1].is_ascii_whitespace();
+        let after = lowered[at + 2..].trim_start();
+        if !starts_word || !after.starts_with('(') {
+            continue;
+        }
+
+        let open = definition.len() - after.len();
+        let mut depth = 0;
+        let mut quote: Option<char> = None;
+        for (offset, c) in definition[open..].char_indices() {
+            match quote {
+                Some(close) => {
+                    if c == close {
+                        quote = None;
+                    }
+                }
+                None => match c {
+                    '"' | '`' | '\'' => quote = Some(c),
+                    '(' => depth += 1,
+                    ')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(definition[open + 1..open + offset].trim().to_string());
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+        return None;
+    }
+    None
+}
+
 fn infer_stored_schema_namespace(schema: &ast::Schema) -> Option<String> {
     let mut namespaces = std::collections::HashSet::new();
 
@@ -725,19 +960,13 @@ pub fn extract_links(schema: &ast::Schema, tables: &[Table]) -> Vec<Link> {
                 let table_name = ast::get_tablename(name, fields);
                 let record_links = ast::collect_links(fields);
 
-                // Get primary key field name for this record
-                let primary_key_name = ast::get_primary_id_field_name(fields);
-
                 for link in record_links {
                     // Get the actual table name for the foreign table
                     let foreign_table_name = ast::get_foreign_tablename(schema, &link);
 
                     // Determine link type: if local_ids contains non-primary-key fields, it's many-to-one
                     // Otherwise (all local_ids are primary keys), it's one-to-many (reverse link)
-                    let is_many_to_one = link
-                        .local_ids
-                        .iter()
-                        .any(|id| primary_key_name.as_ref().map(|pk| id != pk).unwrap_or(true));
+                    let is_many_to_one = !ast::ids_are_primary_key(&link.local_ids, fields);
 
                     let link_type = if is_many_to_one {
                         "many-to-one"
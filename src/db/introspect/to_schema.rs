@@ -1,4 +1,7 @@
-use crate::ast::{Column, ColumnDirective, ColumnType, Definition, Field, SchemaFile};
+use crate::ast::{
+    Column, ColumnDirective, ColumnType, ComputedExpr, ComputedOperator, Definition, Field,
+    FieldDirective, SchemaFile,
+};
 use crate::db::introspect::{ColumnInfo, Introspection};
 
 pub fn to_schema(introspection: &Introspection) -> SchemaFile {
@@ -6,10 +9,31 @@ pub fn to_schema(introspection: &Introspection) -> SchemaFile {
 
     for table in &introspection.tables {
         let mut fields = Vec::new();
+        let composite_key = table.primary_key.len() > 1;
+
+        if composite_key {
+            fields.push(Field::FieldDirective(FieldDirective::PrimaryKey(
+                table.primary_key.clone(),
+            )));
+        }
+
+        if table.strict {
+            fields.push(Field::FieldDirective(FieldDirective::Strict));
+        }
+
+        if table.without_rowid {
+            fields.push(Field::FieldDirective(FieldDirective::WithoutRowid));
+        }
 
         // Convert columns to fields
         for column in &table.columns {
-            fields.push(Field::Column(column_info_to_column(column)));
+            let mut column = column_info_to_column(column);
+            if composite_key {
+                column
+                    .directives
+                    .retain(|directive| *directive != ColumnDirective::PrimaryKey);
+            }
+            fields.push(Field::Column(column));
         }
 
         // Add the record definition
@@ -42,6 +66,15 @@ fn column_info_to_column(info: &ColumnInfo) -> Column {
         directives.push(ColumnDirective::Index);
     }
 
+    if let Some(collation) = info.collation {
+        directives.push(ColumnDirective::Collate(collation));
+    }
+
+    // Expressions that don't fit `@computed` are left off, the column is still kept.
+    if let Some(expr) = info.generated.as_deref().and_then(computed_from_sql) {
+        directives.push(ColumnDirective::Computed(expr));
+    }
+
     // Handle not null constraint
     let nullable = !info.notnull;
 
@@ -68,3 +101,217 @@ fn column_info_to_column(info: &ColumnInfo) -> Column {
         inline_comment: None,
     }
 }
+
+#[derive(Debug, Clone, PartialEq)]
+enum SqlToken {
+    Identifier(String),
+    QuotedIdentifier(String),
+    String(String),
+    Number(String),
+    Operator(ComputedOperator),
+    OpenParen,
+    CloseParen,
+    Comma,
+}
+
+/// Read a generated column's SQL expression back into a `@computed` expression.
+pub fn computed_from_sql(sql: &str) -> Option<ComputedExpr> {
+    let tokens = tokenize_sql(sql)?;
+    let mut position = 0;
+    let expr = parse_sql_expr(&tokens, &mut position)?;
+    if position == tokens.len() {
+        Some(expr)
+    } else {
+        None
+    }
+}
+
+fn tokenize_sql(sql: &str) -> Option<Vec<SqlToken>> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            _ if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(SqlToken::OpenParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(SqlToken::CloseParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(SqlToken::Comma);
+                i += 1;
+            }
+            '+' => {
+                tokens.push(SqlToken::Operator(ComputedOperator::Add));
+                i += 1;
+            }
+            '-' => {
+                tokens.push(SqlToken::Operator(ComputedOperator::Subtract));
+                i += 1;
+            }
+            '*' => {
+                tokens.push(SqlToken::Operator(ComputedOperator::Multiply));
+                i += 1;
+            }
+            '/' => {
+                tokens.push(SqlToken::Operator(ComputedOperator::Divide));
+                i += 1;
+            }
+            '|' if chars.get(i + 1) == Some(&'|') => {
+                tokens.push(SqlToken::Operator(ComputedOperator::Concat));
+                i += 2;
+            }
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    let next = *chars.get(i)?;
+                    i += 1;
+                    if next == close {
+                        // A doubled quote is an escaped quote.
+                        if close != ']' && chars.get(i) == Some(&close) {
+                            text.push(close);
+                            i += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    text.push(next);
+                }
+                tokens.push(if c == '\'' {
+                    SqlToken::String(text)
+                } else {
+                    SqlToken::QuotedIdentifier(text)
+                });
+            }
+            _ if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                tokens.push(SqlToken::Number(chars[start..i].iter().collect()));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(SqlToken::Identifier(chars[start..i].iter().collect()));
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+fn parse_sql_expr(tokens: &[SqlToken], position: &mut usize) -> Option<ComputedExpr> {
+    let mut left = parse_sql_term(tokens, position)?;
+    while let Some(SqlToken::Operator(
+        op @ (ComputedOperator::Add | ComputedOperator::Subtract | ComputedOperator::Concat),
+    )) = tokens.get(*position)
+    {
+        *position += 1;
+        let right = parse_sql_term(tokens, position)?;
+        left = ComputedExpr::Binary {
+            op: *op,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+    }
+    Some(left)
+}
+
+fn parse_sql_term(tokens: &[SqlToken], position: &mut usize) -> Option<ComputedExpr> {
+    let mut left = parse_sql_atom(tokens, position)?;
+    while let Some(SqlToken::Operator(
+        op @ (ComputedOperator::Multiply | ComputedOperator::Divide),
+    )) = tokens.get(*position)
+    {
+        *position += 1;
+        let right = parse_sql_atom(tokens, position)?;
+        left = ComputedExpr::Binary {
+            op: *op,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+    }
+    Some(left)
+}
+
+fn parse_sql_atom(tokens: &[SqlToken], position: &mut usize) -> Option<ComputedExpr> {
+    let token = tokens.get(*position)?.clone();
+    *position += 1;
+    match token {
+        SqlToken::OpenParen => {
+            let expr = parse_sql_expr(tokens, position)?;
+            match tokens.get(*position) {
+                Some(SqlToken::CloseParen) => {
+                    *position += 1;
+                    Some(expr)
+                }
+                _ => None,
+            }
+        }
+        SqlToken::String(value) => Some(ComputedExpr::String(value)),
+        SqlToken::Number(number) => number_expr(&number, false),
+        SqlToken::Operator(ComputedOperator::Subtract) => match tokens.get(*position)? {
+            SqlToken::Number(number) => {
+                *position += 1;
+                number_expr(number, true)
+            }
+            _ => None,
+        },
+        SqlToken::QuotedIdentifier(name) => Some(ComputedExpr::Column(name)),
+        SqlToken::Identifier(name) => {
+            if tokens.get(*position) == Some(&SqlToken::OpenParen) {
+                *position += 1;
+                let mut args = Vec::new();
+                if tokens.get(*position) == Some(&SqlToken::CloseParen) {
+                    *position += 1;
+                } else {
+                    loop {
+                        args.push(parse_sql_expr(tokens, position)?);
+                        match tokens.get(*position)? {
+                            SqlToken::Comma => *position += 1,
+                            SqlToken::CloseParen => {
+                                *position += 1;
+                                break;
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                Some(ComputedExpr::Call {
+                    name: name.to_ascii_lowercase(),
+                    args,
+                })
+            } else if name.eq_ignore_ascii_case("null") {
+                Some(ComputedExpr::Null)
+            } else {
+                Some(ComputedExpr::Column(name))
+            }
+        }
+        _ => None,
+    }
+}
+
+fn number_expr(number: &str, negative: bool) -> Option<ComputedExpr> {
+    let sign = if negative { "-" } else { "" };
+    if number.contains('.') {
+        format!("{}{}", sign, number)
+            .parse::<f64>()
+            .ok()
+            .map(ComputedExpr::Float)
+    } else {
+        format!("{}{}", sign, number)
+            .parse::<i64>()
+            .ok()
+            .map(ComputedExpr::Int)
+    }
+}
@@ -6,6 +6,7 @@ use crate::error;
 use crate::generate::sql::to_sql::SqlAndParams;
 use crate::parser;
 use crate::typecheck;
+use sha2::{Digest, Sha256};
 
 pub const MIGRATION_TABLE: &str = "_pyre_migrations";
 
@@ -13,6 +14,12 @@ pub const SCHEMA_TABLE: &str = "_pyre_schema";
 
 pub const SYNC_TABLE: &str = "_pyre_sync";
 
+pub const CHANGES_TABLE: &str = "_pyre_changes";
+
+pub const TOMBSTONES_TABLE: &str = "_pyre_tombstones";
+
+pub const IDEMPOTENCY_TABLE: &str = "_pyre_idempotency";
+
 pub const LIST_MIGRATIONS: &str = "select name from _pyre_migrations";
 
 //
@@ -25,7 +32,8 @@ pub const CREATE_MIGRATION_TABLE: &str = "create table if not exists _pyre_migra
     name text not null,
     finished_at integer,
     error text,
-    sql text not null
+    sql text not null,
+    checksum text
 )";
 
 pub const CREATE_SCHEMA_TABLE: &str = "create table if not exists _pyre_schema (
@@ -39,6 +47,39 @@ pub const CREATE_SYNC_TABLE: &str = "create table if not exists _pyre_sync (
     value integer not null
 )";
 
+/// Affected rows by server revision, read by other `pyre serve` processes to fan out
+/// live deltas for writes they did not handle.
+pub const CREATE_CHANGES_TABLE: &str = "create table if not exists _pyre_changes (
+    server_revision integer not null primary key,
+    created_at integer not null default (unixepoch()),
+    instance_id text not null,
+    affected_rows text not null
+)";
+
+/// Keys of deleted rows, written by a `_pyre_tombstone_<table>` trigger on every table
+/// so catchup can tell offline clients which rows to drop.
+pub const CREATE_TOMBSTONES_TABLE: &str = "create table if not exists _pyre_tombstones (
+    id integer not null primary key autoincrement,
+    table_name text not null,
+    row_key text not null,
+    deleted_at integer not null default (unixepoch())
+)";
+
+pub const CREATE_TOMBSTONES_INDEX: &str =
+    "create index if not exists pyre_tombstones_by_table on _pyre_tombstones (table_name, id)";
+
+/// Responses of mutations run with an idempotency key, so a retried request returns
+/// the first response instead of applying the mutation again.
+pub const CREATE_IDEMPOTENCY_TABLE: &str = "create table if not exists _pyre_idempotency (
+    scope text not null,
+    key text not null,
+    created_at integer not null default (unixepoch()),
+    query_id text not null,
+    fingerprint text not null,
+    response text not null,
+    primary key (scope, key)
+)";
+
 pub const INSERT_SYNC_REVISION_ROW: &str =
     "insert into _pyre_sync (key, value) values ('server_revision', 0) on conflict(key) do nothing";
 
@@ -48,6 +89,26 @@ pub const INSERT_MIGRATION_ERROR: &str =
 pub const INSERT_MIGRATION_SUCCESS: &str =
     "insert into _pyre_migrations (name, sql, finished_at) values (?, ?, unixepoch())";
 
+pub const INSERT_MIGRATION_SUCCESS_WITH_CHECKSUM: &str =
+    "insert into _pyre_migrations (name, sql, checksum, finished_at) values (?, ?, ?, unixepoch())";
+
+// Databases created before checksums were recorded need the column added.
+pub const HAS_MIGRATION_CHECKSUM_COLUMN: &str =
+    "select count(*) from pragma_table_info('_pyre_migrations') where name = 'checksum'";
+
+pub const ADD_MIGRATION_CHECKSUM_COLUMN: &str =
+    "alter table _pyre_migrations add column checksum text";
+
+pub const LIST_APPLIED_MIGRATIONS: &str =
+    "select name, sql, checksum from _pyre_migrations where error is null";
+
+/// `LIST_APPLIED_MIGRATIONS` for tables without the `checksum` column.
+pub const LIST_APPLIED_MIGRATIONS_LEGACY: &str =
+    "select name, sql, null from _pyre_migrations where error is null";
+
+pub const BACKFILL_MIGRATION_CHECKSUM: &str =
+    "update _pyre_migrations set checksum = ? where name = ? and checksum is null";
+
 pub const INSERT_SCHEMA: &str = "insert into _pyre_schema (schema) values (?)";
 
 pub fn internal_setup_sql() -> Vec<SqlAndParams> {
@@ -56,6 +117,10 @@ pub fn internal_setup_sql() -> Vec<SqlAndParams> {
         SqlAndParams::Sql(CREATE_SCHEMA_TABLE.to_string()),
         SqlAndParams::Sql(CREATE_SYNC_TABLE.to_string()),
         SqlAndParams::Sql(INSERT_SYNC_REVISION_ROW.to_string()),
+        SqlAndParams::Sql(CREATE_CHANGES_TABLE.to_string()),
+        SqlAndParams::Sql(CREATE_TOMBSTONES_TABLE.to_string()),
+        SqlAndParams::Sql(CREATE_TOMBSTONES_INDEX.to_string()),
+        SqlAndParams::Sql(CREATE_IDEMPOTENCY_TABLE.to_string()),
     ]
 }
 
@@ -76,6 +141,45 @@ fn quote_internal_table_names(sql: &str) -> String {
     sql.replace(MIGRATION_TABLE, &crate::ext::string::quote(MIGRATION_TABLE))
         .replace(SCHEMA_TABLE, &crate::ext::string::quote(SCHEMA_TABLE))
         .replace(SYNC_TABLE, &crate::ext::string::quote(SYNC_TABLE))
+        .replace(CHANGES_TABLE, &crate::ext::string::quote(CHANGES_TABLE))
+        .replace(
+            TOMBSTONES_TABLE,
+            &crate::ext::string::quote(TOMBSTONES_TABLE),
+        )
+        .replace(
+            IDEMPOTENCY_TABLE,
+            &crate::ext::string::quote(IDEMPOTENCY_TABLE),
+        )
+}
+
+/// The trigger that records a tombstone for every row deleted from `table_name`,
+/// including rows removed by `on delete cascade`.
+///
+/// Tables without a primary key have nothing to key a tombstone by and get no trigger.
+pub fn tombstone_trigger_sql(table_name: &str, primary_key: &[String]) -> Option<String> {
+    if primary_key.is_empty() {
+        return None;
+    }
+
+    let key = primary_key
+        .iter()
+        .map(|column| {
+            format!(
+                "{}, old.{}",
+                crate::ext::string::single_quote(column),
+                crate::ext::string::quote(column)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    Some(format!(
+        "create trigger if not exists {} after delete on {} begin insert into _pyre_tombstones (table_name, row_key) values ({}, json_object({})); end",
+        crate::ext::string::quote(&format!("_pyre_tombstone_{}", table_name)),
+        crate::ext::string::quote(table_name),
+        crate::ext::string::single_quote(table_name),
+        key
+    ))
 }
 
 /// Result type for dynamic migrations (used in WASM)
@@ -185,6 +289,114 @@ pub fn migrate_dynamic(
     })
 }
 
+/// Comment prefix used by squashed baseline migrations to list the migrations they replace.
+/// One line is written per squashed migration.
+pub const SQUASHES_MARKER: &str = "-- pyre:squashes ";
+
+/// Lists the SQL needed to recreate every user-defined table, index, view and trigger,
+/// in an order that can be replayed. Tables Pyre manages itself all start with `_pyre_`.
+pub const LIST_SCHEMA_OBJECTS_SQL: &str = "select sql from sqlite_master
+    where sql is not null
+    and name not like 'sqlite_%'
+    and tbl_name not like '\\_pyre\\_%' escape '\\'
+    order by case type when 'table' then 0 when 'index' then 1 when 'view' then 2 else 3 end, rowid";
+
+/// Names of the migrations a baseline migration replaces.
+/// Returns an empty list for regular migrations.
+pub fn squashed_migration_names(sql: &str) -> Vec<String> {
+    sql.lines()
+        .filter_map(|line| line.strip_prefix(SQUASHES_MARKER))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// A migration is satisfied if it has been applied, or if it is a baseline
+/// and every migration it squashed has been applied.
+pub fn is_migration_satisfied(name: &str, sql: &str, applied_names: &[String]) -> bool {
+    if applied_names.iter().any(|applied| applied == name) {
+        return true;
+    }
+
+    let squashed = squashed_migration_names(sql);
+    !squashed.is_empty()
+        && squashed
+            .iter()
+            .all(|squashed_name| applied_names.contains(squashed_name))
+}
+
+/// A baseline whose squashed migrations were only partly applied.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartiallyAppliedSquash {
+    pub name: String,
+    pub applied: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+/// Find pending baselines that replace migrations this database has only partly applied.
+///
+/// Running such a baseline would recreate what the applied migrations already made,
+/// and recording it as applied would skip the missing ones.
+pub fn partially_applied_squashes(
+    migration_files: &[(String, String)],
+    applied_names: &[String],
+) -> Vec<PartiallyAppliedSquash> {
+    let mut partial = Vec::new();
+
+    for (name, sql) in migration_files {
+        if applied_names.contains(name) {
+            continue;
+        }
+
+        let (applied, missing): (Vec<String>, Vec<String>) = squashed_migration_names(sql)
+            .into_iter()
+            .partition(|squashed_name| applied_names.contains(squashed_name));
+        if !applied.is_empty() && !missing.is_empty() {
+            partial.push(PartiallyAppliedSquash {
+                name: name.clone(),
+                applied,
+                missing,
+            });
+        }
+    }
+
+    partial
+}
+
+/// Render the `migration.sql` for a baseline that replaces `squashed`.
+///
+/// Baselines that are themselves squashed again carry their own squashed names forward,
+/// so databases that only ever applied the original files still recognize the new baseline.
+pub fn squash_baseline_sql(squashed: &[(String, String)], schema_statements: &[String]) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for (name, sql) in squashed {
+        for squashed_name in squashed_migration_names(sql)
+            .into_iter()
+            .chain(std::iter::once(name.clone()))
+        {
+            if !names.contains(&squashed_name) {
+                names.push(squashed_name);
+            }
+        }
+    }
+
+    let mut baseline = String::from("-- Baseline generated by `pyre migration squash`.\n");
+    for name in &names {
+        baseline.push_str(SQUASHES_MARKER);
+        baseline.push_str(name);
+        baseline.push('\n');
+    }
+    baseline.push('\n');
+
+    for statement in schema_statements {
+        baseline.push_str(statement.trim_end_matches(';'));
+        baseline.push_str(";\n");
+    }
+
+    baseline
+}
+
 /// File-based migration approach - executes pre-written SQL migration files.
 /// This is used in CLI environments where migration files are stored on disk.
 ///
@@ -201,6 +413,9 @@ pub fn migrate_dynamic(
 pub struct FileBasedMigrationPlan {
     /// Migrations that should be executed (name, sql_content)
     pub migrations_to_run: Vec<(String, String)>,
+    /// Baseline migrations whose squashed migrations were all applied already.
+    /// These are recorded as applied without being executed.
+    pub migrations_to_record: Vec<(String, String)>,
     /// SQL to insert the schema after migrations
     pub insert_schema_sql: String,
     /// Schema string to insert
@@ -215,18 +430,28 @@ pub fn plan_file_based_migrations(
     schema: &ast::Schema,
 ) -> FileBasedMigrationPlan {
     // Determine which migrations need to be run
-    let migrations_to_run: Vec<(String, String)> = match migration_state {
+    let mut migrations_to_run: Vec<(String, String)> = Vec::new();
+    let mut migrations_to_record: Vec<(String, String)> = Vec::new();
+    match migration_state {
         introspect::MigrationState::NoMigrationTable => {
             // Run all migrations if no migration table exists
-            migration_files.to_vec()
+            migrations_to_run = migration_files.to_vec();
         }
         introspect::MigrationState::MigrationTable { migrations } => {
+            let applied_names: Vec<String> = migrations.iter().map(|m| m.name.clone()).collect();
+
             // Filter out migrations that have already been run
-            migration_files
-                .iter()
-                .filter(|(name, _)| !migrations.iter().any(|m| m.name == *name))
-                .cloned()
-                .collect()
+            for (name, sql) in migration_files {
+                if applied_names.contains(name) {
+                    continue;
+                }
+
+                if is_migration_satisfied(name, sql, &applied_names) {
+                    migrations_to_record.push((name.clone(), sql.clone()));
+                } else {
+                    migrations_to_run.push((name.clone(), sql.clone()));
+                }
+            }
         }
     };
 
@@ -239,7 +464,206 @@ pub fn plan_file_based_migrations(
 
     FileBasedMigrationPlan {
         migrations_to_run,
+        migrations_to_record,
         insert_schema_sql,
         schema_string,
     }
 }
+
+/// Content hash recorded for each applied migration file.
+pub fn migration_checksum(sql: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(sql.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// A row from `_pyre_migrations` that finished successfully.
+/// `checksum` is `None` for migrations applied before checksums were recorded.
+#[derive(Debug, Clone)]
+pub struct AppliedMigration {
+    pub name: String,
+    pub sql: String,
+    pub checksum: Option<String>,
+}
+
+impl AppliedMigration {
+    /// Falls back to hashing the recorded SQL when no checksum was stored.
+    pub fn recorded_checksum(&self) -> String {
+        match &self.checksum {
+            Some(checksum) => checksum.clone(),
+            None => migration_checksum(&self.sql),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedMigration {
+    pub name: String,
+    pub recorded_checksum: String,
+    pub file_checksum: String,
+}
+
+/// Find migration files whose contents no longer match what was applied.
+pub fn changed_migrations(
+    migration_files: &[(String, String)],
+    applied: &[AppliedMigration],
+) -> Vec<ChangedMigration> {
+    let mut changed = Vec::new();
+
+    for (name, sql) in migration_files {
+        let Some(applied_migration) = applied.iter().find(|migration| migration.name == *name)
+        else {
+            continue;
+        };
+
+        let recorded_checksum = applied_migration.recorded_checksum();
+        let file_checksum = migration_checksum(sql);
+        if recorded_checksum != file_checksum {
+            changed.push(ChangedMigration {
+                name: name.clone(),
+                recorded_checksum,
+                file_checksum,
+            });
+        }
+    }
+
+    changed
+}
+
+/// Compare two introspected sets of tables and describe every difference.
+///
+/// `expected` is usually the result of replaying migration files into a scratch
+/// database and `live` is the database being verified.
+pub fn schema_drift(expected: &[introspect::Table], live: &[introspect::Table]) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for expected_table in expected {
+        match live.iter().find(|table| table.name == expected_table.name) {
+            None => changes.push(format!("missing table {}", expected_table.name)),
+            Some(live_table) => table_drift(expected_table, live_table, &mut changes),
+        }
+    }
+
+    for live_table in live {
+        if !expected.iter().any(|table| table.name == live_table.name) {
+            changes.push(format!("unexpected table {}", live_table.name));
+        }
+    }
+
+    changes
+}
+
+fn table_drift(expected: &introspect::Table, live: &introspect::Table, changes: &mut Vec<String>) {
+    let table_name = &expected.name;
+
+    if live.strict != expected.strict || live.without_rowid != expected.without_rowid {
+        changes.push(format!("modified table options {}", table_name));
+    }
+
+    for expected_column in &expected.columns {
+        match live
+            .columns
+            .iter()
+            .find(|column| column.name == expected_column.name)
+        {
+            None => changes.push(format!(
+                "missing column {}.{}",
+                table_name, expected_column.name
+            )),
+            Some(live_column) => {
+                if !live_column
+                    .column_type
+                    .eq_ignore_ascii_case(&expected_column.column_type)
+                    || live_column.notnull != expected_column.notnull
+                    || live_column.default_value != expected_column.default_value
+                    || live_column.pk != expected_column.pk
+                    || live_column.collation != expected_column.collation
+                    || live_column.generated != expected_column.generated
+                {
+                    changes.push(format!(
+                        "modified column {}.{}",
+                        table_name, expected_column.name
+                    ));
+                }
+            }
+        }
+    }
+
+    for live_column in &live.columns {
+        if !expected
+            .columns
+            .iter()
+            .any(|column| column.name == live_column.name)
+        {
+            changes.push(format!(
+                "unexpected column {}.{}",
+                table_name, live_column.name
+            ));
+        }
+    }
+
+    let same_foreign_key = |left: &introspect::ForeignKey, right: &introspect::ForeignKey| {
+        left.from == right.from && left.table == right.table && left.to == right.to
+    };
+
+    for expected_key in &expected.foreign_keys {
+        if !live
+            .foreign_keys
+            .iter()
+            .any(|key| same_foreign_key(key, expected_key))
+        {
+            changes.push(format!(
+                "missing foreign key {}.{} -> {}.{}",
+                table_name, expected_key.from, expected_key.table, expected_key.to
+            ));
+        }
+    }
+
+    for live_key in &live.foreign_keys {
+        if !expected
+            .foreign_keys
+            .iter()
+            .any(|key| same_foreign_key(key, live_key))
+        {
+            changes.push(format!(
+                "unexpected foreign key {}.{} -> {}.{}",
+                table_name, live_key.from, live_key.table, live_key.to
+            ));
+        }
+    }
+
+    for expected_index in &expected.indexes {
+        match live
+            .indexes
+            .iter()
+            .find(|index| index.name == expected_index.name)
+        {
+            None => changes.push(format!("missing index {}", expected_index.name)),
+            Some(live_index) => {
+                let same_columns = live_index.columns.len() == expected_index.columns.len()
+                    && live_index
+                        .columns
+                        .iter()
+                        .zip(expected_index.columns.iter())
+                        .all(|(left, right)| left.name == right.name && left.desc == right.desc);
+                if !same_columns
+                    || live_index.unique != expected_index.unique
+                    || live_index.where_clause != expected_index.where_clause
+                {
+                    changes.push(format!("modified index {}", expected_index.name));
+                }
+            }
+        }
+    }
+
+    for live_index in &live.indexes {
+        if !expected
+            .indexes
+            .iter()
+            .any(|index| index.name == live_index.name)
+        {
+            changes.push(format!("unexpected index {}", live_index.name));
+        }
+    }
+}
@@ -80,6 +80,10 @@ pub enum ErrorType {
         record: String,
         field: String,
     },
+    WithoutRowidAutoIncrementKey {
+        record: String,
+        field: String,
+    },
     MultipleTableNames {
         record: String,
     },
@@ -115,6 +119,11 @@ pub enum ErrorType {
         foreign_table: String,
         unknown_foreign_field: String,
     },
+    LinkColumnCountMismatch {
+        link_name: String,
+        local_ids: Vec<String>,
+        foreign_fields: Vec<String>,
+    },
     LinkSelectionIsEmpty {
         link_name: String,
         foreign_table: String,
@@ -257,6 +266,18 @@ pub enum ErrorType {
         default_value: String,
         expected: Vec<String>,
     },
+    InvalidComputedColumn {
+        field_name: String,
+        message: String,
+    },
+    InvalidVersionField {
+        field_name: String,
+        message: String,
+    },
+    InvalidVersionCheck {
+        query: String,
+        message: String,
+    },
     MigrationSchemaNotFound {
         namespace: Option<String>,
     },
@@ -652,6 +673,10 @@ fn render_expecting(expecting: &Expecting, in_color: bool) -> String {
         ),
         Expecting::LinkDirective => {
             let explicit_example = format!("{}(authorId, User.id)", yellow_if(in_color, "@link"));
+            let composite_example = format!(
+                "{}(orgId, userId, Membership.orgId, Membership.userId)",
+                yellow_if(in_color, "@link")
+            );
             let reverse_example = format!("{}(Post.authorId)", yellow_if(in_color, "@link"));
             let example_breakdown = format!("       {}  {}",
                 cyan_if(in_color, "^^^^^^^^"), 
@@ -664,9 +689,10 @@ fn render_expecting(expecting: &Expecting, in_color: bool) -> String {
                 cyan_if(in_color, "Foreign table.key"));
 
             return format!(
-                "This {} looks off. Expected one of:\n\n        {}\n        {}\n\n    Explicit form breakdown:\n        {}\n        {}\n        {}\n\n    Tip: if this looks correct, check for invisible whitespace characters around the comma/parentheses.",
+                "This {} looks off. Expected one of:\n\n        {}\n        {}\n        {}\n\n    Explicit form breakdown:\n        {}\n        {}\n        {}\n\n    Tip: if this looks correct, check for invisible whitespace characters around the comma/parentheses.",
                 yellow_if(in_color, "@link"),
                 explicit_example,
+                composite_example,
                 reverse_example,
                 example_breakdown,
                 example_breakdown_connector,
@@ -956,6 +982,23 @@ fn to_error_description(error: &Error, in_color: bool) -> String {
 
             result
         }
+        ErrorType::LinkColumnCountMismatch {
+            link_name,
+            local_ids,
+            foreign_fields,
+        } => {
+            let mut result = "".to_string();
+            result.push_str(&format!(
+                "{} links {} local columns ({}) to {} foreign columns ({}), but every local column needs exactly one foreign column to pair with.",
+                yellow_if(in_color, link_name),
+                local_ids.len(),
+                local_ids.iter().map(|id| yellow_if(in_color, id)).collect::<Vec<_>>().join(", "),
+                foreign_fields.len(),
+                foreign_fields.iter().map(|id| yellow_if(in_color, id)).collect::<Vec<_>>().join(", "),
+            ));
+
+            result
+        }
         ErrorType::LinkToUnknownTable {
             link_name,
             unknown_table,
@@ -1058,17 +1101,38 @@ fn to_error_description(error: &Error, in_color: bool) -> String {
             result
         }
 
-        ErrorType::MultiplePrimaryKeys { record, .. } => {
+        ErrorType::MultiplePrimaryKeys { record, field } => {
             let mut result = "".to_string();
 
             result.push_str(&format!(
-                "{} has multiple primary keys, let's only have one.",
-                cyan_if(in_color, record)
+                "{} has multiple primary keys, let's only have one. {} is an extra one.",
+                cyan_if(in_color, record),
+                yellow_if(in_color, field)
+            ));
+            result.push_str(&format!(
+                "\n\nTo key a record by more than one field, use a single record-level {} instead.",
+                yellow_if(in_color, "@id(fieldOne, fieldTwo)")
             ));
 
             result
         }
 
+        ErrorType::WithoutRowidAutoIncrementKey { record, field } => {
+            let mut result = "".to_string();
+
+            result.push_str(&format!(
+                "{} is marked {}, but its primary key {} is an auto-incrementing integer.",
+                cyan_if(in_color, record),
+                yellow_if(in_color, "@withoutRowid"),
+                cyan_if(in_color, field)
+            ));
+            result.push_str(
+                "\n\nSQLite only assigns integer ids for rowid tables, so every insert would need to provide one. Use a key your app supplies, like a composite @id(...), or remove @withoutRowid.",
+            );
+
+            result
+        }
+
         ErrorType::MultipleTableNames { record } => {
             let mut result = "".to_string();
 
@@ -1567,6 +1631,30 @@ fn to_error_description(error: &Error, in_color: bool) -> String {
                 expected_text
             )
         }
+        ErrorType::InvalidComputedColumn {
+            field_name,
+            message,
+        } => format!(
+            "The {} expression for {} doesn't work: {}",
+            yellow_if(in_color, "@computed"),
+            yellow_if(in_color, field_name),
+            message
+        ),
+        ErrorType::InvalidVersionField {
+            field_name,
+            message,
+        } => format!(
+            "The {} field {} doesn't work: {}",
+            yellow_if(in_color, "@version"),
+            yellow_if(in_color, field_name),
+            message
+        ),
+        ErrorType::InvalidVersionCheck { query, message } => format!(
+            "{} doesn't work on {}: {}",
+            yellow_if(in_color, "@ifVersion"),
+            yellow_if(in_color, query),
+            message
+        ),
         ErrorType::MigrationSchemaNotFound { namespace } => match namespace {
             Some(name) => format!(
                 "A migration was attempted for the schema named {}, but it was not found.",
@@ -1598,6 +1686,7 @@ fn to_error_title(error_type: &ErrorType) -> String {
         ErrorType::InvalidTypeUsage { .. } => "Invalid Type Usage",
         ErrorType::NoPrimaryKey { .. } => "No Primary Key",
         ErrorType::MultiplePrimaryKeys { .. } => "Multiple Primary Keys",
+        ErrorType::WithoutRowidAutoIncrementKey { .. } => "Without Rowid Auto-increment Key",
         ErrorType::MultipleTableNames { .. } => "Multiple table names",
         ErrorType::MultiplePermissions { .. } => "Multiple Permissions",
         ErrorType::MissingPermissions { .. } => "Missing Permissions",
@@ -1606,6 +1695,7 @@ fn to_error_title(error_type: &ErrorType) -> String {
         ErrorType::LinkToUnknownTable { .. } => "Link to unknown table",
         ErrorType::LinkToUnknownField { .. } => "Link to unknown field",
         ErrorType::LinkToUnknownForeignField { .. } => "Link to Unknown Foreign Field",
+        ErrorType::LinkColumnCountMismatch { .. } => "Link Column Count Mismatch",
         ErrorType::LinkSelectionIsEmpty { .. } => "Link Selection Is Empty",
         ErrorType::LinkToUnknownSchema { .. } => "Link to Unknown Schema",
         ErrorType::ForeignKeyToUnknownTable { .. } => "Foreign key to unknown table",
@@ -1641,6 +1731,9 @@ fn to_error_title(error_type: &ErrorType) -> String {
         ErrorType::MigrationColumnModified { .. } => "Column Modified",
         ErrorType::MigrationVariantRemoved { .. } => "Variant Removed",
         ErrorType::InvalidColumnDefault { .. } => "Invalid Column Default",
+        ErrorType::InvalidComputedColumn { .. } => "Invalid Computed Column",
+        ErrorType::InvalidVersionField { .. } => "Invalid Version Field",
+        ErrorType::InvalidVersionCheck { .. } => "Invalid Version Check",
         ErrorType::MigrationSchemaNotFound { .. } => "Schema Not Found",
         ErrorType::MigrationMissingSchema => "Missing Schema",
     }
@@ -122,10 +122,12 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
     // Separate fields into categories
     let mut tablename: Option<ast::Field> = None;
     let mut watch: Option<ast::Field> = None;
+    let mut primary_key: Option<ast::Field> = None;
     let mut unique_directives: Vec<ast::Field> = Vec::new();
     let mut index_directives: Vec<ast::Field> = Vec::new();
     let mut permissions: Vec<ast::Field> = Vec::new();
     let mut timestamps: Vec<ast::Field> = Vec::new();
+    let mut table_options: Vec<ast::Field> = Vec::new();
     let mut non_directive_fields: Vec<ast::Field> = Vec::new();
     let mut links: Vec<ast::Field> = Vec::new();
 
@@ -138,6 +140,9 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
             ast::Field::FieldDirective(ast::FieldDirective::Watched(_)) => {
                 watch = Some(field);
             }
+            ast::Field::FieldDirective(ast::FieldDirective::PrimaryKey(_)) => {
+                primary_key = Some(field);
+            }
             ast::Field::FieldDirective(ast::FieldDirective::Unique(_)) => {
                 unique_directives.push(field);
             }
@@ -150,6 +155,10 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
             ast::Field::FieldDirective(ast::FieldDirective::Timestamps) => {
                 timestamps.push(field);
             }
+            ast::Field::FieldDirective(ast::FieldDirective::Strict)
+            | ast::Field::FieldDirective(ast::FieldDirective::WithoutRowid) => {
+                table_options.push(field);
+            }
             ast::Field::FieldDirective(ast::FieldDirective::Link(_)) => {
                 links.push(field);
             }
@@ -173,9 +182,11 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
     let has_directives = tablename.is_some()
         || watch.is_some()
         || !permissions.is_empty()
+        || primary_key.is_some()
         || !unique_directives.is_empty()
         || !index_directives.is_empty()
-        || !timestamps.is_empty();
+        || !timestamps.is_empty()
+        || !table_options.is_empty();
     let has_content = !non_directive_fields.is_empty() || !links.is_empty();
 
     // 1. @tablename
@@ -191,16 +202,28 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
     // 3. @allowed (or @public)
     fields.extend(permissions);
 
-    // 4. @timestamps
+    // 4. @timestamps, then @strict and @withoutRowid
     fields.extend(timestamps);
+    table_options.sort_by_key(|field| {
+        matches!(
+            field,
+            ast::Field::FieldDirective(ast::FieldDirective::WithoutRowid)
+        )
+    });
+    fields.extend(table_options);
 
-    // 5. @unique
+    // 5. @id
+    if let Some(pk) = primary_key {
+        fields.push(pk);
+    }
+
+    // 6. @unique
     fields.extend(unique_directives);
 
-    // 6. @index
+    // 7. @index
     fields.extend(index_directives);
 
-    // 7. Empty line (if we have directives and non-directive fields/links)
+    // 8. Empty line (if we have directives and non-directive fields/links)
     if has_directives && has_content {
         // Check if there's already a ColumnLines at the start of non_directive_fields
         let needs_separator = match non_directive_fields.first() {
@@ -212,10 +235,10 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
         }
     }
 
-    // 8. Non-directive fields (columns, comments, column_lines) - preserve order
+    // 9. Non-directive fields (columns, comments, column_lines) - preserve order
     fields.extend(non_directive_fields);
 
-    // 9. Empty line (if links exist and we have other fields)
+    // 10. Empty line (if links exist and we have other fields)
     if !links.is_empty() && !fields.is_empty() {
         // Check if the last field is already a ColumnLines
         let needs_separator = match fields.last() {
@@ -227,7 +250,7 @@ fn reorder_record_fields(fields: &mut Vec<ast::Field>) {
         }
     }
 
-    // 10. Links
+    // 11. Links
     fields.extend(links);
 }
 
@@ -376,13 +399,14 @@ fn format_definition(
             // 1. @tablename
             // 2. @watch
             // 3. @allowed (or @public) - ordered: query, update, insert, delete
-            // 4. @timestamps
-            // 5. @unique
-            // 6. @index
-            // 7. Empty line
-            // 8. Columns (in order)
-            // 9. Empty line (if links exist)
-            // 10. Links
+            // 4. @timestamps, @strict, @withoutRowid
+            // 5. @id
+            // 6. @unique
+            // 7. @index
+            // 8. Empty line
+            // 9. Columns (in order)
+            // 10. Empty line (if links exist)
+            // 11. Links
             reorder_record_fields(fields);
         }
     }
@@ -532,6 +556,7 @@ fn reorder_query_field_args(arg_fields: &mut Vec<ast::ArgField>) {
     let mut limits: Vec<ast::ArgField> = Vec::new();
     let mut sorts: Vec<ast::ArgField> = Vec::new();
     let mut wheres: Vec<ast::ArgField> = Vec::new();
+    let mut versions: Vec<ast::ArgField> = Vec::new();
     let mut fields: Vec<ast::ArgField> = Vec::new();
     let mut comments: Vec<ast::ArgField> = Vec::new();
     let mut lines: Vec<ast::ArgField> = Vec::new();
@@ -542,6 +567,7 @@ fn reorder_query_field_args(arg_fields: &mut Vec<ast::ArgField>) {
                 ast::Arg::Limit(_) => limits.push(arg_field),
                 ast::Arg::OrderBy(_, _) => sorts.push(arg_field),
                 ast::Arg::Where(_) => wheres.push(arg_field),
+                ast::Arg::IfVersion(_) => versions.push(arg_field),
             },
             ast::ArgField::Field(_) => fields.push(arg_field),
             ast::ArgField::QueryComment { .. } => comments.push(arg_field),
@@ -550,7 +576,8 @@ fn reorder_query_field_args(arg_fields: &mut Vec<ast::ArgField>) {
     }
 
     // Check if we have args and fields before moving
-    let has_args = !limits.is_empty() || !sorts.is_empty() || !wheres.is_empty();
+    let has_args =
+        !limits.is_empty() || !sorts.is_empty() || !wheres.is_empty() || !versions.is_empty();
     let has_fields = !fields.is_empty();
 
     // Reassemble in the correct order
@@ -564,6 +591,9 @@ fn reorder_query_field_args(arg_fields: &mut Vec<ast::ArgField>) {
 
     // 3. @where
     arg_fields.extend(wheres);
+
+    // 4. @ifVersion
+    arg_fields.extend(versions);
     if has_args && has_fields {
         // Merge all lines into one if needed
         let mut total_lines = 0;
@@ -1021,6 +1021,46 @@ pub fn write_schema(database: &ast::Database) -> String {
         }
     }
 
+    result.push_str(&to_composite_key_aliases(database));
+
+    result
+}
+
+/// A record alias per record with a record-level `@id(a, b)`, holding every key column.
+fn to_composite_key_aliases(database: &ast::Database) -> String {
+    let mut keys = Vec::new();
+    for schema in &database.schemas {
+        for file in &schema.files {
+            for definition in &file.definitions {
+                if let ast::Definition::Record { name, fields, .. } = definition {
+                    if let Some(columns) = ast::composite_primary_key_columns(fields) {
+                        keys.push((name, columns));
+                    }
+                }
+            }
+        }
+    }
+    keys.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut result = String::new();
+    for (name, columns) in keys {
+        result.push_str(&format!("type alias {}Id =\n", name));
+        let mut is_first = true;
+        for column in columns {
+            let type_str = match &column.type_ {
+                ast::ColumnType::IdInt { .. }
+                | ast::ColumnType::IdUuid { .. }
+                | ast::ColumnType::ForeignKey { .. } => {
+                    entity_stream_elm_type(database, &column.type_)
+                }
+                type_ => elm_type_from_column_type(type_, false),
+            };
+            let prefix = if is_first { "{" } else { "," };
+            result.push_str(&format!("    {} {} : {}\n", prefix, column.name, type_str));
+            is_first = false;
+        }
+        result.push_str("    }\n\n\n");
+    }
     result
 }
 
@@ -2295,6 +2335,7 @@ fn to_query_field_spec_json(
                         limit = Some(*val);
                     }
                 }
+                ast::Arg::IfVersion(_) => {}
             },
             ast::ArgField::Field(nested_field) => {
                 if nested_field.name == "*" {
@@ -2933,13 +2974,8 @@ fn relationship_info_for_field<'a>(
     for f in &table.record.fields {
         if let ast::Field::FieldDirective(ast::FieldDirective::Link(link)) = f {
             if link.link_name == field_name {
-                let primary_key_name = ast::get_primary_id_field_name(&table.record.fields);
-                let is_one_to_many = link.local_ids.iter().all(|id| {
-                    primary_key_name
-                        .as_ref()
-                        .map(|pk| id == pk)
-                        .unwrap_or(false)
-                });
+                let is_one_to_many =
+                    ast::ids_are_primary_key(&link.local_ids, &table.record.fields);
 
                 let linked_table = typecheck::get_linked_table(context, link);
                 let linked_to_unique = if let Some(linked_table) = linked_table {
@@ -9,6 +9,8 @@ use std::path::Path;
 #[derive(Serialize)]
 struct Manifest {
     version: u32,
+    /// `crate::hash::hash_manifest`, sent back by clients to pin this manifest.
+    hash: String,
     session_schema: BTreeMap<String, FieldSchema>,
     queries: BTreeMap<String, QueryManifest>,
 }
@@ -26,6 +28,9 @@ struct QueryManifest {
     sql: Vec<SqlInfo>,
     #[serde(rename = "syncSql", skip_serializing_if = "Option::is_none")]
     sync_sql: Option<Vec<SqlInfo>>,
+    /// Set for queries that clients can subscribe to as partial sync shapes.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    shapes: Vec<crate::sync::ShapeDefinition>,
 }
 
 #[derive(Serialize)]
@@ -75,13 +80,22 @@ fn write_manifest(
     queries: Vec<QueryManifest>,
     files: &mut Vec<filesystem::GeneratedFile<String>>,
 ) {
+    let session_schema = session_schema(context);
+    let queries: BTreeMap<String, QueryManifest> = queries
+        .into_iter()
+        .map(|query| (query.id.clone(), query))
+        .collect();
+    let hash = crate::hash::hash_manifest(
+        session_schema
+            .iter()
+            .map(|(name, field)| (name.as_str(), field.type_.as_str(), field.nullable)),
+        queries.keys().map(String::as_str),
+    );
     let manifest = Manifest {
         version: 1,
-        session_schema: session_schema(context),
-        queries: queries
-            .into_iter()
-            .map(|query| (query.id.clone(), query))
-            .collect(),
+        hash,
+        session_schema,
+        queries,
     };
     let content = serde_json::to_string_pretty(&manifest).expect("manifest should serialize");
 
@@ -124,6 +138,7 @@ fn query_manifest(
         } else {
             Some(query_sql(context, query, query_info, true))
         },
+        shapes: crate::sync::shape_definitions(context, query).unwrap_or_default(),
     }
 }
 
@@ -209,7 +224,11 @@ fn query_sql(
         };
 
         for prepared in prepared {
-            if sync_mode && prepared.include && !prepared.sql.contains("_affectedRows") {
+            if sync_mode
+                && prepared.include
+                && !prepared.sql.contains("_affectedRows")
+                && !prepared.sql.contains("_conflicts")
+            {
                 continue;
             }
             result.push(SqlInfo {
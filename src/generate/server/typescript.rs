@@ -36,6 +36,57 @@ fn collect_brands(database: &ast::Database) -> Vec<String> {
     brands_vec
 }
 
+/// Records keyed by a record-level `@id(a, b)`, with the TypeScript type of each key column
+fn collect_composite_keys(database: &ast::Database) -> Vec<(String, Vec<(String, String)>)> {
+    let mut keys = Vec::new();
+
+    for schema in &database.schemas {
+        for file in &schema.files {
+            for definition in &file.definitions {
+                if let ast::Definition::Record { name, fields, .. } = definition {
+                    if let Some(columns) = ast::composite_primary_key_columns(fields) {
+                        let columns = columns
+                            .into_iter()
+                            .map(|column| {
+                                (column.name.clone(), key_column_ts_type(database, column))
+                            })
+                            .collect();
+                        keys.push((name.clone(), columns));
+                    }
+                }
+            }
+        }
+    }
+
+    keys.sort_by(|a, b| a.0.cmp(&b.0));
+    keys
+}
+
+/// Key columns that reference another record use the brand of the column they point to
+fn key_column_ts_type(database: &ast::Database, column: &ast::Column) -> String {
+    if let ast::ColumnType::ForeignKey { table, field } = &column.type_ {
+        for schema in &database.schemas {
+            for file in &schema.files {
+                for definition in &file.definitions {
+                    if let ast::Definition::Record { name, fields, .. } = definition {
+                        if !name.eq_ignore_ascii_case(table) {
+                            continue;
+                        }
+                        for candidate in fields {
+                            if let ast::Field::Column(referenced) = candidate {
+                                if &referenced.name == field {
+                                    return column_to_ts_type(referenced);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    column_to_ts_type(column)
+}
+
 /// Convert a column to its TypeScript type representation
 /// For ID types with brands, generates branded types like `UserId` or `string & Post`
 fn column_to_ts_type(column: &ast::Column) -> String {
@@ -116,6 +167,23 @@ pub fn schema(database: &ast::Database) -> String {
         result.push_str("\n\n");
     }
 
+    // Records with a composite primary key are identified by all of their key columns
+    let composite_keys = collect_composite_keys(database);
+    if !composite_keys.is_empty() {
+        result.push_str("// Composite key types\n");
+        for (name, columns) in &composite_keys {
+            let fields = columns
+                .iter()
+                .map(|(column, type_str)| {
+                    format!("{}: {}", crate::ext::string::quote(column), type_str)
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            result.push_str(&format!("type {}Id = {{ {} }}\n", name, fields));
+        }
+        result.push_str("\n\n");
+    }
+
     for schema in &database.schemas {
         result.push_str("\n\n");
         for file in &schema.files {
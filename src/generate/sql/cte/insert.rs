@@ -193,7 +193,7 @@ fn insert_linked(
     let table_name = ast::get_tablename(&table.record.name, &table.record.fields);
     let new_fieldnames =
         &to_fieldnames(table, &ast::collect_query_fields(&query_table_field.fields));
-    field_names.push(link.foreign.fields.clone().join(", "));
+    field_names.append(&mut link.foreign.fields.clone());
     field_names.append(&mut new_fieldnames.clone());
 
     let mut result = format!(
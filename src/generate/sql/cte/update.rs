@@ -41,6 +41,15 @@ pub fn update_to_string(
         values.push("updatedAt = unixepoch()".to_string());
     }
 
+    let version_column = ast::get_version_column(&table.record.fields);
+    if let Some(column) = version_column {
+        values.push(format!(
+            "{} = {} + 1",
+            string::quote(&column.name),
+            string::quote(&column.name)
+        ));
+    }
+
     result.push_str(&format!("set {}", values.join(", ")));
 
     result.push_str("\n");
@@ -52,8 +61,89 @@ pub fn update_to_string(
         &ast::QueryOperation::Update,
         &mut where_clause,
     );
+
+    // With @ifVersion, only rows still at the expected version are updated. The others
+    // matched the update too, and are reported as conflicts before anything changes.
+    let version_check = match (version_column, ast::get_if_version(&query_field.fields)) {
+        (Some(column), Some(value)) => Some(VersionCheck {
+            column: to_sql::render_real_where_field(table, query_info, false, &column.name),
+            value: to_sql::render_column_value(column, value),
+            temp_table_name: format!("temp_versioned_{}", table_name),
+            primary_key: ast::get_primary_key_field_names(&table.record.fields),
+        }),
+        _ => None,
+    };
+    let response_where_clause = match &version_check {
+        Some(check) => {
+            let conflict_where = and_where(
+                &where_clause,
+                &format!("{} != {}", check.column, check.value),
+            );
+            statements.push(to_sql::include(generate_typed_response_query(
+                context,
+                table,
+                query_field,
+                &ast::version_conflicts_name(query_field),
+                &table_name,
+                &conflict_where,
+            )));
+            if include_affected_rows {
+                statements.push(to_sql::include(generate_row_group_query(
+                    context,
+                    table,
+                    "_conflicts",
+                    &conflict_where,
+                )));
+            }
+
+            where_clause = and_where(
+                &where_clause,
+                &format!("{} = {}", check.column, check.value),
+            );
+            statements.push(to_sql::ignore(format!(
+                "drop table if exists {}",
+                check.temp_table_name
+            )));
+            statements.push(to_sql::ignore(format!(
+                "create temp table {} as select {} from {} {}",
+                check.temp_table_name,
+                quoted_columns(&check.primary_key, None),
+                string::quote(&table_name),
+                where_clause
+            )));
+            // The update moves the version on, so updated rows are found by primary key.
+            format!(
+                "where\n ({}) in (select {} from {})\n",
+                quoted_columns(&check.primary_key, Some(&string::quote(&table_name))),
+                quoted_columns(&check.primary_key, None),
+                check.temp_table_name
+            )
+        }
+        None => where_clause.clone(),
+    };
     result.push_str(&where_clause);
 
+    // Capture the rows about to change, so sessions that could see a row before
+    // the update can be told when they no longer can.
+    let previous = if include_affected_rows {
+        previous_rows(table)
+    } else {
+        None
+    };
+    if let Some(previous) = &previous {
+        statements.push(to_sql::ignore(format!(
+            "drop table if exists {}",
+            previous.temp_table_name
+        )));
+        statements.push(to_sql::ignore(format!(
+            "create temp table {} as select {} from {} {}",
+            previous.temp_table_name,
+            quoted_columns(&previous.columns, None),
+            string::quote(&table_name),
+            where_clause
+        )));
+    }
+
     // Always execute UPDATE (with or without RETURNING)
     if include_affected_rows {
         result.push_str(" returning *");
@@ -62,14 +152,21 @@ pub fn update_to_string(
 
     // Always generate the typed response query - mutations must return typed data
     // Use the same table_name as the UPDATE statement for consistency
-    let typed_response_sql =
-        generate_typed_response_query(context, table, query_field, &table_name, &where_clause);
+    let typed_response_sql = generate_typed_response_query(
+        context,
+        table,
+        query_field,
+        &query_field.name,
+        &table_name,
+        &response_where_clause,
+    );
     statements.push(to_sql::include(typed_response_sql));
 
     // Generate affected rows query if requested
     // Execute this BEFORE the final selection to avoid lock conflicts
     if include_affected_rows {
-        let affected_rows_sql = generate_affected_rows_query(context, table, &where_clause);
+        let affected_rows_sql =
+            generate_affected_rows_query(context, table, &where_clause, previous.as_ref());
         // Insert before the final selection (which now always exists)
         let final_idx = statements.len() - 1;
         statements.insert(final_idx, to_sql::include(affected_rows_sql));
@@ -78,14 +175,31 @@ pub fn update_to_string(
     statements
 }
 
+/// Checks the `@version` column of the rows an `@ifVersion` update matches.
+struct VersionCheck {
+    column: String,
+    value: String,
+    /// Holds the primary keys of the rows at the expected version, which are the ones updated.
+    temp_table_name: String,
+    primary_key: Vec<String>,
+}
+
+/// Add `condition` to a rendered WHERE clause.
+fn and_where(where_clause: &str, condition: &str) -> String {
+    match where_clause.strip_prefix("where\n") {
+        Some(existing) => format!("where\n ({}) and {}\n", existing.trim(), condition),
+        None => format!("where\n {}\n", condition),
+    }
+}
+
 fn generate_typed_response_query(
     context: &typecheck::Context,
     table: &typecheck::Table,
     query_field: &ast::QueryField,
+    query_field_name: &str,
     table_name: &str,
     where_clause: &str,
 ) -> String {
-    let query_field_name = &query_field.name;
     let quoted_table_name = string::quote(table_name);
 
     // Replace table name in WHERE clause with alias 't'
@@ -172,49 +286,111 @@ fn generate_typed_response_query(
     sql
 }
 
+/// The temp table holding rows as they were before an update.
+struct PreviousRows {
+    temp_table_name: String,
+    primary_key: Vec<String>,
+    /// The primary key followed by the table's other columns.
+    columns: Vec<String>,
+}
+
+fn previous_rows(table: &typecheck::Table) -> Option<PreviousRows> {
+    let primary_key = ast::get_primary_key_field_names(&table.record.fields);
+    if primary_key.is_empty() {
+        return None;
+    }
+
+    // Permissions and sync shapes may read any column, so the whole row is kept.
+    let mut columns = primary_key.clone();
+    for field in &table.record.fields {
+        if let ast::Field::Column(column) = field {
+            if !columns.contains(&column.name) {
+                columns.push(column.name.clone());
+            }
+        }
+    }
+
+    Some(PreviousRows {
+        temp_table_name: format!(
+            "temp_previous_{}",
+            ast::get_tablename(&table.record.name, &table.record.fields)
+        ),
+        primary_key,
+        columns,
+    })
+}
+
+fn quoted_columns(columns: &[String], alias: Option<&str>) -> String {
+    columns
+        .iter()
+        .map(|column| match alias {
+            Some(alias) => format!("{}.{}", alias, string::quote(column)),
+            None => string::quote(column),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 fn generate_affected_rows_query(
     context: &typecheck::Context,
     table: &typecheck::Table,
     where_clause: &str,
+    previous: Option<&PreviousRows>,
 ) -> String {
+    let Some(previous) = previous else {
+        return generate_row_group_query(context, table, "_affectedRows", where_clause);
+    };
     let table_name = ast::get_tablename(&table.record.name, &table.record.fields);
-    let column_names: Vec<String> = typecheck::to_sql_column_info(context, &table.record.fields)
-        .into_iter()
-        .map(|column| column.name)
-        .collect();
-
-    // Build json_array call for each row - values in same order as headers
-    let mut row_value_parts = Vec::new();
-    for col in &column_names {
-        let quoted_col = string::quote(col);
-        row_value_parts.push(format!("t.{}", quoted_col));
-    }
+    let (header_parts, row_value_parts) = row_group_columns(context, table);
+    let quoted_table_name = string::quote(&table_name);
 
-    // Build json_array call for headers
-    let mut header_parts = Vec::new();
-    for col in &column_names {
-        header_parts.push(format!("'{}'", col));
-    }
+    // With the rows captured before the update, updated rows are found by primary key,
+    // which still works when the update changed a column the WHERE clause reads.
+    // Format: { table_name, headers, rows: [[...], [...]], previous: { headers, rows } }
+    format!(
+        "select json_group_array(json(affected_row)) as _affectedRows\nfrom (\n  select json_object(\n    'table_name', '{}',\n    'headers', json_array({}),\n    'rows', json_group_array(json_array({})),\n    'previous', json_object(\n      'headers', json_array({}),\n      'rows', json((select json_group_array(json_array({})) from {} p))\n    )\n  ) as affected_row\n  from {} t\n  where ({}) in (select {} from {})\n)",
+        table_name,
+        header_parts.join(", "),
+        row_value_parts.join(", "),
+        previous
+            .columns
+            .iter()
+            .map(|column| format!("'{}'", column))
+            .collect::<Vec<String>>()
+            .join(", "),
+        quoted_columns(&previous.columns, Some("p")),
+        previous.temp_table_name,
+        quoted_table_name,
+        quoted_columns(&previous.primary_key, Some("t")),
+        quoted_columns(&previous.primary_key, None),
+        previous.temp_table_name
+    )
+}
+
+/// Select the rows matching `where_clause` as one table group named `column_name`.
+///
+/// Format: { table_name, headers, rows: [[...], [...]] }
+fn generate_row_group_query(
+    context: &typecheck::Context,
+    table: &typecheck::Table,
+    column_name: &str,
+    where_clause: &str,
+) -> String {
+    let table_name = ast::get_tablename(&table.record.name, &table.record.fields);
+    let (header_parts, row_value_parts) = row_group_columns(context, table);
 
-    // Select affected rows using the same WHERE conditions
-    // Note: For UPDATE, this selects rows after the update, so the WHERE conditions
-    // should still match the updated rows
     // Replace table name in WHERE clause with alias 't'
     // WHERE clauses use format: "users"."id" so we need to replace "users"." with t."
     let quoted_table_name = string::quote(&table_name);
-    // Replace quoted table name references: "users"."column" -> t."column"
-    // The WHERE clause format is: "where\n "users"."id" = $id\n"
-    // We need to replace "users"." with t." to use the alias
-    // quoted_table_name is already "users" (with quotes), so we need to match "users"."column"
     let mut where_with_alias = where_clause.to_string();
     // Pattern 1: "users"."id" -> t."id" (most common)
-    // quoted_table_name is "users", so we match {quoted_table_name}." which is "users"."
     where_with_alias = where_with_alias.replace(&format!("{}.\"", quoted_table_name), "t.\"");
     // Pattern 2: users.id -> t.id (unquoted, shouldn't happen but be safe)
     where_with_alias = where_with_alias.replace(&format!("{}.", table_name), "t.");
-    // Format: { table_name, headers, rows: [[...], [...]] }
+
     format!(
-        "select json_group_array(json(affected_row)) as _affectedRows\nfrom (\n  select json_object(\n    'table_name', '{}',\n    'headers', json_array({}),\n    'rows', json_group_array(json_array({}))\n  ) as affected_row\n  from {} t\n{}\n)",
+        "select json_group_array(json(affected_row)) as {}\nfrom (\n  select json_object(\n    'table_name', '{}',\n    'headers', json_array({}),\n    'rows', json_group_array(json_array({}))\n  ) as affected_row\n  from {} t\n{}\n)",
+        column_name,
         table_name,
         header_parts.join(", "),
         row_value_parts.join(", "),
@@ -223,6 +399,22 @@ fn generate_affected_rows_query(
     )
 }
 
+/// The header names and row values of a table group, in the same order.
+fn row_group_columns(
+    context: &typecheck::Context,
+    table: &typecheck::Table,
+) -> (Vec<String>, Vec<String>) {
+    typecheck::to_sql_column_info(context, &table.record.fields)
+        .into_iter()
+        .map(|column| {
+            (
+                format!("'{}'", column.name),
+                format!("t.{}", string::quote(&column.name)),
+            )
+        })
+        .unzip()
+}
+
 // SET values
 
 fn to_field_set_values(
@@ -164,6 +164,7 @@ pub fn select_to_string(
                                     query,
                                     query_info,
                                     parent_table_alias,
+                                    &table.record,
                                     linked_table,
                                     query_field,
                                     link,
@@ -254,7 +255,8 @@ fn select_linked(
     context: &typecheck::Context,
     query: &ast::Query,
     query_info: &typecheck::QueryInfo,
-    parent_table_name: &String,
+    parent_table_name: &str,
+    parent_record: &ast::RecordDetails,
     table: &typecheck::Table,
     query_field: &ast::QueryField,
     link: &ast::LinkDetails,
@@ -320,6 +322,7 @@ fn select_linked(
             indent,
             context,
             parent_table_name,
+            parent_record,
             table,
             query_field,
             link,
@@ -333,7 +336,7 @@ fn select_linked(
     let mut field_names: Vec<String> = Vec::new();
     let table_name = ast::get_tablename(&table.record.name, &table.record.fields);
     let new_fieldnames = &to_fieldnames(context, table, &query_field.fields);
-    field_names.push(link.foreign.fields.clone().join(", "));
+    field_names.append(&mut link.foreign.fields.clone());
     field_names.append(&mut new_fieldnames.clone());
     // field_names.push
 
@@ -355,17 +358,6 @@ fn select_linked(
 
     let all_query_fields = ast::collect_query_fields(&query_field.fields);
 
-    let mut full_local_id = String::new();
-    for local_id in &link.local_ids {
-        full_local_id.push_str(local_id);
-    }
-
-    // This is the link.foreign_id, which is the id on this table
-    let mut full_foreign_id = String::new();
-    for foreign_id in &link.foreign.fields {
-        full_foreign_id.push_str(foreign_id);
-    }
-
     // Check if render_where will add a WHERE clause
     let mut where_check = String::new();
     to_sql::render_where(
@@ -388,13 +380,15 @@ fn select_linked(
     // Use WHERE ... IN (SELECT ...) - JOIN optimization causes memory issues in some cases
     if has_where {
         sql.push_str(&format!(
-            "{}and {}.{} in (select {} from {})\n",
-            indent_str, table_alias, full_foreign_id, full_local_id, parent_table_name
+            "{}and {}\n",
+            indent_str,
+            link_in_parent(table_alias, link, parent_table_name)
         ));
     } else {
         sql.push_str(&format!(
-            "{}where {}.{} in (select {} from {})\n",
-            indent_str, table_alias, full_foreign_id, full_local_id, parent_table_name
+            "{}where {}\n",
+            indent_str,
+            link_in_parent(table_alias, link, parent_table_name)
         ));
     }
     // result.push_str(&format!("{}from {}", indent_str, parent_table_name));
@@ -424,6 +418,7 @@ fn select_linked(
                             query,
                             query_info,
                             parent_temp_table,
+                            &table.record,
                             linked_table,
                             query_field,
                             &link,
@@ -440,7 +435,15 @@ fn select_linked(
 
     sql.push_str(&format!("), {} as (", json_table_name));
     // Format as JSON
-    select_formatted_as_json(indent, context, table, query_field, link, sql);
+    select_formatted_as_json(
+        indent,
+        context,
+        parent_record,
+        table,
+        query_field,
+        link,
+        sql,
+    );
 }
 
 fn selects_for_link(query: &ast::QueryField, table: &typecheck::Table) -> bool {
@@ -487,7 +490,8 @@ fn select_single_json(
     indent: usize,
     context: &typecheck::Context,
 
-    parent_table_name: &String,
+    parent_table_name: &str,
+    parent_record: &ast::RecordDetails,
     table: &typecheck::Table,
     query_table_field: &ast::QueryField,
     link: &ast::LinkDetails,
@@ -496,17 +500,14 @@ fn select_single_json(
     sql: &mut String,
 ) {
     let indent_str = " ".repeat(indent);
-    let aggregate_to_array = !link_returns_singular_result(context, &table.record, link);
-
-    // This is the link.foreign_id, which is the id on this table
-    let mut full_foreign_id = String::new();
-    for foreign_id in &link.foreign.fields {
-        full_foreign_id.push_str(foreign_id);
-    }
+    let aggregate_to_array = !link_returns_singular_result(context, parent_record, link);
 
     let query_aliased_as = &ast::get_aliased_name(&query_table_field);
     let table_alias = "t";
 
+    // This is the link.foreign_id, which is the id on this table
+    let full_foreign_id = link_columns(table_alias, &link.foreign.fields);
+
     // Compose main json payload
     let mut json_object = String::new();
     let mut first_field = true;
@@ -606,10 +607,9 @@ fn select_single_json(
     let table_name = ast::get_tablename(&table.record.name, &table.record.fields);
 
     sql.push_str(&format!(
-        "\n{}select\n  {}{}.{},\n{}  {}jsonb_object(\n{}\n{}  ){} as {}\n{}from {} {}\n",
+        "\n{}select\n  {}{},\n{}  {}jsonb_object(\n{}\n{}  ){} as {}\n{}from {} {}\n",
         indent_str,
         indent_str,
-        table_alias,
         full_foreign_id,
         indent_str,
         array_agg_start,
@@ -622,27 +622,17 @@ fn select_single_json(
         table_alias,
     ));
 
-    let mut full_local_id = String::new();
-    for local_id in &link.local_ids {
-        full_local_id.push_str(local_id);
-    }
-
     // Use WHERE ... IN (SELECT ...) - JOIN optimization causes memory issues in some cases
     sql.push_str(&format!(
-        "{}where {}.{} in (select {} from {})\n",
-        indent_str, table_alias, full_foreign_id, full_local_id, parent_table_name
+        "{}where {}\n",
+        indent_str,
+        link_in_parent(table_alias, link, parent_table_name)
     ));
 
     if aggregate_to_array {
-        sql.push_str(&format!(
-            "{}group by {}.{}\n",
-            indent_str, table_alias, full_foreign_id
-        ));
+        sql.push_str(&format!("{}group by {}\n", indent_str, full_foreign_id));
         // Add ORDER BY after GROUP BY to ensure deterministic ordering
-        sql.push_str(&format!(
-            "{}order by {}.{}\n",
-            indent_str, table_alias, full_foreign_id
-        ));
+        sql.push_str(&format!("{}order by {}\n", indent_str, full_foreign_id));
     }
 }
 
@@ -686,6 +676,7 @@ fn select_formatted_as_json(
     indent: usize,
     context: &typecheck::Context,
 
+    parent_record: &ast::RecordDetails,
     table: &typecheck::Table,
     query_table_field: &ast::QueryField,
     link: &ast::LinkDetails,
@@ -694,16 +685,13 @@ fn select_formatted_as_json(
     sql: &mut String,
 ) {
     let indent_str = " ".repeat(indent);
-    let aggregate_to_array = !link_returns_singular_result(context, &table.record, link);
+    let aggregate_to_array = !link_returns_singular_result(context, parent_record, link);
 
     let aliased_name = ast::get_aliased_name(query_table_field);
     let base_table_name = format!("temp_selected_{}", &aliased_name);
 
     // This is the link.foreign_id, which is the id on this table
-    let mut full_foreign_id = String::new();
-    for foreign_id in &link.foreign.fields {
-        full_foreign_id.push_str(foreign_id);
-    }
+    let full_foreign_id = link_columns(&base_table_name, &link.foreign.fields);
 
     // initial selection
     let array_agg_start = if aggregate_to_array {
@@ -712,8 +700,8 @@ fn select_formatted_as_json(
         ""
     };
     sql.push_str(&format!(
-        "\n{}select\n  {}{}.{},\n{}  {}jsonb_object(\n",
-        indent_str, indent_str, base_table_name, full_foreign_id, indent_str, array_agg_start
+        "\n{}select\n  {}{},\n{}  {}jsonb_object(\n",
+        indent_str, indent_str, full_foreign_id, indent_str, array_agg_start
     ));
 
     // Compose main json payload
@@ -850,14 +838,11 @@ fn select_formatted_as_json(
                                 let local_temp_alias = format!("temp__{}", link.link_name);
 
                                 sql.push_str(&format!(
-                                    "{}  left join {} {} on {}.{} = {}.{}\n",
+                                    "{}  left join {} {} on {}\n",
                                     indent_str,
                                     query_temp_table,
                                     local_temp_alias,
-                                    local_temp_alias,
-                                    link.foreign.fields.join(""),
-                                    base_table_name,
-                                    link.local_ids.join(""),
+                                    link_join_condition(&local_temp_alias, &base_table_name, link),
                                 ));
                             }
                         }
@@ -872,15 +857,9 @@ fn select_formatted_as_json(
     }
 
     if aggregate_to_array {
-        sql.push_str(&format!(
-            "{}group by {}.{}\n",
-            indent_str, base_table_name, full_foreign_id
-        ));
+        sql.push_str(&format!("{}group by {}\n", indent_str, full_foreign_id));
         // Add ORDER BY after GROUP BY to ensure deterministic ordering
-        sql.push_str(&format!(
-            "{}order by {}.{}\n",
-            indent_str, base_table_name, full_foreign_id
-        ));
+        sql.push_str(&format!("{}order by {}\n", indent_str, full_foreign_id));
     }
 }
 
@@ -1295,14 +1274,11 @@ fn final_select_formatted_as_json(
                                 let local_temp_alias = format!("temp__{}", link.link_name);
 
                                 sql.push_str(&format!(
-                                    "{}  left join {} {} on {}.{} = {}.{}\n",
+                                    "{}  left join {} {} on {}\n",
                                     indent_str,
                                     query_temp_table,
                                     local_temp_alias,
-                                    local_temp_alias,
-                                    link.foreign.fields.join(""),
-                                    base_table_name,
-                                    link.local_ids.join(""),
+                                    link_join_condition(&local_temp_alias, &base_table_name, link),
                                 ));
                             }
                         }
@@ -1319,18 +1295,54 @@ fn final_select_formatted_as_json(
     // sql.push_str(&format!("{}group by {}\n", indent_str, full_foreign_id));
 }
 
+/// `alias.a, alias.b` for each column of one side of a link.
+fn link_columns(alias: &str, columns: &[String]) -> String {
+    columns
+        .iter()
+        .map(|column| format!("{}.{}", alias, column))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Restricts `alias` to the rows linked from the parent selection.
+/// Composite links compare every column at once as a row value.
+fn link_in_parent(alias: &str, link: &ast::LinkDetails, parent_table_name: &str) -> String {
+    let foreign = link_columns(alias, &link.foreign.fields);
+    let local = link.local_ids.join(", ");
+    if link.foreign.fields.len() == 1 {
+        format!(
+            "{} in (select {} from {})",
+            foreign, local, parent_table_name
+        )
+    } else {
+        format!(
+            "({}) in (select {} from {})",
+            foreign, local, parent_table_name
+        )
+    }
+}
+
+fn link_join_condition(foreign_alias: &str, local_alias: &str, link: &ast::LinkDetails) -> String {
+    link.foreign
+        .fields
+        .iter()
+        .zip(&link.local_ids)
+        .map(|(foreign_id, local_id)| {
+            format!(
+                "{}.{} = {}.{}",
+                foreign_alias, foreign_id, local_alias, local_id
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
 fn link_returns_singular_result(
     context: &typecheck::Context,
     local_record: &ast::RecordDetails,
     link: &ast::LinkDetails,
 ) -> bool {
-    let primary_key_name = ast::get_primary_id_field_name(&local_record.fields);
-    let is_one_to_many = link.local_ids.iter().all(|id| {
-        primary_key_name
-            .as_ref()
-            .map(|pk| id == pk)
-            .unwrap_or(false)
-    });
+    let is_one_to_many = ast::ids_are_primary_key(&link.local_ids, &local_record.fields);
 
     let linked_to_unique = if let Some(linked_table) = typecheck::get_linked_table(context, link) {
         ast::linked_to_unique_field_with_record(link, &linked_table.record)
@@ -499,49 +499,41 @@ fn to_subfrom(
                     &ast::collect_query_fields(&query_field.fields),
                 );
 
-                let local_table_identifier = match table_alias_kind {
-                    TableAliasKind::Normal => to_sql::render_real_where_field(
-                        table,
-                        query_info,
-                        false,
-                        &link.local_ids.join(" "),
-                    ),
-                    TableAliasKind::Insert => {
-                        format!(
-                            "{}.{}",
-                            string::quote(&table_alias),
-                            string::quote(&link.local_ids.join(" "))
-                        )
-                    }
-                };
-
                 // Use the query field's aliased name as a table alias to avoid collisions
                 // when multiple fields point to the same table
-                let table_alias = ast::get_aliased_name(query_field);
-                let foreign_table_identifier = match table_alias_kind {
-                    TableAliasKind::Normal => {
-                        // For normal queries, use the table alias in the identifier
-                        format!(
-                            "{}.{}",
-                            string::quote(&table_alias),
-                            string::quote(&link.foreign.fields.join(""))
-                        )
-                    }
-                    TableAliasKind::Insert => {
-                        format!(
+                let foreign_alias = ast::get_aliased_name(query_field);
+                let conditions = link
+                    .local_ids
+                    .iter()
+                    .zip(&link.foreign.fields)
+                    .map(|(local_id, foreign_id)| {
+                        let local_table_identifier = match table_alias_kind {
+                            TableAliasKind::Normal => {
+                                to_sql::render_real_where_field(table, query_info, false, local_id)
+                            }
+                            TableAliasKind::Insert => {
+                                format!(
+                                    "{}.{}",
+                                    string::quote(&table_alias),
+                                    string::quote(local_id)
+                                )
+                            }
+                        };
+                        let foreign_table_identifier = format!(
                             "{}.{}",
-                            string::quote(&table_alias),
-                            string::quote(&link.foreign.fields.join(""))
-                        )
-                    }
-                };
+                            string::quote(&foreign_alias),
+                            string::quote(foreign_id)
+                        );
+                        format!("{} = {}", local_table_identifier, foreign_table_identifier)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" and ");
 
                 let join = format!(
-                    "left join {} {} on {} = {}",
+                    "left join {} {} on {}",
                     string::quote(&foreign_table_name),
-                    string::quote(&table_alias),
-                    local_table_identifier,
-                    foreign_table_identifier
+                    string::quote(&foreign_alias),
+                    conditions
                 );
                 inner_list.push(join);
                 inner_list
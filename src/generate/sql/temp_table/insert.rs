@@ -325,7 +325,7 @@ fn insert_linked(
         table,
         &ast::collect_query_fields(&query_table_field.fields),
     );
-    field_names.push(link.foreign.fields.clone().join(", "));
+    field_names.append(&mut link.foreign.fields.clone());
     field_names.append(&mut new_fieldnames.clone());
 
     let all_query_fields = ast::collect_query_fields(&query_table_field.fields);
@@ -390,19 +390,25 @@ fn insert_linked(
         )));
 
         // Create temp table with rowids of inserted rows by joining on foreign key
-        let foreign_key = &link.foreign.fields[0];
-        let local_key = &link.local_ids[0];
-        let quoted_foreign_key = string::quote(foreign_key);
-        let quoted_local_key = string::quote(local_key);
+        let join_condition = link
+            .foreign
+            .fields
+            .iter()
+            .zip(&link.local_ids)
+            .map(|(foreign_key, local_key)| {
+                format!(
+                    "t.{} = p.{}",
+                    string::quote(foreign_key),
+                    string::quote(local_key)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" and ");
         let quoted_table_name_for_temp = string::quote(&table_name);
         let quoted_parent_table = string::quote(parent_table_name);
         statements.push(to_sql::ignore(format!(
-            "create temp table {} as\n  select t.rowid as id\n  from {} t\n  join {} p on t.{} = p.{}",
-            temp_table_name,
-            quoted_table_name_for_temp,
-            quoted_parent_table,
-            quoted_foreign_key,
-            quoted_local_key
+            "create temp table {} as\n  select t.rowid as id\n  from {} t\n  join {} p on {}",
+            temp_table_name, quoted_table_name_for_temp, quoted_parent_table, join_condition
         )));
     }
 
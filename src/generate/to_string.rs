@@ -425,10 +425,15 @@ fn to_string_field_directive(
         ast::FieldDirective::Unique(details) => {
             format!("{}@unique{}\n", spaces, index_directive_to_string(details))
         }
+        ast::FieldDirective::PrimaryKey(names) => {
+            format!("{}@id({})\n", spaces, names.join(", "))
+        }
         ast::FieldDirective::Permissions(info) => {
             to_string_permissions_details(namespace, indent, info)
         }
         ast::FieldDirective::Timestamps => format!("{}@timestamps\n", spaces),
+        ast::FieldDirective::Strict => format!("{}@strict\n", spaces),
+        ast::FieldDirective::WithoutRowid => format!("{}@withoutRowid\n", spaces),
     }
 }
 
@@ -654,15 +659,16 @@ fn to_string_link_details_shorthand(
 
     result.push_str("@link(");
     let mut added = false;
+    // A single `id` local column is the shorthand form, `@link(Table.field)`
+    let is_shorthand = details.local_ids.len() == 1 && details.local_ids[0] == "id";
     for id in &details.local_ids {
+        if is_shorthand {
+            continue;
+        }
         if added {
             result.push_str(", ");
         }
-        if id == "id" {
-            continue;
-        } else {
-            result.push_str(id);
-        }
+        result.push_str(id);
         added = true
     }
     for id in &details.foreign.fields {
@@ -708,12 +714,15 @@ fn to_string_directive(directive: &ast::ColumnDirective) -> String {
         ast::ColumnDirective::Index => "@index".to_string(),
         ast::ColumnDirective::CreatedAt => "@createdAt".to_string(),
         ast::ColumnDirective::UpdatedAt => "@updatedAt".to_string(),
+        ast::ColumnDirective::Version => "@version".to_string(),
         ast::ColumnDirective::Default { id: _, value, .. } => match value {
             ast::DefaultValue::Now => "@default(now)".to_string(),
             ast::DefaultValue::Value(value) => {
                 format!("@default({})", &value_to_string(value))
             }
         },
+        ast::ColumnDirective::Collate(collation) => format!("@collate({})", collation.to_sql()),
+        ast::ColumnDirective::Computed(expr) => format!("@computed({})", computed_to_string(expr)),
     }
 }
 
@@ -895,6 +904,9 @@ fn to_string_param(indent_size: usize, arg: &ast::Arg) -> String {
             let content = format_where_for_braces(where_arg, indent_size);
             format!("{}@where {}\n", indent, content)
         }
+        ast::Arg::IfVersion(version) => {
+            format!("{}@ifVersion({})\n", indent, value_to_string(version))
+        }
     }
 }
 
@@ -935,6 +947,47 @@ fn format_where(where_arg: &ast::WhereArg) -> String {
     }
 }
 
+pub fn computed_to_string(expr: &ast::ComputedExpr) -> String {
+    match expr {
+        ast::ComputedExpr::Column(name) => name.clone(),
+        ast::ComputedExpr::String(value) => {
+            format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+        }
+        ast::ComputedExpr::Int(value) => value.to_string(),
+        ast::ComputedExpr::Float(value) => format!("{:?}", value),
+        ast::ComputedExpr::Null => "null".to_string(),
+        ast::ComputedExpr::Call { name, args } => format!(
+            "{}({})",
+            name,
+            args.iter()
+                .map(computed_to_string)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        ast::ComputedExpr::Binary { op, left, right } => {
+            // Operators are left associative, so only a right operand of the
+            // same precedence needs parentheses.
+            let wrap = |operand: &ast::ComputedExpr, needs_parens: fn(u8, u8) -> bool| {
+                let rendered = computed_to_string(operand);
+                match operand {
+                    ast::ComputedExpr::Binary { op: inner, .. }
+                        if needs_parens(inner.precedence(), op.precedence()) =>
+                    {
+                        format!("({})", rendered)
+                    }
+                    _ => rendered,
+                }
+            };
+            format!(
+                "{} {} {}",
+                wrap(left, |inner, outer| inner < outer),
+                op.to_pyre(),
+                wrap(right, |inner, outer| inner <= outer)
+            )
+        }
+    }
+}
+
 fn value_to_string(value: &ast::QueryValue) -> String {
     match value {
         ast::QueryValue::Fn(func) => format!(
@@ -82,34 +82,41 @@ pub fn return_data_aliases(
         &crate::ext::string::capitalize("ReturnData"),
     ));
 
-    let last_field_index = query
-        .fields
-        .iter()
-        .rposition(|field| matches!(field, ast::TopLevelQueryField::Field(_)))
-        .unwrap_or(0);
-
-    for (i, field) in query.fields.iter().enumerate() {
-        let is_last = i == last_field_index;
-        match field {
-            ast::TopLevelQueryField::Field(query_field) => {
-                let field_name: String = ast::get_aliased_name(query_field);
-
-                result.push_str(&(formatter.to_field)(
-                    &crate::ext::string::decapitalize(&field_name),
-                    &string::capitalize(&field_name),
-                    FieldMetadata {
-                        is_link: true,
-                        is_optional: false,
-                        is_array_relationship: true, // Top-level query fields are always arrays
-                    },
+    // Top-level query fields are always arrays. An `@ifVersion` update also lists the
+    // rows it skipped, which have the same type as the rows it updated.
+    let mut return_fields: Vec<(String, String)> = Vec::new();
+    for field in &query.fields {
+        if let ast::TopLevelQueryField::Field(query_field) = field {
+            let field_name: String = ast::get_aliased_name(query_field);
+            return_fields.push((
+                crate::ext::string::decapitalize(&field_name),
+                string::capitalize(&field_name),
+            ));
+            if ast::get_if_version(&query_field.fields).is_some() {
+                return_fields.push((
+                    crate::ext::string::decapitalize(&ast::version_conflicts_name(query_field)),
+                    string::capitalize(&field_name),
                 ));
-
-                result.push_str(&(formatter.to_field_separator)(is_last));
             }
-            _ => {}
         }
     }
 
+    for (i, (name, type_)) in return_fields.iter().enumerate() {
+        result.push_str(&(formatter.to_field)(
+            name,
+            type_,
+            FieldMetadata {
+                is_link: true,
+                is_optional: false,
+                is_array_relationship: true,
+            },
+        ));
+
+        result.push_str(&(formatter.to_field_separator)(
+            i + 1 == return_fields.len(),
+        ));
+    }
+
     result.push_str(&(formatter.to_type_def_end)());
     result.push_str("\n\n");
 }
@@ -245,13 +252,7 @@ fn to_query_type_alias(
                         continue;
                     }
 
-                    let primary_key_name = ast::get_primary_id_field_name(&table.fields);
-                    let is_one_to_many = link.local_ids.iter().all(|id| {
-                        primary_key_name
-                            .as_ref()
-                            .map(|pk| id == pk)
-                            .unwrap_or(false)
-                    });
+                    let is_one_to_many = ast::ids_are_primary_key(&link.local_ids, &table.fields);
 
                     let linked_to_unique =
                         if let Some(linked_table) = typecheck::get_linked_table(context, link) {
@@ -434,6 +434,7 @@ fn format_sql_entries(
                             if sync_mode
                                 && prepped.include
                                 && !prepped.sql.contains("_affectedRows")
+                                && !prepped.sql.contains("_conflicts")
                             {
                                 continue;
                             }
@@ -727,6 +728,7 @@ fn to_query_field_spec(
                         limit = Some(*val);
                     }
                 }
+                ast::Arg::IfVersion(_) => {}
             },
             ast::ArgField::Field(nested_field) => {
                 if nested_field.name == "*" {
@@ -985,6 +987,10 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
         ));
         result.push_str("      columns: [\n");
 
+        let primary_key = ast::get_primary_key_field_names(&table.record.fields);
+        // Members of a composite key are only unique together, not individually.
+        let single_column_key = primary_key.len() == 1;
+
         let mut is_first_column = true;
         for field in &table.record.fields {
             if let ast::Field::Column(column) = field {
@@ -993,7 +999,7 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
                 }
                 is_first_column = false;
 
-                let is_primary = ast::is_primary_key(column);
+                let is_primary = primary_key.contains(&column.name);
                 let is_unique = column
                     .directives
                     .iter()
@@ -1022,7 +1028,7 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
                 ));
                 result.push_str(&format!(
                     "          unique: {},\n",
-                    if is_unique || is_primary {
+                    if is_unique || (is_primary && single_column_key) {
                         "true"
                     } else {
                         "false"
@@ -1047,8 +1053,6 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
         result.push_str("      links: {\n");
 
         let links = ast::collect_links(&table.record.fields);
-        let primary_key_name = ast::get_primary_id_field_name(&table.record.fields);
-
         let mut is_first_rel = true;
         for link in links {
             if !is_first_rel {
@@ -1056,10 +1060,7 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
             }
             is_first_rel = false;
 
-            let is_many_to_one = link
-                .local_ids
-                .iter()
-                .any(|id| primary_key_name.as_ref().map(|pk| id != pk).unwrap_or(true));
+            let is_many_to_one = !ast::ids_are_primary_key(&link.local_ids, &table.record.fields);
 
             let foreign_table = get_linked_table(context, &link).expect(&format!(
                 "Failed to find linked table '{}' in context. This indicates a schema error.",
@@ -1068,18 +1069,9 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
             let foreign_table_name =
                 ast::get_tablename(&foreign_table.record.name, &foreign_table.record.fields);
 
-            let is_one_to_one = if is_many_to_one {
-                let foreign_field_is_unique =
-                    ast::linked_to_unique_field_with_record(&link, &foreign_table.record);
-                let local_field_is_unique = if link.local_ids.len() == 1 {
-                    ast::field_is_unique(&link.local_ids[0], &table.record)
-                } else {
-                    false
-                };
-                foreign_field_is_unique && local_field_is_unique
-            } else {
-                false
-            };
+            let is_one_to_one = is_many_to_one
+                && ast::linked_to_unique_field_with_record(&link, &foreign_table.record)
+                && ast::fields_are_unique(&link.local_ids, &table.record);
 
             let link_type = if is_one_to_one {
                 "one-to-one"
@@ -1089,34 +1081,38 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
                 "one-to-many"
             };
 
-            let (from_column, to_table, to_column) = if is_many_to_one {
-                (
-                    link.local_ids[0].clone(),
-                    foreign_table_name,
-                    link.foreign.fields[0].clone(),
-                )
-            } else {
-                (
-                    primary_key_name.clone().unwrap_or_else(|| "id".to_string()),
-                    foreign_table_name,
-                    link.foreign.fields[0].clone(),
-                )
-            };
-
             result.push_str(&format!("        {}: {{\n", string::quote(&link.link_name)));
             result.push_str(&format!("          type: {},\n", string::quote(link_type)));
             result.push_str(&format!(
                 "          from: {},\n",
-                string::quote(&from_column)
+                string::quote(&link.local_ids[0])
             ));
+            // `from` and `to.column` hold the first pair. Composite links
+            // also list every pair, and clients join on all of them.
+            if link.local_ids.len() > 1 {
+                let pairs = link
+                    .local_ids
+                    .iter()
+                    .zip(&link.foreign.fields)
+                    .map(|(from, to)| {
+                        format!(
+                            "{{ from: {}, to: {} }}",
+                            string::quote(from),
+                            string::quote(to)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                result.push_str(&format!("          columns: [{}],\n", pairs));
+            }
             result.push_str("          to: {\n");
             result.push_str(&format!(
                 "            table: {},\n",
-                string::quote(&to_table)
+                string::quote(&foreign_table_name)
             ));
             result.push_str(&format!(
                 "            column: {}\n",
-                string::quote(&to_column)
+                string::quote(&link.foreign.fields[0])
             ));
             result.push_str("          }\n");
             result.push_str("        }");
@@ -1129,7 +1125,7 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
         let mut is_first_index = true;
         for field in &table.record.fields {
             if let ast::Field::Column(column) = field {
-                let is_primary = ast::is_primary_key(column);
+                let is_primary = primary_key.contains(&column.name);
                 let is_unique = column
                     .directives
                     .iter()
@@ -1152,7 +1148,7 @@ fn to_schema_metadata(context: &typecheck::Context) -> String {
                     ));
                     result.push_str(&format!(
                         "          unique: {},\n",
-                        if is_unique || is_primary {
+                        if is_unique || (is_primary && single_column_key) {
                             "true"
                         } else {
                             "false"
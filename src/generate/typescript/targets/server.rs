@@ -169,7 +169,6 @@ fn to_seed_types(context: &typecheck::Context) -> String {
         }
 
         let links = ast::collect_links(&table.record.fields);
-        let primary_key_name = ast::get_primary_id_field_name(&table.record.fields);
         for link in links {
             let linked_table = typecheck::get_linked_table(context, &link).expect(&format!(
                 "Failed to find linked table '{}' in context. This indicates a schema error.",
@@ -178,12 +177,8 @@ fn to_seed_types(context: &typecheck::Context) -> String {
             let linked_table_name =
                 ast::get_tablename(&linked_table.record.name, &linked_table.record.fields);
             let linked_type_name = seed_row_type_name(&linked_table_name);
-            let is_parent_to_child = link.local_ids.iter().all(|id| {
-                primary_key_name
-                    .as_ref()
-                    .map(|pk| id == pk)
-                    .unwrap_or(false)
-            });
+            let is_parent_to_child =
+                ast::ids_are_primary_key(&link.local_ids, &table.record.fields);
             let link_type = if is_parent_to_child {
                 format!("{}[]", linked_type_name)
             } else {
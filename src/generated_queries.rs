@@ -165,23 +165,14 @@ fn build_create_query(table: &typecheck::Table) -> ast::Query {
 }
 
 fn build_update_query(table: &typecheck::Table) -> ast::Query {
-    let primary_key = primary_key_column(table).expect("generated CRUD requires primary key");
+    let primary_key = primary_key_columns(table);
     let writable_columns = writable_update_columns(table);
     let return_columns = scalar_return_columns(table);
 
-    let mut args = vec![ast::QueryParamDefinition {
-        name: primary_key.name.clone(),
-        type_: Some(typecheck::query_param_type_for_column(
-            &table.record,
-            primary_key,
-        )),
-        nullable: false,
-        omittable: false,
-        start_name: None,
-        end_name: None,
-        start_type: None,
-        end_type: None,
-    }];
+    let mut args = primary_key
+        .iter()
+        .map(|column| primary_key_param(table, column))
+        .collect::<Vec<_>>();
 
     args.extend(
         writable_columns
@@ -201,7 +192,7 @@ fn build_update_query(table: &typecheck::Table) -> ast::Query {
             }),
     );
 
-    let mut fields = vec![where_equals_field(&primary_key.name)];
+    let mut fields = vec![where_primary_key_field(&primary_key)];
     fields.extend(writable_columns.iter().map(|column| {
         ast::ArgField::Field(query_field_with_set(&column.name, variable(&column.name)))
     }));
@@ -224,12 +215,33 @@ fn build_update_query(table: &typecheck::Table) -> ast::Query {
 }
 
 fn build_delete_query(table: &typecheck::Table) -> ast::Query {
-    let primary_key = primary_key_column(table).expect("generated CRUD requires primary key");
-    let args = vec![ast::QueryParamDefinition {
-        name: primary_key.name.clone(),
+    let primary_key = primary_key_columns(table);
+    let args = primary_key
+        .iter()
+        .map(|column| primary_key_param(table, column))
+        .collect::<Vec<_>>();
+
+    let mut fields = vec![where_primary_key_field(&primary_key)];
+    fields.extend(
+        primary_key
+            .iter()
+            .map(|column| ast::ArgField::Field(selection_field(&column.name))),
+    );
+
+    build_query(
+        ast::QueryOperation::Delete,
+        format!("{}Delete", table.record.name),
+        args,
+        table_root_field(table, fields),
+    )
+}
+
+fn primary_key_param(table: &typecheck::Table, column: &ast::Column) -> ast::QueryParamDefinition {
+    ast::QueryParamDefinition {
+        name: column.name.clone(),
         type_: Some(typecheck::query_param_type_for_column(
             &table.record,
-            primary_key,
+            column,
         )),
         nullable: false,
         omittable: false,
@@ -237,19 +249,7 @@ fn build_delete_query(table: &typecheck::Table) -> ast::Query {
         end_name: None,
         start_type: None,
         end_type: None,
-    }];
-
-    let fields = vec![
-        where_equals_field(&primary_key.name),
-        ast::ArgField::Field(selection_field(&primary_key.name)),
-    ];
-
-    build_query(
-        ast::QueryOperation::Delete,
-        format!("{}Delete", table.record.name),
-        args,
-        table_root_field(table, fields),
-    )
+    }
 }
 
 fn build_query(
@@ -313,20 +313,36 @@ fn query_field_with_set(name: &str, set: Option<ast::QueryValue>) -> ast::QueryF
     }
 }
 
-fn where_equals_field(name: &str) -> ast::ArgField {
+/// A single `@where` matching every primary key column to its same-named variable.
+fn where_primary_key_field(primary_key: &[&ast::Column]) -> ast::ArgField {
+    let mut conditions = primary_key
+        .iter()
+        .map(|column| where_equals(&column.name))
+        .collect::<Vec<_>>();
+
+    let where_ = if conditions.len() == 1 {
+        conditions.remove(0)
+    } else {
+        ast::WhereArg::And(conditions)
+    };
+
     ast::ArgField::Arg(ast::LocatedArg {
-        arg: ast::Arg::Where(ast::WhereArg::Column(
-            false,
-            name.to_string(),
-            ast::Operator::Equal,
-            variable_value(name),
-            ast::empty_range(),
-        )),
+        arg: ast::Arg::Where(where_),
         start: None,
         end: None,
     })
 }
 
+fn where_equals(name: &str) -> ast::WhereArg {
+    ast::WhereArg::Column(
+        false,
+        name.to_string(),
+        ast::Operator::Equal,
+        variable_value(name),
+        ast::empty_range(),
+    )
+}
+
 fn variable(name: &str) -> Option<ast::QueryValue> {
     Some(variable_value(name))
 }
@@ -346,14 +362,19 @@ fn writable_create_columns(table: &typecheck::Table) -> Vec<&ast::Column> {
         .into_iter()
         .filter(|column| !ast::is_integer_primary_key(column))
         .filter(|column| !ast::is_managed_timestamp(column))
+        .filter(|column| !ast::is_computed(column))
+        .filter(|column| !ast::is_version(column))
         .collect()
 }
 
 fn writable_update_columns(table: &typecheck::Table) -> Vec<&ast::Column> {
+    let primary_key = ast::get_primary_key_field_names(&table.record.fields);
     scalar_columns(table)
         .into_iter()
-        .filter(|column| !ast::is_primary_key(column))
+        .filter(|column| !primary_key.contains(&column.name))
         .filter(|column| !ast::is_managed_timestamp(column))
+        .filter(|column| !ast::is_computed(column))
+        .filter(|column| !ast::is_version(column))
         .collect()
 }
 
@@ -373,8 +394,11 @@ fn scalar_columns(table: &typecheck::Table) -> Vec<&ast::Column> {
         .collect()
 }
 
-fn primary_key_column(table: &typecheck::Table) -> Option<&ast::Column> {
-    scalar_columns(table)
-        .into_iter()
-        .find(|column| ast::is_primary_key(column))
+/// Primary key columns in key order; composite keys produce one entry per key column.
+fn primary_key_columns(table: &typecheck::Table) -> Vec<&ast::Column> {
+    let columns = scalar_columns(table);
+    ast::get_primary_key_field_names(&table.record.fields)
+        .iter()
+        .filter_map(|name| columns.iter().copied().find(|column| &column.name == name))
+        .collect()
 }
@@ -23,6 +23,38 @@ pub fn hash_query_interface(query: &Query) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Identify a generated manifest by the interfaces it serves: its session fields and the
+/// interface hash of every query. Changes that only touch the SQL keep the same hash.
+pub fn hash_manifest<'a>(
+    session_fields: impl IntoIterator<Item = (&'a str, &'a str, bool)>,
+    query_ids: impl IntoIterator<Item = &'a str>,
+) -> String {
+    let mut hasher = Sha256::new();
+
+    let mut session_fields: Vec<_> = session_fields.into_iter().collect();
+    session_fields.sort();
+    for (name, type_, nullable) in session_fields {
+        update_length_prefixed(&mut hasher, name);
+        update_length_prefixed(&mut hasher, type_);
+        hasher.update(if nullable { "?" } else { "!" });
+    }
+
+    let mut query_ids: Vec<_> = query_ids.into_iter().collect();
+    query_ids.sort();
+    for query_id in query_ids {
+        update_length_prefixed(&mut hasher, query_id);
+    }
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// Hash `value` after its length, so neighbouring values can't run into each other:
+/// `("ab", "c")` and `("a", "bc")` hash differently.
+fn update_length_prefixed(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_le_bytes());
+    hasher.update(value);
+}
+
 pub fn hash_query_full(query: &Query) -> String {
     let mut hasher = Sha256::new();
 
@@ -90,6 +122,10 @@ fn hash_arg(hasher: &mut Sha256, arg: &Arg) {
             hasher.update("where");
             hash_where_arg(hasher, where_arg);
         }
+        Arg::IfVersion(value) => {
+            hasher.update("if_version");
+            hash_query_value(hasher, value);
+        }
     }
 }
 
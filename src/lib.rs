@@ -1,4 +1,5 @@
 pub mod ast;
+pub mod client;
 pub mod color;
 pub mod db;
 pub mod error;
@@ -14,5 +15,7 @@ pub mod seed;
 pub mod server;
 pub mod sync;
 pub mod sync_deltas;
+#[cfg(feature = "compact")]
+pub mod sync_encoding;
 pub mod sync_shape;
 pub mod typecheck;
@@ -534,9 +534,12 @@ fn parse_table_directive(input: Text) -> ParseResult<ast::Field> {
         parse_tablename(to_location(&start_pos)),
         parse_table_index_directive(false),
         parse_table_index_directive(true),
+        parse_table_primary_key,
         parse_table_permission,
         parse_public,
         parse_timestamps,
+        parse_strict,
+        parse_without_rowid,
         parse_watch(),
     )))(input)?;
     let input = expecting(input, crate::error::Expecting::SchemaColumn);
@@ -552,6 +555,24 @@ fn parse_timestamps(input: Text) -> ParseResult<ast::Field> {
     ))
 }
 
+fn parse_strict(input: Text) -> ParseResult<ast::Field> {
+    let (input, _) = tag("strict")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        ast::Field::FieldDirective(ast::FieldDirective::Strict),
+    ))
+}
+
+fn parse_without_rowid(input: Text) -> ParseResult<ast::Field> {
+    let (input, _) = tag("withoutRowid")(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        ast::Field::FieldDirective(ast::FieldDirective::WithoutRowid),
+    ))
+}
+
 fn parse_table_permission(input: Text) -> ParseResult<ast::Field> {
     let (input, _) = tag("allow")(input)?;
     // Commit to this branch once we've recognized @allow
@@ -760,6 +781,32 @@ fn parse_table_index_directive(is_unique: bool) -> impl Fn(Text) -> ParseResult<
     }
 }
 
+fn parse_table_primary_key(input: Text) -> ParseResult<ast::Field> {
+    let (input, _) = tag("id")(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = tag("(")(input)?;
+    let (input, _) = space0(input)?;
+    let (input, names) = cut(separated_list1(
+        |input| {
+            let (input, _) = space0(input)?;
+            let (input, _) = tag(",")(input)?;
+            let (input, _) = space0(input)?;
+            Ok((input, ()))
+        },
+        parse_fieldname,
+    ))(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = cut(tag(")"))(input)?;
+    let (input, _) = space0(input)?;
+
+    Ok((
+        input,
+        ast::Field::FieldDirective(ast::FieldDirective::PrimaryKey(
+            names.into_iter().map(|name| name.to_string()).collect(),
+        )),
+    ))
+}
+
 fn parse_index_column(input: Text) -> ParseResult<ast::IndexedColumn> {
     let (input, name) = parse_fieldname(input)?;
     let (input, _) = space0(input)?;
@@ -792,6 +839,42 @@ fn hspace0(input: Text) -> ParseResult<()> {
     Ok((input, ()))
 }
 
+fn starts_like_local_field(input: &Text) -> bool {
+    input
+        .fragment()
+        .chars()
+        .next()
+        .map(|c| c.is_lowercase() || c == '_')
+        .unwrap_or(false)
+}
+
+/// The foreign side of an explicit `@link`: one or more `Table.field`
+/// references, which must all name the same table.
+fn parse_link_foreign(input: Text) -> ParseResult<ast::Qualified> {
+    let (mut input, mut foreign) = parse_qualified(input)?;
+    loop {
+        let (rest, _) = hspace0(input.clone())?;
+        let (rest, comma) = opt(tag(","))(rest)?;
+        if comma.is_none() {
+            return Ok((input, foreign));
+        }
+        let (rest, _) = hspace0(rest)?;
+        let (rest, next) = parse_qualified(rest)?;
+        if next.schema != foreign.schema || next.table != foreign.table {
+            return Err(nom::Err::Failure(VerboseError {
+                errors: vec![(
+                    rest,
+                    VerboseErrorKind::Context(
+                        "every foreign column in a @link must be on the same table",
+                    ),
+                )],
+            }));
+        }
+        foreign.fields.extend(next.fields);
+        input = rest;
+    }
+}
+
 fn parse_column(input: Text) -> ParseResult<ast::Field> {
     let (input, start_pos) = position(input)?;
     let input = expecting(input, crate::error::Expecting::SchemaColumn);
@@ -808,26 +891,26 @@ fn parse_column(input: Text) -> ParseResult<ast::Field> {
         let (input, _) = hspace0(input)?;
         // We're parsing either
         //          fieldname @link(local_id, ForeignTable.foreignId)
+        //          fieldname @link(local_a, local_b, ForeignTable.a, ForeignTable.b)
         //          fieldname @link(ForeignTable.foreignId)
-        let starts_like_local_field = input
-            .fragment()
-            .chars()
-            .next()
-            .map(|c| c.is_lowercase() || c == '_')
-            .unwrap_or(false);
-
-        let (input, first_arg, foreign) = if starts_like_local_field {
+        let (input, local_ids, foreign) = if starts_like_local_field(&input) {
             // If the first token looks like a local field name, require the explicit form.
             // This avoids swallowing malformed "@link(authorId User.id)" as shorthand.
-            let (input, first_arg) = cut(parse_fieldname)(input)?;
-            let (input, _) = hspace0(input)?;
-            let (input, _) = cut(tag(","))(input)?;
-            let (input, _) = hspace0(input)?;
-            let (input, foreign) = cut(parse_qualified)(input)?;
-            (input, first_arg.to_string(), foreign)
+            let mut input = input;
+            let mut local_ids = vec![];
+            while starts_like_local_field(&input) {
+                let (rest, local_id) = cut(parse_fieldname)(input)?;
+                let (rest, _) = hspace0(rest)?;
+                let (rest, _) = cut(tag(","))(rest)?;
+                let (rest, _) = hspace0(rest)?;
+                local_ids.push(local_id.to_string());
+                input = rest;
+            }
+            let (input, foreign) = cut(parse_link_foreign)(input)?;
+            (input, local_ids, foreign)
         } else {
             let (input, foreign) = cut(parse_qualified)(input)?;
-            (input, "id".to_string(), foreign)
+            (input, vec!["id".to_string()], foreign)
         };
 
         let (input, _) = tag(")")(input)?;
@@ -838,7 +921,7 @@ fn parse_column(input: Text) -> ParseResult<ast::Field> {
 
         let link_details = ast::LinkDetails {
             link_name: name.to_string(),
-            local_ids: vec![first_arg],
+            local_ids,
 
             foreign: foreign,
             start_name: Some(to_location(&start_pos)),
@@ -904,7 +987,10 @@ fn parse_column_directive(input: Text) -> ParseResult<ast::ColumnDirective> {
         parse_directive_named("index", ast::ColumnDirective::Index),
         parse_directive_named("createdAt", ast::ColumnDirective::CreatedAt),
         parse_directive_named("updatedAt", ast::ColumnDirective::UpdatedAt),
+        parse_directive_named("version", ast::ColumnDirective::Version),
         parse_default_directive,
+        parse_collate_directive,
+        parse_computed_directive,
     )))(input)?;
     let (input, end_pos) = position(input)?;
 
@@ -938,6 +1024,160 @@ fn parse_default_directive(input: Text) -> ParseResult<ast::ColumnDirective> {
     ))
 }
 
+fn parse_collate_directive(input: Text) -> ParseResult<ast::ColumnDirective> {
+    let (input, _) = tag("collate(")(input)?;
+    let (input, _) = space0(input)?;
+    let (input, collation) = cut(alt((
+        parse_token("binary", ast::Collation::Binary),
+        parse_token("nocase", ast::Collation::NoCase),
+        parse_token("rtrim", ast::Collation::Rtrim),
+    )))(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = cut(tag(")"))(input)?;
+    Ok((input, ast::ColumnDirective::Collate(collation)))
+}
+
+fn parse_computed_directive(input: Text) -> ParseResult<ast::ColumnDirective> {
+    let (input, _) = tag("computed(")(input)?;
+    let (input, _) = space0(input)?;
+    let (input, expr) = cut(parse_computed_expr)(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = cut(tag(")"))(input)?;
+    Ok((input, ast::ColumnDirective::Computed(expr)))
+}
+
+// Computed expressions are parsed with two precedence levels:
+// `+`, `-` and `++` bind looser than `*` and `/`, and both are left associative.
+fn parse_computed_expr(input: Text) -> ParseResult<ast::ComputedExpr> {
+    let (mut input, mut left) = parse_computed_term(input)?;
+    loop {
+        let (next, op) = opt(tuple((
+            space0,
+            alt((
+                parse_token("++", ast::ComputedOperator::Concat),
+                parse_token("+", ast::ComputedOperator::Add),
+                parse_token("-", ast::ComputedOperator::Subtract),
+            )),
+            space0,
+        )))(input.clone())?;
+        let Some((_, op, _)) = op else {
+            return Ok((input, left));
+        };
+        let (next, right) = cut(parse_computed_term)(next)?;
+        left = ast::ComputedExpr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+        input = next;
+    }
+}
+
+fn parse_computed_term(input: Text) -> ParseResult<ast::ComputedExpr> {
+    let (mut input, mut left) = parse_computed_atom(input)?;
+    loop {
+        let (next, op) = opt(tuple((
+            space0,
+            alt((
+                parse_token("*", ast::ComputedOperator::Multiply),
+                parse_token("/", ast::ComputedOperator::Divide),
+            )),
+            space0,
+        )))(input.clone())?;
+        let Some((_, op, _)) = op else {
+            return Ok((input, left));
+        };
+        let (next, right) = cut(parse_computed_atom)(next)?;
+        left = ast::ComputedExpr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+        input = next;
+    }
+}
+
+fn parse_computed_atom(input: Text) -> ParseResult<ast::ComputedExpr> {
+    alt((
+        |input| {
+            let (input, _) = tag("(")(input)?;
+            let (input, _) = space0(input)?;
+            let (input, expr) = cut(parse_computed_expr)(input)?;
+            let (input, _) = space0(input)?;
+            let (input, _) = cut(tag(")"))(input)?;
+            Ok((input, expr))
+        },
+        |input| {
+            let (input, value) = parse_computed_string(input)?;
+            Ok((input, ast::ComputedExpr::String(value)))
+        },
+        parse_computed_number,
+        parse_computed_call_or_column,
+    ))(input)
+}
+
+/// A string literal where `\"` and `\\` stand for a quote and a backslash.
+/// Any other backslash is kept as it is.
+fn parse_computed_string(input: Text) -> ParseResult<String> {
+    let (mut input, _) = tag("\"")(input)?;
+    let mut value = String::new();
+    loop {
+        let (rest, chunk) = take_while(|c: char| c != '"' && c != '\\')(input)?;
+        value.push_str(chunk.fragment());
+        if let Ok((rest, _)) = tag::<_, _, VerboseError<Text>>("\"")(rest.clone()) {
+            return Ok((rest, value));
+        }
+        let (rest, _) = tag("\\")(rest)?;
+        let (rest, escaped) = nom::character::complete::anychar(rest)?;
+        if escaped != '"' && escaped != '\\' {
+            value.push('\\');
+        }
+        value.push(escaped);
+        input = rest;
+    }
+}
+
+fn parse_computed_number(input: Text) -> ParseResult<ast::ComputedExpr> {
+    let (input, number) = recognize(tuple((
+        opt(char('-')),
+        take_while1(|c: char| c.is_ascii_digit()),
+        opt(tuple((
+            char('.'),
+            take_while1(|c: char| c.is_ascii_digit()),
+        ))),
+    )))(input)?;
+    let number = number.fragment();
+    let expr = if number.contains('.') {
+        number.parse::<f64>().ok().map(ast::ComputedExpr::Float)
+    } else {
+        number.parse::<i64>().ok().map(ast::ComputedExpr::Int)
+    };
+    match expr {
+        Some(expr) => Ok((input, expr)),
+        None => Err(nom::Err::Error(VerboseError {
+            errors: vec![(input, VerboseErrorKind::Context("number"))],
+        })),
+    }
+}
+
+fn parse_computed_call_or_column(input: Text) -> ParseResult<ast::ComputedExpr> {
+    let (input, name) = parse_fieldname(input)?;
+    let (input, args) = opt(delimited(
+        tuple((tag("("), space0)),
+        separated_list0(tuple((space0, char(','), space0)), parse_computed_expr),
+        tuple((space0, cut(tag(")")))),
+    ))(input)?;
+    let expr = match args {
+        Some(args) => ast::ComputedExpr::Call {
+            name: name.to_string(),
+            args,
+        },
+        None if name == "null" => ast::ComputedExpr::Null,
+        None => ast::ComputedExpr::Column(name.to_string()),
+    };
+    Ok((input, expr))
+}
+
 fn parse_default_value(input: Text) -> ParseResult<(String, ast::DefaultValue)> {
     let start_offset = input.location_offset();
     let (input_after, val) = parse_value(input)?;
@@ -1458,7 +1698,12 @@ fn parse_query_field(input: Text) -> ParseResult<ast::QueryField> {
 fn parse_query_arg(input: Text) -> ParseResult<ast::Arg> {
     let (input, _) = tag("@")(input)?;
     let input = expecting(input, crate::error::Expecting::AtDirective);
-    cut(alt((parse_limit, parse_sort, parse_where)))(input)
+    cut(alt((
+        parse_limit,
+        parse_sort,
+        parse_where,
+        parse_if_version,
+    )))(input)
 }
 
 fn parse_limit(input: Text) -> ParseResult<ast::Arg> {
@@ -1472,6 +1717,17 @@ fn parse_limit(input: Text) -> ParseResult<ast::Arg> {
     Ok((input, ast::Arg::Limit(val)))
 }
 
+fn parse_if_version(input: Text) -> ParseResult<ast::Arg> {
+    let (input, _) = tag("ifVersion")(input)?;
+    let (input, _) = tag("(")(input)?;
+    let (input, _) = space0(input)?;
+    let (input, val) = parse_value(input)?;
+    let (input, _) = space0(input)?;
+    let (input, _) = tag(")")(input)?;
+
+    Ok((input, ast::Arg::IfVersion(val)))
+}
+
 fn parse_sort(input: Text) -> ParseResult<ast::Arg> {
     let (input, _) = multispace0(input)?;
     let (input, _) = tag("sort")(input)?;
@@ -111,7 +111,11 @@ pub fn seed_database(
             .unwrap_or(options.default_rows_per_table);
 
         // Get columns and links
-        let columns = ast::collect_columns(&record.fields);
+        // Computed and version columns are filled in by SQLite.
+        let columns: Vec<ast::Column> = ast::collect_columns(&record.fields)
+            .into_iter()
+            .filter(|column| !ast::is_computed(column) && !ast::is_version(column))
+            .collect();
         let links = ast::collect_links(&record.fields);
 
         // Pre-compute column names and foreign key mappings (same for all rows)
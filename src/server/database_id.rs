@@ -1,15 +1,23 @@
 use crate::sync::SyncPageResult;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 pub type DatabaseId = String;
 
+/// The placeholder replaced by the requested database id in a path template.
+pub const DATABASE_ID_PLACEHOLDER: &str = "{databaseId}";
+
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct DatabaseIdError {
-    label: String,
+pub enum DatabaseIdError {
+    Missing { label: String },
+    Invalid { database_id: String },
+    Unknown { database_id: String },
+    Mismatch { requested: String, expected: String },
 }
 
 impl DatabaseIdError {
-    fn new(label: &str) -> Self {
-        Self {
+    fn missing(label: &str) -> Self {
+        DatabaseIdError::Missing {
             label: label.to_string(),
         }
     }
@@ -17,7 +25,25 @@ impl DatabaseIdError {
 
 impl std::fmt::Display for DatabaseIdError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} is required", self.label)
+        match self {
+            DatabaseIdError::Missing { label } => write!(f, "{} is required", label),
+            DatabaseIdError::Invalid { database_id } => write!(
+                f,
+                "databaseId '{}' may only contain letters, digits, '_', '-', '.' and ':'",
+                database_id
+            ),
+            DatabaseIdError::Unknown { database_id } => {
+                write!(f, "databaseId '{}' is not served here", database_id)
+            }
+            DatabaseIdError::Mismatch {
+                requested,
+                expected,
+            } => write!(
+                f,
+                "databaseId '{}' does not match this server's databaseId '{}'",
+                requested, expected
+            ),
+        }
     }
 }
 
@@ -33,7 +59,7 @@ pub fn require_database_id_with_label(
 ) -> Result<DatabaseId, DatabaseIdError> {
     let value = value.as_ref();
     if value.trim().is_empty() {
-        return Err(DatabaseIdError::new(label));
+        return Err(DatabaseIdError::missing(label));
     }
 
     Ok(value.to_string())
@@ -46,3 +72,123 @@ pub fn with_database_id(
     result.database_id = Some(require_database_id(database_id)?);
     Ok(result)
 }
+
+/// Maps a requested database id to the database that should serve it.
+///
+/// Resolved values use the same conventions as the CLI database argument:
+/// a local path, a libSQL URL, or an environment variable prefixed with `$`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DatabaseResolver {
+    /// One database served under a single id.
+    Fixed {
+        database_id: DatabaseId,
+        database: String,
+    },
+    /// A template such as `data/{databaseId}.db`.
+    Template(String),
+    /// A directory holding one `<databaseId>.db` file per database.
+    Directory(PathBuf),
+    /// An explicit list of database ids.
+    Map(HashMap<DatabaseId, String>),
+}
+
+impl DatabaseResolver {
+    /// Pick a resolver for a database argument.
+    ///
+    /// Arguments containing `{databaseId}` become templates and existing
+    /// directories are served file by file. Anything else is a single database.
+    pub fn from_database_arg(database: &str, database_id: &str) -> Result<Self, DatabaseIdError> {
+        if database.contains(DATABASE_ID_PLACEHOLDER) {
+            Ok(DatabaseResolver::Template(database.to_string()))
+        } else if Path::new(database).is_dir() {
+            Ok(DatabaseResolver::Directory(PathBuf::from(database)))
+        } else {
+            Ok(DatabaseResolver::Fixed {
+                database_id: require_database_id(database_id)?,
+                database: database.to_string(),
+            })
+        }
+    }
+
+    /// The id used when a request doesn't name a database.
+    pub fn default_database_id(&self) -> Option<&str> {
+        match self {
+            DatabaseResolver::Fixed { database_id, .. } => Some(database_id),
+            _ => None,
+        }
+    }
+
+    /// Pick the database id for a request, falling back to the default one.
+    pub fn database_id_for(&self, requested: Option<&str>) -> Result<DatabaseId, DatabaseIdError> {
+        match requested {
+            Some(database_id) => require_database_id(database_id),
+            None => self
+                .default_database_id()
+                .map(str::to_string)
+                .ok_or_else(|| DatabaseIdError::missing("databaseId")),
+        }
+    }
+
+    pub fn resolve(&self, database_id: &str) -> Result<String, DatabaseIdError> {
+        let database_id = require_database_id(database_id)?;
+        match self {
+            DatabaseResolver::Fixed {
+                database_id: expected,
+                database,
+            } => {
+                if &database_id == expected {
+                    Ok(database.clone())
+                } else {
+                    Err(DatabaseIdError::Mismatch {
+                        requested: database_id,
+                        expected: expected.clone(),
+                    })
+                }
+            }
+            DatabaseResolver::Template(template) => {
+                require_path_safe(&database_id)?;
+                let database = template.replace(DATABASE_ID_PLACEHOLDER, &database_id);
+                require_existing_local(database, &database_id)
+            }
+            DatabaseResolver::Directory(directory) => {
+                require_path_safe(&database_id)?;
+                let path = directory.join(format!("{}.db", database_id));
+                require_existing_local(path.to_string_lossy().to_string(), &database_id)
+            }
+            DatabaseResolver::Map(databases) => databases
+                .get(&database_id)
+                .cloned()
+                .ok_or(DatabaseIdError::Unknown { database_id }),
+        }
+    }
+}
+
+fn require_path_safe(database_id: &str) -> Result<(), DatabaseIdError> {
+    let safe = !database_id.starts_with('.')
+        && database_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+    if safe {
+        Ok(())
+    } else {
+        Err(DatabaseIdError::Invalid {
+            database_id: database_id.to_string(),
+        })
+    }
+}
+
+/// Opening a missing local file would create an empty database, so unknown ids
+/// are rejected instead. Remote URLs are left for the connection to check.
+fn require_existing_local(database: String, database_id: &str) -> Result<String, DatabaseIdError> {
+    let remote = database.starts_with("http://")
+        || database.starts_with("https://")
+        || database.starts_with("libsql://")
+        || database.starts_with('$');
+    if remote || Path::new(&database).is_file() {
+        Ok(database)
+    } else {
+        Err(DatabaseIdError::Unknown {
+            database_id: database_id.to_string(),
+        })
+    }
+}
@@ -0,0 +1,1947 @@
+//! The HTTP server behind `pyre serve`, as an axum `Router` that apps can mount
+//! next to their own routes.
+//!
+//! ```rust,ignore
+//! let manifest = Manifest::load("pyre/generated/manifest.json")?;
+//! let databases = DatabaseResolver::from_database_arg("db/app.db", "default")?;
+//! let pyre = HttpServer::new(
+//!     HttpConfig::new(manifest, databases).with_session(|headers: &HeaderMap| {
+//!         session_from_my_cookie(headers)
+//!     }),
+//! );
+//! tokio::spawn(pyre.watch_for_changes());
+//!
+//! let app = Router::new()
+//!     .route("/", get(index))
+//!     .nest("/pyre", pyre.router());
+//! ```
+use crate::server::database_id::{DatabaseId, DatabaseIdError, DatabaseResolver};
+use crate::server::idempotency::{
+    self, ensure_idempotency, Begun, DEFAULT_IDEMPOTENCY_RETENTION_SECONDS,
+};
+use crate::server::manifest::{FieldSchema, Manifest, PyreSession};
+use crate::server::query::{BatchItem, BatchItemError, BatchOptions, QueryResult};
+use crate::server::schema::{load_schema_from_database, schema_version, LoadedSchema};
+use crate::server::sync::{
+    ensure_change_log, ensure_tombstones, sync_page_value, ConnectedSessions, DeltaMessage,
+    RecentDeltas, SessionShapes, SyncServer, DEFAULT_RECENT_DELTAS_CAPACITY,
+};
+use crate::sync::{ShapeFilter, SyncCursor, SyncPageResult};
+use crate::sync_encoding::{self, SyncEncoding, SyncFormat};
+use axum::extract::{Path as AxumPath, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, Mutex, MutexGuard, OnceCell};
+
+mod change_log;
+mod idempotency_keys;
+mod manifests;
+mod reload;
+mod session;
+mod sessions;
+mod socket;
+mod tombstones;
+
+use change_log::ChangeLogSettings;
+use manifests::ManifestHistory;
+pub use session::{SessionSource, DEFAULT_SESSION_HEADER};
+pub use sessions::SessionsUpdated;
+
+/// Sent by clients with the `hash` of the manifest they were built against.
+const MANIFEST_VERSION_HEADER: &str = "x-pyre-manifest";
+
+/// Messages buffered per live connection before it is collapsed into `syncRequired`.
+pub const DEFAULT_CONNECTION_QUEUE_SIZE: usize = 256;
+
+pub const DEFAULT_CHANGE_LOG_POLL_MS: u64 = 250;
+
+pub const DEFAULT_CHANGE_LOG_RETENTION_SECONDS: u64 = 3600;
+
+/// Seven days. Clients offline for longer resync their tables from scratch.
+pub const DEFAULT_TOMBSTONE_RETENTION_SECONDS: u64 = 7 * 24 * 3600;
+
+/// Previous manifests kept for clients built before a deploy.
+pub const DEFAULT_MANIFEST_HISTORY: usize = 5;
+
+/// Databases a server keeps open at once, see `HttpConfig::max_open_databases`.
+pub const DEFAULT_MAX_OPEN_DATABASES: usize = 1024;
+
+/// How long a WebSocket opened without a session may take to send an `auth` message.
+pub const DEFAULT_AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most mutations accepted by one `POST /db/replay`.
+pub const MAX_REPLAY_MUTATIONS: usize = 500;
+
+/// Sent with a query or mutation to run it at most once, see `crate::server::idempotency`.
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Opens the database that a `DatabaseResolver` resolved a database id to.
+pub type OpenDatabase = Arc<
+    dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<libsql::Database, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Everything an `HttpServer` is configured with. Databases bring their own schema.
+pub struct HttpConfig {
+    pub databases: DatabaseResolver,
+    pub open_database: OpenDatabase,
+    pub manifest: Manifest,
+    /// When set, `HttpServer::watch_for_changes` reloads the manifest from this path.
+    pub manifest_path: Option<PathBuf>,
+    /// Older manifests still served to clients built against them.
+    pub previous_manifests: Vec<Manifest>,
+    pub manifest_history: usize,
+    pub session: Arc<dyn SessionExtractor>,
+    pub authorizer: Option<Arc<dyn Authorizer>>,
+    /// Origins allowed to call the server from a browser. Empty disables CORS headers.
+    pub cors_origins: Vec<String>,
+    pub page_size: usize,
+    pub queue_size: usize,
+    /// Share live deltas with other processes through `_pyre_changes`.
+    pub change_log: Option<ChangeLogConfig>,
+    /// Seconds to keep `_pyre_tombstones` rows before pruning them.
+    pub tombstone_retention_seconds: u64,
+    /// Seconds to keep `_pyre_idempotency` rows before pruning them.
+    pub idempotency_retention_seconds: u64,
+    /// Databases kept open at once. Requests for another database are refused with
+    /// 503 once this many are open, since open databases are never closed.
+    pub max_open_databases: usize,
+    /// Bearer token for `POST /sync/sessions`. The endpoint is disabled without one.
+    pub admin_token: Option<String>,
+    /// WebSockets that haven't authenticated within this long are closed.
+    pub auth_timeout: Duration,
+}
+
+impl HttpConfig {
+    /// A config for a schema without session fields, opening databases with
+    /// `open_libsql(None)`.
+    pub fn new(manifest: Manifest, databases: DatabaseResolver) -> Self {
+        HttpConfig {
+            databases,
+            open_database: open_libsql(None),
+            manifest,
+            manifest_path: None,
+            previous_manifests: Vec::new(),
+            manifest_history: DEFAULT_MANIFEST_HISTORY,
+            session: Arc::new(SessionSource::Empty),
+            authorizer: None,
+            cors_origins: Vec::new(),
+            page_size: crate::sync::DEFAULT_SYNC_PAGE_SIZE,
+            queue_size: DEFAULT_CONNECTION_QUEUE_SIZE,
+            change_log: None,
+            tombstone_retention_seconds: DEFAULT_TOMBSTONE_RETENTION_SECONDS,
+            idempotency_retention_seconds: DEFAULT_IDEMPOTENCY_RETENTION_SECONDS,
+            max_open_databases: DEFAULT_MAX_OPEN_DATABASES,
+            admin_token: None,
+            auth_timeout: DEFAULT_AUTH_TIMEOUT,
+        }
+    }
+
+    pub fn with_session(mut self, session: impl SessionExtractor + 'static) -> Self {
+        self.session = Arc::new(session);
+        self
+    }
+
+    pub fn with_authorizer(mut self, authorizer: impl Authorizer + 'static) -> Self {
+        self.authorizer = Some(Arc::new(authorizer));
+        self
+    }
+
+    pub fn with_admin_token(mut self, admin_token: impl Into<String>) -> Self {
+        self.admin_token = Some(admin_token.into());
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangeLogConfig {
+    /// How often `_pyre_changes` is read.
+    pub poll_interval: Duration,
+    /// Seconds to keep `_pyre_changes` rows before pruning them.
+    pub retention_seconds: u64,
+}
+
+impl Default for ChangeLogConfig {
+    fn default() -> Self {
+        ChangeLogConfig {
+            poll_interval: Duration::from_millis(DEFAULT_CHANGE_LOG_POLL_MS),
+            retention_seconds: DEFAULT_CHANGE_LOG_RETENTION_SECONDS,
+        }
+    }
+}
+
+/// Open local database files, and remote libSQL/Turso databases with `auth_token`.
+pub fn open_libsql(auth_token: Option<String>) -> OpenDatabase {
+    Arc::new(move |database: String| {
+        let auth_token = auth_token.clone();
+        Box::pin(async move {
+            let remote = ["http://", "https://", "libsql://"]
+                .iter()
+                .any(|scheme| database.starts_with(scheme));
+            let built = if remote {
+                let Some(auth_token) = auth_token else {
+                    return Err(format!("an auth token is required for {}", database));
+                };
+                libsql::Builder::new_remote(database, auth_token)
+                    .build()
+                    .await
+            } else {
+                libsql::Builder::new_local(database).build().await
+            };
+            built.map_err(|error| format!("database error: {}", error))
+        })
+    })
+}
+
+/// Decides the Pyre session of each request.
+///
+/// The returned value is validated against the manifest's session schema, so an
+/// extractor only has to find the session, not check it.
+pub trait SessionExtractor: Send + Sync {
+    /// The session for an HTTP request or WebSocket upgrade.
+    fn session(
+        &self,
+        headers: &HeaderMap,
+        session_schema: &HashMap<String, FieldSchema>,
+    ) -> Result<JsonValue, HttpError>;
+
+    /// The session for the credential of a WebSocket `auth` message, for browsers
+    /// that cannot set headers on a socket.
+    fn session_from_credential(
+        &self,
+        _credential: &str,
+        _session_schema: &HashMap<String, FieldSchema>,
+    ) -> Result<JsonValue, HttpError> {
+        Err(HttpError::Unauthorized(
+            "auth messages are not supported".to_string(),
+        ))
+    }
+
+    /// When the session for `headers` expires, in unix seconds. Live connections
+    /// opened with it get `sessionExpired` and are closed once it passes.
+    fn expires_at(&self, _headers: &HeaderMap) -> Option<i64> {
+        None
+    }
+
+    /// When the session for a WebSocket `auth` credential expires, in unix seconds.
+    fn credential_expires_at(&self, _credential: &str) -> Option<i64> {
+        None
+    }
+}
+
+impl<F> SessionExtractor for F
+where
+    F: Fn(&HeaderMap) -> Result<JsonValue, HttpError> + Send + Sync,
+{
+    fn session(
+        &self,
+        headers: &HeaderMap,
+        _session_schema: &HashMap<String, FieldSchema>,
+    ) -> Result<JsonValue, HttpError> {
+        self(headers)
+    }
+}
+
+/// Decides whether a session may do something with a database, before Pyre's own
+/// permissions are applied to the rows involved.
+pub trait Authorizer: Send + Sync {
+    fn authorize(&self, access: &Access<'_>) -> Result<(), HttpError>;
+}
+
+impl<F> Authorizer for F
+where
+    F: Fn(&Access<'_>) -> Result<(), HttpError> + Send + Sync,
+{
+    fn authorize(&self, access: &Access<'_>) -> Result<(), HttpError> {
+        self(access)
+    }
+}
+
+/// A request checked by an `Authorizer`.
+pub struct Access<'a> {
+    pub database_id: &'a str,
+    pub session: &'a PyreSession,
+    /// The request's headers. For a WebSocket, the headers of the upgrade request.
+    pub headers: &'a HeaderMap,
+    pub action: Action<'a>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action<'a> {
+    /// `POST /sync`, or a `catchup` message on `/sync/ws`.
+    Catchup,
+    /// Opening `/sync/events` or authenticating `/sync/ws` to receive live deltas.
+    Subscribe,
+    /// Running a query or mutation, on its own, in a batch or on `/sync/ws`.
+    Run { query_id: &'a str },
+}
+
+/// Serves Pyre queries, mutations and sync for the databases of one `HttpConfig`.
+#[derive(Clone)]
+pub struct HttpServer {
+    state: Arc<AppState>,
+}
+
+struct AppState {
+    databases: DatabaseRouter,
+    /// The current manifest is replaced as a whole when `manifest.json` changes on
+    /// disk, and the replaced one is kept for clients that still use it.
+    manifests: RwLock<ManifestHistory>,
+    manifest_path: Option<PathBuf>,
+    session: Arc<dyn SessionExtractor>,
+    authorizer: Option<Arc<dyn Authorizer>>,
+    page_size: usize,
+    queue_size: usize,
+    cors_origins: Vec<String>,
+    admin_token: Option<String>,
+    auth_timeout: Duration,
+}
+
+/// Opens databases on first use and keeps them, along with their schema and
+/// live connections, for later requests.
+struct DatabaseRouter {
+    resolver: DatabaseResolver,
+    opener: OpenDatabase,
+    change_log: Option<ChangeLogSettings>,
+    tombstone_retention_seconds: u64,
+    idempotency_retention_seconds: u64,
+    max_open_databases: usize,
+    /// Only held to find or add a database's cell. Each database opens inside its own
+    /// cell, so a slow database doesn't hold up requests for the others.
+    open: Mutex<HashMap<DatabaseId, Arc<OnceCell<Arc<RoutedDatabase>>>>>,
+}
+
+/// Everything served for one database id. Live connections never cross ids.
+struct RoutedDatabase {
+    database_id: DatabaseId,
+    db: libsql::Database,
+    /// Replaced as a whole when a migration writes a new `_pyre_schema` row.
+    loaded_schema: RwLock<Arc<LoadedSchema>>,
+    schema_version: Mutex<Option<i64>>,
+    connections: Mutex<HashMap<String, Connection>>,
+    /// Held while deltas are calculated and sent, so revisions are recorded in order.
+    recent_deltas: Mutex<RecentDeltas>,
+    dropped_messages: AtomicU64,
+    overflows: AtomicU64,
+    /// Set when deltas are also written to `_pyre_changes` for other processes.
+    change_log_instance: Option<String>,
+}
+
+struct Connection {
+    /// Replaced by `HttpServer::update_sessions` while the connection stays open.
+    session: PyreSession,
+    /// The rows the connection subscribed to. `None` receives every row it can see.
+    shapes: Option<ShapeFilter>,
+    sender: ConnectionSender,
+}
+
+/// The sending half of a live connection's bounded queue.
+///
+/// When the queue is full the connection is marked as overflowed and later messages
+/// are dropped, until the receiver replaces the backlog with one `syncRequired`.
+#[derive(Clone)]
+struct ConnectionSender {
+    sender: mpsc::Sender<JsonValue>,
+    overflowed: Arc<AtomicBool>,
+}
+
+struct ConnectionReceiver {
+    receiver: mpsc::Receiver<JsonValue>,
+    overflowed: Arc<AtomicBool>,
+    database_id: DatabaseId,
+}
+
+fn connection_queue(
+    queue_size: usize,
+    database_id: &str,
+) -> (ConnectionSender, ConnectionReceiver) {
+    let (sender, receiver) = mpsc::channel(queue_size.max(1));
+    let overflowed = Arc::new(AtomicBool::new(false));
+    (
+        ConnectionSender {
+            sender,
+            overflowed: Arc::clone(&overflowed),
+        },
+        ConnectionReceiver {
+            receiver,
+            overflowed,
+            database_id: database_id.to_string(),
+        },
+    )
+}
+
+enum Delivery {
+    Sent,
+    /// Dropped because the queue is full. `first` is set for the message that overflowed it.
+    Dropped {
+        first: bool,
+    },
+    Closed,
+}
+
+impl ConnectionSender {
+    fn send(&self, message: JsonValue) -> Delivery {
+        if self.overflowed.load(Ordering::Acquire) {
+            return Delivery::Dropped { first: false };
+        }
+        match self.sender.try_send(message) {
+            Ok(()) => Delivery::Sent,
+            Err(mpsc::error::TrySendError::Full(_)) => Delivery::Dropped {
+                first: !self.overflowed.swap(true, Ordering::AcqRel),
+            },
+            Err(mpsc::error::TrySendError::Closed(_)) => Delivery::Closed,
+        }
+    }
+}
+
+impl ConnectionReceiver {
+    async fn recv(&mut self) -> Option<JsonValue> {
+        let message = self.receiver.recv().await?;
+        if !self.overflowed.swap(false, Ordering::AcqRel) {
+            return Some(message);
+        }
+
+        // The client is missing deltas either way, so the backlog is discarded and the
+        // client catches up instead.
+        while self.receiver.try_recv().is_ok() {}
+        let sync_required = DeltaMessage::sync_required_for_database(&self.database_id)
+            .ok()
+            .and_then(|message| serde_json::to_value(message).ok());
+        Some(sync_required.unwrap_or(message))
+    }
+}
+
+#[derive(Deserialize)]
+struct SyncRequest {
+    #[serde(rename = "databaseId")]
+    database_id: Option<String>,
+    #[serde(rename = "syncCursor")]
+    sync_cursor: Option<SyncCursor>,
+    #[serde(default)]
+    shapes: Vec<ShapeCatchup>,
+}
+
+/// A subscription to the rows a query reads, see `crate::sync::ShapeDefinition`.
+#[derive(Clone, Deserialize)]
+struct ShapeRequest {
+    #[serde(rename = "queryId")]
+    query_id: String,
+    #[serde(default)]
+    params: serde_json::Map<String, JsonValue>,
+}
+
+/// A shape caught up on its own cursor. `id` names its result in the response.
+#[derive(Deserialize)]
+struct ShapeCatchup {
+    id: String,
+    #[serde(flatten)]
+    shape: ShapeRequest,
+    #[serde(rename = "syncCursor", default)]
+    sync_cursor: SyncCursor,
+}
+
+/// The pages a catchup request returns, before they are encoded for the client.
+enum SyncResponse {
+    Page(SyncPageResult),
+    /// Pages keyed by the id of the shape they catch up.
+    Shapes(HashMap<String, SyncPageResult>),
+}
+
+impl SyncResponse {
+    fn into_value(self, format: SyncFormat) -> Result<JsonValue, HttpError> {
+        let value = match self {
+            SyncResponse::Page(page) => sync_page_value(page, format),
+            SyncResponse::Shapes(pages) => pages
+                .into_iter()
+                .map(|(id, page)| sync_page_value(page, format).map(|page| (id, page)))
+                .collect::<serde_json::Result<serde_json::Map<_, _>>>()
+                .map(|shapes| json!({ "shapes": shapes })),
+        };
+        value.map_err(|error| HttpError::Internal(error.to_string()))
+    }
+}
+
+/// An offline client's mutation log, applied in order by `POST /db/replay`.
+#[derive(Deserialize)]
+struct ReplayRequest {
+    mutations: Vec<ReplayMutation>,
+}
+
+#[derive(Deserialize)]
+struct ReplayMutation {
+    #[serde(rename = "idempotencyKey")]
+    idempotency_key: String,
+    #[serde(flatten)]
+    item: BatchItem,
+}
+
+#[derive(Deserialize)]
+struct BatchRequest {
+    #[serde(default)]
+    transaction: bool,
+    queries: Vec<BatchItem>,
+}
+
+#[derive(Deserialize)]
+struct RequestQuery {
+    #[serde(rename = "databaseId")]
+    database_id: Option<String>,
+    #[serde(rename = "connectionId")]
+    connection_id: Option<String>,
+    sync: Option<String>,
+    /// For WebSockets, where browsers cannot set the manifest version header.
+    #[serde(rename = "manifestVersion")]
+    manifest_version: Option<String>,
+    /// For `/sync/events`, a JSON array of shapes to receive live deltas for.
+    shapes: Option<String>,
+    /// For WebSockets, `msgpack` to receive binary MessagePack frames.
+    encoding: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HealthResponse<'a> {
+    ok: bool,
+    #[serde(rename = "databaseId", skip_serializing_if = "Option::is_none")]
+    database_id: Option<&'a str>,
+    #[serde(rename = "manifestVersion")]
+    manifest_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connections: Option<ConnectionStats>,
+}
+
+/// Live connection counters for one database, reported by `/health`.
+#[derive(Serialize)]
+struct ConnectionStats {
+    open: usize,
+    /// Deltas dropped because a connection's queue was full.
+    #[serde(rename = "droppedMessages")]
+    dropped_messages: u64,
+    /// Times a connection overflowed and was sent `syncRequired`.
+    overflows: u64,
+}
+
+/// An error response. Hooks return these to reject a request.
+#[derive(Debug)]
+pub enum HttpError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    /// The client's manifest is neither current nor kept, so it should reload.
+    ClientOutdated {
+        manifest_version: String,
+    },
+    /// An idempotency key was sent again with a different request.
+    IdempotencyKeyReused(String),
+    /// The server can't take the request right now, e.g. too many databases are open.
+    Unavailable(String),
+    Internal(String),
+}
+
+impl HttpError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            HttpError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            HttpError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            HttpError::Forbidden(_) => StatusCode::FORBIDDEN,
+            HttpError::NotFound(_) => StatusCode::NOT_FOUND,
+            HttpError::ClientOutdated { .. } | HttpError::IdempotencyKeyReused(_) => {
+                StatusCode::CONFLICT
+            }
+            HttpError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            HttpError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            HttpError::BadRequest(message)
+            | HttpError::Unauthorized(message)
+            | HttpError::Forbidden(message)
+            | HttpError::NotFound(message)
+            | HttpError::IdempotencyKeyReused(message)
+            | HttpError::Unavailable(message)
+            | HttpError::Internal(message) => message,
+            HttpError::ClientOutdated { .. } => {
+                "client is outdated; reload to get the current manifest"
+            }
+        }
+    }
+
+    fn body(&self) -> JsonValue {
+        match self {
+            HttpError::ClientOutdated { manifest_version } => json!({
+                "error": self.message(),
+                "code": "clientOutdated",
+                "manifestVersion": manifest_version,
+            }),
+            HttpError::IdempotencyKeyReused(_) => json!({
+                "error": self.message(),
+                "code": "idempotencyKeyReused",
+            }),
+            _ => json!({ "error": self.message() }),
+        }
+    }
+}
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        (status, Json(self.body())).into_response()
+    }
+}
+
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl HttpServer {
+    pub fn new(config: HttpConfig) -> Self {
+        let mut manifests = ManifestHistory::new(config.manifest, config.manifest_history);
+        for previous in config.previous_manifests {
+            manifests.add_previous(previous);
+        }
+        HttpServer {
+            state: Arc::new(AppState {
+                databases: DatabaseRouter {
+                    resolver: config.databases,
+                    opener: config.open_database,
+                    change_log: config.change_log.map(|change_log| ChangeLogSettings {
+                        instance_id: new_instance_id(),
+                        poll_interval: change_log.poll_interval.max(Duration::from_millis(1)),
+                        retention_seconds: change_log.retention_seconds,
+                    }),
+                    tombstone_retention_seconds: config.tombstone_retention_seconds,
+                    idempotency_retention_seconds: config.idempotency_retention_seconds,
+                    max_open_databases: config.max_open_databases.max(1),
+                    open: Mutex::new(HashMap::new()),
+                },
+                manifests: RwLock::new(manifests),
+                manifest_path: config.manifest_path,
+                session: config.session,
+                authorizer: config.authorizer,
+                page_size: config.page_size,
+                queue_size: config.queue_size,
+                cors_origins: config.cors_origins,
+                admin_token: config.admin_token,
+                auth_timeout: config.auth_timeout,
+            }),
+        }
+    }
+
+    /// Open a database ahead of its first request, so a bad database or schema is
+    /// reported at startup.
+    pub async fn open_database(&self, database_id: &str) -> Result<(), HttpError> {
+        self.state.databases.get(database_id).await.map(|_| ())
+    }
+
+    /// Reload the manifest and each open database's schema as they change. Runs
+    /// until dropped, so spawn it next to the server.
+    pub fn watch_for_changes(&self) -> impl Future<Output = ()> + Send + 'static {
+        reload::watch_for_changes(Arc::clone(&self.state))
+    }
+
+    /// Replace the session of the live connections to `database_id` that `update`
+    /// returns a new session for. `update` sees each session as JSON and returns
+    /// `None` to leave it as it is.
+    ///
+    /// Connections whose permission hashes changed are sent a `syncRequired` listing
+    /// the tables to catch up on.
+    pub async fn update_sessions<F>(
+        &self,
+        database_id: &str,
+        update: F,
+    ) -> Result<SessionsUpdated, HttpError>
+    where
+        F: Fn(&JsonValue) -> Option<JsonValue>,
+    {
+        let database = self.state.databases.get(database_id).await?;
+        sessions::update_sessions(&self.state, &database, update).await
+    }
+
+    /// The Pyre routes, ready to serve on their own or be nested under an app's router.
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new()
+            .route("/health", get(health).options(cors_preflight))
+            .route("/sync", post(sync).options(cors_preflight))
+            .route("/sync/events", get(sync_events).options(cors_preflight))
+            .route("/sync/ws", get(socket::sync_socket))
+            .route("/sync/sessions", post(sessions::update_sessions_request))
+            .route("/db/batch", post(run_batch).options(cors_preflight))
+            .route("/db/replay", post(run_replay).options(cors_preflight))
+            .route("/db/:query_id", post(run_query).options(cors_preflight))
+            .with_state(Arc::clone(&self.state))
+    }
+}
+
+/// `HttpServer::new(config).router()`, for apps that don't need the server's
+/// other methods. The manifest and schemas are not reloaded.
+pub fn router<S>(config: HttpConfig) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    HttpServer::new(config).router()
+}
+
+impl AppState {
+    fn authorize(
+        &self,
+        headers: &HeaderMap,
+        database: &RoutedDatabase,
+        session: &PyreSession,
+        action: Action<'_>,
+    ) -> Result<(), HttpError> {
+        match &self.authorizer {
+            Some(authorizer) => authorizer.authorize(&Access {
+                database_id: &database.database_id,
+                session,
+                headers,
+                action,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn manifests(&self) -> std::sync::RwLockReadGuard<'_, ManifestHistory> {
+        self.manifests
+            .read()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn manifest(&self) -> Arc<Manifest> {
+        self.manifests().current()
+    }
+
+    /// The manifest to run `query_ids` with, see `ManifestHistory::resolve`.
+    fn manifest_for(
+        &self,
+        client_version: Option<&str>,
+        query_ids: &[&str],
+    ) -> Result<Arc<Manifest>, HttpError> {
+        self.manifests().resolve(client_version, query_ids)
+    }
+}
+
+impl RoutedDatabase {
+    fn loaded_schema(&self) -> Arc<LoadedSchema> {
+        Arc::clone(
+            &self
+                .loaded_schema
+                .read()
+                .unwrap_or_else(|error| error.into_inner()),
+        )
+    }
+}
+
+impl DatabaseRouter {
+    async fn get(&self, database_id: &str) -> Result<Arc<RoutedDatabase>, HttpError> {
+        let cell = {
+            let mut open = self.open.lock().await;
+            match open.get(database_id) {
+                Some(cell) => Arc::clone(cell),
+                None => {
+                    if open.len() >= self.max_open_databases {
+                        return Err(HttpError::Unavailable(format!(
+                            "databaseId '{}' can't be opened, {} databases are already open",
+                            database_id, self.max_open_databases
+                        )));
+                    }
+                    let cell = Arc::new(OnceCell::new());
+                    open.insert(database_id.to_string(), Arc::clone(&cell));
+                    cell
+                }
+            }
+        };
+
+        match cell
+            .get_or_try_init(|| self.open_database(database_id))
+            .await
+        {
+            Ok(database) => Ok(Arc::clone(database)),
+            Err(error) => {
+                // Forget the failed attempt, so the next request tries again and unknown
+                // ids don't count toward the limit.
+                let mut open = self.open.lock().await;
+                if open
+                    .get(database_id)
+                    .is_some_and(|current| Arc::ptr_eq(current, &cell))
+                {
+                    open.remove(database_id);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Every database that has finished opening.
+    async fn opened(&self) -> Vec<Arc<RoutedDatabase>> {
+        self.open
+            .lock()
+            .await
+            .values()
+            .filter_map(|cell| cell.get().cloned())
+            .collect()
+    }
+
+    async fn open_database(&self, database_id: &str) -> Result<Arc<RoutedDatabase>, HttpError> {
+        let database = self
+            .resolver
+            .resolve(database_id)
+            .map_err(database_id_error)?;
+        let db = (self.opener)(database).await.map_err(HttpError::Internal)?;
+        let conn = db
+            .connect()
+            .map_err(|error| HttpError::Internal(format!("database error: {}", error)))?;
+        let schema_version = schema_version(&conn)
+            .await
+            .map_err(|error| HttpError::Internal(error.to_string()))?;
+        let loaded_schema = load_schema_from_database(&conn).await.map_err(|error| {
+            HttpError::Internal(format!("databaseId '{}': {}", database_id, error))
+        })?;
+        if let Ok(context) = loaded_schema.context() {
+            ensure_tombstones(&conn, context).await.map_err(|error| {
+                HttpError::Internal(format!("databaseId '{}': {}", database_id, error))
+            })?;
+        }
+        if self.change_log.is_some() {
+            ensure_change_log(&conn)
+                .await
+                .map_err(|error| HttpError::Internal(error.to_string()))?;
+        }
+        ensure_idempotency(&conn)
+            .await
+            .map_err(|error| HttpError::Internal(error.to_string()))?;
+        let recent_deltas = RecentDeltas::open(&conn, DEFAULT_RECENT_DELTAS_CAPACITY)
+            .await
+            .map_err(|error| HttpError::Internal(error.to_string()))?;
+
+        let routed = Arc::new(RoutedDatabase {
+            database_id: database_id.to_string(),
+            db,
+            loaded_schema: RwLock::new(Arc::new(loaded_schema)),
+            schema_version: Mutex::new(schema_version),
+            connections: Mutex::new(HashMap::new()),
+            recent_deltas: Mutex::new(recent_deltas),
+            dropped_messages: AtomicU64::new(0),
+            overflows: AtomicU64::new(0),
+            change_log_instance: self
+                .change_log
+                .as_ref()
+                .map(|settings| settings.instance_id.clone()),
+        });
+        if let Some(settings) = &self.change_log {
+            tokio::spawn(change_log::tail_change_log(
+                Arc::clone(&routed),
+                settings.clone(),
+            ));
+        }
+        tokio::spawn(tombstones::prune_periodically(
+            Arc::clone(&routed),
+            self.tombstone_retention_seconds,
+        ));
+        tokio::spawn(idempotency_keys::prune_periodically(
+            Arc::clone(&routed),
+            self.idempotency_retention_seconds,
+        ));
+        Ok(routed)
+    }
+}
+
+fn database_id_error(error: DatabaseIdError) -> HttpError {
+    match error {
+        DatabaseIdError::Unknown { .. } => HttpError::NotFound(error.to_string()),
+        _ => HttpError::BadRequest(error.to_string()),
+    }
+}
+
+async fn health(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+) -> Result<Response, HttpError> {
+    let database = match query
+        .database_id
+        .as_deref()
+        .or(state.databases.resolver.default_database_id())
+    {
+        Some(database_id) => Some(state.databases.get(database_id).await?),
+        None => None,
+    };
+    let connections = match &database {
+        Some(database) => Some(ConnectionStats {
+            open: database.connections.lock().await.len(),
+            dropped_messages: database.dropped_messages.load(Ordering::Relaxed),
+            overflows: database.overflows.load(Ordering::Relaxed),
+        }),
+        None => None,
+    };
+    Ok(with_cors(
+        &state,
+        &headers,
+        Json(HealthResponse {
+            ok: true,
+            database_id: database
+                .as_ref()
+                .map(|database| database.database_id.as_str()),
+            manifest_version: state.manifests().current_version().to_string(),
+            connections,
+        })
+        .into_response(),
+    ))
+}
+
+async fn cors_preflight(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    with_cors(&state, &headers, StatusCode::NO_CONTENT.into_response())
+}
+
+async fn sync(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+    Json(body): Json<SyncRequest>,
+) -> Result<Response, HttpError> {
+    let database = database_for_request(&state, body.database_id.as_deref()).await?;
+    let session = pyre_session_from_request(&state, &headers)?;
+    let manifest_version = client_manifest_version(&headers, &query);
+    let result = sync_request(
+        &state,
+        &headers,
+        &database,
+        &session,
+        manifest_version.as_deref(),
+        body.sync_cursor,
+        body.shapes,
+    )
+    .await?;
+
+    let encoding = SyncEncoding::negotiate(
+        header_str(&headers, header::ACCEPT),
+        header_str(&headers, header::ACCEPT_ENCODING),
+    );
+    let body = sync_encoding::encode(&result.into_value(encoding.format)?, encoding)
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+    let mut response = (
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(encoding.content_type()),
+        )],
+        body,
+    )
+        .into_response();
+    if encoding.gzip {
+        response
+            .headers_mut()
+            .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+    response.headers_mut().insert(
+        header::VARY,
+        HeaderValue::from_static("accept, accept-encoding"),
+    );
+
+    Ok(with_cors(&state, &headers, response))
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Catch up on `sync_cursor`, or on each shape with its own cursor.
+async fn sync_request(
+    state: &AppState,
+    headers: &HeaderMap,
+    database: &RoutedDatabase,
+    session: &PyreSession,
+    manifest_version: Option<&str>,
+    sync_cursor: Option<SyncCursor>,
+    shapes: Vec<ShapeCatchup>,
+) -> Result<SyncResponse, HttpError> {
+    state.authorize(headers, database, session, Action::Catchup)?;
+    match (sync_cursor, shapes.is_empty()) {
+        (Some(sync_cursor), true) => Ok(SyncResponse::Page(
+            catchup(state, database, session, None, &sync_cursor).await?,
+        )),
+        (None, false) => {
+            let mut results = HashMap::new();
+            for shape_catchup in shapes {
+                let shape = resolve_shapes(
+                    state,
+                    headers,
+                    database,
+                    session,
+                    manifest_version,
+                    std::slice::from_ref(&shape_catchup.shape),
+                )?;
+                let result = catchup(
+                    state,
+                    database,
+                    session,
+                    Some(&shape),
+                    &shape_catchup.sync_cursor,
+                )
+                .await?;
+                results.insert(shape_catchup.id, result);
+            }
+            Ok(SyncResponse::Shapes(results))
+        }
+        (Some(_), false) => Err(HttpError::BadRequest(
+            "send either syncCursor or shapes, not both".to_string(),
+        )),
+        (None, true) => Err(HttpError::BadRequest(
+            "syncCursor or shapes is required".to_string(),
+        )),
+    }
+}
+
+/// Authorize each shape as a run of its query, then merge them into the rows they
+/// cover together.
+fn resolve_shapes(
+    state: &AppState,
+    headers: &HeaderMap,
+    database: &RoutedDatabase,
+    session: &PyreSession,
+    manifest_version: Option<&str>,
+    shapes: &[ShapeRequest],
+) -> Result<ShapeFilter, HttpError> {
+    let query_ids = shapes
+        .iter()
+        .map(|shape| shape.query_id.as_str())
+        .collect::<Vec<_>>();
+    let manifest = state.manifest_for(manifest_version, &query_ids)?;
+    let mut filter = ShapeFilter::default();
+    for shape in shapes {
+        state.authorize(
+            headers,
+            database,
+            session,
+            Action::Run {
+                query_id: &shape.query_id,
+            },
+        )?;
+        let query = manifest
+            .queries
+            .get(&shape.query_id)
+            .ok_or_else(|| HttpError::BadRequest(format!("unknown query: {}", shape.query_id)))?;
+        if query.shapes.is_empty() {
+            return Err(HttpError::BadRequest(format!(
+                "query '{}' cannot be used as a sync shape",
+                shape.query_id
+            )));
+        }
+        let resolved = ShapeFilter::resolve(&query.shapes, &shape.params, session.logical())
+            .map_err(|error| {
+                HttpError::BadRequest(crate::server::sync::Error::Sync(error).to_string())
+            })?;
+        filter.merge(resolved);
+    }
+    Ok(filter)
+}
+
+async fn catchup(
+    state: &AppState,
+    database: &RoutedDatabase,
+    session: &PyreSession,
+    shape: Option<&ShapeFilter>,
+    sync_cursor: &SyncCursor,
+) -> Result<SyncPageResult, HttpError> {
+    let conn = database
+        .db
+        .connect()
+        .map_err(|error| HttpError::Internal(format!("database error: {}", error)))?;
+    let loaded_schema = database.loaded_schema();
+    let context = loaded_schema
+        .context()
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+    let server = SyncServer::new(context);
+    match shape {
+        Some(shape) => {
+            server
+                .catchup_shape(
+                    &conn,
+                    shape,
+                    sync_cursor,
+                    session.logical(),
+                    state.page_size,
+                    &database.database_id,
+                )
+                .await
+        }
+        None => {
+            server
+                .catchup(
+                    &conn,
+                    sync_cursor,
+                    session.logical(),
+                    state.page_size,
+                    &database.database_id,
+                )
+                .await
+        }
+    }
+    .map_err(|error| HttpError::Internal(error.to_string()))
+}
+
+async fn sync_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+) -> Result<Response, HttpError> {
+    let database = database_for_request(&state, query.database_id.as_deref()).await?;
+    let session = pyre_session_from_request(&state, &headers)?;
+    state.authorize(&headers, &database, &session, Action::Subscribe)?;
+    let shapes = match &query.shapes {
+        Some(shapes) => {
+            let shapes = serde_json::from_str::<Vec<ShapeRequest>>(shapes)
+                .map_err(|error| HttpError::BadRequest(format!("invalid shapes: {}", error)))?;
+            Some(resolve_shapes(
+                &state,
+                &headers,
+                &database,
+                &session,
+                client_manifest_version(&headers, &query).as_deref(),
+                &shapes,
+            )?)
+        }
+        None => None,
+    };
+    let last_event_id = last_event_id(&headers)?;
+    let expires_at = state.session.expires_at(&headers);
+    let session_id = new_connection_id();
+    let (sender, mut receiver) = connection_queue(state.queue_size, &database.database_id);
+
+    // Registering and replaying under the delta lock means every revision is either
+    // replayed here or sent live, never both or neither.
+    let recent_deltas = database.recent_deltas.lock().await;
+    database.connections.lock().await.insert(
+        session_id.clone(),
+        Connection {
+            session: session.clone(),
+            shapes: shapes.clone(),
+            sender,
+        },
+    );
+    let replayed = match last_event_id {
+        Some(after_revision) => {
+            let loaded_schema = database.loaded_schema();
+            let context = loaded_schema
+                .context()
+                .map_err(|error| HttpError::Internal(error.to_string()))?;
+            let server = SyncServer::new(context);
+            match &shapes {
+                Some(shape) => server.replay_shape_deltas(
+                    &recent_deltas,
+                    after_revision,
+                    session.logical(),
+                    shape,
+                    &database.database_id,
+                ),
+                None => server.replay_deltas(
+                    &recent_deltas,
+                    after_revision,
+                    session.logical(),
+                    &database.database_id,
+                ),
+            }
+            .map_err(|error| HttpError::Internal(error.to_string()))?
+        }
+        None => Vec::new(),
+    };
+    drop(recent_deltas);
+
+    let connected = json!({
+        "type": "connected",
+        "sessionId": session_id,
+        "connectionId": session_id,
+        "databaseId": database.database_id,
+    });
+    let cleanup = ConnectionCleanup {
+        database: Arc::clone(&database),
+        session_id: session_id.clone(),
+    };
+
+    let stream = async_stream::stream! {
+        let _cleanup = cleanup;
+        yield Ok::<_, Infallible>(sse_event(connected));
+        for message in replayed {
+            if let Ok(message) = serde_json::to_value(message) {
+                yield Ok::<_, Infallible>(sse_event(message));
+            }
+        }
+        loop {
+            let (message, expired) = tokio::select! {
+                message = receiver.recv() => match message {
+                    Some(message) => (message, false),
+                    None => break,
+                },
+                _ = session_expired(expires_at.unwrap_or_default()), if expires_at.is_some() => {
+                    (session_expired_message(), true)
+                }
+            };
+            yield Ok::<_, Infallible>(sse_event(message));
+            if expired {
+                break;
+            }
+        }
+    };
+
+    let response = Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response();
+    Ok(with_cors(&state, &headers, response))
+}
+
+/// Wait until `expires_at`, in unix seconds.
+async fn session_expired(expires_at: i64) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    tokio::time::sleep(Duration::from_secs(
+        expires_at.saturating_sub(now).max(0) as u64
+    ))
+    .await;
+}
+
+/// Sent before a live connection is closed because its session expired. Clients
+/// reconnect, or send `auth` on a WebSocket, with a fresh credential.
+fn session_expired_message() -> JsonValue {
+    json!({ "type": "sessionExpired" })
+}
+
+/// An SSE event carrying a message, with its server revision as the event id so a
+/// reconnecting browser sends it back as `Last-Event-ID`.
+fn sse_event(message: JsonValue) -> Event {
+    let event = match message.get("serverRevision").and_then(JsonValue::as_i64) {
+        Some(server_revision) => Event::default().id(server_revision.to_string()),
+        None => Event::default(),
+    };
+    event
+        .json_data(message)
+        .unwrap_or_else(|_| Event::default())
+}
+
+fn client_manifest_version(headers: &HeaderMap, query: &RequestQuery) -> Option<String> {
+    headers
+        .get(MANIFEST_VERSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| query.manifest_version.clone())
+}
+
+fn last_event_id(headers: &HeaderMap) -> Result<Option<i64>, HttpError> {
+    let Some(value) = headers.get("last-event-id") else {
+        return Ok(None);
+    };
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .map(Some)
+        .ok_or_else(|| HttpError::BadRequest("invalid Last-Event-ID header".to_string()))
+}
+
+async fn run_query(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+    AxumPath(query_id): AxumPath<String>,
+    Json(input): Json<JsonValue>,
+) -> Result<Response, HttpError> {
+    let database = database_for_request(&state, query.database_id.as_deref()).await?;
+    let session = pyre_session_from_request(&state, &headers)?;
+    state.authorize(
+        &headers,
+        &database,
+        &session,
+        Action::Run {
+            query_id: &query_id,
+        },
+    )?;
+    let manifest = state.manifest_for(
+        client_manifest_version(&headers, &query).as_deref(),
+        &[&query_id],
+    )?;
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| HttpError::BadRequest("invalid Idempotency-Key header".to_string()))
+        })
+        .transpose()?;
+    let response = run_one(
+        &manifest,
+        &database,
+        &session,
+        &query_id,
+        input,
+        RunOptions {
+            sync: query.sync.as_deref() == Some("true"),
+            origin_connection_id: query.connection_id.as_deref(),
+            idempotency_key,
+        },
+    )
+    .await?;
+
+    Ok(with_cors(&state, &headers, Json(response).into_response()))
+}
+
+#[derive(Clone, Copy)]
+struct RunOptions<'a> {
+    /// Use the query's sync SQL and send live deltas to other connections.
+    sync: bool,
+    /// The connection that sent the request. It gets its delta in the response.
+    origin_connection_id: Option<&'a str>,
+    /// Run the query at most once per key, see `crate::server::idempotency`.
+    idempotency_key: Option<&'a str>,
+}
+
+/// Run one query or mutation, sending live deltas to other connections in sync mode.
+async fn run_one(
+    manifest: &Manifest,
+    database: &RoutedDatabase,
+    session: &PyreSession,
+    query_id: &str,
+    input: JsonValue,
+    options: RunOptions<'_>,
+) -> Result<JsonValue, HttpError> {
+    let RunOptions {
+        sync,
+        origin_connection_id,
+        idempotency_key,
+    } = options;
+    let conn = database
+        .db
+        .connect()
+        .map_err(|error| HttpError::Internal(format!("database error: {}", error)))?;
+    if let Some(key) = idempotency_key {
+        let run = IdempotentRun {
+            manifest,
+            database,
+            conn: &conn,
+            session,
+            origin_connection_id,
+            include_origin: false,
+        };
+        return run
+            .run(query_id, input, sync, key)
+            .await
+            .map(|(response, _)| response);
+    }
+
+    let mut result = if sync {
+        crate::server::query::run_sync(&conn, manifest, query_id, input, session).await
+    } else {
+        crate::server::query::run(&conn, manifest, query_id, input, session).await
+    }
+    .map_err(|error| HttpError::BadRequest(error.to_string()))?;
+
+    if sync {
+        publish_deltas(database, &conn, &mut result, origin_connection_id, None).await?;
+    }
+
+    Ok(result.response)
+}
+
+/// Runs queries and mutations with idempotency keys on one connection.
+struct IdempotentRun<'a> {
+    manifest: &'a Manifest,
+    database: &'a RoutedDatabase,
+    conn: &'a libsql::Connection,
+    session: &'a PyreSession,
+    origin_connection_id: Option<&'a str>,
+    /// Include the delta for the request's own session in the response, even when
+    /// `origin_connection_id` is not a live connection.
+    include_origin: bool,
+}
+
+impl IdempotentRun<'_> {
+    /// Returns the response, and whether it was recorded by an earlier request.
+    async fn run(
+        &self,
+        query_id: &str,
+        input: JsonValue,
+        sync: bool,
+        key: &str,
+    ) -> Result<(JsonValue, bool), HttpError> {
+        let begun = idempotency::begin(self.conn, query_id, &input, self.session, key)
+            .await
+            .map_err(|error| match error {
+                idempotency::Error::KeyReused(_) => {
+                    HttpError::IdempotencyKeyReused(error.to_string())
+                }
+                idempotency::Error::InvalidKey(_) => HttpError::BadRequest(error.to_string()),
+                _ => HttpError::Internal(error.to_string()),
+            })?;
+        let pending = match begun {
+            Begun::Replayed(response) => return Ok((response, true)),
+            Begun::Pending(pending) => pending,
+        };
+
+        let result = if sync {
+            crate::server::query::run_sync(
+                pending.conn(),
+                self.manifest,
+                query_id,
+                input,
+                self.session,
+            )
+            .await
+        } else {
+            crate::server::query::run(pending.conn(), self.manifest, query_id, input, self.session)
+                .await
+        };
+        let mut result = match result {
+            Ok(result) => result,
+            Err(error) => {
+                pending
+                    .rollback()
+                    .await
+                    .map_err(|error| HttpError::Internal(error.to_string()))?;
+                return Err(HttpError::BadRequest(error.to_string()));
+            }
+        };
+
+        // The server revision is stamped inside the transaction, so the recorded
+        // response is the one returned now. Deltas are sent once it commits.
+        let deltas = if sync {
+            Some(
+                prepare_deltas(
+                    self.database,
+                    pending.conn(),
+                    &mut result,
+                    self.origin_connection_id,
+                    self.include_origin.then_some(self.session),
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+        pending
+            .commit(&result.response)
+            .await
+            .map_err(|error| HttpError::Internal(error.to_string()))?;
+        if let Some(deltas) = deltas {
+            deltas.publish(&result).await;
+        }
+        Ok((result.response, false))
+    }
+}
+
+async fn run_batch(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+    Json(body): Json<BatchRequest>,
+) -> Result<Response, HttpError> {
+    let database = database_for_request(&state, query.database_id.as_deref()).await?;
+    let session = pyre_session_from_request(&state, &headers)?;
+    let conn = database
+        .db
+        .connect()
+        .map_err(|error| HttpError::Internal(format!("database error: {}", error)))?;
+    let sync = query.sync.as_deref() == Some("true");
+    let query_ids: Vec<&str> = body
+        .queries
+        .iter()
+        .map(|item| item.query_id.as_str())
+        .collect();
+    for query_id in &query_ids {
+        state.authorize(&headers, &database, &session, Action::Run { query_id })?;
+    }
+    let manifest = state.manifest_for(
+        client_manifest_version(&headers, &query).as_deref(),
+        &query_ids,
+    )?;
+    let batch = crate::server::query::run_batch(
+        &conn,
+        &manifest,
+        &body.queries,
+        &session,
+        BatchOptions {
+            transaction: body.transaction,
+            sync,
+        },
+    )
+    .await
+    .map_err(|error| HttpError::BadRequest(error.to_string()))?;
+
+    // Deltas are calculated once for the whole batch, so clients get a single revision.
+    let mut result = batch.into_query_result();
+    if sync {
+        publish_deltas(
+            &database,
+            &conn,
+            &mut result,
+            query.connection_id.as_deref(),
+            None,
+        )
+        .await?;
+    }
+
+    Ok(with_cors(
+        &state,
+        &headers,
+        Json(result.response).into_response(),
+    ))
+}
+
+/// Apply an offline client's mutations in order, each at most once by its idempotency
+/// key, and return each outcome along with the client's own deltas.
+///
+/// Every mutation commits on its own. The first failure stops the replay, and the
+/// mutations after it are not run.
+async fn run_replay(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+    Json(body): Json<ReplayRequest>,
+) -> Result<Response, HttpError> {
+    if body.mutations.is_empty() || body.mutations.len() > MAX_REPLAY_MUTATIONS {
+        return Err(HttpError::BadRequest(format!(
+            "a replay must contain between 1 and {} mutations",
+            MAX_REPLAY_MUTATIONS
+        )));
+    }
+    let database = database_for_request(&state, query.database_id.as_deref()).await?;
+    let session = pyre_session_from_request(&state, &headers)?;
+    let query_ids: Vec<&str> = body
+        .mutations
+        .iter()
+        .map(|mutation| mutation.item.query_id.as_str())
+        .collect();
+    for query_id in &query_ids {
+        state.authorize(&headers, &database, &session, Action::Run { query_id })?;
+    }
+    let manifest = state.manifest_for(
+        client_manifest_version(&headers, &query).as_deref(),
+        &query_ids,
+    )?;
+    let conn = database
+        .db
+        .connect()
+        .map_err(|error| HttpError::Internal(format!("database error: {}", error)))?;
+    let origin_connection_id = query
+        .connection_id
+        .clone()
+        .unwrap_or_else(new_connection_id);
+    let run = IdempotentRun {
+        manifest: &manifest,
+        database: &database,
+        conn: &conn,
+        session: &session,
+        origin_connection_id: Some(&origin_connection_id),
+        include_origin: true,
+    };
+
+    let mut results = Vec::with_capacity(body.mutations.len());
+    let mut deltas = Vec::new();
+    let mut failed = false;
+    for mutation in body.mutations {
+        if failed {
+            results.push(json!({ "ok": false, "error": BatchItemError::NotRun.to_string() }));
+            continue;
+        }
+        let outcome = run
+            .run(
+                &mutation.item.query_id,
+                mutation.item.input,
+                true,
+                &mutation.idempotency_key,
+            )
+            .await;
+        match outcome {
+            Ok((response, replayed)) => {
+                let mut outcome = serde_json::Map::new();
+                outcome.insert("ok".to_string(), JsonValue::Bool(true));
+                outcome.insert("replayed".to_string(), JsonValue::Bool(replayed));
+                let result = match response {
+                    JsonValue::Object(mut envelope) if envelope.contains_key("serverRevision") => {
+                        if let Some(server_revision) = envelope.remove("serverRevision") {
+                            outcome.insert("serverRevision".to_string(), server_revision);
+                        }
+                        deltas.extend(envelope.remove("sync"));
+                        envelope.remove("result").unwrap_or(JsonValue::Null)
+                    }
+                    response => response,
+                };
+                outcome.insert("result".to_string(), result);
+                results.push(JsonValue::Object(outcome));
+            }
+            Err(HttpError::Internal(message)) => return Err(HttpError::Internal(message)),
+            Err(error) => {
+                failed = true;
+                let mut outcome = error.body();
+                if let JsonValue::Object(fields) = &mut outcome {
+                    fields.insert("ok".to_string(), JsonValue::Bool(false));
+                }
+                results.push(outcome);
+            }
+        }
+    }
+
+    Ok(with_cors(
+        &state,
+        &headers,
+        Json(json!({ "results": results, "deltas": deltas })).into_response(),
+    ))
+}
+
+/// Send live deltas for `result`. `origin_session` gets the origin's delta into the
+/// response when `origin_connection_id` is not a live connection.
+async fn publish_deltas(
+    database: &RoutedDatabase,
+    conn: &libsql::Connection,
+    result: &mut QueryResult,
+    origin_connection_id: Option<&str>,
+    origin_session: Option<&PyreSession>,
+) -> Result<(), HttpError> {
+    prepare_deltas(database, conn, result, origin_connection_id, origin_session)
+        .await?
+        .publish(result)
+        .await;
+    Ok(())
+}
+
+/// Deltas stamped with a server revision, waiting for their rows to commit.
+struct PreparedDeltas<'a> {
+    database: &'a RoutedDatabase,
+    /// Held until the deltas are sent, so revisions go out in order.
+    recent_deltas: MutexGuard<'a, RecentDeltas>,
+    messages: Vec<crate::server::sync::SessionDeltaMessage>,
+}
+
+impl PreparedDeltas<'_> {
+    async fn publish(mut self, result: &QueryResult) {
+        send_messages(self.database, self.messages).await;
+        if let Some(server_revision) = result.server_revision() {
+            if !result.affected_rows.is_empty() {
+                self.recent_deltas
+                    .record(server_revision, result.affected_rows.clone());
+            }
+        }
+    }
+}
+
+/// Calculate the live deltas for `result` and stamp it with the next server revision,
+/// writing the revision through `conn`.
+async fn prepare_deltas<'a>(
+    database: &'a RoutedDatabase,
+    conn: &libsql::Connection,
+    result: &mut QueryResult,
+    origin_connection_id: Option<&str>,
+    origin_session: Option<&PyreSession>,
+) -> Result<PreparedDeltas<'a>, HttpError> {
+    let recent_deltas = database.recent_deltas.lock().await;
+    let (mut connected_sessions, session_shapes) = live_connections(database).await;
+    if let (Some(origin_connection_id), Some(origin_session)) =
+        (origin_connection_id, origin_session)
+    {
+        connected_sessions
+            .entry(origin_connection_id.to_string())
+            .or_insert_with(|| origin_session.logical().clone());
+    }
+    let loaded_schema = database.loaded_schema();
+    let context = loaded_schema
+        .context()
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+    let mut server = SyncServer::new(context).with_session_shapes(session_shapes);
+    if let Some(instance_id) = &database.change_log_instance {
+        server = server.with_change_log(instance_id.clone());
+    }
+    let messages = server
+        .calculate_deltas(
+            conn,
+            result,
+            &connected_sessions,
+            &database.database_id,
+            origin_connection_id,
+        )
+        .await
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+    Ok(PreparedDeltas {
+        database,
+        recent_deltas,
+        messages,
+    })
+}
+
+/// The sessions of the open connections, and the shapes of those that subscribed to any.
+async fn live_connections(database: &RoutedDatabase) -> (ConnectedSessions, SessionShapes) {
+    let connections = database.connections.lock().await;
+    let sessions = connections
+        .iter()
+        .map(|(id, connection)| (id.clone(), connection.session.logical().clone()))
+        .collect();
+    let shapes = connections
+        .iter()
+        .filter_map(|(id, connection)| Some((id.clone(), connection.shapes.clone()?)))
+        .collect();
+    (sessions, shapes)
+}
+
+async fn send_messages(
+    database: &RoutedDatabase,
+    messages: Vec<crate::server::sync::SessionDeltaMessage>,
+) {
+    let connections = database.connections.lock().await;
+    for message in messages {
+        if let Some(connection) = connections.get(&message.session_id) {
+            if let Ok(value) = serde_json::to_value(message.message) {
+                if let Delivery::Dropped { first } = connection.sender.send(value) {
+                    database.dropped_messages.fetch_add(1, Ordering::Relaxed);
+                    if first {
+                        database.overflows.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct ConnectionCleanup {
+    database: Arc<RoutedDatabase>,
+    session_id: String,
+}
+
+impl Drop for ConnectionCleanup {
+    fn drop(&mut self) {
+        let database = Arc::clone(&self.database);
+        let session_id = self.session_id.clone();
+        tokio::spawn(async move {
+            database.connections.lock().await.remove(&session_id);
+        });
+    }
+}
+
+fn pyre_session_from_request(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<PyreSession, HttpError> {
+    let manifest = state.manifest();
+    let value = state.session.session(headers, &manifest.session_schema)?;
+    validate_session(&manifest, value)
+}
+
+/// Build the session from the credential of a WebSocket `auth` message.
+fn session_from_credential(state: &AppState, credential: &str) -> Result<PyreSession, HttpError> {
+    let manifest = state.manifest();
+    let value = state
+        .session
+        .session_from_credential(credential, &manifest.session_schema)?;
+    validate_session(&manifest, value)
+}
+
+fn validate_session(manifest: &Manifest, value: JsonValue) -> Result<PyreSession, HttpError> {
+    PyreSession::new(value, &manifest.session_schema)
+        .map_err(|error| HttpError::Unauthorized(format!("invalid Pyre session: {}", error)))
+}
+
+async fn database_for_request(
+    state: &AppState,
+    value: Option<&str>,
+) -> Result<Arc<RoutedDatabase>, HttpError> {
+    let database_id = state
+        .databases
+        .resolver
+        .database_id_for(value)
+        .map_err(database_id_error)?;
+    state.databases.get(&database_id).await
+}
+
+/// Identifies this process in `_pyre_changes`.
+fn new_instance_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("{}-{}", std::process::id(), nanos)
+}
+
+fn new_connection_id() -> String {
+    static NEXT_CONNECTION_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    let id = NEXT_CONNECTION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    format!("conn_{}", id)
+}
+
+fn allowed_cors_origin<'a>(state: &'a AppState, headers: &HeaderMap) -> Option<&'a str> {
+    allowed_cors_origin_for(&state.cors_origins, headers)
+}
+
+fn allowed_cors_origin_for<'a>(cors_origins: &'a [String], headers: &HeaderMap) -> Option<&'a str> {
+    if cors_origins.is_empty() {
+        return None;
+    }
+    let request_origin = headers.get(header::ORIGIN)?.to_str().ok()?;
+
+    cors_origins
+        .iter()
+        .find(|origin| origin.as_str() == request_origin)
+        .map(String::as_str)
+}
+
+fn with_cors(state: &AppState, request_headers: &HeaderMap, mut response: Response) -> Response {
+    let Some(origin) = allowed_cors_origin(state, request_headers) else {
+        return response;
+    };
+
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(origin) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+    }
+    // Appended, since responses may already vary on other headers.
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static("GET, POST, OPTIONS"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        HeaderValue::from_static(
+            "content-type, authorization, x-pyre-session, x-pyre-manifest, last-event-id",
+        ),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest_with_queries(query_ids: &[&str]) -> Manifest {
+        let queries: serde_json::Map<String, JsonValue> = query_ids
+            .iter()
+            .map(|query_id| {
+                (
+                    query_id.to_string(),
+                    json!({
+                        "id": query_id,
+                        "operation": "query",
+                        "input_schema": {},
+                        "session_args": [],
+                        "optional_input_args": [],
+                        "json_input_args": [],
+                        "sql": [],
+                    }),
+                )
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "version": 1,
+            "session_schema": {},
+            "queries": queries,
+        }))
+        .expect("manifest")
+    }
+    #[tokio::test]
+    async fn overflowed_connection_queue_collapses_into_sync_required() {
+        let (sender, mut receiver) = connection_queue(2, "main");
+        assert!(matches!(sender.send(json!({ "n": 1 })), Delivery::Sent));
+        assert!(matches!(sender.send(json!({ "n": 2 })), Delivery::Sent));
+        assert!(matches!(
+            sender.send(json!({ "n": 3 })),
+            Delivery::Dropped { first: true }
+        ));
+        assert!(matches!(
+            sender.send(json!({ "n": 4 })),
+            Delivery::Dropped { first: false }
+        ));
+
+        let message = receiver.recv().await.expect("sync required");
+        assert_eq!(message["type"], json!("syncRequired"));
+        assert_eq!(message["databaseId"], json!("main"));
+        assert!(receiver.receiver.try_recv().is_err());
+
+        assert!(matches!(sender.send(json!({ "n": 5 })), Delivery::Sent));
+        assert_eq!(receiver.recv().await.expect("message")["n"], json!(5));
+    }
+
+    #[test]
+    fn manifest_history_routes_outdated_clients_by_manifest_version() {
+        let first = manifest_with_queries(&["listPosts", "oldQuery"]);
+        let first_version = first.version_hash();
+        let mut manifests = ManifestHistory::new(first, 1);
+        manifests.replace(manifest_with_queries(&["listPosts", "newQuery"]));
+        let current_version = manifests.current_version().to_string();
+        assert_ne!(first_version, current_version);
+
+        // Queries the current manifest still has always run against it.
+        let manifest = manifests
+            .resolve(Some("unknown"), &["listPosts"])
+            .expect("current manifest");
+        assert!(manifest.queries.contains_key("newQuery"));
+
+        let manifest = manifests
+            .resolve(Some(&first_version), &["oldQuery"])
+            .expect("previous manifest");
+        assert!(manifest.queries.contains_key("oldQuery"));
+        assert!(manifests.resolve(None, &["oldQuery"]).is_ok());
+
+        let error = manifests
+            .resolve(Some("unknown"), &["oldQuery"])
+            .expect_err("outdated client");
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+        assert_eq!(error.body()["code"], json!("clientOutdated"));
+        assert_eq!(error.body()["manifestVersion"], json!(current_version));
+
+        // Only `limit` previous manifests are kept.
+        manifests.replace(manifest_with_queries(&["listPosts"]));
+        assert!(manifests
+            .resolve(Some(&first_version), &["oldQuery"])
+            .is_err());
+        assert!(manifests
+            .resolve(Some(&current_version), &["newQuery"])
+            .is_ok());
+    }
+
+    #[test]
+    fn cors_echoes_only_matching_request_origin() {
+        let cors_origins = vec![
+            "http://localhost:5173".to_string(),
+            "http://localhost:3001".to_string(),
+        ];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("http://localhost:5173"),
+        );
+
+        assert_eq!(
+            allowed_cors_origin_for(&cors_origins, &headers),
+            Some("http://localhost:5173")
+        );
+    }
+
+    #[test]
+    fn cors_ignores_unlisted_request_origin() {
+        let cors_origins = vec!["http://localhost:5173".to_string()];
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ORIGIN,
+            HeaderValue::from_static("http://evil.example"),
+        );
+
+        assert_eq!(allowed_cors_origin_for(&cors_origins, &headers), None);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::server::sync::{
+    prune_changes, read_changes, DeltaMessage, SessionDeltaMessage, SyncServer,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{live_connections, send_messages, HttpError, RoutedDatabase};
+
+/// Changes read from `_pyre_changes` per poll.
+const CHANGE_LOG_BATCH: usize = 500;
+
+/// How often old `_pyre_changes` rows are pruned.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How `pyre serve` shares live deltas with other processes serving the same database.
+#[derive(Clone)]
+pub(super) struct ChangeLogSettings {
+    /// Written with every change so a process can skip the ones it already sent.
+    pub instance_id: String,
+    pub poll_interval: Duration,
+    pub retention_seconds: u64,
+}
+
+/// Read changes written by other processes and fan them out to this process's
+/// connections, pruning old rows along the way. Runs for the life of the server.
+pub(super) async fn tail_change_log(database: Arc<RoutedDatabase>, settings: ChangeLogSettings) {
+    let conn = match database.db.connect() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!(
+                "databaseId '{}': change log disabled: {}",
+                database.database_id,
+                error
+            );
+            return;
+        }
+    };
+    let mut after_revision = database.recent_deltas.lock().await.covered_through();
+    let mut poll = tokio::time::interval(settings.poll_interval);
+    let mut last_prune = tokio::time::Instant::now();
+
+    loop {
+        poll.tick().await;
+        match fan_out_changes(&database, &conn, &settings, after_revision).await {
+            Ok(revision) => after_revision = revision,
+            Err(error) => log::error!(
+                "databaseId '{}': change log: {}",
+                database.database_id,
+                error.message()
+            ),
+        }
+
+        if last_prune.elapsed() >= PRUNE_INTERVAL {
+            last_prune = tokio::time::Instant::now();
+            if let Err(error) = prune_changes(&conn, settings.retention_seconds).await {
+                log::error!(
+                    "databaseId '{}': change log pruning: {}",
+                    database.database_id,
+                    error
+                );
+            }
+        }
+    }
+}
+
+/// Fan out the changes after `after_revision`, returning the newest revision read.
+async fn fan_out_changes(
+    database: &RoutedDatabase,
+    conn: &libsql::Connection,
+    settings: &ChangeLogSettings,
+    mut after_revision: i64,
+) -> Result<i64, HttpError> {
+    let changes = read_changes(conn, after_revision, CHANGE_LOG_BATCH)
+        .await
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+    let loaded_schema = database.loaded_schema();
+    let context = loaded_schema
+        .context()
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+
+    for change in changes {
+        // Revisions are logged in the same transaction that allocates them, so a hole
+        // means rows were pruned before this process read them.
+        let missed_changes = change.server_revision > after_revision + 1;
+        after_revision = change.server_revision;
+        if change.instance_id == settings.instance_id && !missed_changes {
+            continue;
+        }
+
+        let mut recent_deltas = database.recent_deltas.lock().await;
+        let (connected_sessions, session_shapes) = live_connections(database).await;
+        let messages = if missed_changes {
+            let message = DeltaMessage::sync_required_for_database(&database.database_id)
+                .map_err(|error| HttpError::Internal(error.to_string()))?;
+            connected_sessions
+                .into_keys()
+                .map(|session_id| SessionDeltaMessage {
+                    session_id,
+                    message: message.clone(),
+                })
+                .collect()
+        } else {
+            SyncServer::new(context)
+                .with_session_shapes(session_shapes)
+                .change_deltas(&change, &connected_sessions, &database.database_id)
+                .map_err(|error| HttpError::Internal(error.to_string()))?
+        };
+        send_messages(database, messages).await;
+        recent_deltas.record(change.server_revision, change.affected_rows);
+    }
+
+    Ok(after_revision)
+}
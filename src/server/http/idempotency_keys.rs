@@ -0,0 +1,35 @@
+use crate::server::idempotency::prune_idempotency;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::RoutedDatabase;
+
+/// How often old `_pyre_idempotency` rows are pruned.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Prune idempotency keys older than `retention_seconds`. Runs for the life of the server.
+pub(super) async fn prune_periodically(database: Arc<RoutedDatabase>, retention_seconds: u64) {
+    let conn = match database.db.connect() {
+        Ok(conn) => conn,
+        Err(error) => {
+            log::error!(
+                "databaseId '{}': idempotency key pruning disabled: {}",
+                database.database_id,
+                error
+            );
+            return;
+        }
+    };
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        if let Err(error) = prune_idempotency(&conn, retention_seconds).await {
+            log::error!(
+                "databaseId '{}': idempotency key pruning: {}",
+                database.database_id,
+                error
+            );
+        }
+    }
+}
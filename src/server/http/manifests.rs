@@ -0,0 +1,105 @@
+use crate::server::manifest::Manifest;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::HttpError;
+
+/// The manifest being served, along with the ones it replaced.
+///
+/// Each manifest is keyed by its version, the `hash` written by `pyre generate`.
+pub(super) struct ManifestHistory {
+    current: Arc<Manifest>,
+    current_version: String,
+    /// Newest first.
+    previous: VecDeque<(String, Arc<Manifest>)>,
+    limit: usize,
+}
+
+impl ManifestHistory {
+    pub fn new(current: Manifest, limit: usize) -> Self {
+        ManifestHistory {
+            current_version: current.version_hash(),
+            current: Arc::new(current),
+            previous: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub fn current(&self) -> Arc<Manifest> {
+        Arc::clone(&self.current)
+    }
+
+    pub fn current_version(&self) -> &str {
+        &self.current_version
+    }
+
+    /// Keep an older manifest loaded at startup, behind the ones already kept.
+    pub fn add_previous(&mut self, manifest: Manifest) {
+        let version = manifest.version_hash();
+        if version == self.current_version || self.version(&version).is_some() {
+            return;
+        }
+        self.previous.push_back((version, Arc::new(manifest)));
+        self.previous.truncate(self.limit);
+    }
+
+    /// Serve `manifest` from now on, keeping the one it replaces.
+    pub fn replace(&mut self, manifest: Manifest) {
+        let version = manifest.version_hash();
+        let replaced = std::mem::replace(&mut self.current, Arc::new(manifest));
+        let replaced_version = std::mem::replace(&mut self.current_version, version);
+        if replaced_version != self.current_version {
+            self.previous.push_front((replaced_version, replaced));
+        }
+        let current_version = &self.current_version;
+        self.previous
+            .retain(|(version, _)| version != current_version);
+        self.previous.truncate(self.limit);
+    }
+
+    /// Pick the manifest to run `query_ids` with for a client built against
+    /// `client_version`.
+    ///
+    /// The current manifest is used whenever it has every query. Otherwise the client's
+    /// own manifest is used while it is still kept, and a client whose manifest is gone
+    /// is told it is outdated. Clients that don't send a version get the newest kept
+    /// manifest with every query, falling back to the current one.
+    pub fn resolve(
+        &self,
+        client_version: Option<&str>,
+        query_ids: &[&str],
+    ) -> Result<Arc<Manifest>, HttpError> {
+        let has_queries = |manifest: &Manifest| {
+            query_ids
+                .iter()
+                .all(|query_id| manifest.queries.contains_key(*query_id))
+        };
+        if has_queries(&self.current) {
+            return Ok(self.current());
+        }
+
+        match client_version {
+            Some(version) if version == self.current_version => Ok(self.current()),
+            Some(version) => match self.version(version) {
+                Some(manifest) if has_queries(manifest) => Ok(Arc::clone(manifest)),
+                _ => Err(HttpError::ClientOutdated {
+                    manifest_version: self.current_version.clone(),
+                }),
+            },
+            None => Ok(self
+                .previous
+                .iter()
+                .map(|(_, manifest)| manifest)
+                .find(|manifest| has_queries(manifest))
+                .map(Arc::clone)
+                .unwrap_or_else(|| self.current())),
+        }
+    }
+
+    fn version(&self, version: &str) -> Option<&Arc<Manifest>> {
+        self.previous
+            .iter()
+            .find(|(kept, _)| kept == version)
+            .map(|(_, manifest)| manifest)
+    }
+}
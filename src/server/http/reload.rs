@@ -0,0 +1,129 @@
+use crate::server::manifest::Manifest;
+use crate::server::schema::{load_schema_from_database, schema_version};
+use crate::server::sync::{
+    DeltaMessage, RecentDeltas, SessionDeltaMessage, DEFAULT_RECENT_DELTAS_CAPACITY,
+};
+use crate::sync::table_permission_hashes;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{live_connections, send_messages, AppState, RoutedDatabase};
+
+/// How often `manifest.json` and each open database's `_pyre_schema` are checked.
+const RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Swap in a new manifest or schema when `pyre generate` or a migration changes them,
+/// keeping the previous version whenever the new one fails to load.
+pub(super) async fn watch_for_changes(state: Arc<AppState>) {
+    let mut manifest_contents = state
+        .manifest_path
+        .as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok());
+    let mut poll = tokio::time::interval(RELOAD_POLL_INTERVAL);
+    poll.tick().await;
+
+    loop {
+        poll.tick().await;
+        if let Some(path) = &state.manifest_path {
+            reload_manifest(&state, path, &mut manifest_contents);
+        }
+
+        for database in state.databases.opened().await {
+            if let Err(error) = reload_schema(&database).await {
+                log::warn!("databaseId '{}': {}", database.database_id, error);
+            }
+        }
+    }
+}
+
+fn reload_manifest(state: &AppState, path: &Path, last_contents: &mut Option<String>) {
+    // A missing or unreadable file is usually `pyre generate` mid-write.
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return;
+    };
+    if last_contents.as_deref() == Some(contents.as_str()) {
+        return;
+    }
+
+    match serde_json::from_str::<Manifest>(&contents) {
+        Ok(manifest) => {
+            state
+                .manifests
+                .write()
+                .unwrap_or_else(|error| error.into_inner())
+                .replace(manifest);
+            log::info!("Reloaded {}", path.display());
+        }
+        Err(error) => log::warn!(
+            "Ignoring invalid {}, still serving the previous manifest: {}",
+            path.display(),
+            error
+        ),
+    }
+    // Remembered even when invalid, so the same error is only reported once.
+    *last_contents = Some(contents);
+}
+
+async fn reload_schema(database: &RoutedDatabase) -> Result<(), String> {
+    let conn = database
+        .db
+        .connect()
+        .map_err(|error| format!("database error: {}", error))?;
+    let version = schema_version(&conn)
+        .await
+        .map_err(|error| error.to_string())?;
+    let mut current_version = database.schema_version.lock().await;
+    if *current_version == version {
+        return Ok(());
+    }
+
+    let loaded_schema = load_schema_from_database(&conn).await.map_err(|error| {
+        format!(
+            "still serving the previous schema, the new one failed to load: {}",
+            error
+        )
+    })?;
+
+    // Holding the delta lock keeps deltas from being built against either schema
+    // while they are swapped.
+    let mut recent_deltas = database.recent_deltas.lock().await;
+    let previous_schema = database.loaded_schema();
+    let (Ok(previous), Ok(next)) = (previous_schema.context(), loaded_schema.context()) else {
+        return Err("schema context is unavailable".to_string());
+    };
+
+    let (connected_sessions, _) = live_connections(database).await;
+    let sync_required = DeltaMessage::sync_required_for_database(&database.database_id)
+        .map_err(|error| error.to_string())?;
+    let messages: Vec<SessionDeltaMessage> = connected_sessions
+        .into_iter()
+        .filter(|(_, session)| {
+            table_permission_hashes(previous, session) != table_permission_hashes(next, session)
+        })
+        .map(|(session_id, _)| SessionDeltaMessage {
+            session_id,
+            message: sync_required.clone(),
+        })
+        .collect();
+
+    // Deltas recorded under the previous schema may not fit the new one.
+    let next_deltas = RecentDeltas::open(&conn, DEFAULT_RECENT_DELTAS_CAPACITY)
+        .await
+        .map_err(|error| error.to_string())?;
+
+    *database
+        .loaded_schema
+        .write()
+        .unwrap_or_else(|error| error.into_inner()) = Arc::new(loaded_schema);
+    *recent_deltas = next_deltas;
+    // Only recorded once swapped, so a version that failed to load is tried again.
+    *current_version = version;
+    send_messages(database, messages).await;
+    log::info!(
+        "databaseId '{}': reloaded schema version {}",
+        database.database_id,
+        version.unwrap_or_default()
+    );
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use crate::server::jwt::JwtVerifier;
+use crate::server::manifest::FieldSchema;
+use axum::http::{header, HeaderMap};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{HttpError, SessionExtractor};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const DEFAULT_SESSION_HEADER: &str = "x-pyre-session";
+
+/// The session sources built into `pyre serve`.
+#[derive(Clone, Debug)]
+pub enum SessionSource {
+    /// Every request gets an empty session, for schemas without session fields.
+    Empty,
+    /// Every request gets the same session, for local development.
+    Dev(JsonValue),
+    /// A base64url JSON session header set by a trusted upstream. With a `secret`, the
+    /// header is `payload.signature`, HMAC-SHA256 signed with an expiration.
+    Header {
+        name: String,
+        secret: Option<String>,
+    },
+    /// A bearer JWT whose claims are mapped onto session fields.
+    Jwt(JwtVerifier),
+}
+
+#[derive(Deserialize)]
+struct SignedSessionPayload {
+    session: JsonValue,
+    exp: i64,
+}
+
+impl SessionExtractor for SessionSource {
+    fn session(
+        &self,
+        headers: &HeaderMap,
+        session_schema: &HashMap<String, FieldSchema>,
+    ) -> Result<JsonValue, HttpError> {
+        match self {
+            SessionSource::Empty => Ok(JsonValue::Object(serde_json::Map::new())),
+            SessionSource::Dev(value) => Ok(value.clone()),
+            SessionSource::Header { name, .. } => {
+                let credential = headers
+                    .get(name)
+                    .ok_or_else(|| HttpError::Unauthorized(format!("missing {} header", name)))?
+                    .to_str()
+                    .map_err(|_| HttpError::Unauthorized(format!("invalid {} header", name)))?;
+                self.session_from_credential(credential, session_schema)
+            }
+            SessionSource::Jwt(_) => {
+                self.session_from_credential(bearer_token(headers)?, session_schema)
+            }
+        }
+    }
+
+    /// The credential is the session header value, or a bearer JWT.
+    fn session_from_credential(
+        &self,
+        credential: &str,
+        session_schema: &HashMap<String, FieldSchema>,
+    ) -> Result<JsonValue, HttpError> {
+        match self {
+            SessionSource::Empty => Ok(JsonValue::Object(serde_json::Map::new())),
+            SessionSource::Dev(value) => Ok(value.clone()),
+            SessionSource::Header { secret, .. } => match secret {
+                Some(secret) => decode_signed_session(credential, secret),
+                None => decode_unsigned_session(credential),
+            },
+            SessionSource::Jwt(verifier) => {
+                let claims = verifier
+                    .verify(credential, unix_now()?)
+                    .map_err(|error| HttpError::Unauthorized(error.to_string()))?;
+                Ok(verifier.session_from_claims(&claims, session_schema.keys()))
+            }
+        }
+    }
+
+    fn expires_at(&self, headers: &HeaderMap) -> Option<i64> {
+        match self {
+            SessionSource::Empty | SessionSource::Dev(_) => None,
+            SessionSource::Header { name, .. } => {
+                self.credential_expires_at(headers.get(name)?.to_str().ok()?)
+            }
+            SessionSource::Jwt(_) => self.credential_expires_at(bearer_token(headers).ok()?),
+        }
+    }
+
+    fn credential_expires_at(&self, credential: &str) -> Option<i64> {
+        match self {
+            SessionSource::Empty | SessionSource::Dev(_) => None,
+            SessionSource::Header { secret, .. } => match secret {
+                Some(_) => signed_session_expires_at(credential),
+                None => None,
+            },
+            SessionSource::Jwt(_) => crate::server::jwt::expires_at(credential),
+        }
+    }
+}
+
+pub(super) fn bearer_token(headers: &HeaderMap) -> Result<&str, HttpError> {
+    let raw = headers
+        .get(header::AUTHORIZATION)
+        .ok_or_else(|| HttpError::Unauthorized("missing authorization header".to_string()))?
+        .to_str()
+        .map_err(|_| HttpError::Unauthorized("invalid authorization header".to_string()))?;
+    match raw.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => Ok(token.trim()),
+        _ => Err(HttpError::Unauthorized(
+            "authorization header must be a Bearer token".to_string(),
+        )),
+    }
+}
+
+fn unix_now() -> Result<i64, HttpError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .map_err(|_| HttpError::Internal("system clock is before unix epoch".to_string()))
+}
+
+fn decode_unsigned_session(raw: &str) -> Result<JsonValue, HttpError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(raw)
+        .map_err(|_| HttpError::Unauthorized("invalid session header encoding".to_string()))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|_| HttpError::Unauthorized("invalid session header JSON".to_string()))
+}
+
+fn decode_signed_session(raw: &str, secret: &str) -> Result<JsonValue, HttpError> {
+    let Some((payload, signature)) = raw.split_once('.') else {
+        return Err(HttpError::Unauthorized(
+            "signed session header must contain payload and signature".to_string(),
+        ));
+    };
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| HttpError::Unauthorized("invalid session signature encoding".to_string()))?;
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .map_err(|_| HttpError::Internal("invalid session secret".to_string()))?;
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| HttpError::Unauthorized("invalid session signature".to_string()))?;
+
+    let payload_bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| HttpError::Unauthorized("invalid session payload encoding".to_string()))?;
+    let payload: SignedSessionPayload = serde_json::from_slice(&payload_bytes)
+        .map_err(|_| HttpError::Unauthorized("invalid session payload JSON".to_string()))?;
+    if payload.exp <= unix_now()? {
+        return Err(HttpError::Unauthorized(
+            "session header is expired".to_string(),
+        ));
+    }
+    Ok(payload.session)
+}
+
+/// The `exp` of a signed session header that `decode_signed_session` accepted.
+fn signed_session_expires_at(raw: &str) -> Option<i64> {
+    let (payload, _) = raw.split_once('.')?;
+    let payload_bytes = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    serde_json::from_slice::<SignedSessionPayload>(&payload_bytes)
+        .ok()
+        .map(|payload| payload.exp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    #[test]
+    fn unsigned_session_header_contains_full_session_json() {
+        let encoded = URL_SAFE_NO_PAD.encode(r#"{"userId":123}"#);
+        let session = decode_unsigned_session(&encoded).expect("decoded session");
+
+        assert_eq!(session["userId"], json!(123));
+    }
+
+    #[test]
+    fn signed_session_header_verifies_signature_and_expiration() {
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"session":{"userId":123},"exp":4102444800}"#);
+        let mut mac = HmacSha256::new_from_slice(b"secret").expect("hmac");
+        mac.update(payload.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+        let raw = format!("{}.{}", payload, signature);
+
+        let session = decode_signed_session(&raw, "secret").expect("decoded session");
+
+        assert_eq!(session["userId"], json!(123));
+        assert!(decode_signed_session(&raw, "wrong-secret").is_err());
+        assert_eq!(signed_session_expires_at(&raw), Some(4102444800));
+    }
+
+    #[test]
+    fn bearer_token_requires_the_bearer_scheme() {
+        let mut headers = HeaderMap::new();
+        assert!(bearer_token(&headers).is_err());
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic abc"));
+        assert!(bearer_token(&headers).is_err());
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("bearer a.b.c"),
+        );
+        assert_eq!(bearer_token(&headers).unwrap(), "a.b.c");
+    }
+}
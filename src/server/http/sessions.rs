@@ -0,0 +1,149 @@
+use crate::server::sync::{DeltaMessage, SessionDeltaMessage};
+use crate::sync::{session_value_to_json, table_permission_hashes};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use super::session::bearer_token;
+use super::{
+    database_for_request, send_messages, validate_session, AppState, HttpError, RoutedDatabase,
+};
+
+/// The live connections `HttpServer::update_sessions` changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionsUpdated {
+    /// Connections given a new session.
+    pub connections: usize,
+    /// Connections sent a `syncRequired`, because a table's permission hash changed.
+    pub sync_required: usize,
+}
+
+#[derive(Deserialize)]
+pub(super) struct UpdateSessionsRequest {
+    #[serde(rename = "databaseId")]
+    database_id: Option<String>,
+    /// Session fields a connection must have, with these values, to be updated.
+    #[serde(rename = "match")]
+    match_: Map<String, JsonValue>,
+    /// Session fields to replace on each matching connection.
+    set: Map<String, JsonValue>,
+}
+
+/// `POST /sync/sessions`, for a backend to report that a user's session changed.
+pub(super) async fn update_sessions_request(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<UpdateSessionsRequest>,
+) -> Result<Json<SessionsUpdated>, HttpError> {
+    let Some(admin_token) = &state.admin_token else {
+        return Err(HttpError::NotFound(
+            "session updates are disabled; configure an admin token".to_string(),
+        ));
+    };
+    // Comparing digests keeps the comparison from depending on how much of the token matched.
+    if Sha256::digest(bearer_token(&headers)?) != Sha256::digest(admin_token) {
+        return Err(HttpError::Unauthorized("invalid admin token".to_string()));
+    }
+    if request.match_.is_empty() {
+        return Err(HttpError::BadRequest(
+            "match must name at least one session field".to_string(),
+        ));
+    }
+
+    let database = database_for_request(&state, request.database_id.as_deref()).await?;
+    let updated = update_sessions(&state, &database, |session| {
+        let JsonValue::Object(fields) = session else {
+            return None;
+        };
+        let matches = request
+            .match_
+            .iter()
+            .all(|(name, value)| fields.get(name) == Some(value));
+        if !matches {
+            return None;
+        }
+        let mut fields = fields.clone();
+        fields.extend(request.set.clone());
+        Some(JsonValue::Object(fields))
+    })
+    .await?;
+    Ok(Json(updated))
+}
+
+pub(super) async fn update_sessions<F>(
+    state: &AppState,
+    database: &RoutedDatabase,
+    update: F,
+) -> Result<SessionsUpdated, HttpError>
+where
+    F: Fn(&JsonValue) -> Option<JsonValue>,
+{
+    let manifest = state.manifest();
+    let loaded_schema = database.loaded_schema();
+    let context = loaded_schema
+        .context()
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+
+    // Holding the delta lock keeps deltas built for the previous session from being
+    // sent after the `syncRequired`.
+    let _recent_deltas = database.recent_deltas.lock().await;
+    let mut connections = database.connections.lock().await;
+
+    // Every new session is validated before any is applied, so a bad update changes nothing.
+    let mut replacements = Vec::new();
+    for (connection_id, connection) in connections.iter() {
+        let current = JsonValue::Object(
+            connection
+                .session
+                .logical()
+                .iter()
+                .map(|(name, value)| (name.clone(), session_value_to_json(value)))
+                .collect(),
+        );
+        let Some(next) = update(&current) else {
+            continue;
+        };
+        let next = validate_session(&manifest, next)
+            .map_err(|error| HttpError::BadRequest(error.message().to_string()))?;
+        replacements.push((connection_id.clone(), next));
+    }
+
+    let sync_required = DeltaMessage::sync_required_for_database(&database.database_id)
+        .map_err(|error| HttpError::Internal(error.to_string()))?;
+    let mut updated = SessionsUpdated::default();
+    let mut messages = Vec::new();
+    for (connection_id, next) in replacements {
+        let Some(connection) = connections.get_mut(&connection_id) else {
+            continue;
+        };
+        let previous = table_permission_hashes(context, connection.session.logical());
+        let current = table_permission_hashes(context, next.logical());
+        let mut tables = current
+            .iter()
+            .filter(|(table, hash)| previous.get(*table) != Some(*hash))
+            .map(|(table, _)| table.clone())
+            .collect::<Vec<_>>();
+        tables.sort();
+
+        connection.session = next;
+        updated.connections += 1;
+        if !tables.is_empty() {
+            updated.sync_required += 1;
+            messages.push(SessionDeltaMessage {
+                session_id: connection_id,
+                message: DeltaMessage {
+                    tables,
+                    ..sync_required.clone()
+                },
+            });
+        }
+    }
+    drop(connections);
+    send_messages(database, messages).await;
+    Ok(updated)
+}
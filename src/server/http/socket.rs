@@ -0,0 +1,475 @@
+use crate::server::manifest::PyreSession;
+use crate::sync::{ShapeFilter, SyncCursor};
+use crate::sync_encoding::{self, SyncEncoding, SyncFormat};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{
+    allowed_cors_origin, client_manifest_version, connection_queue, database_for_request,
+    new_connection_id, pyre_session_from_request, resolve_shapes, run_one, session_expired,
+    session_expired_message, session_from_credential, sync_request, Action, AppState, Connection,
+    ConnectionCleanup, ConnectionSender, HttpError, RequestQuery, RoutedDatabase, RunOptions,
+    ShapeCatchup, ShapeRequest,
+};
+
+/// How often the server pings an idle socket. A socket that has not answered the
+/// previous ping by the next tick is closed.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Close code sent when a socket's credential is rejected or never arrives.
+const POLICY_VIOLATION: u16 = 1008;
+
+/// A message sent by the client. `id` is echoed back on the matching reply.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ClientMessage {
+    Auth {
+        #[serde(default)]
+        id: JsonValue,
+        credential: String,
+    },
+    Catchup {
+        #[serde(default)]
+        id: JsonValue,
+        #[serde(rename = "syncCursor")]
+        sync_cursor: Option<SyncCursor>,
+        #[serde(default)]
+        shapes: Vec<ShapeCatchup>,
+    },
+    /// Limit live deltas to `shapes`, or receive every visible row again with `null`.
+    Subscribe {
+        #[serde(default)]
+        id: JsonValue,
+        shapes: Option<Vec<ShapeRequest>>,
+    },
+    Run {
+        #[serde(default)]
+        id: JsonValue,
+        #[serde(rename = "queryId")]
+        query_id: String,
+        #[serde(default = "empty_input")]
+        input: JsonValue,
+        #[serde(default)]
+        sync: bool,
+        #[serde(rename = "idempotencyKey")]
+        idempotency_key: Option<String>,
+    },
+    Ping {
+        #[serde(default)]
+        id: JsonValue,
+    },
+}
+
+fn empty_input() -> JsonValue {
+    JsonValue::Object(serde_json::Map::new())
+}
+
+pub(super) async fn sync_socket(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<RequestQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, HttpError> {
+    // Browsers do not apply CORS to WebSockets, so check the origin here instead.
+    if !state.cors_origins.is_empty()
+        && headers.contains_key(header::ORIGIN)
+        && allowed_cors_origin(&state, &headers).is_none()
+    {
+        return Err(HttpError::Unauthorized("origin is not allowed".to_string()));
+    }
+
+    let database = database_for_request(&state, query.database_id.as_deref()).await?;
+    let manifest_version = client_manifest_version(&headers, &query);
+    // Browsers cannot set headers on a WebSocket, so a socket without a valid session
+    // header may still authenticate with an `auth` message.
+    let session = pyre_session_from_request(&state, &headers).ok();
+    let mut expires_at = None;
+    if let Some(session) = &session {
+        state.authorize(&headers, &database, session, Action::Subscribe)?;
+        expires_at = state.session.expires_at(&headers);
+    }
+    let format = match query.encoding.as_deref() {
+        None | Some("json") => SyncFormat::Json,
+        Some("msgpack") => SyncFormat::MessagePack,
+        Some(encoding) => {
+            return Err(HttpError::BadRequest(format!(
+                "unknown encoding {}; expected json or msgpack",
+                encoding
+            )))
+        }
+    };
+
+    Ok(upgrade
+        .on_upgrade(move |socket| {
+            let live = LiveSocket {
+                state,
+                database: Arc::clone(&database),
+                connection_id: new_connection_id(),
+                session,
+                expires_at,
+                shapes: None,
+                manifest_version,
+                headers,
+                format,
+            };
+            live.run(socket)
+        })
+        .into_response())
+}
+
+struct LiveSocket {
+    state: Arc<AppState>,
+    database: Arc<RoutedDatabase>,
+    connection_id: String,
+    session: Option<PyreSession>,
+    /// When `session` expires, in unix seconds. Replaced by each `auth` message.
+    expires_at: Option<i64>,
+    /// The shapes of the last `subscribe` message, resolved again for each new session.
+    shapes: Option<Vec<ShapeRequest>>,
+    /// The client's manifest version, used to run queries a newer manifest dropped.
+    manifest_version: Option<String>,
+    /// The upgrade request's headers, passed to the `Authorizer`.
+    headers: HeaderMap,
+    /// How replies and deltas are framed. Client messages are always JSON text.
+    format: SyncFormat,
+}
+
+impl LiveSocket {
+    async fn run(mut self, mut socket: WebSocket) {
+        let (sender, mut receiver) =
+            connection_queue(self.state.queue_size, &self.database.database_id);
+        let _cleanup = ConnectionCleanup {
+            database: Arc::clone(&self.database),
+            session_id: self.connection_id.clone(),
+        };
+
+        if self.session.is_some() {
+            self.register(&sender, None).await;
+            if send_json(&mut socket, self.connected(), self.format)
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        ping.tick().await;
+        let mut awaiting_pong = false;
+        // Only armed while the socket has no session, so it can't be held open unauthenticated.
+        let auth_deadline = tokio::time::sleep(self.state.auth_timeout);
+        tokio::pin!(auth_deadline);
+
+        loop {
+            tokio::select! {
+                incoming = socket.recv() => {
+                    let text = match incoming {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Pong(_))) => {
+                            awaiting_pong = false;
+                            continue;
+                        }
+                        Some(Ok(Message::Ping(_) | Message::Binary(_))) => continue,
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    };
+                    match self.handle(&text, &sender).await {
+                        Reply::Send(reply) => {
+                            if send_json(&mut socket, reply, self.format).await.is_err() {
+                                return;
+                            }
+                        }
+                        Reply::SendMany(replies) => {
+                            for reply in replies {
+                                if send_json(&mut socket, reply, self.format).await.is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                        Reply::Close(reply) => {
+                            close(&mut socket, reply, "authentication failed", self.format).await;
+                            return;
+                        }
+                    }
+                }
+                Some(message) = receiver.recv() => {
+                    if send_json(&mut socket, message, self.format).await.is_err() {
+                        return;
+                    }
+                }
+                _ = &mut auth_deadline, if self.session.is_none() => {
+                    let reply = error_message(&JsonValue::Null, "authentication timed out");
+                    close(&mut socket, reply, "authentication timed out", self.format).await;
+                    return;
+                }
+                _ = session_expired(self.expires_at.unwrap_or_default()), if self.expires_at.is_some() => {
+                    close(&mut socket, session_expired_message(), "session expired", self.format).await;
+                    return;
+                }
+                _ = ping.tick() => {
+                    if awaiting_pong {
+                        return;
+                    }
+                    if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                        return;
+                    }
+                    awaiting_pong = true;
+                }
+            }
+        }
+    }
+
+    async fn handle(&mut self, text: &str, sender: &ConnectionSender) -> Reply {
+        self.refresh_session().await;
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(error) => {
+                return Reply::Send(error_message(
+                    &JsonValue::Null,
+                    &format!("invalid message: {}", error),
+                ))
+            }
+        };
+
+        match message {
+            ClientMessage::Auth { id, credential } => {
+                let session =
+                    session_from_credential(&self.state, &credential).and_then(|session| {
+                        self.state
+                            .authorize(&self.headers, &self.database, &session, Action::Subscribe)
+                            .map(|()| session)
+                    });
+                let session = session.and_then(|session| {
+                    let shapes = self.resolve_shapes(&session, self.shapes.as_deref())?;
+                    Ok((session, shapes))
+                });
+                match session {
+                    Ok((session, shapes)) => {
+                        let first = self.session.is_none();
+                        self.session = Some(session);
+                        self.expires_at = self.state.session.credential_expires_at(&credential);
+                        // Re-registering replaces the session used to filter live deltas.
+                        self.register(sender, shapes).await;
+                        let authenticated = json!({ "type": "authenticated", "id": id });
+                        if first {
+                            Reply::SendMany(vec![authenticated, self.connected()])
+                        } else {
+                            Reply::Send(authenticated)
+                        }
+                    }
+                    Err(error) => Reply::Close(error_message(&id, error.message())),
+                }
+            }
+            ClientMessage::Ping { id } => Reply::Send(json!({ "type": "pong", "id": id })),
+            ClientMessage::Catchup {
+                id,
+                sync_cursor,
+                shapes,
+            } => {
+                let Some(session) = &self.session else {
+                    return Reply::Send(not_authenticated(&id));
+                };
+                match sync_request(
+                    &self.state,
+                    &self.headers,
+                    &self.database,
+                    session,
+                    self.manifest_version.as_deref(),
+                    sync_cursor,
+                    shapes,
+                )
+                .await
+                {
+                    Ok(result) => match result.into_value(self.format) {
+                        Ok(result) => Reply::Send(json!({
+                            "type": "catchup",
+                            "id": id,
+                            "result": result,
+                        })),
+                        Err(error) => Reply::Send(serve_error_message(&id, &error)),
+                    },
+                    Err(error) => Reply::Send(serve_error_message(&id, &error)),
+                }
+            }
+            ClientMessage::Subscribe { id, shapes } => {
+                let Some(session) = &self.session else {
+                    return Reply::Send(not_authenticated(&id));
+                };
+                match self.resolve_shapes(session, shapes.as_deref()) {
+                    Ok(resolved) => {
+                        self.shapes = shapes;
+                        self.register(sender, resolved).await;
+                        Reply::Send(json!({ "type": "subscribed", "id": id }))
+                    }
+                    Err(error) => Reply::Send(serve_error_message(&id, &error)),
+                }
+            }
+            ClientMessage::Run {
+                id,
+                query_id,
+                input,
+                sync,
+                idempotency_key,
+            } => {
+                let Some(session) = &self.session else {
+                    return Reply::Send(not_authenticated(&id));
+                };
+                let manifest = match self
+                    .state
+                    .authorize(
+                        &self.headers,
+                        &self.database,
+                        session,
+                        Action::Run {
+                            query_id: &query_id,
+                        },
+                    )
+                    .and_then(|()| {
+                        self.state
+                            .manifest_for(self.manifest_version.as_deref(), &[&query_id])
+                    }) {
+                    Ok(manifest) => manifest,
+                    Err(error) => return Reply::Send(serve_error_message(&id, &error)),
+                };
+                match run_one(
+                    &manifest,
+                    &self.database,
+                    session,
+                    &query_id,
+                    input,
+                    RunOptions {
+                        sync,
+                        origin_connection_id: Some(&self.connection_id),
+                        idempotency_key: idempotency_key.as_deref(),
+                    },
+                )
+                .await
+                {
+                    Ok(result) => Reply::Send(json!({
+                        "type": "result",
+                        "id": id,
+                        "result": result,
+                    })),
+                    Err(error) => Reply::Send(serve_error_message(&id, &error)),
+                }
+            }
+        }
+    }
+
+    fn resolve_shapes(
+        &self,
+        session: &PyreSession,
+        shapes: Option<&[ShapeRequest]>,
+    ) -> Result<Option<ShapeFilter>, HttpError> {
+        shapes
+            .map(|shapes| {
+                resolve_shapes(
+                    &self.state,
+                    &self.headers,
+                    &self.database,
+                    session,
+                    self.manifest_version.as_deref(),
+                    shapes,
+                )
+            })
+            .transpose()
+    }
+
+    /// Pick up a session replaced by `HttpServer::update_sessions`, so catchups and
+    /// queries run with the same session as live deltas.
+    async fn refresh_session(&mut self) {
+        if self.session.is_none() {
+            return;
+        }
+        let connections = self.database.connections.lock().await;
+        if let Some(connection) = connections.get(&self.connection_id) {
+            self.session = Some(connection.session.clone());
+        }
+    }
+
+    async fn register(&self, sender: &ConnectionSender, shapes: Option<ShapeFilter>) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        self.database.connections.lock().await.insert(
+            self.connection_id.clone(),
+            Connection {
+                session: session.clone(),
+                shapes,
+                sender: sender.clone(),
+            },
+        );
+    }
+
+    fn connected(&self) -> JsonValue {
+        json!({
+            "type": "connected",
+            "sessionId": self.connection_id,
+            "connectionId": self.connection_id,
+            "databaseId": self.database.database_id,
+        })
+    }
+}
+
+enum Reply {
+    Send(JsonValue),
+    SendMany(Vec<JsonValue>),
+    /// Send the message, then close the socket.
+    Close(JsonValue),
+}
+
+fn error_message(id: &JsonValue, error: &str) -> JsonValue {
+    json!({ "type": "error", "id": id, "error": error })
+}
+
+/// An error reply carrying the same fields as the HTTP error body, such as `code`.
+fn serve_error_message(id: &JsonValue, error: &HttpError) -> JsonValue {
+    let mut message = error.body();
+    if let JsonValue::Object(fields) = &mut message {
+        fields.insert("type".to_string(), json!("error"));
+        fields.insert("id".to_string(), id.clone());
+    }
+    message
+}
+
+fn not_authenticated(id: &JsonValue) -> JsonValue {
+    error_message(id, "not authenticated; send an auth message first")
+}
+
+/// Send `reply`, then close the socket as a policy violation.
+async fn close(socket: &mut WebSocket, reply: JsonValue, reason: &'static str, format: SyncFormat) {
+    let _ = send_json(socket, reply, format).await;
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: POLICY_VIOLATION,
+            reason: reason.into(),
+        })))
+        .await;
+}
+
+/// Send `value` as a text frame, or as a binary MessagePack frame.
+async fn send_json(
+    socket: &mut WebSocket,
+    value: JsonValue,
+    format: SyncFormat,
+) -> Result<(), axum::Error> {
+    let message = match format {
+        SyncFormat::Json => Message::Text(value.to_string()),
+        SyncFormat::MessagePack => Message::Binary(
+            sync_encoding::encode(
+                &value,
+                SyncEncoding {
+                    format,
+                    gzip: false,
+                },
+            )
+            .map_err(axum::Error::new)?,
+        ),
+    };
+    socket.send(message).await
+}
@@ -0,0 +1,229 @@
+//! Idempotency keys for queries and mutations.
+//!
+//! A request run with a key records its response in `_pyre_idempotency` in the same
+//! transaction as its writes. Retrying with the same key returns the recorded response
+//! instead of applying the mutation a second time. Keys are scoped to the session, so
+//! two users picking the same key don't collide.
+//!
+//! The scope covers every field of the schema's `session { ... }` block. Pyre can't
+//! tell which of them identify a user, and permissions may read any of them, so a
+//! retry whose session changed, for example after the user moved teams, runs again
+//! under the new session. Values outside the session block, such as a refreshed
+//! token's `exp`, aren't part of the session and don't change the scope.
+
+use crate::server::manifest::{Manifest, PyreSession};
+use crate::server::query::{self, QueryResult};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// One day. Retries after that apply the mutation again.
+pub const DEFAULT_IDEMPOTENCY_RETENTION_SECONDS: u64 = 24 * 3600;
+
+/// The longest idempotency key accepted.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+pub enum Outcome {
+    /// The request ran now. Its response has been recorded under the key.
+    Applied(QueryResult),
+    /// The key was already used by the same request, whose recorded response this is.
+    Replayed(JsonValue),
+}
+
+pub enum Begun {
+    /// The key was already used by the same request, whose recorded response this is.
+    Replayed(JsonValue),
+    /// The key is unused. Run the request on `Pending::conn`, then commit its response.
+    Pending(Pending),
+}
+
+/// An open transaction for a request whose key hasn't been used yet.
+pub struct Pending {
+    transaction: libsql::Transaction,
+    scope: String,
+    key: String,
+    query_id: String,
+    fingerprint: String,
+}
+
+impl Pending {
+    /// The transaction's connection. Everything the request writes goes through it.
+    pub fn conn(&self) -> &libsql::Connection {
+        &self.transaction
+    }
+
+    /// Record `response` under the key and commit it with the request's writes.
+    pub async fn commit(self, response: &JsonValue) -> Result<(), Error> {
+        let response = serde_json::to_string(response).map_err(Error::Json)?;
+        self.transaction
+            .execute(
+                "INSERT INTO _pyre_idempotency (scope, key, query_id, fingerprint, response) VALUES (?, ?, ?, ?, ?)",
+                libsql::params![self.scope, self.key, self.query_id, self.fingerprint, response],
+            )
+            .await
+            .map_err(Error::Database)?;
+        self.transaction.commit().await.map_err(Error::Database)
+    }
+
+    /// Undo the request's writes, recording nothing so the key can be used again.
+    pub async fn rollback(self) -> Result<(), Error> {
+        self.transaction.rollback().await.map_err(Error::Database)
+    }
+}
+
+/// Run `query_id` once per `key`, see the module docs.
+///
+/// A failed request records nothing, so it can be retried with the same key. A key
+/// reused for a different query or input is rejected with `Error::KeyReused`.
+pub async fn run(
+    conn: &libsql::Connection,
+    manifest: &Manifest,
+    query_id: &str,
+    input: JsonValue,
+    session: &PyreSession,
+    key: &str,
+    sync: bool,
+) -> Result<Outcome, Error> {
+    let pending = match begin(conn, query_id, &input, session, key).await? {
+        Begun::Replayed(response) => return Ok(Outcome::Replayed(response)),
+        Begun::Pending(pending) => pending,
+    };
+    let result = if sync {
+        query::run_sync(pending.conn(), manifest, query_id, input, session).await
+    } else {
+        query::run(pending.conn(), manifest, query_id, input, session).await
+    };
+    let result = match result {
+        Ok(result) => result,
+        Err(error) => {
+            pending.rollback().await?;
+            return Err(Error::Query(error));
+        }
+    };
+    pending.commit(&result.response).await?;
+
+    Ok(Outcome::Applied(result))
+}
+
+/// Look up `key` in the session's scope, returning the recorded response or an open
+/// transaction to run the request in. For callers that add their own writes, such as
+/// a server revision, before the response is recorded.
+pub async fn begin(
+    conn: &libsql::Connection,
+    query_id: &str,
+    input: &JsonValue,
+    session: &PyreSession,
+    key: &str,
+) -> Result<Begun, Error> {
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(Error::InvalidKey(format!(
+            "idempotency keys must be between 1 and {} bytes",
+            MAX_IDEMPOTENCY_KEY_LENGTH
+        )));
+    }
+    let scope = scope(session);
+    let fingerprint = fingerprint(query_id, input);
+
+    // Taking the write lock up front means a concurrent retry waits for this request
+    // to commit, and then finds its response.
+    let transaction = conn
+        .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+        .await
+        .map_err(Error::Database)?;
+    let mut rows = transaction
+        .query(
+            "SELECT fingerprint, response FROM _pyre_idempotency WHERE scope = ? AND key = ?",
+            libsql::params![scope.as_str(), key],
+        )
+        .await
+        .map_err(Error::Database)?;
+    if let Some(row) = rows.next().await.map_err(Error::Database)? {
+        let recorded_fingerprint = row.get::<String>(0).map_err(Error::Database)?;
+        let response = row.get::<String>(1).map_err(Error::Database)?;
+        drop(rows);
+        transaction.rollback().await.map_err(Error::Database)?;
+        if recorded_fingerprint != fingerprint {
+            return Err(Error::KeyReused(key.to_string()));
+        }
+        return serde_json::from_str(&response)
+            .map(Begun::Replayed)
+            .map_err(Error::Json);
+    }
+    drop(rows);
+
+    Ok(Begun::Pending(Pending {
+        transaction,
+        scope,
+        key: key.to_string(),
+        query_id: query_id.to_string(),
+        fingerprint,
+    }))
+}
+
+/// Create `_pyre_idempotency` in databases migrated before it existed.
+pub async fn ensure_idempotency(conn: &libsql::Connection) -> Result<(), Error> {
+    conn.execute(crate::db::migrate::CREATE_IDEMPOTENCY_TABLE, ())
+        .await
+        .map_err(Error::Database)?;
+    Ok(())
+}
+
+/// Delete recorded responses older than `retention_seconds`, returning how many were
+/// removed.
+pub async fn prune_idempotency(
+    conn: &libsql::Connection,
+    retention_seconds: u64,
+) -> Result<u64, Error> {
+    conn.execute(
+        "DELETE FROM _pyre_idempotency WHERE created_at < unixepoch() - ?",
+        libsql::params![retention_seconds as i64],
+    )
+    .await
+    .map_err(Error::Database)
+}
+
+/// The keys a session can see: a hash of all its fields, sorted so equal sessions
+/// match. See the module docs for why no field is left out.
+fn scope(session: &PyreSession) -> String {
+    let session = session.sql_args().iter().collect::<BTreeMap<_, _>>();
+    let session = serde_json::to_string(&session).unwrap_or_default();
+    format!("{:x}", Sha256::digest(session.as_bytes()))
+}
+
+/// Identifies a request by its query and input. Object keys serialize sorted, so equal
+/// requests always hash the same.
+fn fingerprint(query_id: &str, input: &JsonValue) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(query_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(input.to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Database(libsql::Error),
+    Json(serde_json::Error),
+    Query(query::Error),
+    InvalidKey(String),
+    /// The session already used the key for a request with a different query or input.
+    KeyReused(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Database(error) => write!(f, "database error: {}", error),
+            Error::Json(error) => write!(f, "json error: {}", error),
+            Error::Query(error) => write!(f, "{}", error),
+            Error::InvalidKey(message) => write!(f, "invalid idempotency key: {}", message),
+            Error::KeyReused(key) => write!(
+                f,
+                "idempotency key '{}' was already used for a different request",
+                key
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
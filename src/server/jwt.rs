@@ -0,0 +1,348 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Seconds of clock skew tolerated when checking `exp` and `nbf`.
+pub const JWT_LEEWAY_SECONDS: i64 = 60;
+
+/// A key that can verify JWT signatures.
+#[derive(Clone, Debug)]
+pub enum JwtKey {
+    /// Shared secret for HS256.
+    Hmac {
+        kid: Option<String>,
+        secret: Vec<u8>,
+    },
+    /// RSA public key for RS256, as big-endian modulus and exponent.
+    Rsa {
+        kid: Option<String>,
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// P-256 public key for ES256, as an uncompressed point.
+    EcP256 { kid: Option<String>, point: Vec<u8> },
+}
+
+impl JwtKey {
+    fn kid(&self) -> Option<&str> {
+        match self {
+            JwtKey::Hmac { kid, .. } | JwtKey::Rsa { kid, .. } | JwtKey::EcP256 { kid, .. } => {
+                kid.as_deref()
+            }
+        }
+    }
+
+    fn alg(&self) -> &'static str {
+        match self {
+            JwtKey::Hmac { .. } => "HS256",
+            JwtKey::Rsa { .. } => "RS256",
+            JwtKey::EcP256 { .. } => "ES256",
+        }
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            JwtKey::Hmac { secret, .. } => {
+                let Ok(mut mac) = HmacSha256::new_from_slice(secret) else {
+                    return false;
+                };
+                mac.update(message);
+                mac.verify_slice(signature).is_ok()
+            }
+            JwtKey::Rsa { n, e, .. } => ring::signature::RsaPublicKeyComponents { n, e }
+                .verify(
+                    &ring::signature::RSA_PKCS1_2048_8192_SHA256,
+                    message,
+                    signature,
+                )
+                .is_ok(),
+            JwtKey::EcP256 { point, .. } => ring::signature::UnparsedPublicKey::new(
+                &ring::signature::ECDSA_P256_SHA256_FIXED,
+                point,
+            )
+            .verify(message, signature)
+            .is_ok(),
+        }
+    }
+}
+
+/// Verifies bearer JWTs and maps their claims onto Pyre session fields.
+#[derive(Clone, Debug, Default)]
+pub struct JwtVerifier {
+    pub keys: Vec<JwtKey>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    /// Session field name to a dot-separated claim path, such as `app_metadata.role`.
+    /// Fields without an entry read the top-level claim with the same name.
+    pub claim_paths: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+impl JwtVerifier {
+    /// Check a compact JWT's signature and registered claims, returning its claims.
+    pub fn verify(&self, token: &str, now: i64) -> Result<JsonValue, JwtError> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(JwtError::Malformed(
+                "expected header.payload.signature".to_string(),
+            ));
+        };
+
+        // The signature covers the encoded header and payload exactly as sent.
+        let message = &token[..header.len() + 1 + payload.len()];
+        let header: JwtHeader = decode_json_segment(header, "header")?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| JwtError::Malformed("invalid signature encoding".to_string()))?;
+
+        // Only keys of the header's algorithm are tried, so an RSA public key can never
+        // be used as an HMAC secret.
+        let mut candidates = self
+            .keys
+            .iter()
+            .filter(|key| key.alg() == header.alg)
+            .filter(|key| match (&header.kid, key.kid()) {
+                (Some(wanted), Some(kid)) => wanted == kid,
+                _ => true,
+            })
+            .peekable();
+        if candidates.peek().is_none() {
+            return Err(JwtError::NoMatchingKey {
+                alg: header.alg,
+                kid: header.kid,
+            });
+        }
+        if !candidates.any(|key| key.verify(message.as_bytes(), &signature)) {
+            return Err(JwtError::InvalidSignature);
+        }
+
+        let claims: JsonValue = decode_json_segment(payload, "payload")?;
+        self.validate_claims(&claims, now)?;
+        Ok(claims)
+    }
+
+    fn validate_claims(&self, claims: &JsonValue, now: i64) -> Result<(), JwtError> {
+        let exp = claims
+            .get("exp")
+            .and_then(JsonValue::as_i64)
+            .ok_or(JwtError::MissingClaim("exp".to_string()))?;
+        if exp + JWT_LEEWAY_SECONDS <= now {
+            return Err(JwtError::Expired);
+        }
+
+        if let Some(nbf) = claims.get("nbf") {
+            let nbf = nbf
+                .as_i64()
+                .ok_or(JwtError::InvalidClaim("nbf".to_string()))?;
+            if nbf - JWT_LEEWAY_SECONDS > now {
+                return Err(JwtError::NotYetValid);
+            }
+        }
+
+        if let Some(issuer) = &self.issuer {
+            match claims.get("iss").and_then(JsonValue::as_str) {
+                Some(iss) if iss == issuer => {}
+                Some(_) => return Err(JwtError::InvalidClaim("iss".to_string())),
+                None => return Err(JwtError::MissingClaim("iss".to_string())),
+            }
+        }
+
+        if let Some(audience) = &self.audience {
+            let matches = match claims.get("aud") {
+                Some(JsonValue::String(aud)) => aud == audience,
+                Some(JsonValue::Array(auds)) => auds
+                    .iter()
+                    .any(|aud| aud.as_str() == Some(audience.as_str())),
+                Some(_) => false,
+                None => return Err(JwtError::MissingClaim("aud".to_string())),
+            };
+            if !matches {
+                return Err(JwtError::InvalidClaim("aud".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the session object for `PyreSession::new` from verified claims.
+    ///
+    /// Missing claims are left out, so required session fields still fail validation.
+    pub fn session_from_claims<'a>(
+        &self,
+        claims: &JsonValue,
+        session_fields: impl IntoIterator<Item = &'a String>,
+    ) -> JsonValue {
+        let mut session = serde_json::Map::new();
+        for field in session_fields {
+            let path = self
+                .claim_paths
+                .get(field)
+                .map(String::as_str)
+                .unwrap_or(field);
+            if let Some(value) = claim_at_path(claims, path) {
+                session.insert(field.clone(), value.clone());
+            }
+        }
+        JsonValue::Object(session)
+    }
+}
+
+/// When `verify` starts rejecting `token` as expired: its `exp` plus
+/// `JWT_LEEWAY_SECONDS`. The signature isn't checked, so only call this on a token
+/// that already verified.
+pub fn expires_at(token: &str) -> Option<i64> {
+    let payload = token.split('.').nth(1)?;
+    let claims: JsonValue = decode_json_segment(payload, "payload").ok()?;
+    claims
+        .get("exp")
+        .and_then(JsonValue::as_i64)
+        .map(|exp| exp + JWT_LEEWAY_SECONDS)
+}
+
+fn claim_at_path<'a>(claims: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.')
+        .try_fold(claims, |value, segment| value.get(segment))
+        .filter(|value| !value.is_null())
+}
+
+fn decode_json_segment<T: serde::de::DeserializeOwned>(
+    segment: &str,
+    label: &str,
+) -> Result<T, JwtError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(segment)
+        .map_err(|_| JwtError::Malformed(format!("invalid {} encoding", label)))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|_| JwtError::Malformed(format!("invalid {} JSON", label)))
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default)]
+    alg: Option<String>,
+    #[serde(default, rename = "use")]
+    use_: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+    #[serde(default)]
+    k: Option<String>,
+}
+
+/// Parse the signing keys out of a JWKS document.
+///
+/// Encryption keys and key types other than RS256, ES256 and HS256 are skipped.
+pub fn parse_jwks(json: &str) -> Result<Vec<JwtKey>, JwtError> {
+    let jwks: Jwks =
+        serde_json::from_str(json).map_err(|error| JwtError::InvalidJwks(error.to_string()))?;
+    let mut keys = Vec::new();
+    for jwk in jwks.keys {
+        if jwk.use_.as_deref() == Some("enc") {
+            continue;
+        }
+        let key = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("RSA", _) => JwtKey::Rsa {
+                kid: jwk.kid,
+                n: decode_jwk_field(jwk.n, "n")?,
+                e: decode_jwk_field(jwk.e, "e")?,
+            },
+            ("EC", Some("P-256")) => {
+                let mut point = vec![0x04];
+                point.extend(decode_jwk_field(jwk.x, "x")?);
+                point.extend(decode_jwk_field(jwk.y, "y")?);
+                JwtKey::EcP256 {
+                    kid: jwk.kid,
+                    point,
+                }
+            }
+            ("oct", _) => JwtKey::Hmac {
+                kid: jwk.kid,
+                secret: decode_jwk_field(jwk.k, "k")?,
+            },
+            _ => continue,
+        };
+        if jwk.alg.as_deref().is_some_and(|alg| alg != key.alg()) {
+            continue;
+        }
+        keys.push(key);
+    }
+
+    if keys.is_empty() {
+        return Err(JwtError::InvalidJwks(
+            "no RS256, ES256 or HS256 signing keys found".to_string(),
+        ));
+    }
+    Ok(keys)
+}
+
+fn decode_jwk_field(value: Option<String>, field: &str) -> Result<Vec<u8>, JwtError> {
+    let value =
+        value.ok_or_else(|| JwtError::InvalidJwks(format!("key is missing '{}'", field)))?;
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| JwtError::InvalidJwks(format!("key has an invalid '{}'", field)))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwtError {
+    Malformed(String),
+    NoMatchingKey { alg: String, kid: Option<String> },
+    InvalidSignature,
+    Expired,
+    NotYetValid,
+    MissingClaim(String),
+    InvalidClaim(String),
+    InvalidJwks(String),
+}
+
+impl std::fmt::Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JwtError::Malformed(message) => write!(f, "malformed JWT: {}", message),
+            JwtError::NoMatchingKey {
+                alg,
+                kid: Some(kid),
+            } => {
+                write!(f, "no {} key with kid '{}'", alg, kid)
+            }
+            JwtError::NoMatchingKey { alg, kid: None } => write!(f, "no {} key configured", alg),
+            JwtError::InvalidSignature => write!(f, "invalid JWT signature"),
+            JwtError::Expired => write!(f, "JWT is expired"),
+            JwtError::NotYetValid => write!(f, "JWT is not valid yet"),
+            JwtError::MissingClaim(claim) => write!(f, "JWT is missing the '{}' claim", claim),
+            JwtError::InvalidClaim(claim) => write!(f, "JWT has an invalid '{}' claim", claim),
+            JwtError::InvalidJwks(message) => write!(f, "invalid JWKS: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
@@ -22,6 +22,19 @@ pub struct Manifest {
     pub queries: HashMap<String, QueryManifest>,
 }
 
+impl Manifest {
+    /// The manifest's `crate::hash::hash_manifest`, matching the `hash` written by
+    /// `pyre generate`.
+    pub fn version_hash(&self) -> String {
+        crate::hash::hash_manifest(
+            self.session_schema
+                .iter()
+                .map(|(name, field)| (name.as_str(), field.type_.as_str(), field.nullable)),
+            self.queries.keys().map(String::as_str),
+        )
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct QueryManifest {
     pub id: String,
@@ -37,6 +50,10 @@ pub struct QueryManifest {
     pub sql: Vec<SqlInfo>,
     #[serde(default, rename = "syncSql")]
     pub sync_sql: Option<Vec<SqlInfo>>,
+    /// The rows this query reads, for clients syncing it as a partial sync shape.
+    /// Empty when the query cannot be used as one.
+    #[serde(default)]
+    pub shapes: Vec<sync::ShapeDefinition>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -1,5 +1,11 @@
 #[cfg(feature = "database")]
 pub mod database_id;
+#[cfg(all(feature = "serve", feature = "database"))]
+pub mod http;
+#[cfg(feature = "database")]
+pub mod idempotency;
+#[cfg(feature = "serve")]
+pub mod jwt;
 pub mod manifest;
 #[cfg(feature = "database")]
 pub mod query;
@@ -7,3 +13,5 @@ pub mod query;
 pub mod schema;
 #[cfg(feature = "database")]
 pub mod sync;
+#[cfg(feature = "database")]
+pub mod sync_inspect;
@@ -1,12 +1,102 @@
 use crate::server::manifest::{FieldSchema, Manifest, PyreSession, QueryManifest, SqlInfo};
 use crate::sync_deltas::AffectedRowTableGroup;
-use serde_json::Value as JsonValue;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug)]
 pub struct QueryResult {
     pub response: JsonValue,
     pub affected_rows: Vec<AffectedRowTableGroup>,
+    /// Rows an `@ifVersion` update skipped because their version had moved on, as they
+    /// are now. Only extracted in sync mode.
+    pub conflicts: Vec<AffectedRowTableGroup>,
+}
+
+impl QueryResult {
+    /// The server revision stamped on the response by live sync, if any.
+    pub fn server_revision(&self) -> Option<i64> {
+        self.response.get("serverRevision")?.as_i64()
+    }
+}
+
+/// The most queries accepted by one `run_batch` call.
+pub const MAX_BATCH_QUERIES: usize = 50;
+
+/// One query in a batch, as sent to `POST /db/batch`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BatchItem {
+    #[serde(rename = "queryId")]
+    pub query_id: String,
+    #[serde(default = "empty_input")]
+    pub input: JsonValue,
+}
+
+fn empty_input() -> JsonValue {
+    JsonValue::Object(serde_json::Map::new())
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchOptions {
+    /// Run every item in one transaction, rolling all of them back if any fails.
+    pub transaction: bool,
+    /// Use each query's sync SQL, like `run_sync`.
+    pub sync: bool,
+}
+
+#[derive(Debug)]
+pub enum BatchItemError {
+    Failed(Error),
+    /// The item ran, but another item failed and the transaction was rolled back.
+    RolledBack,
+    /// The item was skipped because an earlier item in the transaction failed.
+    NotRun,
+}
+
+impl std::fmt::Display for BatchItemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchItemError::Failed(error) => write!(f, "{}", error),
+            BatchItemError::RolledBack => write!(f, "rolled back because another query failed"),
+            BatchItemError::NotRun => write!(f, "not run because an earlier query failed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BatchResult {
+    pub items: Vec<Result<JsonValue, BatchItemError>>,
+    /// Affected rows of every item that took effect, in batch order.
+    pub affected_rows: Vec<AffectedRowTableGroup>,
+    /// Version conflicts of every item that took effect, in batch order.
+    pub conflicts: Vec<AffectedRowTableGroup>,
+    /// Whether the transaction committed, when the batch ran in one.
+    pub committed: Option<bool>,
+}
+
+impl BatchResult {
+    /// The combined result, ready to pass to `SyncServer::calculate_deltas` once.
+    pub fn into_query_result(self) -> QueryResult {
+        let results = self
+            .items
+            .iter()
+            .map(|item| match item {
+                Ok(response) => json!({ "ok": true, "result": response }),
+                Err(error) => json!({ "ok": false, "error": error.to_string() }),
+            })
+            .collect::<Vec<_>>();
+        let mut response = serde_json::Map::new();
+        response.insert("results".to_string(), JsonValue::Array(results));
+        if let Some(committed) = self.committed {
+            response.insert("committed".to_string(), JsonValue::Bool(committed));
+        }
+
+        QueryResult {
+            response: JsonValue::Object(response),
+            affected_rows: self.affected_rows,
+            conflicts: self.conflicts,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -49,6 +139,103 @@ pub async fn run_sync(
     run_inner(conn, manifest, query_id, input, session, true).await
 }
 
+/// Execute several manifest queries on one connection.
+///
+/// Without a transaction every item runs on its own and a failure only affects
+/// that item. With one, the first failure rolls back the whole batch.
+pub async fn run_batch(
+    conn: &libsql::Connection,
+    manifest: &Manifest,
+    items: &[BatchItem],
+    session: &PyreSession,
+    options: BatchOptions,
+) -> Result<BatchResult, Error> {
+    if items.is_empty() || items.len() > MAX_BATCH_QUERIES {
+        return Err(Error::InvalidInput(format!(
+            "a batch must contain between 1 and {} queries",
+            MAX_BATCH_QUERIES
+        )));
+    }
+
+    if !options.transaction {
+        let mut results = Vec::with_capacity(items.len());
+        let mut affected_rows = Vec::new();
+        let mut conflicts = Vec::new();
+        for item in items {
+            match run_batch_item(conn, manifest, item, session, options.sync).await {
+                Ok(result) => {
+                    affected_rows.extend(result.affected_rows);
+                    conflicts.extend(result.conflicts);
+                    results.push(Ok(result.response));
+                }
+                Err(error) => results.push(Err(BatchItemError::Failed(error))),
+            }
+        }
+        return Ok(BatchResult {
+            items: results,
+            affected_rows,
+            conflicts,
+            committed: None,
+        });
+    }
+
+    let transaction = conn
+        .transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+        .await
+        .map_err(Error::Database)?;
+    let mut responses = Vec::with_capacity(items.len());
+    let mut affected_rows = Vec::new();
+    let mut conflicts = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match run_batch_item(&transaction, manifest, item, session, options.sync).await {
+            Ok(result) => {
+                affected_rows.extend(result.affected_rows);
+                conflicts.extend(result.conflicts);
+                responses.push(result.response);
+            }
+            Err(error) => {
+                transaction.rollback().await.map_err(Error::Database)?;
+                let mut results = Vec::with_capacity(items.len());
+                results.extend((0..index).map(|_| Err(BatchItemError::RolledBack)));
+                results.push(Err(BatchItemError::Failed(error)));
+                results.extend((index + 1..items.len()).map(|_| Err(BatchItemError::NotRun)));
+                return Ok(BatchResult {
+                    items: results,
+                    affected_rows: Vec::new(),
+                    conflicts: Vec::new(),
+                    committed: Some(false),
+                });
+            }
+        }
+    }
+    transaction.commit().await.map_err(Error::Database)?;
+
+    Ok(BatchResult {
+        items: responses.into_iter().map(Ok).collect(),
+        affected_rows,
+        conflicts,
+        committed: Some(true),
+    })
+}
+
+async fn run_batch_item(
+    conn: &libsql::Connection,
+    manifest: &Manifest,
+    item: &BatchItem,
+    session: &PyreSession,
+    sync_mode: bool,
+) -> Result<QueryResult, Error> {
+    run_inner(
+        conn,
+        manifest,
+        &item.query_id,
+        item.input.clone(),
+        session,
+        sync_mode,
+    )
+    .await
+}
+
 pub async fn explain(
     conn: &libsql::Connection,
     manifest: &Manifest,
@@ -123,7 +310,11 @@ async fn run_inner(
         } else {
             format_response(&included_result_sets)?
         },
-        affected_rows: extract_affected_rows(&included_result_sets)?,
+        affected_rows: extract_row_groups(&included_result_sets, "_affectedRows")?,
+        conflicts: extract_row_groups(&included_result_sets, "_conflicts")?
+            .into_iter()
+            .filter(|group| !group.rows.is_empty())
+            .collect(),
     })
 }
 
@@ -397,16 +588,20 @@ fn format_response(result_sets: &[ResultSet]) -> Result<JsonValue, Error> {
     Ok(JsonValue::Object(response))
 }
 
-fn extract_affected_rows(result_sets: &[ResultSet]) -> Result<Vec<AffectedRowTableGroup>, Error> {
+/// The table groups selected as `column`, such as `_affectedRows`.
+fn extract_row_groups(
+    result_sets: &[ResultSet],
+    column: &str,
+) -> Result<Vec<AffectedRowTableGroup>, Error> {
     let mut groups = Vec::new();
 
     for result_set in result_sets {
-        if result_set.columns.first().map(|column| column.as_str()) != Some("_affectedRows") {
+        if result_set.columns.first().map(|name| name.as_str()) != Some(column) {
             continue;
         }
 
         for row in &result_set.rows {
-            let Some(raw) = row.get("_affectedRows") else {
+            let Some(raw) = row.get(column) else {
                 continue;
             };
             let parsed = match raw {
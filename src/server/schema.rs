@@ -22,6 +22,16 @@ impl LoadedSchema {
 
 /// Load and typecheck the Pyre schema stored in a migrated database.
 pub async fn load_schema_from_database(conn: &libsql::Connection) -> Result<LoadedSchema, Error> {
+    let introspection = introspect_database(conn).await?;
+    context_from_introspection(&introspection)?;
+
+    Ok(LoadedSchema { introspection })
+}
+
+/// Introspect a database that may not have been migrated yet.
+pub async fn introspect_database(
+    conn: &libsql::Connection,
+) -> Result<introspect::Introspection, Error> {
     let is_initialized = is_initialized(conn).await?;
     let sql = if is_initialized {
         introspect::INTROSPECT_SQL
@@ -29,16 +39,25 @@ pub async fn load_schema_from_database(conn: &libsql::Connection) -> Result<Load
         introspect::INTROSPECT_UNINITIALIZED_SQL
     };
     let raw = query_introspection(conn, sql).await?;
-    let introspection = introspect::from_raw(raw);
-    context_from_introspection(&introspection)?;
-
-    Ok(LoadedSchema { introspection })
+    Ok(introspect::from_raw(raw))
 }
 
 pub async fn load_context_from_database(conn: &libsql::Connection) -> Result<LoadedSchema, Error> {
     load_schema_from_database(conn).await
 }
 
+/// The id of the newest `_pyre_schema` row, which changes whenever a migration runs.
+pub async fn schema_version(conn: &libsql::Connection) -> Result<Option<i64>, Error> {
+    let mut rows = conn
+        .query("select max(id) from _pyre_schema", ())
+        .await
+        .map_err(Error::Database)?;
+    let Some(row) = rows.next().await.map_err(Error::Database)? else {
+        return Ok(None);
+    };
+    row.get::<Option<i64>>(0).map_err(Error::Database)
+}
+
 async fn is_initialized(conn: &libsql::Connection) -> Result<bool, Error> {
     let mut rows = conn
         .query(introspect::IS_INITIALIZED, ())
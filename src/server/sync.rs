@@ -1,27 +1,70 @@
 use crate::server::database_id::{self, DatabaseId};
 use crate::server::query::QueryResult;
-use crate::sync::{self, SyncCursor, SyncPageResult, TableSyncData};
-use crate::sync_deltas::{self, AffectedRowTableGroup};
+use crate::sync::{self, ShapeFilter, SyncCursor, SyncPageResult, TableSyncData};
+use crate::sync_deltas::{self, AffectedRowTableGroup, RemovedRowGroup};
 use crate::sync_shape;
 use crate::typecheck;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub type SyncSession = HashMap<String, sync::SessionValue>;
 pub type ConnectedSessions = HashMap<String, SyncSession>;
+/// Sync shapes keyed by session id. Sessions without an entry receive every row they
+/// can see.
+pub type SessionShapes = HashMap<String, ShapeFilter>;
 
 pub const MAX_LIVE_SYNC_DELTA_ROWS: usize = 5000;
 pub const MAX_LIVE_SYNC_DELTA_PAYLOAD_BYTES: usize = 1024 * 1024;
 pub const MAX_LIVE_SYNC_FANOUT_RECIPIENTS: usize = 1000;
+pub const DEFAULT_RECENT_DELTAS_CAPACITY: usize = 256;
 
 pub struct SyncServer<'a> {
     context: &'a typecheck::Context,
+    change_log_instance: Option<String>,
+    session_shapes: SessionShapes,
 }
 
 impl<'a> SyncServer<'a> {
     pub fn new(context: &'a typecheck::Context) -> Self {
-        Self { context }
+        Self {
+            context,
+            change_log_instance: None,
+            session_shapes: SessionShapes::new(),
+        }
+    }
+
+    /// Also write each revision's affected rows to `_pyre_changes`, tagged with
+    /// `instance_id`, so other processes can fan them out with `change_deltas`.
+    pub fn with_change_log(mut self, instance_id: impl Into<String>) -> Self {
+        self.change_log_instance = Some(instance_id.into());
+        self
+    }
+
+    /// Limit the live deltas of the listed sessions to the rows their shapes cover.
+    pub fn with_session_shapes(mut self, session_shapes: SessionShapes) -> Self {
+        self.session_shapes = session_shapes;
+        self
+    }
+
+    /// Build live deltas for a change read from `_pyre_changes`, stamped with its revision.
+    pub fn change_deltas(
+        &self,
+        change: &Change,
+        connected_sessions: &ConnectedSessions,
+        database_id: impl AsRef<str>,
+    ) -> Result<Vec<SessionDeltaMessage>, Error> {
+        let mut messages = build_delta_messages_for_database(
+            self.context,
+            &change.affected_rows,
+            connected_sessions,
+            &self.session_shapes,
+            database_id,
+        )?;
+        for message in &mut messages {
+            message.message.server_revision = Some(change.server_revision);
+        }
+        Ok(messages)
     }
 
     pub async fn catchup(
@@ -36,6 +79,87 @@ impl<'a> SyncServer<'a> {
         database_id::with_database_id(database_id, result).map_err(Error::DatabaseId)
     }
 
+    pub async fn catchup_shape(
+        &self,
+        conn: &libsql::Connection,
+        shape: &ShapeFilter,
+        sync_cursor: &SyncCursor,
+        session: &SyncSession,
+        page_size: usize,
+        database_id: impl AsRef<str>,
+    ) -> Result<SyncPageResult, Error> {
+        let result =
+            catchup_shape(conn, self.context, shape, sync_cursor, session, page_size).await?;
+        database_id::with_database_id(database_id, result).map_err(Error::DatabaseId)
+    }
+
+    /// Rebuild the live deltas a session missed after `after_revision`, filtered by
+    /// what that session can see.
+    ///
+    /// Returns one `syncRequired` message when `recent` no longer covers the gap.
+    pub fn replay_deltas(
+        &self,
+        recent: &RecentDeltas,
+        after_revision: i64,
+        session: &SyncSession,
+        database_id: impl AsRef<str>,
+    ) -> Result<Vec<DeltaMessage>, Error> {
+        self.replay(recent, after_revision, session, None, database_id.as_ref())
+    }
+
+    /// Like `replay_deltas`, limited to the rows `shape` covers.
+    pub fn replay_shape_deltas(
+        &self,
+        recent: &RecentDeltas,
+        after_revision: i64,
+        session: &SyncSession,
+        shape: &ShapeFilter,
+        database_id: impl AsRef<str>,
+    ) -> Result<Vec<DeltaMessage>, Error> {
+        self.replay(
+            recent,
+            after_revision,
+            session,
+            Some(shape),
+            database_id.as_ref(),
+        )
+    }
+
+    fn replay(
+        &self,
+        recent: &RecentDeltas,
+        after_revision: i64,
+        session: &SyncSession,
+        shape: Option<&ShapeFilter>,
+        database_id: &str,
+    ) -> Result<Vec<DeltaMessage>, Error> {
+        let Some(entries) = recent.since(after_revision) else {
+            let mut message = DeltaMessage::sync_required_for_database(database_id)?;
+            message.server_revision = Some(recent.latest_revision());
+            return Ok(vec![message]);
+        };
+
+        let sessions = ConnectedSessions::from([(String::new(), session.clone())]);
+        let shapes = shape
+            .map(|shape| SessionShapes::from([(String::new(), shape.clone())]))
+            .unwrap_or_default();
+        let mut messages = Vec::new();
+        for (server_revision, affected_rows) in entries {
+            let built = build_delta_messages_for_database(
+                self.context,
+                affected_rows,
+                &sessions,
+                &shapes,
+                database_id,
+            )?;
+            for mut message in built.into_iter().map(|message| message.message) {
+                message.server_revision = Some(*server_revision);
+                messages.push(message);
+            }
+        }
+        Ok(messages)
+    }
+
     pub async fn calculate_deltas(
         &self,
         conn: &libsql::Connection,
@@ -50,12 +174,14 @@ impl<'a> SyncServer<'a> {
             self.context,
             &query_result.affected_rows,
             &broadcast_sessions,
+            &self.session_shapes,
             database_id,
         )?;
         let origin_message = build_origin_delta_message(
             self.context,
-            &query_result.affected_rows,
+            query_result,
             connected_sessions,
+            &self.session_shapes,
             database_id,
             origin_session_id,
         )?;
@@ -64,6 +190,7 @@ impl<'a> SyncServer<'a> {
             messages,
             query_result,
             origin_message,
+            self.change_log_instance.as_deref(),
         )
         .await
     }
@@ -84,8 +211,9 @@ fn sessions_without_origin(
 
 fn build_origin_delta_message(
     context: &typecheck::Context,
-    affected_row_groups: &[AffectedRowTableGroup],
+    query_result: &QueryResult,
     connected_sessions: &ConnectedSessions,
+    session_shapes: &SessionShapes,
     database_id: &str,
     origin_session_id: Option<&str>,
 ) -> Result<Option<DeltaMessage>, Error> {
@@ -99,12 +227,32 @@ fn build_origin_delta_message(
     let origin_sessions = HashMap::from([(origin_session_id.to_string(), origin_session.clone())]);
     let mut messages = build_delta_messages_for_database(
         context,
-        affected_row_groups,
+        &query_result.affected_rows,
         &origin_sessions,
+        session_shapes,
         database_id,
     )?;
+    let mut message = messages.pop().map(|message| message.message);
 
-    Ok(messages.pop().map(|message| message.message))
+    // Conflicting rows go through the same permission and shape filter as deltas, so
+    // the origin only learns about rows it could read.
+    let conflicts = build_delta_messages_for_database(
+        context,
+        &query_result.conflicts,
+        &origin_sessions,
+        session_shapes,
+        database_id,
+    )?
+    .pop()
+    .map(|conflicts| conflicts.message.data)
+    .unwrap_or_default();
+    if !conflicts.is_empty() {
+        message
+            .get_or_insert(DeltaMessage::delta_for_database(database_id, Vec::new())?)
+            .conflicts = conflicts;
+    }
+
+    Ok(message)
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -117,6 +265,16 @@ pub struct DeltaMessage {
     pub database_id: Option<DatabaseId>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub data: Vec<AffectedRowTableGroup>,
+    /// Rows the recipient could see before an update and no longer can.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<RemovedRowGroup>,
+    /// Rows the recipient's own `@ifVersion` update skipped because their version had
+    /// moved on, as they are now.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<AffectedRowTableGroup>,
+    /// For `syncRequired`, the tables to catch up. Empty means every table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tables: Vec<String>,
 }
 
 impl DeltaMessage {
@@ -126,6 +284,9 @@ impl DeltaMessage {
             server_revision: None,
             database_id: None,
             data,
+            removed: Vec::new(),
+            conflicts: Vec::new(),
+            tables: Vec::new(),
         }
     }
 
@@ -140,6 +301,9 @@ impl DeltaMessage {
                 database_id::require_database_id(database_id).map_err(Error::DatabaseId)?,
             ),
             data,
+            removed: Vec::new(),
+            conflicts: Vec::new(),
+            tables: Vec::new(),
         })
     }
 
@@ -149,6 +313,9 @@ impl DeltaMessage {
             server_revision: None,
             database_id: None,
             data: Vec::new(),
+            removed: Vec::new(),
+            conflicts: Vec::new(),
+            tables: Vec::new(),
         }
     }
 
@@ -160,6 +327,9 @@ impl DeltaMessage {
                 database_id::require_database_id(database_id).map_err(Error::DatabaseId)?,
             ),
             data: Vec::new(),
+            removed: Vec::new(),
+            conflicts: Vec::new(),
+            tables: Vec::new(),
         })
     }
 }
@@ -170,6 +340,94 @@ pub struct SessionDeltaMessage {
     pub message: DeltaMessage,
 }
 
+/// A bounded log of recently committed affected rows, keyed by server revision, so
+/// that live deltas can be replayed to a client that briefly disconnected.
+///
+/// Revisions at or below `covered_through()` have been evicted. Revisions may be recorded
+/// out of order, for example when another process's change is read from `_pyre_changes`
+/// after a local one; a replay that would cross a missing revision is refused.
+#[derive(Clone, Debug)]
+pub struct RecentDeltas {
+    capacity: usize,
+    covered_through: i64,
+    entries: VecDeque<(i64, Vec<AffectedRowTableGroup>)>,
+}
+
+impl RecentDeltas {
+    /// Start an empty log that covers revisions after `server_revision`.
+    pub fn new(capacity: usize, server_revision: i64) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            covered_through: server_revision,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Start an empty log at the database's current server revision.
+    pub async fn open(conn: &libsql::Connection, capacity: usize) -> Result<Self, Error> {
+        let server_revision = current_server_revision(conn).await?.unwrap_or(0);
+        Ok(Self::new(capacity, server_revision))
+    }
+
+    pub fn record(&mut self, server_revision: i64, affected_rows: Vec<AffectedRowTableGroup>) {
+        if server_revision <= self.covered_through {
+            return;
+        }
+        let index = self
+            .entries
+            .partition_point(|(existing, _)| *existing < server_revision);
+        if self
+            .entries
+            .get(index)
+            .is_some_and(|(existing, _)| *existing == server_revision)
+        {
+            return;
+        }
+        self.entries.insert(index, (server_revision, affected_rows));
+        while self.entries.len() > self.capacity {
+            if let Some((evicted, _)) = self.entries.pop_front() {
+                self.covered_through = evicted;
+            }
+        }
+    }
+
+    /// The newest revision the log knows about.
+    pub fn latest_revision(&self) -> i64 {
+        self.entries
+            .back()
+            .map(|(server_revision, _)| *server_revision)
+            .unwrap_or(self.covered_through)
+    }
+
+    /// Revisions at or below this one can no longer be replayed.
+    pub fn covered_through(&self) -> i64 {
+        self.covered_through
+    }
+
+    /// Entries after `after_revision`, or `None` when some of them are no longer kept,
+    /// have not been recorded, or `after_revision` is newer than anything recorded.
+    pub fn since(
+        &self,
+        after_revision: i64,
+    ) -> Option<impl Iterator<Item = &(i64, Vec<AffectedRowTableGroup>)>> {
+        if after_revision < self.covered_through || after_revision > self.latest_revision() {
+            return None;
+        }
+        let start = self
+            .entries
+            .partition_point(|(server_revision, _)| *server_revision <= after_revision);
+        let contiguous = self
+            .entries
+            .range(start..)
+            .zip(after_revision + 1..)
+            .all(|((server_revision, _), expected)| *server_revision == expected);
+        if !contiguous {
+            return None;
+        }
+        Some(self.entries.range(start..))
+    }
+}
+
 /// Run a catchup sync request using a client cursor and logical session values.
 pub async fn catchup(
     conn: &libsql::Connection,
@@ -178,15 +436,79 @@ pub async fn catchup(
     session: &SyncSession,
     page_size: usize,
 ) -> Result<SyncPageResult, Error> {
-    let page_size = sync::normalize_page_size(page_size).map_err(Error::Sync)?;
+    catchup_with_shape(conn, context, None, sync_cursor, session, page_size).await
+}
 
-    let status_statement =
-        sync::get_sync_status_statement(sync_cursor, context, session).map_err(Error::Sync)?;
+/// Catch up on the rows `shape` covers. `sync_cursor` is the shape's own cursor;
+/// rows that leave a shape are only reported by live deltas.
+pub async fn catchup_shape(
+    conn: &libsql::Connection,
+    context: &typecheck::Context,
+    shape: &ShapeFilter,
+    sync_cursor: &SyncCursor,
+    session: &SyncSession,
+    page_size: usize,
+) -> Result<SyncPageResult, Error> {
+    catchup_with_shape(conn, context, Some(shape), sync_cursor, session, page_size).await
+}
+
+/// A catchup page as it is sent in `format`. MessagePack pages use the compact layout,
+/// with each table's column names listed once instead of on every row.
+#[cfg(feature = "compact")]
+pub fn sync_page_value(
+    page: SyncPageResult,
+    format: crate::sync_encoding::SyncFormat,
+) -> serde_json::Result<JsonValue> {
+    match format {
+        crate::sync_encoding::SyncFormat::Json => serde_json::to_value(page),
+        crate::sync_encoding::SyncFormat::MessagePack => {
+            serde_json::to_value(crate::sync_encoding::CompactSyncPage::from(page))
+        }
+    }
+}
+
+async fn catchup_with_shape(
+    conn: &libsql::Connection,
+    context: &typecheck::Context,
+    shape: Option<&ShapeFilter>,
+    sync_cursor: &SyncCursor,
+    session: &SyncSession,
+    page_size: usize,
+) -> Result<SyncPageResult, Error> {
+    let page_size = sync::normalize_page_size(page_size).map_err(Error::Sync)?;
+    let client_cursor = sync_cursor;
+
+    // A cursor behind the pruned tombstones may have missed deletes, so its table
+    // is resent from the start.
+    let tombstones = tombstone_range(conn).await?;
+    let mut sync_cursor = client_cursor.clone();
+    sync_cursor.retain(|_, cursor| {
+        cursor
+            .last_seen_tombstone
+            .is_none_or(|seen| seen >= tombstones.pruned_through)
+    });
+    let sync_cursor = &sync_cursor;
+
+    let status_statement = match shape {
+        Some(shape) => sync::get_shape_sync_status_statement(sync_cursor, context, session, shape),
+        None => sync::get_sync_status_statement(sync_cursor, context, session),
+    }
+    .map_err(Error::Sync)?;
     let status_rows = query_objects(conn, &status_statement.sql, &status_statement.params).await?;
     let sync_status = sync::parse_sync_status(sync_cursor, context, session, &status_rows)
         .map_err(Error::Sync)?;
-    let sync_sql = sync::get_sync_sql(&sync_status, sync_cursor, context, session, page_size)
-        .map_err(Error::Sync)?;
+    let sync_sql = match shape {
+        Some(shape) => sync::get_shape_sync_sql(
+            &sync_status,
+            sync_cursor,
+            context,
+            session,
+            shape,
+            page_size,
+        ),
+        None => sync::get_sync_sql(&sync_status, sync_cursor, context, session, page_size),
+    }
+    .map_err(Error::Sync)?;
 
     let mut result = SyncPageResult {
         database_id: None,
@@ -243,6 +565,34 @@ pub async fn catchup(
             }
         }
 
+        let table_cursor = sync_cursor
+            .get(&table_sql.table_name)
+            .filter(|cursor| cursor.permission_hash == table_sql.permission_hash);
+
+        // The cursor advances to the last row's (updatedAt, primary key). A page that
+        // came back empty leaves it where it was.
+        let (last_seen_updated_at, last_seen_key) = match table_rows.last() {
+            Some(last_row) if max_updated_at.is_some() => (
+                max_updated_at,
+                Some(
+                    table_sql
+                        .primary_key
+                        .iter()
+                        .map(|column| {
+                            let value = last_row.get(column).cloned().unwrap_or(JsonValue::Null);
+                            (column.clone(), value)
+                        })
+                        .collect::<serde_json::Map<_, _>>(),
+                )
+                .filter(|key| !key.is_empty()),
+            ),
+            Some(_) => (None, None),
+            None => (
+                table_cursor.and_then(|cursor| cursor.last_seen_updated_at),
+                table_cursor.and_then(|cursor| cursor.last_seen_key.clone()),
+            ),
+        };
+
         let raw_group = AffectedRowTableGroup {
             table_name: table_sql.table_name.clone(),
             headers: table_sql.headers.clone(),
@@ -256,6 +606,7 @@ pub async fn catchup(
                         .collect()
                 })
                 .collect(),
+            previous: None,
         };
 
         let reshaped_group = sync_shape::reshape_table_groups(&[raw_group], context)
@@ -271,12 +622,82 @@ pub async fn catchup(
             })
             .unwrap_or_default();
 
+        let reset = table_cursor.is_none() && client_cursor.contains_key(&table_sql.table_name);
+        let (deleted, last_seen_tombstone) = match table_cursor {
+            Some(cursor) => {
+                let deleted = read_deleted_keys(
+                    conn,
+                    &table_sql.table_name,
+                    cursor.last_seen_tombstone,
+                    &tombstones,
+                    page_size,
+                )
+                .await?;
+                result.has_more |= deleted.has_more;
+                (deleted.keys, Some(deleted.last_seen_tombstone))
+            }
+            None => (Vec::new(), Some(tombstones.latest)),
+        };
+
         result.tables.insert(
             table_sql.table_name,
             TableSyncData {
                 rows,
                 permission_hash: table_sql.permission_hash,
-                last_seen_updated_at: max_updated_at,
+                last_seen_updated_at,
+                last_seen_key,
+                primary_key: table_sql.primary_key,
+                deleted,
+                last_seen_tombstone,
+                reset,
+            },
+        );
+    }
+
+    // Tables without new rows still need the keys deleted since their cursor.
+    for status in &sync_status.tables {
+        if result.tables.contains_key(&status.table_name) {
+            continue;
+        }
+        let Some(cursor) = sync_cursor
+            .get(&status.table_name)
+            .filter(|cursor| cursor.permission_hash == status.permission_hash)
+        else {
+            continue;
+        };
+        let deleted = read_deleted_keys(
+            conn,
+            &status.table_name,
+            cursor.last_seen_tombstone,
+            &tombstones,
+            page_size,
+        )
+        .await?;
+        if deleted.keys.is_empty() {
+            continue;
+        }
+
+        result.has_more |= deleted.has_more;
+        let primary_key = context
+            .tables
+            .values()
+            .find(|table| {
+                crate::ast::get_tablename(&table.record.name, &table.record.fields)
+                    == status.table_name
+            })
+            .map(|table| crate::ast::get_primary_key_field_names(&table.record.fields))
+            .unwrap_or_default();
+        result.tables.insert(
+            status.table_name.clone(),
+            TableSyncData {
+                rows: Vec::new(),
+                permission_hash: status.permission_hash.clone(),
+                last_seen_updated_at: cursor.last_seen_updated_at,
+                last_seen_key: cursor.last_seen_key.clone(),
+                primary_key,
+                deleted: deleted.keys,
+                last_seen_tombstone: Some(deleted.last_seen_tombstone),
+                reset: false,
             },
         );
     }
@@ -286,6 +707,154 @@ pub async fn catchup(
     Ok(result)
 }
 
+/// Bounds of `_pyre_tombstones` ids. Tombstones at or below `pruned_through` have been
+/// pruned; `latest` is the newest id ever recorded.
+struct TombstoneRange {
+    pruned_through: i64,
+    latest: i64,
+}
+
+async fn tombstone_range(conn: &libsql::Connection) -> Result<TombstoneRange, Error> {
+    // Ids come from autoincrement and only pruning deletes tombstones, so every id
+    // below the oldest remaining one, or every id when none remain, was pruned.
+    let mut rows = conn
+        .query(
+            "SELECT coalesce((SELECT min(id) - 1 FROM _pyre_tombstones), seq, 0), coalesce(seq, 0) \
+             FROM (SELECT (SELECT seq FROM sqlite_sequence WHERE name = '_pyre_tombstones') AS seq)",
+            (),
+        )
+        .await
+        .map_err(Error::Database)?;
+
+    let Some(row) = rows.next().await.map_err(Error::Database)? else {
+        return Ok(TombstoneRange {
+            pruned_through: 0,
+            latest: 0,
+        });
+    };
+    Ok(TombstoneRange {
+        pruned_through: row.get::<i64>(0).map_err(Error::Database)?,
+        latest: row.get::<i64>(1).map_err(Error::Database)?,
+    })
+}
+
+struct DeletedKeys {
+    keys: Vec<JsonValue>,
+    last_seen_tombstone: i64,
+    has_more: bool,
+}
+
+/// Keys deleted from `table_name` after the cursor's tombstone, at most `page_size` of them.
+///
+/// Cursors from clients that predate tombstones get no keys, only the position to
+/// continue from.
+async fn read_deleted_keys(
+    conn: &libsql::Connection,
+    table_name: &str,
+    last_seen_tombstone: Option<i64>,
+    tombstones: &TombstoneRange,
+    page_size: usize,
+) -> Result<DeletedKeys, Error> {
+    let Some(after) = last_seen_tombstone else {
+        return Ok(DeletedKeys {
+            keys: Vec::new(),
+            last_seen_tombstone: tombstones.latest,
+            has_more: false,
+        });
+    };
+
+    let mut rows = conn
+        .query(
+            "SELECT id, row_key FROM _pyre_tombstones \
+             WHERE table_name = ? AND id > ? AND id <= ? ORDER BY id LIMIT ?",
+            libsql::params![table_name, after, tombstones.latest, page_size as i64 + 1],
+        )
+        .await
+        .map_err(Error::Database)?;
+
+    let mut keys = Vec::new();
+    let mut last_seen_tombstone = tombstones.latest;
+    let mut has_more = false;
+    while let Some(row) = rows.next().await.map_err(Error::Database)? {
+        if keys.len() == page_size {
+            has_more = true;
+            break;
+        }
+        last_seen_tombstone = row.get::<i64>(0).map_err(Error::Database)?;
+        let row_key = row.get::<String>(1).map_err(Error::Database)?;
+        keys.push(serde_json::from_str(&row_key).map_err(Error::Json)?);
+    }
+    if !has_more {
+        last_seen_tombstone = tombstones.latest;
+    }
+
+    Ok(DeletedKeys {
+        keys,
+        last_seen_tombstone,
+        has_more,
+    })
+}
+
+/// Create `_pyre_tombstones` in databases migrated before it existed, and check that
+/// every table has the trigger that fills it.
+///
+/// The triggers come from migrations, so tables missing one are reported in
+/// `Error::MissingTombstoneTriggers` rather than created here.
+pub async fn ensure_tombstones(
+    conn: &libsql::Connection,
+    context: &typecheck::Context,
+) -> Result<(), Error> {
+    conn.execute(crate::db::migrate::CREATE_TOMBSTONES_TABLE, ())
+        .await
+        .map_err(Error::Database)?;
+    conn.execute(crate::db::migrate::CREATE_TOMBSTONES_INDEX, ())
+        .await
+        .map_err(Error::Database)?;
+
+    let mut rows = conn
+        .query("SELECT name FROM sqlite_master WHERE type = 'trigger'", ())
+        .await
+        .map_err(Error::Database)?;
+    let mut triggers = HashSet::new();
+    while let Some(row) = rows.next().await.map_err(Error::Database)? {
+        triggers.insert(row.get::<String>(0).map_err(Error::Database)?);
+    }
+
+    let mut missing = Vec::new();
+    for table in context.tables.values() {
+        let table_name = crate::ast::get_tablename(&table.record.name, &table.record.fields);
+        let primary_key = crate::ast::get_primary_key_field_names(&table.record.fields);
+        if !primary_key.is_empty() && !triggers.contains(&format!("_pyre_tombstone_{}", table_name))
+        {
+            missing.push(table_name);
+        }
+    }
+    if !missing.is_empty() {
+        missing.sort();
+        return Err(Error::MissingTombstoneTriggers(missing));
+    }
+    Ok(())
+}
+
+/// Delete tombstones older than `retention_seconds`, returning how many were removed.
+///
+/// Clients whose cursor is behind a pruned tombstone get the table resent with `reset`.
+pub async fn prune_tombstones(
+    conn: &libsql::Connection,
+    retention_seconds: u64,
+) -> Result<u64, Error> {
+    // Pruning stops at the first tombstone still inside the window, so the ids left
+    // behind stay contiguous with the ones removed.
+    conn.execute(
+        "DELETE FROM _pyre_tombstones WHERE id < coalesce(\
+         (SELECT min(id) FROM _pyre_tombstones WHERE deleted_at >= unixepoch() - ?), \
+         (SELECT max(id) + 1 FROM _pyre_tombstones))",
+        libsql::params![retention_seconds as i64],
+    )
+    .await
+    .map_err(Error::Database)
+}
+
 async fn current_server_revision(conn: &libsql::Connection) -> Result<Option<i64>, Error> {
     let mut rows = conn
         .query(
@@ -320,17 +889,111 @@ async fn next_server_revision(conn: &libsql::Connection) -> Result<i64, Error> {
     row.get::<i64>(0).map_err(Error::Database)
 }
 
+/// Allocate a revision and log its affected rows in one immediate transaction, so
+/// changes become visible to readers of `_pyre_changes` in revision order. A caller
+/// that already holds a transaction, such as an idempotent request, gets both written
+/// in that one instead.
+async fn log_next_server_revision(
+    conn: &libsql::Connection,
+    instance_id: &str,
+    affected_rows: &[AffectedRowTableGroup],
+) -> Result<i64, Error> {
+    let affected_rows = serde_json::to_string(affected_rows).map_err(Error::Json)?;
+    let tx = if conn.is_autocommit() {
+        Some(
+            conn.transaction_with_behavior(libsql::TransactionBehavior::Immediate)
+                .await
+                .map_err(Error::Database)?,
+        )
+    } else {
+        None
+    };
+    let server_revision = next_server_revision(conn).await?;
+    conn.execute(
+        "INSERT INTO _pyre_changes (server_revision, instance_id, affected_rows) VALUES (?, ?, ?)",
+        libsql::params![server_revision, instance_id, affected_rows],
+    )
+    .await
+    .map_err(Error::Database)?;
+    if let Some(tx) = tx {
+        tx.commit().await.map_err(Error::Database)?;
+    }
+    Ok(server_revision)
+}
+
+/// One row of `_pyre_changes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub server_revision: i64,
+    pub instance_id: String,
+    pub affected_rows: Vec<AffectedRowTableGroup>,
+}
+
+/// Create `_pyre_changes` in databases migrated before it existed.
+pub async fn ensure_change_log(conn: &libsql::Connection) -> Result<(), Error> {
+    conn.execute(crate::db::migrate::CREATE_CHANGES_TABLE, ())
+        .await
+        .map_err(Error::Database)?;
+    Ok(())
+}
+
+/// Read up to `limit` logged changes after `after_revision`, oldest first.
+pub async fn read_changes(
+    conn: &libsql::Connection,
+    after_revision: i64,
+    limit: usize,
+) -> Result<Vec<Change>, Error> {
+    let mut rows = conn
+        .query(
+            "SELECT server_revision, instance_id, affected_rows FROM _pyre_changes \
+             WHERE server_revision > ? ORDER BY server_revision LIMIT ?",
+            libsql::params![after_revision, limit as i64],
+        )
+        .await
+        .map_err(Error::Database)?;
+
+    let mut changes = Vec::new();
+    while let Some(row) = rows.next().await.map_err(Error::Database)? {
+        let affected_rows = row.get::<String>(2).map_err(Error::Database)?;
+        changes.push(Change {
+            server_revision: row.get::<i64>(0).map_err(Error::Database)?,
+            instance_id: row.get::<String>(1).map_err(Error::Database)?,
+            affected_rows: serde_json::from_str(&affected_rows).map_err(Error::Json)?,
+        });
+    }
+    Ok(changes)
+}
+
+/// Delete logged changes older than `retention_seconds`, returning how many were removed.
+pub async fn prune_changes(
+    conn: &libsql::Connection,
+    retention_seconds: u64,
+) -> Result<u64, Error> {
+    conn.execute(
+        "DELETE FROM _pyre_changes WHERE created_at < unixepoch() - ?",
+        libsql::params![retention_seconds as i64],
+    )
+    .await
+    .map_err(Error::Database)
+}
+
 async fn stamp_messages_and_response_with_next_server_revision(
     conn: &libsql::Connection,
     mut messages: Vec<SessionDeltaMessage>,
     query_result: &mut QueryResult,
     mut origin_message: Option<DeltaMessage>,
+    change_log_instance: Option<&str>,
 ) -> Result<Vec<SessionDeltaMessage>, Error> {
     if query_result.affected_rows.is_empty() {
         return Ok(messages);
     }
 
-    let server_revision = next_server_revision(conn).await?;
+    let server_revision = match change_log_instance {
+        Some(instance_id) => {
+            log_next_server_revision(conn, instance_id, &query_result.affected_rows).await?
+        }
+        None => next_server_revision(conn).await?,
+    };
     for message in &mut messages {
         message.message.server_revision = Some(server_revision);
     }
@@ -359,6 +1022,7 @@ fn build_delta_messages_for_database(
     context: &typecheck::Context,
     affected_row_groups: &[AffectedRowTableGroup],
     connected_sessions: &ConnectedSessions,
+    session_shapes: &SessionShapes,
     database_id: impl AsRef<str>,
 ) -> Result<Vec<SessionDeltaMessage>, Error> {
     let database_id = database_id::require_database_id(database_id).map_err(Error::DatabaseId)?;
@@ -366,6 +1030,7 @@ fn build_delta_messages_for_database(
         context,
         affected_row_groups,
         connected_sessions,
+        session_shapes,
         Some(database_id),
     )
 }
@@ -374,25 +1039,31 @@ fn build_delta_messages(
     context: &typecheck::Context,
     affected_row_groups: &[AffectedRowTableGroup],
     connected_sessions: &ConnectedSessions,
+    session_shapes: &SessionShapes,
     database_id: Option<DatabaseId>,
 ) -> Result<Vec<SessionDeltaMessage>, Error> {
     if affected_row_groups.is_empty() || connected_sessions.is_empty() {
         return Ok(Vec::new());
     }
 
-    let result =
-        sync_deltas::calculate_sync_deltas(affected_row_groups, connected_sessions, context)
-            .map_err(Error::SyncDeltas)?;
+    let result = sync_deltas::calculate_shape_sync_deltas(
+        affected_row_groups,
+        connected_sessions,
+        session_shapes,
+        context,
+    )
+    .map_err(Error::SyncDeltas)?;
     let mut messages = Vec::new();
 
     for group in result.groups {
         let reshaped_table_groups = sync_shape::reshape_table_groups(&group.table_groups, context);
-        let delta_message = match &database_id {
+        let mut delta_message = match &database_id {
             Some(database_id) => {
                 DeltaMessage::delta_for_database(database_id, reshaped_table_groups)?
             }
             None => DeltaMessage::delta(reshaped_table_groups),
         };
+        delta_message.removed = group.removed;
         let message = if live_sync_requires_catchup(&delta_message, group.session_ids.len())? {
             match &database_id {
                 Some(database_id) => DeltaMessage::sync_required_for_database(database_id)?,
@@ -418,7 +1089,7 @@ fn live_sync_requires_catchup(
     message: &DeltaMessage,
     recipient_count: usize,
 ) -> Result<bool, Error> {
-    if count_rows(&message.data) > MAX_LIVE_SYNC_DELTA_ROWS {
+    if count_rows(&message.data) + count_removed_keys(&message.removed) > MAX_LIVE_SYNC_DELTA_ROWS {
         return Ok(true);
     }
 
@@ -433,7 +1104,11 @@ fn count_rows(table_groups: &[AffectedRowTableGroup]) -> usize {
     table_groups.iter().map(|group| group.rows.len()).sum()
 }
 
-async fn query_objects(
+fn count_removed_keys(removed: &[RemovedRowGroup]) -> usize {
+    removed.iter().map(|group| group.keys.len()).sum()
+}
+
+pub(crate) async fn query_objects(
     conn: &libsql::Connection,
     sql: &str,
     params: &[sync::SessionValue],
@@ -595,6 +1270,8 @@ pub enum Error {
     DatabaseId(database_id::DatabaseIdError),
     InvalidPageSize,
     Json(serde_json::Error),
+    /// Tables with no `_pyre_tombstone_<table>` trigger, so their deletes never reach clients.
+    MissingTombstoneTriggers(Vec<String>),
     Sync(sync::SyncError),
     SyncDeltas(sync_deltas::SyncDeltasError),
 }
@@ -606,6 +1283,11 @@ impl std::fmt::Display for Error {
             Error::DatabaseId(error) => write!(f, "database id error: {}", error),
             Error::InvalidPageSize => write!(f, "page_size must be greater than zero"),
             Error::Json(error) => write!(f, "json error: {}", error),
+            Error::MissingTombstoneTriggers(tables) => write!(
+                f,
+                "no tombstone trigger on {}; generate a migration with `pyre migration` and apply it with `pyre migrate`",
+                tables.join(", ")
+            ),
             Error::Sync(sync::SyncError::DatabaseError(message)) => {
                 write!(f, "sync database error: {}", message)
             }
@@ -621,6 +1303,9 @@ impl std::fmt::Display for Error {
             Error::Sync(sync::SyncError::InvalidSyncCursor(message)) => {
                 write!(f, "invalid sync cursor: {}", message)
             }
+            Error::Sync(sync::SyncError::InvalidShape(message)) => {
+                write!(f, "invalid sync shape: {}", message)
+            }
             Error::SyncDeltas(sync_deltas::SyncDeltasError::TableNotFound(table_name)) => {
                 write!(f, "sync delta table not found: {}", table_name)
             }
@@ -0,0 +1,301 @@
+//! Explain what a session receives from catchup, and why.
+//!
+//! `inspect_tables` reports each synced table's permission, rendered for the session,
+//! and how many rows it lets through. `inspect_row` checks one row against each
+//! condition of its table's permission.
+use crate::ast;
+use crate::ext::string;
+use crate::server::sync::{query_objects, Error as SyncServerError, SyncSession};
+use crate::sync::{self, PermissionCondition, SessionValue, SyncCursor, SyncStatement};
+use crate::typecheck;
+use serde_json::Value as JsonValue;
+
+/// What a session sees of one synced table.
+#[derive(Clone, Debug)]
+pub struct TableInspection {
+    pub table_name: String,
+    pub sync_layer: usize,
+    pub permission_hash: String,
+    /// `None` when the table has no select permission, so every row is visible.
+    pub permission: Option<PermissionCondition>,
+    pub visible_rows: i64,
+    pub total_rows: i64,
+    /// The statements of a first catchup page for this table.
+    pub catchup: Vec<SyncStatement>,
+}
+
+/// Why one row is or isn't visible to a session.
+#[derive(Clone, Debug)]
+pub struct RowInspection {
+    pub table_name: String,
+    pub primary_key: String,
+    pub id: SessionValue,
+    pub synced: bool,
+    pub found: bool,
+    pub visible: bool,
+    /// Each permission condition with its outcome for the row. `None` when the table
+    /// has no select permission, or the row is missing.
+    pub permission: Option<ConditionOutcome>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConditionOutcome {
+    pub sql: String,
+    pub params: Vec<SessionValue>,
+    /// `None` when the condition is SQL `NULL` for the row, which hides it.
+    pub passed: Option<bool>,
+    pub children: Vec<ConditionOutcome>,
+}
+
+/// Inspect every synced table for `session`, in `sync_layer` order.
+pub async fn inspect_tables(
+    conn: &libsql::Connection,
+    context: &typecheck::Context,
+    session: &SyncSession,
+    page_size: usize,
+) -> Result<Vec<TableInspection>, Error> {
+    let sync_cursor = SyncCursor::new();
+    let status_statement = sync::get_sync_status_statement(&sync_cursor, context, session)
+        .map_err(|error| Error::SyncServer(SyncServerError::Sync(error)))?;
+    let status_rows = query_objects(conn, &status_statement.sql, &status_statement.params)
+        .await
+        .map_err(Error::SyncServer)?;
+    let sync_status = sync::parse_sync_status(&sync_cursor, context, session, &status_rows)
+        .map_err(|error| Error::SyncServer(SyncServerError::Sync(error)))?;
+    let sync_sql = sync::get_sync_sql(&sync_status, &sync_cursor, context, session, page_size)
+        .map_err(|error| Error::SyncServer(SyncServerError::Sync(error)))?;
+
+    let mut tables = Vec::new();
+    for status in sync_status.tables {
+        let table = find_table(context, &status.table_name)?;
+        let permission = sync::permission_condition(table, session);
+        let quoted_table_name = string::quote(&status.table_name);
+
+        let total_rows = count(
+            conn,
+            &format!("SELECT COUNT(*) AS count FROM {}", quoted_table_name),
+            &[],
+        )
+        .await?;
+        let visible_rows = match &permission {
+            Some(permission) => {
+                count(
+                    conn,
+                    &format!(
+                        "SELECT COUNT(*) AS count FROM {} WHERE {}",
+                        quoted_table_name, permission.sql
+                    ),
+                    &permission.params,
+                )
+                .await?
+            }
+            None => total_rows,
+        };
+        let catchup = sync_sql
+            .tables
+            .iter()
+            .filter(|table_sql| table_sql.table_name == status.table_name)
+            .flat_map(|table_sql| {
+                table_sql
+                    .sql
+                    .iter()
+                    .zip(&table_sql.params)
+                    .map(|(sql, params)| SyncStatement {
+                        sql: sql.clone(),
+                        params: params.clone(),
+                    })
+            })
+            .collect();
+
+        tables.push(TableInspection {
+            table_name: status.table_name,
+            sync_layer: status.sync_layer,
+            permission_hash: status.permission_hash,
+            permission,
+            visible_rows,
+            total_rows,
+            catchup,
+        });
+    }
+
+    tables.sort_by(|left, right| {
+        (left.sync_layer, &left.table_name).cmp(&(right.sync_layer, &right.table_name))
+    });
+    Ok(tables)
+}
+
+/// Explain whether the row of `table` with primary key `id` is visible to `session`.
+///
+/// `table` is a record name or a table name. `id` is parsed by the primary key's type.
+pub async fn inspect_row(
+    conn: &libsql::Connection,
+    context: &typecheck::Context,
+    session: &SyncSession,
+    table: &str,
+    id: &str,
+) -> Result<RowInspection, Error> {
+    let table = find_table(context, table)?;
+    let table_name = ast::get_tablename(&table.record.name, &table.record.fields);
+    let primary_key = match ast::get_primary_key_field_names(&table.record.fields).as_slice() {
+        [primary_key] => primary_key.clone(),
+        _ => return Err(Error::CompoundPrimaryKey(table_name)),
+    };
+    let id = parse_id(table, &primary_key, id)?;
+    let quoted_table_name = string::quote(&table_name);
+    let row_where = format!("{}.{} = ?", quoted_table_name, string::quote(&primary_key));
+
+    let found = count(
+        conn,
+        &format!(
+            "SELECT COUNT(*) AS count FROM {} WHERE {}",
+            quoted_table_name, row_where
+        ),
+        std::slice::from_ref(&id),
+    )
+    .await?
+        > 0;
+    let synced = sync::table_sync_enabled(context, table);
+
+    let permission = match sync::permission_condition(table, session) {
+        Some(condition) if found => {
+            Some(check_condition(conn, &quoted_table_name, &row_where, &id, &condition).await?)
+        }
+        _ => None,
+    };
+    let visible = synced
+        && found
+        && permission
+            .as_ref()
+            .map(|outcome| outcome.passed == Some(true))
+            .unwrap_or(true);
+
+    Ok(RowInspection {
+        table_name,
+        primary_key,
+        id,
+        synced,
+        found,
+        visible,
+        permission,
+    })
+}
+
+async fn check_condition(
+    conn: &libsql::Connection,
+    quoted_table_name: &str,
+    row_where: &str,
+    id: &SessionValue,
+    condition: &PermissionCondition,
+) -> Result<ConditionOutcome, Error> {
+    let mut params = condition.params.clone();
+    params.push(id.clone());
+    let rows = query_objects(
+        conn,
+        &format!(
+            "SELECT ({}) AS passed FROM {} WHERE {}",
+            condition.sql, quoted_table_name, row_where
+        ),
+        &params,
+    )
+    .await
+    .map_err(Error::SyncServer)?;
+    let passed = rows
+        .first()
+        .and_then(|row| row.get("passed"))
+        .and_then(JsonValue::as_i64)
+        .map(|passed| passed != 0);
+
+    let mut children = Vec::new();
+    for child in &condition.children {
+        children.push(
+            Box::pin(check_condition(
+                conn,
+                quoted_table_name,
+                row_where,
+                id,
+                child,
+            ))
+            .await?,
+        );
+    }
+
+    Ok(ConditionOutcome {
+        sql: condition.sql.clone(),
+        params: condition.params.clone(),
+        passed,
+        children,
+    })
+}
+
+async fn count(
+    conn: &libsql::Connection,
+    sql: &str,
+    params: &[SessionValue],
+) -> Result<i64, Error> {
+    let rows = query_objects(conn, sql, params)
+        .await
+        .map_err(Error::SyncServer)?;
+    Ok(rows
+        .first()
+        .and_then(|row| row.get("count"))
+        .and_then(JsonValue::as_i64)
+        .unwrap_or(0))
+}
+
+fn find_table<'a>(
+    context: &'a typecheck::Context,
+    name: &str,
+) -> Result<&'a typecheck::Table, Error> {
+    context
+        .tables
+        .values()
+        .find(|table| {
+            table.record.name == name
+                || ast::get_tablename(&table.record.name, &table.record.fields) == name
+        })
+        .ok_or_else(|| Error::UnknownTable(name.to_string()))
+}
+
+fn parse_id(table: &typecheck::Table, primary_key: &str, id: &str) -> Result<SessionValue, Error> {
+    let type_ = table.record.fields.iter().find_map(|field| match field {
+        ast::Field::Column(column) if column.name == primary_key => Some(&column.type_),
+        _ => None,
+    });
+    match type_ {
+        Some(ast::ColumnType::Int) => id
+            .parse()
+            .map(SessionValue::Integer)
+            .map_err(|_| Error::InvalidId(id.to_string())),
+        Some(ast::ColumnType::Float) => id
+            .parse()
+            .map(SessionValue::Real)
+            .map_err(|_| Error::InvalidId(id.to_string())),
+        _ => Ok(SessionValue::Text(id.to_string())),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// `inspect_row` only looks rows up by a single primary key column.
+    CompoundPrimaryKey(String),
+    InvalidId(String),
+    SyncServer(SyncServerError),
+    UnknownTable(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CompoundPrimaryKey(table_name) => write!(
+                f,
+                "{} has a compound primary key; rows can only be inspected by a single id",
+                table_name
+            ),
+            Error::InvalidId(id) => write!(f, "{} is not a valid id for this table", id),
+            Error::SyncServer(error) => write!(f, "{}", error),
+            Error::UnknownTable(name) => write!(f, "no record or table named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -6,7 +6,7 @@ use std::collections::{HashMap, HashSet};
 
 // Sync module requires json feature for JSON value handling
 #[cfg(feature = "json")]
-use serde_json::Value as JsonValue;
+use serde_json::{Map, Value as JsonValue};
 
 // When json feature is not enabled, sync functionality is not available
 #[cfg(not(feature = "json"))]
@@ -43,7 +43,16 @@ pub fn normalize_page_size(page_size: usize) -> Result<usize, SyncError> {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TableCursor {
     pub last_seen_updated_at: Option<i64>, // Unix timestamp
+    /// Primary key of the last row seen at `last_seen_updated_at`, keyed by column name.
+    /// Cursors without one come from clients that predate compound cursors, and resume
+    /// after their timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_key: Option<Map<String, JsonValue>>,
     pub permission_hash: String,
+    /// The newest `_pyre_tombstones` id the client has applied. Cursors without one
+    /// predate tombstones and are sent no deleted keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_tombstone: Option<i64>,
 }
 
 /// Result of a sync page request
@@ -70,6 +79,338 @@ pub struct TableSyncData {
     pub permission_hash: String,
     /// The maximum updated_at timestamp from the returned rows (client should update cursor with this)
     pub last_seen_updated_at: Option<i64>,
+    /// Primary key of the last returned row (client should update cursor with this)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_key: Option<Map<String, JsonValue>>,
+    /// Columns that identify a row, in key order. Composite keys list more than one column.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub primary_key: Vec<String>,
+    /// Primary keys of rows deleted since the cursor, as objects keyed by column name.
+    /// Clients apply these before `rows`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<JsonValue>,
+    /// The newest tombstone covered by this page (client should update cursor with this)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_tombstone: Option<i64>,
+    /// Set when the client's cursor could not be continued, so this table is being
+    /// resent from the start. The client should drop its rows for the table first.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reset: bool,
+}
+
+/// The rows a query reads from one of its top-level tables, written to the manifest so
+/// clients can sync just those rows instead of the whole table.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ShapeDefinition {
+    pub table_name: String,
+    /// The query's `@where` on the table. `None` covers every row the session can see.
+    #[serde(rename = "where", default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<ShapeCondition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeCondition {
+    And(Vec<ShapeCondition>),
+    Or(Vec<ShapeCondition>),
+    Column {
+        column: String,
+        /// The SQL operator, as rendered by `generate::sql::to_sql::operator`.
+        operator: String,
+        value: ShapeValue,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShapeValue {
+    /// A query argument, filled in from the subscription's params.
+    Arg(String),
+    Session(String),
+    Literal(JsonValue),
+}
+
+/// The rows a set of subscribed shapes covers, keyed by table name. Conditions only
+/// hold literal values. Tables without an entry are not synced; an entry of `None`
+/// covers the whole table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapeFilter {
+    pub tables: HashMap<String, Option<ShapeCondition>>,
+}
+
+impl ShapeFilter {
+    /// Fill in a query's shape definitions with subscription params and session values.
+    pub fn resolve(
+        definitions: &[ShapeDefinition],
+        params: &Map<String, JsonValue>,
+        session: &HashMap<String, SessionValue>,
+    ) -> Result<Self, SyncError> {
+        let mut filter = ShapeFilter::default();
+        for definition in definitions {
+            let condition = definition
+                .condition
+                .as_ref()
+                .map(|condition| resolve_shape_condition(condition, params, session))
+                .transpose()?;
+            filter.merge(ShapeFilter {
+                tables: HashMap::from([(definition.table_name.clone(), condition)]),
+            });
+        }
+        Ok(filter)
+    }
+
+    /// Cover the rows of both filters.
+    pub fn merge(&mut self, other: ShapeFilter) {
+        for (table_name, condition) in other.tables {
+            match self.tables.get_mut(&table_name) {
+                None => {
+                    self.tables.insert(table_name, condition);
+                }
+                Some(existing) => {
+                    *existing = match (existing.take(), condition) {
+                        (Some(ShapeCondition::Or(mut conditions)), Some(condition)) => {
+                            conditions.push(condition);
+                            Some(ShapeCondition::Or(conditions))
+                        }
+                        (Some(left), Some(right)) => Some(ShapeCondition::Or(vec![left, right])),
+                        _ => None,
+                    };
+                }
+            }
+        }
+    }
+}
+
+fn resolve_shape_condition(
+    condition: &ShapeCondition,
+    params: &Map<String, JsonValue>,
+    session: &HashMap<String, SessionValue>,
+) -> Result<ShapeCondition, SyncError> {
+    match condition {
+        ShapeCondition::And(conditions) => Ok(ShapeCondition::And(
+            conditions
+                .iter()
+                .map(|condition| resolve_shape_condition(condition, params, session))
+                .collect::<Result<_, _>>()?,
+        )),
+        ShapeCondition::Or(conditions) => Ok(ShapeCondition::Or(
+            conditions
+                .iter()
+                .map(|condition| resolve_shape_condition(condition, params, session))
+                .collect::<Result<_, _>>()?,
+        )),
+        ShapeCondition::Column {
+            column,
+            operator,
+            value,
+        } => {
+            if shape_operator(operator).is_none() {
+                return Err(SyncError::InvalidShape(format!(
+                    "unknown operator '{}' on '{}'",
+                    operator, column
+                )));
+            }
+            let value = match value {
+                ShapeValue::Arg(name) => params
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| SyncError::InvalidShape(format!("missing param '{}'", name)))?,
+                ShapeValue::Session(name) => session
+                    .get(name)
+                    .map(session_value_to_json)
+                    .unwrap_or(JsonValue::Null),
+                ShapeValue::Literal(value) => value.clone(),
+            };
+            if matches!(operator.as_str(), "in" | "not in") && !value.is_array() {
+                return Err(SyncError::InvalidShape(format!(
+                    "'{}' on '{}' needs a list",
+                    operator, column
+                )));
+            }
+            Ok(ShapeCondition::Column {
+                column: column.clone(),
+                operator: operator.clone(),
+                value: ShapeValue::Literal(value),
+            })
+        }
+    }
+}
+
+/// The operator of a shape condition, parsed back from its SQL form.
+pub fn shape_operator(operator: &str) -> Option<ast::Operator> {
+    match operator {
+        "=" => Some(ast::Operator::Equal),
+        "!=" => Some(ast::Operator::NotEqual),
+        ">" => Some(ast::Operator::GreaterThan),
+        "<" => Some(ast::Operator::LessThan),
+        ">=" => Some(ast::Operator::GreaterThanOrEqual),
+        "<=" => Some(ast::Operator::LessThanOrEqual),
+        "in" => Some(ast::Operator::In),
+        "not in" => Some(ast::Operator::NotIn),
+        "like" => Some(ast::Operator::Like),
+        "not like" => Some(ast::Operator::NotLike),
+        _ => None,
+    }
+}
+
+/// The shapes of a query: one per top-level table, or `None` when a `@where` uses
+/// something a shape cannot express, such as a function call or a related table.
+pub fn shape_definitions(
+    context: &typecheck::Context,
+    query: &ast::Query,
+) -> Option<Vec<ShapeDefinition>> {
+    if query.operation != ast::QueryOperation::Query {
+        return None;
+    }
+
+    let mut definitions = Vec::new();
+    for field in &query.fields {
+        let ast::TopLevelQueryField::Field(query_field) = field else {
+            continue;
+        };
+        let table = context.tables.get(&query_field.name)?;
+        if !table_sync_enabled(context, table) {
+            return None;
+        }
+        let mut conditions = ast::collect_wheres(&query_field.fields)
+            .iter()
+            .map(|where_arg| shape_condition(table, where_arg))
+            .collect::<Option<Vec<_>>>()?;
+        definitions.push(ShapeDefinition {
+            table_name: ast::get_tablename(&table.record.name, &table.record.fields),
+            condition: match conditions.len() {
+                0 => None,
+                1 => conditions.pop(),
+                _ => Some(ShapeCondition::And(conditions)),
+            },
+        });
+    }
+
+    if definitions.is_empty() {
+        None
+    } else {
+        Some(definitions)
+    }
+}
+
+fn shape_condition(table: &typecheck::Table, where_arg: &WhereArg) -> Option<ShapeCondition> {
+    match where_arg {
+        WhereArg::Column(is_session_var, fieldname, op, value, _) => {
+            let is_column = table.record.fields.iter().any(
+                |field| matches!(field, ast::Field::Column(column) if &column.name == fieldname),
+            );
+            if *is_session_var || !is_column {
+                return None;
+            }
+            let value = match value {
+                ast::QueryValue::Variable((_, var)) => match &var.session_field {
+                    Some(session_field) => ShapeValue::Session(session_field.clone()),
+                    None => ShapeValue::Arg(var.name.clone()),
+                },
+                ast::QueryValue::String((_, value)) => {
+                    ShapeValue::Literal(JsonValue::from(value.clone()))
+                }
+                ast::QueryValue::Int((_, value)) => ShapeValue::Literal(JsonValue::from(*value)),
+                ast::QueryValue::Float((_, value)) => ShapeValue::Literal(JsonValue::from(*value)),
+                ast::QueryValue::Bool((_, value)) => ShapeValue::Literal(JsonValue::from(*value)),
+                ast::QueryValue::Null(_) => ShapeValue::Literal(JsonValue::Null),
+                ast::QueryValue::LiteralTypeValue((_, details)) => {
+                    ShapeValue::Literal(JsonValue::from(details.name.clone()))
+                }
+                ast::QueryValue::Fn(_) => return None,
+            };
+            Some(ShapeCondition::Column {
+                column: fieldname.clone(),
+                operator: crate::generate::sql::to_sql::operator(op),
+                value,
+            })
+        }
+        WhereArg::And(args) => Some(ShapeCondition::And(
+            args.iter()
+                .map(|arg| shape_condition(table, arg))
+                .collect::<Option<_>>()?,
+        )),
+        WhereArg::Or(args) => Some(ShapeCondition::Or(
+            args.iter()
+                .map(|arg| shape_condition(table, arg))
+                .collect::<Option<_>>()?,
+        )),
+    }
+}
+
+/// Render a resolved shape condition to SQL, binding its values as params.
+fn render_shape_where(
+    condition: &ShapeCondition,
+    quoted_table_name: &str,
+    params: &mut Vec<SessionValue>,
+) -> String {
+    match condition {
+        ShapeCondition::Column {
+            column,
+            operator,
+            value,
+        } => {
+            let column = format!(
+                "{}.{}",
+                quoted_table_name,
+                crate::ext::string::quote(column)
+            );
+            let value = match value {
+                ShapeValue::Literal(value) => value,
+                // Resolved conditions only hold literals.
+                ShapeValue::Arg(_) | ShapeValue::Session(_) => &JsonValue::Null,
+            };
+            match (value, operator.as_str()) {
+                (JsonValue::Array(values), "in" | "not in") => {
+                    let values = values
+                        .iter()
+                        .map(|value| render_session_param(&json_to_session_value(value), params))
+                        .collect::<Vec<_>>();
+                    format!("{} {} ({})", column, operator, values.join(", "))
+                }
+                (JsonValue::Null, "=") => format!("{} is null", column),
+                (JsonValue::Null, "!=") => format!("{} is not null", column),
+                (value, operator) => format!(
+                    "{} {} {}",
+                    column,
+                    operator,
+                    render_session_param(&json_to_session_value(value), params)
+                ),
+            }
+        }
+        ShapeCondition::And(conditions) if conditions.is_empty() => "1".to_string(),
+        ShapeCondition::Or(conditions) if conditions.is_empty() => "0".to_string(),
+        ShapeCondition::And(conditions) => format!(
+            "({})",
+            conditions
+                .iter()
+                .map(|condition| render_shape_where(condition, quoted_table_name, params))
+                .collect::<Vec<_>>()
+                .join(" and ")
+        ),
+        ShapeCondition::Or(conditions) => format!(
+            "({})",
+            conditions
+                .iter()
+                .map(|condition| render_shape_where(condition, quoted_table_name, params))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        ),
+    }
+}
+
+fn json_to_session_value(value: &JsonValue) -> SessionValue {
+    key_value_to_session_value(value).unwrap_or_else(|| SessionValue::Text(value.to_string()))
+}
+
+pub fn session_value_to_json(value: &SessionValue) -> JsonValue {
+    match value {
+        SessionValue::Null => JsonValue::Null,
+        SessionValue::Integer(value) => JsonValue::from(*value),
+        SessionValue::Real(value) => JsonValue::from(*value),
+        SessionValue::Text(value) => JsonValue::from(value.clone()),
+        SessionValue::Blob(value) => JsonValue::from(value.clone()),
+    }
 }
 
 /// SQL statements for syncing a table
@@ -89,6 +430,8 @@ pub struct TableSyncSql {
     pub headers: Vec<String>,
     /// Column names that should be decoded as JSON values in the runtime
     pub json_columns: Vec<String>,
+    /// Primary key columns, used to order rows deterministically and to key them on the client
+    pub primary_key: Vec<String>,
 }
 
 fn push_storage_column(column_names: &mut Vec<String>, column_name: String) {
@@ -97,7 +440,8 @@ fn push_storage_column(column_names: &mut Vec<String>, column_name: String) {
     }
 }
 
-fn table_sync_enabled(context: &typecheck::Context, table: &typecheck::Table) -> bool {
+/// Whether rows of `table` are synced at all, from its namespace's sync mode.
+pub fn table_sync_enabled(context: &typecheck::Context, table: &typecheck::Table) -> bool {
     context
         .namespace_sync_modes
         .get(&table.schema)
@@ -136,6 +480,10 @@ pub fn validate_sync_cursor(
             )));
         }
 
+        if let Some(last_seen_key) = &cursor.last_seen_key {
+            validate_last_seen_key(context, table_name, cursor, last_seen_key)?;
+        }
+
         if cursor.permission_hash.len() > MAX_SYNC_CURSOR_PERMISSION_HASH_BYTES {
             return Err(SyncError::InvalidSyncCursor(format!(
                 "sync cursor permission_hash for '{}' is too large",
@@ -147,6 +495,107 @@ pub fn validate_sync_cursor(
     Ok(())
 }
 
+fn validate_last_seen_key(
+    context: &typecheck::Context,
+    table_name: &str,
+    cursor: &TableCursor,
+    last_seen_key: &Map<String, JsonValue>,
+) -> Result<(), SyncError> {
+    if cursor.last_seen_updated_at.is_none() {
+        return Err(SyncError::InvalidSyncCursor(format!(
+            "sync cursor last_seen_key for '{}' requires last_seen_updated_at",
+            table_name
+        )));
+    }
+
+    let primary_key = context
+        .tables
+        .values()
+        .find(|table| ast::get_tablename(&table.record.name, &table.record.fields) == table_name)
+        .map(|table| ast::get_primary_key_field_names(&table.record.fields))
+        .unwrap_or_default();
+    if primary_key.is_empty()
+        || last_seen_key.len() != primary_key.len()
+        || primary_key
+            .iter()
+            .any(|column| !last_seen_key.contains_key(column))
+    {
+        return Err(SyncError::InvalidSyncCursor(format!(
+            "sync cursor last_seen_key for '{}' must have exactly the primary key columns",
+            table_name
+        )));
+    }
+
+    if last_seen_key
+        .values()
+        .any(|value| key_value_to_session_value(value).is_none())
+    {
+        return Err(SyncError::InvalidSyncCursor(format!(
+            "sync cursor last_seen_key for '{}' must hold scalar values",
+            table_name
+        )));
+    }
+
+    Ok(())
+}
+
+fn key_value_to_session_value(value: &JsonValue) -> Option<SessionValue> {
+    match value {
+        JsonValue::Null => Some(SessionValue::Null),
+        JsonValue::Bool(value) => Some(SessionValue::Integer(*value as i64)),
+        JsonValue::Number(number) => number
+            .as_i64()
+            .map(SessionValue::Integer)
+            .or_else(|| number.as_f64().map(SessionValue::Real)),
+        JsonValue::String(value) => Some(SessionValue::Text(value.clone())),
+        JsonValue::Array(_) | JsonValue::Object(_) => None,
+    }
+}
+
+/// Render the condition selecting rows after a cursor position, ordered by
+/// `(updatedAt, primary key)`. Without a `last_seen_key` the client predates compound
+/// cursors and won't send back the key it is given, so it keeps the old `updatedAt >`
+/// paging. Re-reading rows at its timestamp would hand it the same page forever.
+fn render_cursor_where(
+    quoted_table_name: &str,
+    primary_key: &[String],
+    updated_at: i64,
+    last_seen_key: Option<&Map<String, JsonValue>>,
+    params: &mut Vec<SessionValue>,
+) -> String {
+    use crate::ext::string;
+
+    let Some(last_seen_key) = last_seen_key.filter(|_| !primary_key.is_empty()) else {
+        params.push(SessionValue::Integer(updated_at));
+        return format!("{}.{} > ?", quoted_table_name, string::quote("updatedAt"));
+    };
+
+    params.push(SessionValue::Integer(updated_at));
+    params.push(SessionValue::Integer(updated_at));
+    let key_columns = primary_key
+        .iter()
+        .map(|column| format!("{}.{}", quoted_table_name, string::quote(column)))
+        .collect::<Vec<_>>();
+    let key_params = primary_key
+        .iter()
+        .map(|column| {
+            let value = last_seen_key
+                .get(column)
+                .and_then(key_value_to_session_value)
+                .unwrap_or(SessionValue::Null);
+            render_session_param(&value, params)
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "({table}.{updated_at} > ? OR ({table}.{updated_at} = ? AND ({columns}) > ({values})))",
+        table = quoted_table_name,
+        updated_at = string::quote("updatedAt"),
+        columns = key_columns.join(", "),
+        values = key_params.join(", ")
+    )
+}
+
 fn collect_sync_storage_columns(
     context: &typecheck::Context,
     column_type: &ast::ColumnType,
@@ -260,6 +709,27 @@ fn extract_session_fields_from_query_value(value: &ast::QueryValue, fields: &mut
     }
 }
 
+/// The permission hash of every synced table for one session, keyed by table name.
+///
+/// A session whose hashes differ between two schemas must run a full catchup.
+pub fn table_permission_hashes(
+    context: &typecheck::Context,
+    session: &HashMap<String, SessionValue>,
+) -> HashMap<String, String> {
+    context
+        .tables
+        .values()
+        .filter(|table| table_sync_enabled(context, table))
+        .map(|table| {
+            let permission = ast::get_permissions(&table.record, &ast::QueryOperation::Query);
+            (
+                ast::get_tablename(&table.record.name, &table.record.fields),
+                calculate_permission_hash(&permission, session),
+            )
+        })
+        .collect()
+}
+
 /// Calculate permission hash from permission AST and session values
 pub fn calculate_permission_hash(
     permission: &Option<WhereArg>,
@@ -465,6 +935,46 @@ fn render_permission_value(
     }
 }
 
+/// A table's select permission rendered for one session, with the conditions an `and`
+/// or `or` combines as `children` so each can be checked on its own.
+#[derive(Clone, Debug)]
+pub struct PermissionCondition {
+    pub sql: String,
+    pub params: Vec<SessionValue>,
+    pub children: Vec<PermissionCondition>,
+}
+
+/// Render the select permission of `table` for `session`, or `None` when every row is
+/// visible.
+pub fn permission_condition(
+    table: &typecheck::Table,
+    session: &HashMap<String, SessionValue>,
+) -> Option<PermissionCondition> {
+    ast::get_permissions(&table.record, &ast::QueryOperation::Query)
+        .map(|permission| render_permission_condition(&permission, table, session))
+}
+
+fn render_permission_condition(
+    where_arg: &WhereArg,
+    table: &typecheck::Table,
+    session: &HashMap<String, SessionValue>,
+) -> PermissionCondition {
+    let mut params = Vec::new();
+    let sql = render_permission_where(where_arg, table, session, &mut params);
+    let children = match where_arg {
+        WhereArg::And(args) | WhereArg::Or(args) => args
+            .iter()
+            .map(|arg| render_permission_condition(arg, table, session))
+            .collect(),
+        WhereArg::Column(..) => Vec::new(),
+    };
+    PermissionCondition {
+        sql,
+        params,
+        children,
+    }
+}
+
 /// Render a permission WHERE clause to SQL
 /// This is a custom renderer for sync operations that doesn't require QueryField or QueryInfo
 /// Handles session variable replacement internally
@@ -534,7 +1044,22 @@ pub fn get_sync_status_statement(
 ) -> Result<SyncStatement, SyncError> {
     validate_sync_cursor(sync_cursor, context)?;
     let mut params = Vec::new();
-    let sql = get_sync_status_sql_with_params(sync_cursor, context, session, &mut params)?;
+    let sql = get_sync_status_sql_with_params(sync_cursor, context, session, None, &mut params)?;
+    Ok(SyncStatement { sql, params })
+}
+
+/// Like `get_sync_status_statement`, limited to the rows a shape covers. `sync_cursor`
+/// is the shape's own cursor.
+pub fn get_shape_sync_status_statement(
+    sync_cursor: &SyncCursor,
+    context: &typecheck::Context,
+    session: &HashMap<String, SessionValue>,
+    shape: &ShapeFilter,
+) -> Result<SyncStatement, SyncError> {
+    validate_sync_cursor(sync_cursor, context)?;
+    let mut params = Vec::new();
+    let sql =
+        get_sync_status_sql_with_params(sync_cursor, context, session, Some(shape), &mut params)?;
     Ok(SyncStatement { sql, params })
 }
 
@@ -559,6 +1084,7 @@ fn get_sync_status_sql_with_params(
     sync_cursor: &SyncCursor,
     context: &typecheck::Context,
     session: &HashMap<String, SessionValue>,
+    shape: Option<&ShapeFilter>,
     params: &mut Vec<SessionValue>,
 ) -> Result<String, SyncError> {
     use crate::ext::string;
@@ -575,6 +1101,12 @@ fn get_sync_status_sql_with_params(
         let actual_table_name = ast::get_tablename(&table.record.name, &table.record.fields);
         let quoted_table_name = string::quote(&actual_table_name);
 
+        let shape_condition = match shape.map(|shape| shape.tables.get(&actual_table_name)) {
+            Some(None) => continue,
+            Some(Some(condition)) => condition.as_ref(),
+            None => None,
+        };
+
         // Get permission for select operation
         let permission = ast::get_permissions(&table.record, &ast::QueryOperation::Query);
 
@@ -585,14 +1117,36 @@ fn get_sync_status_sql_with_params(
         let table_cursor = sync_cursor.get(&actual_table_name);
         let last_seen_updated_at = table_cursor.and_then(|c| c.last_seen_updated_at);
 
-        // Build WHERE clause for permissions. Session values are emitted as bind parameters.
-        let permission_where = if let Some(perm) = &permission {
-            format!(
-                " WHERE {}",
-                render_permission_where(perm, table, session, params)
-            )
-        } else {
+        // Tables with a compound cursor check for rows past the cursor's key, since rows
+        // sharing its timestamp can arrive after it. Rendered first: it binds params
+        // ahead of the WHERE clause.
+        let has_new_rows = match (last_seen_updated_at, table_cursor) {
+            (Some(updated_at), Some(cursor)) if cursor.last_seen_key.is_some() => format!(
+                "MAX(CASE WHEN {} THEN 1 ELSE 0 END)",
+                render_cursor_where(
+                    &quoted_table_name,
+                    &ast::get_primary_key_field_names(&table.record.fields),
+                    updated_at,
+                    cursor.last_seen_key.as_ref(),
+                    params,
+                )
+            ),
+            _ => "NULL".to_string(),
+        };
+
+        // Build WHERE clause for permissions and the shape. Session values are emitted
+        // as bind parameters.
+        let mut where_parts = Vec::new();
+        if let Some(perm) = &permission {
+            where_parts.push(render_permission_where(perm, table, session, params));
+        }
+        if let Some(condition) = shape_condition {
+            where_parts.push(render_shape_where(condition, &quoted_table_name, params));
+        }
+        let permission_where = if where_parts.is_empty() {
             String::new()
+        } else {
+            format!(" WHERE {}", where_parts.join(" AND "))
         };
 
         // Build the subquery for this table
@@ -607,12 +1161,13 @@ fn get_sync_status_sql_with_params(
         };
 
         let subquery = format!(
-            "SELECT {} AS table_name, {} AS sync_layer, {} AS permission_hash, {} AS last_seen_updated_at, MAX({}.updatedAt) AS max_updated_at FROM {}{}",
+            "SELECT {} AS table_name, {} AS sync_layer, {} AS permission_hash, {} AS last_seen_updated_at, MAX({}.updatedAt) AS max_updated_at, {} AS has_new_rows FROM {}{}",
             table_name_literal,
             sync_layer_value,
             permission_hash_literal,
             last_seen_literal,
             quoted_table_name,
+            has_new_rows,
             quoted_table_name,
             permission_where
         );
@@ -622,7 +1177,7 @@ fn get_sync_status_sql_with_params(
 
     if union_parts.is_empty() {
         return Ok(
-            "SELECT NULL AS table_name, NULL AS sync_layer, NULL AS permission_hash, NULL AS last_seen_updated_at, NULL AS max_updated_at WHERE 0"
+            "SELECT NULL AS table_name, NULL AS sync_layer, NULL AS permission_hash, NULL AS last_seen_updated_at, NULL AS max_updated_at, NULL AS has_new_rows WHERE 0"
                 .to_string(),
         );
     }
@@ -634,6 +1189,7 @@ fn get_sync_status_sql_with_params(
 
 /// Parse sync status results from SQL query execution
 /// The SQL should return rows with: table_name, sync_layer, permission_hash, last_seen_updated_at, max_updated_at
+/// and has_new_rows, which is null unless the cursor has a `last_seen_key`
 pub fn parse_sync_status(
     sync_cursor: &SyncCursor,
     _context: &typecheck::Context,
@@ -691,11 +1247,17 @@ pub fn parse_sync_status(
             None => true, // No cursor means first sync
         };
 
-        // Check if max_updated_at > last_seen_updated_at
-        let has_new_data = match (max_updated_at, last_seen_updated_at) {
-            (Some(max), Some(last)) => max > last,
-            (Some(_), None) => true, // Has data but no cursor
-            (None, _) => false,      // No data
+        let has_new_rows = row
+            .get("has_new_rows")
+            .and_then(|v| v.as_i64().or_else(|| v.as_u64().map(|u| u as i64)));
+
+        // Compound cursors report rows past the key directly; otherwise
+        // check if max_updated_at > last_seen_updated_at
+        let has_new_data = match (has_new_rows, max_updated_at, last_seen_updated_at) {
+            (Some(has_new_rows), _, _) => has_new_rows == 1,
+            (None, Some(max), Some(last)) => max > last,
+            (None, Some(_), None) => true, // Has data but no cursor
+            (None, None, _) => false,      // No data
         };
 
         let needs_sync = permission_hash_changed || has_new_data;
@@ -724,6 +1286,37 @@ pub fn get_sync_sql(
     context: &typecheck::Context,
     session: &HashMap<String, SessionValue>,
     page_size: usize,
+) -> Result<SyncSqlResult, SyncError> {
+    get_sync_sql_with_shape(sync_status, sync_cursor, context, session, None, page_size)
+}
+
+/// Like `get_sync_sql`, limited to the rows a shape covers. `sync_status` should come
+/// from `get_shape_sync_status_statement` with the same shape and cursor.
+pub fn get_shape_sync_sql(
+    sync_status: &SyncStatusResult,
+    sync_cursor: &SyncCursor,
+    context: &typecheck::Context,
+    session: &HashMap<String, SessionValue>,
+    shape: &ShapeFilter,
+    page_size: usize,
+) -> Result<SyncSqlResult, SyncError> {
+    get_sync_sql_with_shape(
+        sync_status,
+        sync_cursor,
+        context,
+        session,
+        Some(shape),
+        page_size,
+    )
+}
+
+fn get_sync_sql_with_shape(
+    sync_status: &SyncStatusResult,
+    sync_cursor: &SyncCursor,
+    context: &typecheck::Context,
+    session: &HashMap<String, SessionValue>,
+    shape: Option<&ShapeFilter>,
+    page_size: usize,
 ) -> Result<SyncSqlResult, SyncError> {
     use crate::ext::string;
     validate_sync_cursor(sync_cursor, context)?;
@@ -756,6 +1349,11 @@ pub fn get_sync_sql(
         }
 
         let actual_table_name = &status.table_name;
+        let shape_condition = match shape.map(|shape| shape.tables.get(actual_table_name)) {
+            Some(None) => continue,
+            Some(Some(condition)) => condition.as_ref(),
+            None => None,
+        };
 
         // Get permission for select operation
         let permission = ast::get_permissions(&table.record, &ast::QueryOperation::Query);
@@ -777,8 +1375,9 @@ pub fn get_sync_sql(
             // Use the last_seen_updated_at from cursor (not max_updated_at from status)
             table_cursor.and_then(|c| c.last_seen_updated_at)
         };
+        let primary_key = ast::get_primary_key_field_names(&table.record.fields);
 
-        // Build WHERE clause combining permissions and updatedAt filter
+        // Build WHERE clause combining permissions and the cursor position
         let mut where_parts = Vec::new();
         let mut params = Vec::new();
 
@@ -787,19 +1386,24 @@ pub fn get_sync_sql(
             where_parts.push(render_permission_where(perm, table, session, &mut params));
         }
 
-        // Add updatedAt filter if provided
+        // Add the shape's filter, if syncing a shape
+        if let Some(condition) = shape_condition {
+            where_parts.push(render_shape_where(
+                condition,
+                &string::quote(actual_table_name),
+                &mut params,
+            ));
+        }
+
+        // Add the (updatedAt, primary key) cursor filter if provided
         if let Some(updated_at) = last_seen_updated_at {
-            let table_name = crate::ext::string::quote(&ast::get_tablename(
-                &table.record.name,
-                &table.record.fields,
+            where_parts.push(render_cursor_where(
+                &string::quote(actual_table_name),
+                &primary_key,
+                updated_at,
+                table_cursor.and_then(|c| c.last_seen_key.as_ref()),
+                &mut params,
             ));
-            params.push(SessionValue::Integer(updated_at));
-            let updated_at_where = format!(
-                "{}.{} > ?",
-                table_name,
-                crate::ext::string::quote("updatedAt")
-            );
-            where_parts.push(updated_at_where);
         }
 
         // Build WHERE clause SQL
@@ -858,14 +1462,22 @@ pub fn get_sync_sql(
             })
             .collect::<Vec<_>>();
 
+        // Rows sharing an updatedAt are ordered by primary key so pages are stable.
+        let mut order_by = vec![format!("{}.updatedAt ASC", quoted_table_name)];
+        order_by.extend(
+            primary_key
+                .iter()
+                .map(|column| format!("{}.{} ASC", quoted_table_name, string::quote(column))),
+        );
+
         let sql = format!(
-            "SELECT coalesce(json_group_array(json_array({})), json('[]')) AS {} FROM (SELECT {} FROM {}{} ORDER BY {}.updatedAt ASC LIMIT {})",
+            "SELECT coalesce(json_group_array(json_array({})), json('[]')) AS {} FROM (SELECT {} FROM {}{} ORDER BY {} LIMIT {})",
             row_values.join(", "),
             string::quote(SYNC_ROWS_JSON_COLUMN),
             columns.join(", "),
             quoted_table_name,
             where_clause,
-            quoted_table_name,
+            order_by.join(", "),
             effective_page_size + 1 // +1 to check if there's more
         );
 
@@ -876,6 +1488,7 @@ pub fn get_sync_sql(
             params: vec![params],
             headers,
             json_columns,
+            primary_key,
         });
     }
 
@@ -936,6 +1549,19 @@ pub fn get_sync_page_info(
                 rows: Vec::new(), // Will be populated by query execution
                 permission_hash: current_permission_hash,
                 last_seen_updated_at,
+                last_seen_key: if needs_full_resync {
+                    None
+                } else {
+                    table_cursor.and_then(|c| c.last_seen_key.clone())
+                },
+                primary_key: ast::get_primary_key_field_names(&table.record.fields),
+                deleted: Vec::new(),
+                last_seen_tombstone: if needs_full_resync {
+                    None
+                } else {
+                    table_cursor.and_then(|c| c.last_seen_tombstone)
+                },
+                reset: needs_full_resync && table_cursor.is_some(),
             },
         );
     }
@@ -950,6 +1576,7 @@ pub enum SyncError {
     PermissionError(String),
     InvalidPageSize,
     InvalidSyncCursor(String),
+    InvalidShape(String),
 }
 
 // Display and Error traits removed to avoid formatting infrastructure
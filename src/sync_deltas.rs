@@ -1,5 +1,5 @@
 use crate::ast::{self, WhereArg};
-use crate::sync::SessionValue;
+use crate::sync::{self, SessionValue, ShapeCondition, ShapeFilter, ShapeValue};
 use crate::typecheck;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -19,6 +19,24 @@ pub struct AffectedRowTableGroup {
     pub table_name: String,
     pub headers: Vec<String>,
     pub rows: Vec<Vec<JsonValue>>, // Array of row arrays, each row array has values matching headers order
+    /// For updates: the columns of each row before it changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<PreviousRows>,
+}
+
+/// Rows as they were before an update
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PreviousRows {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+/// Rows a session could see before an update and can no longer see
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RemovedRowGroup {
+    pub table_name: String,
+    /// One object per row, keyed by primary key column
+    pub keys: Vec<JsonValue>,
 }
 
 /// A group of sessions that share the same affected table groups
@@ -27,6 +45,9 @@ pub struct SessionDeltaGroup {
     pub session_ids: HashSet<String>,
     /// Table groups with their rows (keeps grouped format for efficiency)
     pub table_groups: Vec<AffectedRowTableGroup>,
+    /// Rows these sessions should drop because they are no longer visible
+    #[serde(default)]
+    pub removed: Vec<RemovedRowGroup>,
 }
 
 /// Result containing deltas grouped by session permissions
@@ -79,6 +100,56 @@ fn evaluate_permission(
     }
 }
 
+/// Evaluate a resolved shape condition against row data
+pub fn shape_condition_matches(
+    condition: &ShapeCondition,
+    row_data: &Map<String, JsonValue>,
+) -> bool {
+    match condition {
+        ShapeCondition::Column {
+            column,
+            operator,
+            value,
+        } => {
+            let Some(op) = sync::shape_operator(operator) else {
+                return false;
+            };
+            let rhs_value = match value {
+                ShapeValue::Literal(value) => value,
+                ShapeValue::Arg(_) | ShapeValue::Session(_) => &JsonValue::Null,
+            };
+            let lhs_value = row_data.get(column).unwrap_or(&JsonValue::Null);
+            evaluate_operator(&op, lhs_value, rhs_value)
+        }
+        ShapeCondition::And(conditions) => conditions
+            .iter()
+            .all(|condition| shape_condition_matches(condition, row_data)),
+        ShapeCondition::Or(conditions) => conditions
+            .iter()
+            .any(|condition| shape_condition_matches(condition, row_data)),
+    }
+}
+
+/// Whether a session sees a row: its permission allows it and, for sessions
+/// subscribed to shapes, one of the shapes covers it
+fn row_is_visible(
+    permission: Option<&WhereArg>,
+    shape: Option<&ShapeFilter>,
+    table_name: &str,
+    row_data: &Map<String, JsonValue>,
+    session: &HashMap<String, SessionValue>,
+) -> bool {
+    let in_shape = match shape {
+        None => true,
+        Some(shape) => match shape.tables.get(table_name) {
+            None => false,
+            Some(None) => true,
+            Some(Some(condition)) => shape_condition_matches(condition, row_data),
+        },
+    };
+    in_shape && permission.is_none_or(|perm| evaluate_permission(perm, row_data, session))
+}
+
 /// Evaluate an operator between two JSON values
 fn evaluate_operator(op: &ast::Operator, lhs: &JsonValue, rhs: &JsonValue) -> bool {
     match op {
@@ -348,6 +419,25 @@ fn row_array_to_object(headers: &[String], row_array: &[JsonValue]) -> Map<Strin
     obj
 }
 
+/// (table_group_idx, row_idx) of a row within the affected row groups
+type RowPosition = (usize, usize);
+
+/// A row captured before an update, with the index of the same row after it, if any
+struct PreviousRow {
+    group_idx: usize,
+    previous_idx: usize,
+    row_obj: Map<String, JsonValue>,
+    current_idx: Option<usize>,
+}
+
+/// The primary key values of a row, used to match rows before and after an update
+fn row_key(primary_key: &[String], row: &Map<String, JsonValue>) -> Vec<JsonValue> {
+    primary_key
+        .iter()
+        .map(|column| row.get(column).cloned().unwrap_or(JsonValue::Null))
+        .collect()
+}
+
 /// Calculate which sessions should receive which affected rows based on permissions
 /// Keeps grouped format for efficiency (no explosion into individual rows)
 /// Optimized with:
@@ -359,12 +449,34 @@ pub fn calculate_sync_deltas(
     affected_row_groups: &[AffectedRowTableGroup],
     connected_sessions: &HashMap<String, HashMap<String, SessionValue>>,
     context: &typecheck::Context,
+) -> Result<SyncDeltasResult, SyncDeltasError> {
+    calculate_shape_sync_deltas(
+        affected_row_groups,
+        connected_sessions,
+        &HashMap::new(),
+        context,
+    )
+}
+
+/// Like `calculate_sync_deltas`, but sessions with an entry in `session_shapes` only
+/// receive rows their shapes cover. A row that leaves a session's shapes is sent as a
+/// removal, the same as one that leaves its permission.
+pub fn calculate_shape_sync_deltas(
+    affected_row_groups: &[AffectedRowTableGroup],
+    connected_sessions: &HashMap<String, HashMap<String, SessionValue>>,
+    session_shapes: &HashMap<String, ShapeFilter>,
+    context: &typecheck::Context,
 ) -> Result<SyncDeltasResult, SyncDeltasError> {
     // OPTIMIZATION 1: Build table lookup map once (O(k) instead of O(n*m*k))
     let mut table_map: HashMap<String, Option<WhereArg>> = HashMap::new();
+    let mut primary_keys: HashMap<String, Vec<String>> = HashMap::new();
     for table in context.tables.values() {
         let actual_table_name = ast::get_tablename(&table.record.name, &table.record.fields);
         let permission = ast::get_permissions(&table.record, &ast::QueryOperation::Query);
+        primary_keys.insert(
+            actual_table_name.clone(),
+            ast::get_primary_key_field_names(&table.record.fields),
+        );
         table_map.insert(actual_table_name, permission);
     }
 
@@ -388,12 +500,54 @@ pub fn calculate_sync_deltas(
         }
     }
 
-    // OPTIMIZATION 2: Group sessions by which rows they can see
-    // Key: Vec<(group_idx, row_idx)> sorted, Value: session IDs
-    let mut row_visibility_to_sessions: HashMap<Vec<(usize, usize)>, HashSet<String>> =
-        HashMap::new();
+    // Rows captured before an update, paired with the index of the same row after it.
+    // A row with no match after the update no longer matches the mutation at all.
+    let mut previous_rows: Vec<PreviousRow> = Vec::new();
+
+    for (group_idx, table_group) in affected_row_groups.iter().enumerate() {
+        let Some(previous) = &table_group.previous else {
+            continue;
+        };
+        let primary_key = primary_keys
+            .get(&table_group.table_name)
+            .ok_or_else(|| SyncDeltasError::TableNotFound(table_group.table_name.clone()))?;
+        if primary_key.is_empty() {
+            continue;
+        }
+
+        let current_keys: HashMap<Vec<JsonValue>, usize> = table_group
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row_idx, row_array)| {
+                let row_obj = row_array_to_object(&table_group.headers, row_array);
+                (row_key(primary_key, &row_obj), row_idx)
+            })
+            .collect();
+
+        for (previous_idx, previous_array) in previous.rows.iter().enumerate() {
+            let previous_obj = row_array_to_object(&previous.headers, previous_array);
+            let current_idx = current_keys
+                .get(&row_key(primary_key, &previous_obj))
+                .copied();
+            previous_rows.push(PreviousRow {
+                group_idx,
+                previous_idx,
+                row_obj: previous_obj,
+                current_idx,
+            });
+        }
+    }
+
+    // OPTIMIZATION 2: Group sessions by which rows they can see and which they lost
+    // Key: (Vec<(group_idx, row_idx)>, Vec<(group_idx, previous_row_idx)>) sorted, Value: session IDs
+    let mut row_visibility_to_sessions: HashMap<
+        (Vec<RowPosition>, Vec<RowPosition>),
+        HashSet<String>,
+    > = HashMap::new();
 
     for (session_id, session_data) in connected_sessions {
+        let shape = session_shapes.get(session_id);
         let mut visible_rows = Vec::new();
 
         for (_flat_idx, (group_idx, row_idx, row_obj)) in flat_rows.iter().enumerate() {
@@ -405,22 +559,45 @@ pub fn calculate_sync_deltas(
                 .as_ref();
 
             // If no permission (public), all sessions can see it
-            let should_receive = if let Some(perm) = permission {
-                evaluate_permission(perm, row_obj, session_data)
-            } else {
-                true // Public - all sessions can see it
-            };
+            let should_receive =
+                row_is_visible(permission, shape, table_name, row_obj, session_data);
 
             if should_receive {
                 visible_rows.push((*group_idx, *row_idx));
             }
         }
 
-        if !visible_rows.is_empty() {
-            // Sort to ensure consistent key for grouping
-            visible_rows.sort_unstable();
+        // Sort to ensure consistent key for grouping
+        visible_rows.sort_unstable();
+
+        // A row was visible before the update and is not now: tell the session to drop it
+        let mut removed_rows = Vec::new();
+        for previous in &previous_rows {
+            let table_name = &affected_row_groups[previous.group_idx].table_name;
+            let permission = table_map
+                .get(table_name)
+                .and_then(|permission| permission.as_ref());
+            let was_visible = row_is_visible(
+                permission,
+                shape,
+                table_name,
+                &previous.row_obj,
+                session_data,
+            );
+            let is_visible = previous.current_idx.is_some_and(|row_idx| {
+                visible_rows
+                    .binary_search(&(previous.group_idx, row_idx))
+                    .is_ok()
+            });
+
+            if was_visible && !is_visible {
+                removed_rows.push((previous.group_idx, previous.previous_idx));
+            }
+        }
+
+        if !visible_rows.is_empty() || !removed_rows.is_empty() {
             row_visibility_to_sessions
-                .entry(visible_rows)
+                .entry((visible_rows, removed_rows))
                 .or_insert_with(HashSet::new)
                 .insert(session_id.clone());
         }
@@ -430,7 +607,7 @@ pub fn calculate_sync_deltas(
     // This keeps the bandwidth-efficient grouped structure
     let groups: Vec<SessionDeltaGroup> = row_visibility_to_sessions
         .into_iter()
-        .map(|(visible_rows, session_ids)| {
+        .map(|((visible_rows, removed_rows), session_ids)| {
             // Group visible rows by table
             let mut table_to_row_indices: HashMap<usize, Vec<usize>> = HashMap::new();
             for (group_idx, row_idx) in visible_rows {
@@ -453,12 +630,49 @@ pub fn calculate_sync_deltas(
                     table_name: original_group.table_name.clone(),
                     headers: original_group.headers.clone(),
                     rows: filtered_rows,
+                    previous: None,
                 });
             }
 
+            // Removal keys, grouped by table in the order the tables were affected
+            let mut removed: Vec<RemovedRowGroup> = Vec::new();
+            for (group_idx, previous_idx) in removed_rows {
+                let original_group = &affected_row_groups[group_idx];
+                let Some(previous) = &original_group.previous else {
+                    continue;
+                };
+                let previous_obj =
+                    row_array_to_object(&previous.headers, &previous.rows[previous_idx]);
+                let key: Map<String, JsonValue> = primary_keys
+                    .get(&original_group.table_name)
+                    .map(|primary_key| {
+                        primary_key
+                            .iter()
+                            .map(|column| {
+                                let value =
+                                    previous_obj.get(column).cloned().unwrap_or(JsonValue::Null);
+                                (column.clone(), value)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                match removed
+                    .iter_mut()
+                    .find(|group| group.table_name == original_group.table_name)
+                {
+                    Some(group) => group.keys.push(JsonValue::Object(key)),
+                    None => removed.push(RemovedRowGroup {
+                        table_name: original_group.table_name.clone(),
+                        keys: vec![JsonValue::Object(key)],
+                    }),
+                }
+            }
+
             SessionDeltaGroup {
                 session_ids,
                 table_groups,
+                removed,
             }
         })
         .collect();
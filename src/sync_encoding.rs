@@ -0,0 +1,262 @@
+//! A compact encoding for catchup pages and live deltas.
+//!
+//! JSON catchup pages send each row as an object, repeating every column name. The
+//! compact layout lists a table's columns once in `headers` and each row as an array,
+//! like `AffectedRowTableGroup` already does for deltas. It is encoded as MessagePack,
+//! optionally gzip compressed.
+use crate::sync::{SyncPageResult, TableSyncData};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const MESSAGEPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// How a sync response body is encoded, as negotiated with `Accept` and
+/// `Accept-Encoding`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncEncoding {
+    pub format: SyncFormat,
+    pub gzip: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncFormat {
+    #[default]
+    Json,
+    /// MessagePack, with catchup pages in the compact layout.
+    MessagePack,
+}
+
+impl SyncEncoding {
+    /// The encoding for a request's `Accept` and `Accept-Encoding` header values.
+    pub fn negotiate(accept: Option<&str>, accept_encoding: Option<&str>) -> Self {
+        let format = match accept {
+            Some(accept)
+                if media_types(accept).any(|media_type| {
+                    media_type == MESSAGEPACK_CONTENT_TYPE || media_type == "application/x-msgpack"
+                }) =>
+            {
+                SyncFormat::MessagePack
+            }
+            _ => SyncFormat::Json,
+        };
+        SyncEncoding {
+            format,
+            gzip: accept_encoding
+                .map(|accept_encoding| media_types(accept_encoding).any(|coding| coding == "gzip"))
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self.format {
+            SyncFormat::Json => JSON_CONTENT_TYPE,
+            SyncFormat::MessagePack => MESSAGEPACK_CONTENT_TYPE,
+        }
+    }
+}
+
+/// The names in a comma separated header value, without parameters. Names sent with
+/// `q=0` are refused by the client, so they are left out.
+fn media_types(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').filter_map(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next()?.trim();
+        let refused = parts.any(|parameter| {
+            let Some((key, quality)) = parameter.split_once('=') else {
+                return false;
+            };
+            key.trim().eq_ignore_ascii_case("q") && quality.trim().parse::<f32>() == Ok(0.0)
+        });
+        (!name.is_empty() && !refused).then_some(name)
+    })
+}
+
+/// A `SyncPageResult` with each table's rows as arrays under shared `headers`.
+#[derive(Serialize, Deserialize)]
+pub struct CompactSyncPage {
+    #[serde(rename = "databaseId", skip_serializing_if = "Option::is_none")]
+    pub database_id: Option<String>,
+    #[serde(rename = "serverRevision", skip_serializing_if = "Option::is_none")]
+    pub server_revision: Option<i64>,
+    pub tables: HashMap<String, CompactTableSyncData>,
+    pub has_more: bool,
+}
+
+/// A `TableSyncData` whose rows are arrays of values in `headers` order.
+#[derive(Serialize, Deserialize)]
+pub struct CompactTableSyncData {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+    pub permission_hash: String,
+    pub last_seen_updated_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_key: Option<Map<String, JsonValue>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub primary_key: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_tombstone: Option<i64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reset: bool,
+}
+
+impl From<SyncPageResult> for CompactSyncPage {
+    fn from(page: SyncPageResult) -> Self {
+        CompactSyncPage {
+            database_id: page.database_id,
+            server_revision: page.server_revision,
+            tables: page
+                .tables
+                .into_iter()
+                .map(|(table_name, data)| (table_name, CompactTableSyncData::from(data)))
+                .collect(),
+            has_more: page.has_more,
+        }
+    }
+}
+
+impl From<CompactSyncPage> for SyncPageResult {
+    fn from(page: CompactSyncPage) -> Self {
+        SyncPageResult {
+            database_id: page.database_id,
+            server_revision: page.server_revision,
+            tables: page
+                .tables
+                .into_iter()
+                .map(|(table_name, data)| (table_name, TableSyncData::from(data)))
+                .collect(),
+            has_more: page.has_more,
+        }
+    }
+}
+
+impl From<TableSyncData> for CompactTableSyncData {
+    fn from(data: TableSyncData) -> Self {
+        // Rows of one table share their columns, but a column missing from a row is
+        // kept as null rather than assumed absent everywhere.
+        let mut headers: Vec<String> = Vec::new();
+        for row in &data.rows {
+            if let JsonValue::Object(row) = row {
+                for column in row.keys() {
+                    if !headers.contains(column) {
+                        headers.push(column.clone());
+                    }
+                }
+            }
+        }
+        let rows = data
+            .rows
+            .into_iter()
+            .map(|row| match row {
+                JsonValue::Object(mut row) => headers
+                    .iter()
+                    .map(|column| row.remove(column).unwrap_or(JsonValue::Null))
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+
+        CompactTableSyncData {
+            headers,
+            rows,
+            permission_hash: data.permission_hash,
+            last_seen_updated_at: data.last_seen_updated_at,
+            last_seen_key: data.last_seen_key,
+            primary_key: data.primary_key,
+            deleted: data.deleted,
+            last_seen_tombstone: data.last_seen_tombstone,
+            reset: data.reset,
+        }
+    }
+}
+
+impl From<CompactTableSyncData> for TableSyncData {
+    fn from(data: CompactTableSyncData) -> Self {
+        let headers = data.headers;
+        TableSyncData {
+            rows: data
+                .rows
+                .into_iter()
+                .map(|row| JsonValue::Object(headers.iter().cloned().zip(row).collect()))
+                .collect(),
+            permission_hash: data.permission_hash,
+            last_seen_updated_at: data.last_seen_updated_at,
+            last_seen_key: data.last_seen_key,
+            primary_key: data.primary_key,
+            deleted: data.deleted,
+            last_seen_tombstone: data.last_seen_tombstone,
+            reset: data.reset,
+        }
+    }
+}
+
+/// Encode `value` as JSON or MessagePack, gzip compressed if asked to.
+///
+/// Catchup pages should be converted to `CompactSyncPage` first for MessagePack.
+pub fn encode<T: Serialize>(value: &T, encoding: SyncEncoding) -> Result<Vec<u8>, Error> {
+    let bytes = match encoding.format {
+        SyncFormat::Json => serde_json::to_vec(value).map_err(Error::Json)?,
+        // Named, so structs are maps like their JSON form.
+        SyncFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(Error::Encode)?,
+    };
+    if !encoding.gzip {
+        return Ok(bytes);
+    }
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&bytes).map_err(Error::Io)?;
+    encoder.finish().map_err(Error::Io)
+}
+
+/// Decode a body written by `encode` in `format`. Gzip is detected from the bytes.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8], format: SyncFormat) -> Result<T, Error> {
+    let mut decompressed = Vec::new();
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        flate2::read::GzDecoder::new(bytes)
+            .read_to_end(&mut decompressed)
+            .map_err(Error::Io)?;
+        &decompressed
+    } else {
+        bytes
+    };
+    match format {
+        SyncFormat::Json => serde_json::from_slice(bytes).map_err(Error::Json),
+        SyncFormat::MessagePack => rmp_serde::from_slice(bytes).map_err(Error::Decode),
+    }
+}
+
+/// Decode a catchup page in either layout, as a `SyncPageResult` with object rows.
+pub fn decode_sync_page(bytes: &[u8], format: SyncFormat) -> Result<SyncPageResult, Error> {
+    match format {
+        SyncFormat::Json => decode(bytes, format),
+        SyncFormat::MessagePack => {
+            decode::<CompactSyncPage>(bytes, format).map(SyncPageResult::from)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Decode(rmp_serde::decode::Error),
+    Encode(rmp_serde::encode::Error),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Decode(error) => write!(f, "messagepack decode error: {}", error),
+            Error::Encode(error) => write!(f, "messagepack encode error: {}", error),
+            Error::Io(error) => write!(f, "compression error: {}", error),
+            Error::Json(error) => write!(f, "json error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
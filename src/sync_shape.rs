@@ -49,6 +49,7 @@ fn reshape_table_group(
         table_name: table_group.table_name.clone(),
         headers: output_headers,
         rows,
+        previous: None,
     }
 }
 
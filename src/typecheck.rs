@@ -298,6 +298,203 @@ fn is_supported_default_for_column(column: &ast::Column, value: &ast::DefaultVal
     }
 }
 
+// Builtins SQLite refuses to evaluate inside a generated column.
+const NON_DETERMINISTIC_FUNCTIONS: [&str; 6] = [
+    "random",
+    "randomblob",
+    "now",
+    "changes",
+    "total_changes",
+    "last_insert_rowid",
+];
+
+fn check_computed_column(
+    context: &Context,
+    fields: &Vec<ast::Field>,
+    column: &ast::Column,
+    expr: &ast::ComputedExpr,
+) -> Result<(), String> {
+    if column.directives.iter().any(|directive| {
+        matches!(
+            directive,
+            ast::ColumnDirective::PrimaryKey
+                | ast::ColumnDirective::Default { .. }
+                | ast::ColumnDirective::CreatedAt
+                | ast::ColumnDirective::UpdatedAt
+        )
+    }) || ast::get_primary_key_field_names(fields).contains(&column.name)
+    {
+        return Err(
+            "computed columns can't also be a primary key, have a default, or be a managed timestamp."
+                .to_string(),
+        );
+    }
+
+    let columns = ast::collect_columns(fields);
+    let expr_type = computed_expr_type(context, &columns, column, expr)?;
+    let declared = computed_column_type(&column.type_).ok_or_else(|| {
+        format!(
+            "only String, Int, Float, Bool, Date and DateTime columns can be computed, but this one is {}.",
+            column.type_.to_string()
+        )
+    })?;
+
+    let compatible = expr_type == declared
+        || (expr_type == "Int" && declared == "Float")
+        || (expr_type == "Null" && column.nullable);
+    if compatible {
+        Ok(())
+    } else {
+        Err(format!(
+            "it produces {}, but the column is declared as {}.",
+            expr_type,
+            column.type_.to_string()
+        ))
+    }
+}
+
+fn check_version_column(fields: &[ast::Field], column: &ast::Column) -> Result<(), String> {
+    if !matches!(column.type_, ast::ColumnType::Int) || column.nullable {
+        return Err(format!(
+            "it must be an Int, but this one is {}{}.",
+            column.type_.to_string(),
+            if column.nullable { "?" } else { "" }
+        ));
+    }
+
+    if column.directives.iter().any(|directive| {
+        matches!(
+            directive,
+            ast::ColumnDirective::PrimaryKey
+                | ast::ColumnDirective::Default { .. }
+                | ast::ColumnDirective::CreatedAt
+                | ast::ColumnDirective::UpdatedAt
+                | ast::ColumnDirective::Computed(_)
+        )
+    }) || ast::get_primary_key_field_names(fields).contains(&column.name)
+    {
+        return Err(
+            "version fields can't also be a primary key, have a default, be computed, or be a managed timestamp."
+                .to_string(),
+        );
+    }
+
+    match ast::get_version_column(fields) {
+        Some(first) if first.name != column.name => Err(format!(
+            "a record can only have one, and {} is already its @version field.",
+            first.name
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn computed_column_type(type_: &ast::ColumnType) -> Option<&'static str> {
+    match type_ {
+        ast::ColumnType::String | ast::ColumnType::IdUuid { .. } => Some("String"),
+        ast::ColumnType::Int | ast::ColumnType::IdInt { .. } => Some("Int"),
+        ast::ColumnType::Float => Some("Float"),
+        ast::ColumnType::Bool => Some("Bool"),
+        ast::ColumnType::Date => Some("Date"),
+        ast::ColumnType::DateTime => Some("DateTime"),
+        _ => None,
+    }
+}
+
+fn computed_expr_type(
+    context: &Context,
+    columns: &[ast::Column],
+    computed_column: &ast::Column,
+    expr: &ast::ComputedExpr,
+) -> Result<String, String> {
+    match expr {
+        ast::ComputedExpr::Column(name) => {
+            if *name == computed_column.name {
+                return Err(format!("{} can't refer to itself.", name));
+            }
+            let column = columns
+                .iter()
+                .find(|column| column.name == *name)
+                .ok_or_else(|| format!("{} isn't a field on this record.", name))?;
+            computed_column_type(&column.type_)
+                .map(|type_| type_.to_string())
+                .ok_or_else(|| {
+                    format!(
+                        "{} is a {} field, only String, Int, Float, Bool, Date and DateTime fields can be used.",
+                        name,
+                        column.type_.to_string()
+                    )
+                })
+        }
+        ast::ComputedExpr::String(_) => Ok("String".to_string()),
+        ast::ComputedExpr::Int(_) => Ok("Int".to_string()),
+        ast::ComputedExpr::Float(_) => Ok("Float".to_string()),
+        ast::ComputedExpr::Null => Ok("Null".to_string()),
+        ast::ComputedExpr::Call { name, args } => {
+            let func = context
+                .funcs
+                .get(name)
+                .ok_or_else(|| format!("{} isn't a builtin function.", name))?;
+            if NON_DETERMINISTIC_FUNCTIONS.contains(&name.as_str()) {
+                return Err(format!(
+                    "{} returns a different value each time, so it can't be stored in a column.",
+                    name
+                ));
+            }
+            if func.arg_types.len() != args.len() {
+                return Err(format!(
+                    "{} takes {} argument(s), but was given {}.",
+                    name,
+                    func.arg_types.len(),
+                    args.len()
+                ));
+            }
+            for (expected, arg) in func.arg_types.iter().zip(args) {
+                let arg_type = computed_expr_type(context, columns, computed_column, arg)?;
+                let accepted = arg_type == "Null"
+                    || *expected == arg_type
+                    || (expected == "number" && (arg_type == "Int" || arg_type == "Float"));
+                if !accepted {
+                    return Err(format!(
+                        "{} expects {}, but was given {}.",
+                        name, expected, arg_type
+                    ));
+                }
+            }
+            Ok(func.return_type.clone())
+        }
+        ast::ComputedExpr::Binary { op, left, right } => {
+            let left_type = computed_expr_type(context, columns, computed_column, left)?;
+            let right_type = computed_expr_type(context, columns, computed_column, right)?;
+            match op {
+                ast::ComputedOperator::Concat => {
+                    for operand in [&left_type, &right_type] {
+                        if !matches!(operand.as_str(), "String" | "Int" | "Float" | "Null") {
+                            return Err(format!("++ can't join a {} value.", operand));
+                        }
+                    }
+                    Ok("String".to_string())
+                }
+                _ => {
+                    for operand in [&left_type, &right_type] {
+                        if !matches!(operand.as_str(), "Int" | "Float" | "Null") {
+                            return Err(format!(
+                                "{} only works on numbers, but was given {}.",
+                                op.to_pyre(),
+                                operand
+                            ));
+                        }
+                    }
+                    if left_type == "Float" || right_type == "Float" {
+                        Ok("Float".to_string())
+                    } else {
+                        Ok("Int".to_string())
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn invalid_type_usage_error(
     filepath: &str,
     message: String,
@@ -1060,7 +1257,7 @@ pub fn populate_context(database: &ast::Database) -> Result<Context, Vec<Error>>
                                                 filepath: file.path.clone(),
                                                 error_type: ErrorType::MultiplePrimaryKeys {
                                                     record: name.clone(),
-                                                    field: name.clone(),
+                                                    field: column.name.clone(),
                                                 },
                                                 locations: vec![Location {
                                                     contexts: to_range(&start, &end),
@@ -1190,6 +1387,25 @@ pub fn populate_context(database: &ast::Database) -> Result<Context, Vec<Error>>
                                     field_names.insert(name.clone());
                                 }
 
+                                ast::Field::FieldDirective(ast::FieldDirective::PrimaryKey(
+                                    key_fields,
+                                )) => {
+                                    if has_primary_id {
+                                        errors.push(Error {
+                                            filepath: file.path.clone(),
+                                            error_type: ErrorType::MultiplePrimaryKeys {
+                                                record: name.clone(),
+                                                field: format!("@id({})", key_fields.join(", ")),
+                                            },
+                                            locations: vec![Location {
+                                                contexts: vec![],
+                                                primary: to_range(start, end),
+                                            }],
+                                        });
+                                    }
+                                    has_primary_id = true;
+                                }
+
                                 ast::Field::FieldDirective(ast::FieldDirective::TableName((
                                     tablename_range,
                                     tablename,
@@ -1314,6 +1530,21 @@ pub fn populate_context(database: &ast::Database) -> Result<Context, Vec<Error>>
                                         }
                                     }
 
+                                    if link.local_ids.len() != link.foreign.fields.len() {
+                                        errors.push(Error {
+                                            filepath: file.path.clone(),
+                                            error_type: ErrorType::LinkColumnCountMismatch {
+                                                link_name: link.link_name.clone(),
+                                                local_ids: link.local_ids.clone(),
+                                                foreign_fields: link.foreign.fields.clone(),
+                                            },
+                                            locations: vec![Location {
+                                                contexts: to_range(start, end),
+                                                primary: to_range(&link.start_name, &link.end_name),
+                                            }],
+                                        });
+                                    }
+
                                     // Check that the local ids exist
                                     for local_id in &link.local_ids {
                                         if !fields.iter().any(|f| ast::has_fieldname(f, local_id)) {
@@ -1470,6 +1701,30 @@ pub fn populate_context(database: &ast::Database) -> Result<Context, Vec<Error>>
                                 }],
                             });
                         }
+
+                        if ast::is_without_rowid(&fields) {
+                            let auto_key = fields.iter().find_map(|field| match field {
+                                ast::Field::Column(column)
+                                    if ast::is_integer_primary_key(column) =>
+                                {
+                                    Some(column)
+                                }
+                                _ => None,
+                            });
+                            if let Some(column) = auto_key {
+                                errors.push(Error {
+                                    filepath: file.path.clone(),
+                                    error_type: ErrorType::WithoutRowidAutoIncrementKey {
+                                        record: name.clone(),
+                                        field: column.name.clone(),
+                                    },
+                                    locations: vec![Location {
+                                        contexts: to_range(start, end),
+                                        primary: to_range(&column.start_name, &column.end_name),
+                                    }],
+                                });
+                            }
+                        }
                     }
 
                     _ => {}
@@ -1595,6 +1850,40 @@ fn check_schema_definitions(context: &Context, database: &ast::Database, errors:
                                     });
                                 }
 
+                                if matches!(directive, ast::ColumnDirective::Version) {
+                                    if let Err(message) = check_version_column(&fields, &column) {
+                                        errors.push(Error {
+                                            filepath: file.path.clone(),
+                                            error_type: ErrorType::InvalidVersionField {
+                                                field_name: column.name.clone(),
+                                                message,
+                                            },
+                                            locations: vec![Location {
+                                                contexts: to_range(start, end),
+                                                primary: to_range(&column.start, &column.end),
+                                            }],
+                                        });
+                                    }
+                                }
+
+                                if let ast::ColumnDirective::Computed(expr) = directive {
+                                    if let Err(message) =
+                                        check_computed_column(context, &fields, &column, expr)
+                                    {
+                                        errors.push(Error {
+                                            filepath: file.path.clone(),
+                                            error_type: ErrorType::InvalidComputedColumn {
+                                                field_name: column.name.clone(),
+                                                message,
+                                            },
+                                            locations: vec![Location {
+                                                contexts: to_range(start, end),
+                                                primary: to_range(&column.start, &column.end),
+                                            }],
+                                        });
+                                    }
+                                }
+
                                 if let ast::ColumnDirective::Default {
                                     value,
                                     start: default_start,
@@ -2416,6 +2705,46 @@ fn check_record_indexes(record: &ast::RecordDetails, filepath: &String, errors:
             );
         }
     }
+
+    for field in &record.fields {
+        let ast::Field::FieldDirective(ast::FieldDirective::PrimaryKey(names)) = field else {
+            continue;
+        };
+
+        let mut seen = HashSet::new();
+        for name in names {
+            if !known_fields.contains(name) {
+                errors.push(Error {
+                    filepath: filepath.clone(),
+                    error_type: ErrorType::InvalidRecordIndexField {
+                        record: record.name.clone(),
+                        directive: "@id".to_string(),
+                        field: name.clone(),
+                        known_fields: known_fields.clone(),
+                    },
+                    locations: vec![Location {
+                        contexts: to_range(&record.start, &record.end),
+                        primary: vec![],
+                    }],
+                });
+            }
+
+            if !seen.insert(name.clone()) {
+                errors.push(Error {
+                    filepath: filepath.clone(),
+                    error_type: ErrorType::DuplicateRecordIndexField {
+                        record: record.name.clone(),
+                        directive: "@id".to_string(),
+                        field: name.clone(),
+                    },
+                    locations: vec![Location {
+                        contexts: to_range(&record.start, &record.end),
+                        primary: vec![],
+                    }],
+                });
+            }
+        }
+    }
 }
 
 fn check_record_index_where(
@@ -3141,6 +3470,7 @@ fn check_table_query(
 
     let mut limits: Vec<Range> = vec![];
     let mut wheres: Vec<Range> = vec![];
+    let mut version_checks: Vec<Range> = vec![];
     let mut has_nested_selected = false;
 
     // We've already checked that the top-level query field name is valid
@@ -3188,6 +3518,24 @@ fn check_table_query(
                             &where_args,
                         );
                     }
+                    ast::Arg::IfVersion(version_val) => {
+                        if let Some(range) = to_single_range(&arg.start, &arg.end) {
+                            version_checks.push(range);
+                        }
+
+                        check_value(
+                            context,
+                            query_context,
+                            version_val,
+                            &arg.start,
+                            &arg.end,
+                            errors,
+                            params,
+                            &table.record.name,
+                            "Int",
+                            false,
+                        );
+                    }
                     _ => (),
                 }
             }
@@ -3380,6 +3728,37 @@ fn check_table_query(
         });
     }
 
+    if !version_checks.is_empty() {
+        let message = if *operation != ast::QueryOperation::Update {
+            Some("only update queries can check a version.".to_string())
+        } else if through_link.is_some() {
+            Some("it can only check the record the update changes.".to_string())
+        } else if ast::get_version_column(&table.record.fields).is_none() {
+            Some(format!(
+                "{} has no @version field to check.",
+                table.record.name
+            ))
+        } else if version_checks.len() > 1 {
+            Some("only one @ifVersion is allowed.".to_string())
+        } else {
+            None
+        };
+
+        if let Some(message) = message {
+            errors.push(Error {
+                filepath: context.current_filepath.clone(),
+                error_type: ErrorType::InvalidVersionCheck {
+                    query: query.name.clone(),
+                    message,
+                },
+                locations: vec![Location {
+                    contexts: to_range(&query.start, &query.end),
+                    primary: version_checks,
+                }],
+            });
+        }
+    }
+
     match operation {
         ast::QueryOperation::Insert => {
             let mut missing_fields = vec![];
@@ -3406,10 +3785,12 @@ fn check_table_query(
                 if ast::is_integer_primary_key(&col)
                     || ast::has_default_value(&col)
                     || ast::is_managed_timestamp(&col)
+                    || ast::is_computed(&col)
+                    || ast::is_version(&col)
                     || through_link.map_or(false, |link| link.foreign.fields.contains(&col.name))
                 {
-                    // Integer primary keys, fields with defaults, managed timestamps, and nested
-                    // foreign keys are set automatically.
+                    // Integer primary keys, fields with defaults, managed timestamps, computed
+                    // and version columns, and nested foreign keys are set automatically.
                     continue;
                 }
 
@@ -3469,7 +3850,9 @@ fn check_field(
             if matches!(
                 operation,
                 ast::QueryOperation::Insert | ast::QueryOperation::Update
-            ) && ast::is_managed_timestamp(column)
+            ) && (ast::is_managed_timestamp(column)
+                || ast::is_computed(column)
+                || ast::is_version(column))
             {
                 errors.push(Error {
                     filepath: context.current_filepath.clone(),
@@ -3735,16 +4118,14 @@ fn check_link(
 ) {
     match operation {
         ast::QueryOperation::Insert => {
-            // Nested inserts are only allowed if the local_id is a primary key
-            let primary_key_field_name = ast::get_primary_id_field_name(&local_table.fields);
-
-            match primary_key_field_name {
-                None => (),
-                Some(primary_key_name) => {
-                    let are_primary = link
-                        .local_ids
-                        .iter()
-                        .all(|s: &String| s == &primary_key_name);
+            // Nested inserts are only allowed if the local_id is a single-column primary key
+            let primary_key = ast::get_primary_key_field_names(&local_table.fields);
+
+            match primary_key.as_slice() {
+                [] => (),
+                primary_key => {
+                    let are_primary = primary_key.len() == 1
+                        && link.local_ids.iter().all(|s: &String| s == &primary_key[0]);
                     if !are_primary {
                         errors.push(Error {
                             filepath: context.current_filepath.clone(),
@@ -33,7 +33,10 @@ record TestRecord {{
             ast::ColumnDirective::Index => "@index",
             ast::ColumnDirective::CreatedAt => "@createdAt",
             ast::ColumnDirective::UpdatedAt => "@updatedAt",
-            ast::ColumnDirective::Default { .. } => "",
+            ast::ColumnDirective::Version => "@version",
+            ast::ColumnDirective::Default { .. }
+            | ast::ColumnDirective::Collate(_)
+            | ast::ColumnDirective::Computed(_) => "",
         }
     );
 
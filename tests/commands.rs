@@ -959,12 +959,721 @@ query GetUsers {
     let health: serde_json::Value = serde_json::from_str(&health_body).unwrap();
     assert_eq!(health["ok"], serde_json::json!(true));
     assert_eq!(health["databaseId"], serde_json::json!("default"));
+    assert_eq!(health["connections"]["open"], serde_json::json!(0));
+    assert_eq!(
+        health["connections"]["droppedMessages"],
+        serde_json::json!(0)
+    );
 
     let (query_status, query_body) =
         http_request(port, "POST", &format!("/db/{}", query_id), Some("{}"));
     assert_eq!(query_status, 200, "query body: {}", query_body);
     let result: serde_json::Value = serde_json::from_str(&query_body).unwrap();
     assert_eq!(result["user"], serde_json::json!([]));
+
+    let batch = serde_json::json!({
+        "transaction": true,
+        "queries": [
+            { "queryId": query_id, "input": {} },
+            { "queryId": "missing", "input": {} },
+        ],
+    });
+    let (batch_status, batch_body) =
+        http_request(port, "POST", "/db/batch", Some(&batch.to_string()));
+    assert_eq!(batch_status, 200, "batch body: {}", batch_body);
+    let batch: serde_json::Value = serde_json::from_str(&batch_body).unwrap();
+    assert_eq!(batch["committed"], serde_json::json!(false));
+    assert_eq!(batch["results"][0]["ok"], serde_json::json!(false));
+    assert!(batch["results"][1]["error"]
+        .as_str()
+        .unwrap()
+        .contains("unknown query"));
+}
+
+#[test]
+fn test_serve_routes_requests_by_database_id() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+query GetUsers {
+    user {
+        id
+        name
+    }
+}
+
+insert CreateUser($name: String) {
+    user {
+        name = $name
+    }
+}
+        "#,
+    )
+    .unwrap();
+
+    for tenant in ["acme", "globex"] {
+        let db_path = ctx.workspace_path.join(format!("db/tenants/{}.db", tenant));
+        ctx.run_command("migrate")
+            .arg(db_path.to_str().unwrap())
+            .arg("--push")
+            .assert()
+            .success();
+    }
+    ctx.run_command("generate").assert().success();
+
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+    let query_id_for = |operation: &str| {
+        manifest["queries"]
+            .as_object()
+            .unwrap()
+            .values()
+            .find(|query| query["operation"] == operation)
+            .and_then(|query| query["id"].as_str())
+            .expect("generated query id")
+            .to_string()
+    };
+    let get_users = query_id_for("query");
+    let create_user = query_id_for("insert");
+
+    let port = free_loopback_port();
+    let mut command = StdCommand::new(assert_cmd::cargo::cargo_bin("pyre"));
+    command
+        .current_dir(&ctx.workspace_path)
+        .arg("serve")
+        .arg("db/tenants/{databaseId}.db")
+        .arg("--port")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let child = command.spawn().unwrap();
+    let _server = ServerGuard { child };
+
+    wait_for_health(port);
+
+    let (health_status, health_body) = http_request(port, "GET", "/health?databaseId=acme", None);
+    assert_eq!(health_status, 200, "health body: {}", health_body);
+    let health: serde_json::Value = serde_json::from_str(&health_body).unwrap();
+    assert_eq!(health["databaseId"], serde_json::json!("acme"));
+
+    let (create_status, create_body) = http_request(
+        port,
+        "POST",
+        &format!("/db/{}?databaseId=acme", create_user),
+        Some(r#"{"name":"Ada"}"#),
+    );
+    assert_eq!(create_status, 200, "create body: {}", create_body);
+
+    let users_in = |database_id: &str| {
+        let (status, body) = http_request(
+            port,
+            "POST",
+            &format!("/db/{}?databaseId={}", get_users, database_id),
+            Some("{}"),
+        );
+        assert_eq!(status, 200, "query body: {}", body);
+        let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+        result["user"].as_array().unwrap().len()
+    };
+    assert_eq!(users_in("acme"), 1);
+    assert_eq!(users_in("globex"), 0);
+
+    let (unknown_status, _) = http_request(
+        port,
+        "POST",
+        &format!("/db/{}?databaseId=initech", get_users),
+        Some("{}"),
+    );
+    assert_eq!(unknown_status, 404);
+
+    let (traversal_status, _) = http_request(
+        port,
+        "POST",
+        &format!("/db/{}?databaseId=..%2Facme", get_users),
+        Some("{}"),
+    );
+    assert_eq!(traversal_status, 400);
+
+    let (missing_status, _) = http_request(port, "POST", &format!("/db/{}", get_users), Some("{}"));
+    assert_eq!(missing_status, 400);
+}
+
+fn read_socket_message(
+    socket: &mut tungstenite::WebSocket<TcpStream>,
+    message_type: &str,
+) -> serde_json::Value {
+    loop {
+        match socket.read().unwrap() {
+            tungstenite::Message::Text(text) => {
+                let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+                if message["type"] == message_type {
+                    return message;
+                }
+            }
+            tungstenite::Message::Close(frame) => {
+                panic!("socket closed waiting for {}: {:?}", message_type, frame)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn send_socket_message(socket: &mut tungstenite::WebSocket<TcpStream>, message: serde_json::Value) {
+    socket
+        .send(tungstenite::Message::Text(message.to_string()))
+        .unwrap();
+}
+
+#[test]
+fn test_serve_websocket_runs_mutations_and_pushes_deltas() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+insert CreateUser($name: String) {
+    user {
+        name = $name
+    }
+}
+        "#,
+    )
+    .unwrap();
+
+    let db_path = ctx.workspace_path.join("db/app.db");
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    ctx.run_command("generate").assert().success();
+
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+    let create_user = manifest["queries"]
+        .as_object()
+        .unwrap()
+        .values()
+        .find(|query| query["operation"] == "insert")
+        .and_then(|query| query["id"].as_str())
+        .expect("generated insert id")
+        .to_string();
+
+    let port = free_loopback_port();
+    let mut command = StdCommand::new(assert_cmd::cargo::cargo_bin("pyre"));
+    command
+        .current_dir(&ctx.workspace_path)
+        .arg("serve")
+        .arg(db_path.to_str().unwrap())
+        .arg("--port")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let child = command.spawn().unwrap();
+    let _server = ServerGuard { child };
+
+    wait_for_health(port);
+
+    let connect = || {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let (socket, _) =
+            tungstenite::client(format!("ws://127.0.0.1:{}/sync/ws", port), stream).unwrap();
+        socket
+    };
+    let mut writer = connect();
+    let mut watcher = connect();
+    let writer_id = read_socket_message(&mut writer, "connected")["connectionId"].clone();
+    let watcher_connected = read_socket_message(&mut watcher, "connected");
+    assert_ne!(watcher_connected["connectionId"], writer_id);
+    assert_eq!(
+        watcher_connected["databaseId"],
+        serde_json::json!("default")
+    );
+
+    send_socket_message(&mut watcher, serde_json::json!({ "type": "ping", "id": 1 }));
+    assert_eq!(
+        read_socket_message(&mut watcher, "pong")["id"],
+        serde_json::json!(1)
+    );
+
+    send_socket_message(
+        &mut watcher,
+        serde_json::json!({ "type": "catchup", "id": "c", "syncCursor": {} }),
+    );
+    let catchup = read_socket_message(&mut watcher, "catchup");
+    assert_eq!(catchup["id"], serde_json::json!("c"));
+    assert!(catchup["result"].is_object(), "catchup: {}", catchup);
+
+    send_socket_message(
+        &mut writer,
+        serde_json::json!({
+            "type": "run",
+            "id": 7,
+            "queryId": create_user,
+            "input": { "name": "Ada" },
+            "sync": true,
+        }),
+    );
+    let result = read_socket_message(&mut writer, "result");
+    assert_eq!(result["id"], serde_json::json!(7));
+
+    let delta = read_socket_message(&mut watcher, "delta");
+    assert!(delta.to_string().contains("Ada"), "delta: {}", delta);
+
+    send_socket_message(
+        &mut writer,
+        serde_json::json!({ "type": "run", "id": 8, "queryId": "missing" }),
+    );
+    let error = read_socket_message(&mut writer, "error");
+    assert_eq!(error["id"], serde_json::json!(8));
+}
+
+fn open_sse(port: u16, last_event_id: Option<&str>) -> TcpStream {
+    let last_event_id = last_event_id
+        .map(|id| format!("Last-Event-ID: {}\r\n", id))
+        .unwrap_or_default();
+    let request = format!(
+        "GET /sync/events HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nAccept: text/event-stream\r\n{last_event_id}\r\n"
+    );
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    stream
+}
+
+fn read_stream_until(stream: &mut TcpStream, until: &str) -> String {
+    let mut received = String::new();
+    let mut buffer = [0; 4096];
+    while !received.contains(until) {
+        let read = stream.read(&mut buffer).expect("SSE stream timed out");
+        assert!(read > 0, "SSE stream closed: {}", received);
+        received.push_str(&String::from_utf8_lossy(&buffer[..read]));
+    }
+    received
+}
+
+/// Open `/sync/events` and read until `until` appears in the stream.
+fn read_sse_until(port: u16, last_event_id: Option<&str>, until: &str) -> String {
+    read_stream_until(&mut open_sse(port, last_event_id), until)
+}
+
+fn spawn_serve(ctx: &TestContext, database: &str, port: u16, args: &[&str]) -> ServerGuard {
+    let mut command = StdCommand::new(assert_cmd::cargo::cargo_bin("pyre"));
+    command
+        .current_dir(&ctx.workspace_path)
+        .arg("serve")
+        .arg(database)
+        .arg("--port")
+        .arg(port.to_string())
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let child = command.spawn().unwrap();
+    ServerGuard { child }
+}
+
+#[test]
+fn test_serve_replays_missed_deltas_after_last_event_id() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+insert CreateUser($name: String) {
+    user {
+        name = $name
+    }
+}
+        "#,
+    )
+    .unwrap();
+
+    let db_path = ctx.workspace_path.join("db/app.db");
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    ctx.run_command("generate").assert().success();
+
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+    let create_user = manifest["queries"]
+        .as_object()
+        .unwrap()
+        .values()
+        .find(|query| query["operation"] == "insert")
+        .and_then(|query| query["id"].as_str())
+        .expect("generated insert id")
+        .to_string();
+
+    let port = free_loopback_port();
+    let mut command = StdCommand::new(assert_cmd::cargo::cargo_bin("pyre"));
+    command
+        .current_dir(&ctx.workspace_path)
+        .arg("serve")
+        .arg(db_path.to_str().unwrap())
+        .arg("--port")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let child = command.spawn().unwrap();
+    let _server = ServerGuard { child };
+
+    wait_for_health(port);
+
+    for name in ["Ada", "Grace"] {
+        let (status, body) = http_request(
+            port,
+            "POST",
+            &format!("/db/{}?sync=true", create_user),
+            Some(&format!(r#"{{"name":"{}"}}"#, name)),
+        );
+        assert_eq!(status, 200, "insert body: {}", body);
+    }
+
+    let replayed = read_sse_until(port, Some("1"), "Grace");
+    assert!(!replayed.contains("Ada"), "replayed: {}", replayed);
+    assert!(replayed.contains("id: 2") || replayed.contains("id:2"));
+
+    let too_new = read_sse_until(port, Some("99"), "syncRequired");
+    assert!(!too_new.contains("Grace"), "replayed: {}", too_new);
+}
+
+#[test]
+fn test_serve_change_log_fans_out_deltas_across_processes() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+insert CreateUser($name: String) {
+    user {
+        name = $name
+    }
+}
+        "#,
+    )
+    .unwrap();
+
+    let db_path = ctx.workspace_path.join("db/app.db");
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    ctx.run_command("generate").assert().success();
+
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+    let create_user = manifest["queries"]
+        .as_object()
+        .unwrap()
+        .values()
+        .find(|query| query["operation"] == "insert")
+        .and_then(|query| query["id"].as_str())
+        .expect("generated insert id")
+        .to_string();
+
+    let change_log_args = ["--change-log", "--change-log-poll-ms", "20"];
+    let writer_port = free_loopback_port();
+    let _writer = spawn_serve(
+        &ctx,
+        db_path.to_str().unwrap(),
+        writer_port,
+        &change_log_args,
+    );
+    let reader_port = free_loopback_port();
+    let _reader = spawn_serve(
+        &ctx,
+        db_path.to_str().unwrap(),
+        reader_port,
+        &change_log_args,
+    );
+    wait_for_health(writer_port);
+    wait_for_health(reader_port);
+
+    let mut events = open_sse(reader_port, None);
+    read_stream_until(&mut events, "connected");
+
+    let (status, body) = http_request(
+        writer_port,
+        "POST",
+        &format!("/db/{}?sync=true", create_user),
+        Some(r#"{"name":"Ada"}"#),
+    );
+    assert_eq!(status, 200, "insert body: {}", body);
+
+    let received = read_stream_until(&mut events, "Ada");
+    assert!(received.contains("id: 1") || received.contains("id:1"));
+
+    // The reader also keeps the change for reconnecting clients.
+    let replayed = read_sse_until(reader_port, Some("0"), "Ada");
+    assert!(!replayed.contains("syncRequired"), "replayed: {}", replayed);
+}
+
+fn query_id_for(ctx: &TestContext, operation: &str) -> Option<String> {
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(manifest_path).unwrap()).unwrap();
+    manifest["queries"]
+        .as_object()
+        .unwrap()
+        .values()
+        .find(|query| query["operation"] == operation)
+        .and_then(|query| query["id"].as_str())
+        .map(str::to_string)
+}
+
+#[test]
+fn test_serve_reloads_manifest_and_schema_without_restarting() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    let get_users = r#"
+query GetUsers {
+    user {
+        id
+        name
+    }
+}
+"#;
+    std::fs::write(ctx.workspace_path.join("pyre/query.pyre"), get_users).unwrap();
+
+    let db_path = ctx.workspace_path.join("db/app.db");
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    ctx.run_command("generate").assert().success();
+    let get_users_id = query_id_for(&ctx, "query").expect("generated query id");
+
+    let port = free_loopback_port();
+    let _server = spawn_serve(&ctx, db_path.to_str().unwrap(), port, &[]);
+    wait_for_health(port);
+    let mut events = open_sse(port, None);
+    read_stream_until(&mut events, "connected");
+
+    // A manifest that fails to parse is ignored.
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let manifest = std::fs::read_to_string(&manifest_path).unwrap();
+    std::fs::write(&manifest_path, "{").unwrap();
+    std::thread::sleep(Duration::from_millis(1500));
+    let (status, body) = http_request(port, "POST", &format!("/db/{}", get_users_id), Some("{}"));
+    assert_eq!(status, 200, "query body: {}", body);
+    std::fs::write(&manifest_path, manifest).unwrap();
+
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        format!(
+            r#"{}
+insert CreateUser($name: String) {{
+    user {{
+        name = $name
+    }}
+}}
+"#,
+            get_users
+        ),
+    )
+    .unwrap();
+    ctx.run_command("generate").assert().success();
+    let create_user_id = query_id_for(&ctx, "insert").expect("generated insert id");
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let (status, body) = http_request(
+            port,
+            "POST",
+            &format!("/db/{}", create_user_id),
+            Some(r#"{"name":"Ada"}"#),
+        );
+        if status == 200 {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "new query never loaded: {}",
+            body
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    // Changing the read permission tells connected clients to resync.
+    std::fs::write(
+        ctx.workspace_path.join("pyre/schema.pyre"),
+        r#"
+record User {
+    id   Int    @id
+    name String
+    @allow(query) { id == 1 }
+}
+        "#,
+    )
+    .unwrap();
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    read_stream_until(&mut events, "syncRequired");
+}
+
+#[tokio::test]
+async fn test_serve_retries_a_schema_that_failed_to_load() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+query GetUsers {
+    user {
+        id
+        name
+    }
+}
+"#,
+    )
+    .unwrap();
+
+    let db_path = ctx.workspace_path.join("db/app.db");
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    ctx.run_command("generate").assert().success();
+
+    let port = free_loopback_port();
+    let _server = spawn_serve(&ctx, db_path.to_str().unwrap(), port, &[]);
+    wait_for_health(port);
+    let mut events = open_sse(port, None);
+    read_stream_until(&mut events, "connected");
+
+    let db = libsql::Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute("insert into _pyre_schema (schema) values ('record {')", ())
+        .await
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(1500));
+
+    // Fixing the schema without a new version still gets it loaded.
+    conn.execute(
+        "update _pyre_schema set schema = ? where id = (select max(id) from _pyre_schema)",
+        libsql::params_from_iter(vec![libsql::Value::Text(
+            r#"
+record User {
+    id   Int    @id
+    name String
+    @allow(query) { id == 1 }
+}
+"#
+            .to_string(),
+        )]),
+    )
+    .await
+    .unwrap();
+    read_stream_until(&mut events, "syncRequired");
+}
+
+#[test]
+fn test_serve_routes_outdated_clients_to_previous_manifests() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+query GetUsers {
+    user {
+        id
+        name
+    }
+}
+"#,
+    )
+    .unwrap();
+
+    let db_path = ctx.workspace_path.join("db/app.db");
+    ctx.run_command("migrate")
+        .arg(db_path.to_str().unwrap())
+        .arg("--push")
+        .assert()
+        .success();
+    ctx.run_command("generate").assert().success();
+    let old_query_id = query_id_for(&ctx, "query").expect("generated query id");
+    let manifest_path = ctx.workspace_path.join("pyre/generated/manifest.json");
+    let previous_path = ctx.workspace_path.join("previous-manifest.json");
+    std::fs::copy(&manifest_path, &previous_path).unwrap();
+    let previous: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&previous_path).unwrap()).unwrap();
+    let previous_version = previous["hash"].as_str().unwrap().to_string();
+
+    std::fs::write(
+        ctx.workspace_path.join("pyre/query.pyre"),
+        r#"
+query GetUserNames {
+    user {
+        name
+    }
+}
+"#,
+    )
+    .unwrap();
+    ctx.run_command("generate").assert().success();
+    let current: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+    let current_version = current["hash"].as_str().unwrap().to_string();
+    assert_ne!(previous_version, current_version);
+    assert!(current["queries"].get(&old_query_id).is_none());
+
+    let port = free_loopback_port();
+    let _server = spawn_serve(
+        &ctx,
+        db_path.to_str().unwrap(),
+        port,
+        &["--previous-manifest", previous_path.to_str().unwrap()],
+    );
+    wait_for_health(port);
+
+    let (status, body) = http_request(port, "GET", "/health", None);
+    assert_eq!(status, 200, "health body: {}", body);
+    let health: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(
+        health["manifestVersion"],
+        serde_json::json!(current_version)
+    );
+
+    let (status, body) = http_request(
+        port,
+        "POST",
+        &format!("/db/{}?manifestVersion={}", old_query_id, previous_version),
+        Some("{}"),
+    );
+    assert_eq!(status, 200, "pinned query body: {}", body);
+
+    let (status, body) = http_request(
+        port,
+        "POST",
+        &format!("/db/{}?manifestVersion=stale", old_query_id),
+        Some("{}"),
+    );
+    assert_eq!(status, 409, "outdated query body: {}", body);
+    let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(error["code"], serde_json::json!("clientOutdated"));
+    assert_eq!(error["manifestVersion"], serde_json::json!(current_version));
 }
 
 #[test]
@@ -1174,25 +1883,102 @@ record Task {
         "tasks.status should exist after migrate --push for enum-backed fields"
     );
 
-    let mut schema_rows = conn
-        .query(
-            "select schema from _pyre_schema order by created_at desc limit 1",
-            (),
+    let mut schema_rows = conn
+        .query(
+            "select schema from _pyre_schema order by created_at desc limit 1",
+            (),
+        )
+        .await
+        .unwrap();
+
+    let schema_row = schema_rows
+        .next()
+        .await
+        .unwrap()
+        .expect("_pyre_schema should contain at least one schema row");
+    let schema_source: String = schema_row.get(0).unwrap();
+
+    assert!(
+        schema_source.contains("status TaskStatus"),
+        "_pyre_schema should store the pushed schema source"
+    );
+}
+
+#[tokio::test]
+async fn test_sync_inspect_explains_what_a_session_sees() {
+    let ctx = TestContext::new();
+    std::fs::write(
+        ctx.workspace_path.join("pyre/schema.pyre"),
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id        Int    @id
+    ownerId   Int
+    published Bool
+    updatedAt Int
+    @allow(query) { ownerId == Session.userId || published == True }
+    @allow(insert, update, delete) { ownerId == Session.userId }
+}
+        "#,
+    )
+    .unwrap();
+    ctx.run_command("migrate")
+        .arg("app.db")
+        .arg("--push")
+        .assert()
+        .success();
+
+    let db_path = ctx.workspace_path.join("app.db");
+    let db = libsql::Builder::new_local(db_path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap();
+    db.connect()
+        .unwrap()
+        .execute_batch(
+            "insert into notes (id, ownerId, published, updatedAt) values (1, 1, 0, 1), (2, 2, 0, 1), (3, 2, 1, 1);",
         )
         .await
         .unwrap();
 
-    let schema_row = schema_rows
-        .next()
-        .await
-        .unwrap()
-        .expect("_pyre_schema should contain at least one schema row");
-    let schema_source: String = schema_row.get(0).unwrap();
+    ctx.run_command("sync")
+        .args(["inspect", "app.db", "--session", r#"{"userId":1}"#])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("notes (sync layer 0)"))
+        .stdout(predicate::str::contains(
+            r#"permission       ("notes"."ownerId" = ? or "notes"."published" = 1)"#,
+        ))
+        .stdout(predicate::str::contains("params           [1]"))
+        .stdout(predicate::str::contains("rows             2 of 3 visible"));
 
-    assert!(
-        schema_source.contains("status TaskStatus"),
-        "_pyre_schema should store the pushed schema source"
-    );
+    ctx.run_command("sync")
+        .args(["inspect", "app.db", "--session", r#"{"userId":1}"#])
+        .args(["--row", "Note:2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("notes id = 2 is not visible"))
+        .stdout(predicate::str::contains(
+            r#"fail "notes"."ownerId" = ?  [1]"#,
+        ))
+        .stdout(predicate::str::contains(r#"fail "notes"."published" = 1"#));
+
+    ctx.run_command("sync")
+        .args(["inspect", "app.db", "--session", r#"{"userId":1}"#])
+        .args(["--row", "notes:3"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("notes id = 3 is visible"))
+        .stdout(predicate::str::contains(r#"pass "notes"."published" = 1"#));
+
+    ctx.run_command("sync")
+        .args(["inspect", "app.db", "--session", "{}"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("missing session field 'userId'"));
 }
 
 #[test]
@@ -1324,6 +2110,315 @@ fn test_migrate_refuses_changed_applied_migration_sql() {
         .stdout(predicate::str::contains("Create a new migration"));
 }
 
+#[tokio::test]
+async fn test_migrate_records_migration_checksums() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(".yak/yak.db")
+        .arg("init")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .assert()
+        .success();
+
+    let migration_sql = std::fs::read_to_string(first_migration_sql_path(&ctx)).unwrap();
+    let db = libsql::Builder::new_local(ctx.workspace_path.join(".yak/yak.db"))
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    let mut rows = conn
+        .query("select checksum from _pyre_migrations", ())
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    let checksum: String = row.get(0).unwrap();
+
+    assert_eq!(
+        checksum,
+        pyre::db::migrate::migration_checksum(&migration_sql)
+    );
+}
+
+#[tokio::test]
+async fn test_migrate_verify_reads_migration_tables_without_checksums() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(".yak/yak.db")
+        .arg("init")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .assert()
+        .success();
+
+    // Databases migrated before checksums were recorded have no checksum column.
+    let db = libsql::Builder::new_local(ctx.workspace_path.join(".yak/yak.db"))
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch("alter table _pyre_migrations drop column checksum;")
+        .await
+        .unwrap();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .arg("--verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Database matches 1 migration file.",
+        ));
+}
+
+#[test]
+fn test_migrate_verify_passes_for_migrated_database() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(".yak/yak.db")
+        .arg("init")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .arg("--verify")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Database matches 1 migration file.",
+        ));
+}
+
+#[tokio::test]
+async fn test_migrate_verify_reports_out_of_band_schema_changes() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(".yak/yak.db")
+        .arg("init")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .assert()
+        .success();
+
+    let db = libsql::Builder::new_local(ctx.workspace_path.join(".yak/yak.db"))
+        .build()
+        .await
+        .unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute_batch("alter table users add column nickname text;")
+        .await
+        .unwrap();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .arg("--verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Schema Drift Detected"))
+        .stdout(predicate::str::contains("unexpected column users.nickname"));
+}
+
+#[test]
+fn test_migrate_verify_refuses_changed_applied_migration_sql() {
+    let ctx = TestContext::new();
+    write_basic_schema(&ctx);
+
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(".yak/yak.db")
+        .arg("init")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .assert()
+        .success();
+
+    let migration_sql = first_migration_sql_path(&ctx);
+    let mut contents = std::fs::read_to_string(&migration_sql).unwrap();
+    contents.push_str("\n-- edited after apply\n");
+    std::fs::write(migration_sql, contents).unwrap();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .arg("--verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Applied Migration Changed"));
+}
+
+fn write_user_schema(ctx: &TestContext, fields: &str) {
+    std::fs::write(
+        ctx.workspace_path.join("pyre/schema.pyre"),
+        format!(
+            "\nrecord User {{\n    id   Int    @id\n    name String\n{}    @public\n}}\n",
+            fields
+        ),
+    )
+    .unwrap();
+}
+
+fn generate_named_migration(ctx: &TestContext, db: &str, final_name: &str) {
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(db)
+        .arg("next")
+        .assert()
+        .success();
+
+    let migration_root = ctx.workspace_path.join("pyre/migrations");
+    let generated = std::fs::read_dir(&migration_root)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.is_dir() && path.to_string_lossy().ends_with("_next"))
+        .expect("expected a generated migration folder");
+    std::fs::rename(generated, migration_root.join(final_name)).unwrap();
+}
+
+fn write_squashable_migrations(ctx: &TestContext) {
+    write_user_schema(ctx, "");
+    generate_named_migration(ctx, ".yak/already-applied.db", "202601010000_init");
+    ctx.run_command("migrate")
+        .arg(".yak/already-applied.db")
+        .assert()
+        .success();
+
+    write_user_schema(ctx, "    email String?\n");
+    generate_named_migration(ctx, ".yak/already-applied.db", "202601020000_add_email");
+    ctx.run_command("migrate")
+        .arg(".yak/already-applied.db")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_squash_replaces_migrations_with_baseline() {
+    let ctx = TestContext::new();
+    write_squashable_migrations(&ctx);
+
+    ctx.run_command("squash")
+        .arg("--until")
+        .arg("202601020000_add_email")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Squashed 2 migrations into 202601020000_baseline.",
+        ));
+
+    let migration_root = ctx.workspace_path.join("pyre/migrations");
+    assert!(!migration_root.join("202601010000_init").exists());
+    let baseline =
+        std::fs::read_to_string(migration_root.join("202601020000_baseline/migration.sql"))
+            .unwrap();
+    assert!(baseline.contains("-- pyre:squashes 202601010000_init"));
+    assert!(baseline.contains("-- pyre:squashes 202601020000_add_email"));
+    assert!(baseline.contains("`email`"));
+
+    // Databases that applied the squashed migrations do not re-run anything.
+    ctx.run_command("migrate")
+        .arg(".yak/already-applied.db")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Up to date, nothing applied."));
+
+    // Fresh databases run the baseline.
+    ctx.run_command("migrate")
+        .arg(".yak/fresh.db")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 migration applied."));
+
+    ctx.run_command("migrate")
+        .arg(".yak/fresh.db")
+        .arg("--verify")
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_migrate_refuses_baseline_over_partially_applied_migrations() {
+    let ctx = TestContext::new();
+    write_user_schema(&ctx, "");
+    generate_named_migration(&ctx, ".yak/already-applied.db", "202601010000_init");
+    ctx.run_command("migrate")
+        .arg(".yak/already-applied.db")
+        .assert()
+        .success();
+    ctx.run_command("migrate")
+        .arg(".yak/partially-applied.db")
+        .assert()
+        .success();
+
+    write_user_schema(&ctx, "    email String?\n");
+    generate_named_migration(&ctx, ".yak/already-applied.db", "202601020000_add_email");
+    ctx.run_command("migrate")
+        .arg(".yak/already-applied.db")
+        .assert()
+        .success();
+
+    ctx.run_command("squash")
+        .arg("--until")
+        .arg("202601020000_add_email")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/partially-applied.db")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Partially Applied Baseline"))
+        .stdout(predicate::str::contains("202601020000_add_email"));
+
+    ctx.run_command("migrate")
+        .arg(".yak/partially-applied.db")
+        .arg("--verify")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Partially Applied Baseline"));
+}
+
+#[test]
+fn test_squash_rejects_unknown_migration() {
+    let ctx = TestContext::new();
+    write_squashable_migrations(&ctx);
+
+    ctx.run_command("squash")
+        .arg("--until")
+        .arg("202601030000_missing")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Unknown Migration"))
+        .stdout(predicate::str::contains("202601010000_init"));
+}
+
 #[tokio::test]
 async fn test_migrate_fails_on_non_pyre_database_with_guidance() {
     let ctx = TestContext::new();
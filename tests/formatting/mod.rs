@@ -339,6 +339,7 @@ fn field_directive_equal_ignoring_locations(
         (ast::FieldDirective::Permissions(pa), ast::FieldDirective::Permissions(pb)) => {
             permission_details_equal_ignoring_locations(pa, pb)
         }
+        (ast::FieldDirective::PrimaryKey(ka), ast::FieldDirective::PrimaryKey(kb)) => ka == kb,
         _ => false,
     }
 }
@@ -617,7 +618,7 @@ fn arg_fields_equal_ignoring_locations(a: &Vec<ast::ArgField>, b: &Vec<ast::ArgF
     for f in a_fields.iter() {
         match f {
             ast::ArgField::Arg(located_arg) => match &located_arg.arg {
-                ast::Arg::Limit(_) => a_limits.push(f),
+                ast::Arg::Limit(_) | ast::Arg::IfVersion(_) => a_limits.push(f),
                 ast::Arg::OrderBy(_, _) => a_sorts.push(f),
                 ast::Arg::Where(_) => a_wheres.push(f),
             },
@@ -630,7 +631,7 @@ fn arg_fields_equal_ignoring_locations(a: &Vec<ast::ArgField>, b: &Vec<ast::ArgF
     for f in b_fields.iter() {
         match f {
             ast::ArgField::Arg(located_arg) => match &located_arg.arg {
-                ast::Arg::Limit(_) => b_limits.push(f),
+                ast::Arg::Limit(_) | ast::Arg::IfVersion(_) => b_limits.push(f),
                 ast::Arg::OrderBy(_, _) => b_sorts.push(f),
                 ast::Arg::Where(_) => b_wheres.push(f),
             },
@@ -733,6 +734,9 @@ fn arg_field_equal_ignoring_locations(a: &ast::ArgField, b: &ast::ArgField) -> b
 fn arg_equal_ignoring_locations(a: &ast::Arg, b: &ast::Arg) -> bool {
     match (a, b) {
         (ast::Arg::Limit(va), ast::Arg::Limit(vb)) => query_value_equal_ignoring_locations(va, vb),
+        (ast::Arg::IfVersion(va), ast::Arg::IfVersion(vb)) => {
+            query_value_equal_ignoring_locations(va, vb)
+        }
         (ast::Arg::OrderBy(da, sa), ast::Arg::OrderBy(db, sb)) => match (da, db) {
             (ast::Direction::Asc, ast::Direction::Asc)
             | (ast::Direction::Desc, ast::Direction::Desc) => sa == sb,
@@ -1000,6 +1004,33 @@ record Membership {
     round_trip_schema(schema_source);
 }
 
+#[test]
+fn test_schema_round_trip_composite_links() {
+    let schema_source = r#"
+record Membership {
+    @public
+    @id(orgId, userId)
+
+    orgId  Int
+    userId Int
+
+    grants @link(orgId, userId, Grant.orgId, Grant.userId)
+}
+
+record Grant {
+    @public
+
+    id     Int @id
+    orgId  Int
+    userId Int
+
+    membership @link(orgId, userId, Membership.orgId, Membership.userId)
+}
+    "#;
+
+    round_trip_schema(schema_source);
+}
+
 #[test]
 fn test_schema_round_trip_permissions() {
     let schema_source = r#"
@@ -1,5 +1,6 @@
 pub mod error;
 pub mod schema;
+pub mod server;
 pub mod test_database;
 
 pub use error::TestError;
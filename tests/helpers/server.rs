@@ -0,0 +1,55 @@
+use axum::http::HeaderMap;
+use pyre::server::http::HttpError;
+use pyre::server::manifest::Manifest;
+use serde_json::{json, Value as JsonValue};
+
+/// Build the query manifest the server loads for `query_source`,
+/// optionally with the generated CRUD queries appended.
+pub fn manifest_for(
+    context: &pyre::typecheck::Context,
+    query_source: &str,
+    include_generated_crud: bool,
+) -> Result<Manifest, Box<dyn std::error::Error>> {
+    let mut query_list = if query_source.trim().is_empty() {
+        pyre::ast::QueryList {
+            queries: Vec::new(),
+        }
+    } else {
+        pyre::parser::parse_query("query.pyre", query_source)
+            .map_err(|err| format!("query parse failed: {:?}", err))?
+    };
+
+    if include_generated_crud {
+        pyre::generated_queries::append_generated_crud_queries(&mut query_list, context);
+    }
+
+    let query_info = pyre::typecheck::check_queries(&query_list, context)
+        .map_err(|errors| format!("query typecheck failed: {:?}", errors))?;
+    let mut files = Vec::new();
+    pyre::generate::manifest::generate_queries(context, &query_list, &query_info, &mut files);
+    let manifest_file = files
+        .into_iter()
+        .find(|file| file.path == std::path::Path::new("manifest.json"))
+        .ok_or("manifest file should be generated")?;
+
+    Ok(serde_json::from_str(&manifest_file.contents)?)
+}
+
+pub fn query_id(manifest: &Manifest, operation: &str) -> String {
+    manifest
+        .queries
+        .values()
+        .find(|query| query.operation == operation)
+        .map(|query| query.id.clone())
+        .expect("manifest should contain the operation")
+}
+
+/// A session resolver that reads the user id from an `x-user` header.
+pub fn user_session(headers: &HeaderMap) -> Result<JsonValue, HttpError> {
+    let user_id = headers
+        .get("x-user")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| HttpError::Unauthorized("sign in first".to_string()))?;
+    Ok(json!({ "userId": user_id }))
+}
@@ -506,12 +506,12 @@ impl TestDatabase {
         let mut all_param_names = param_names.clone();
         all_param_names.extend(session_param_names.clone());
 
-        // Collect parameter values in the order they appear in SQL
-        // We need to find the order parameters appear in each SQL statement
-        let mut param_values: Vec<libsql::Value> = Vec::new();
+        // Collect parameter values in the order they appear in each SQL statement
+        let mut statement_param_values: Vec<Vec<libsql::Value>> = Vec::new();
 
         // For each SQL statement, collect parameters in the order they appear
         for (_, sql_stmt) in &sql_statements {
+            let mut param_values: Vec<libsql::Value> = Vec::new();
             if let SqlAndParams::Sql(sql) = sql_stmt {
                 // Find parameters in the order they appear in this SQL
                 let mut seen_in_this_sql = std::collections::HashSet::new();
@@ -539,13 +539,16 @@ impl TestDatabase {
                     }
                 }
             }
+            statement_param_values.push(param_values);
         }
 
         let conn = self.db.connect().map_err(TestError::Database)?;
         let mut results = Vec::new();
 
         // Execute statements sequentially
-        for (include, sql_stmt) in sql_statements {
+        for ((include, sql_stmt), param_values) in
+            sql_statements.into_iter().zip(statement_param_values)
+        {
             match sql_stmt {
                 SqlAndParams::Sql(sql) => {
                     let sql_with_params = if all_param_names.is_empty() {
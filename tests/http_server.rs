@@ -0,0 +1,1031 @@
+#[allow(dead_code, unused_imports)]
+mod helpers;
+
+use axum::http::HeaderMap;
+use axum::routing::get;
+use axum::Router;
+use helpers::server::{manifest_for, query_id, user_session};
+use helpers::test_database::TestDatabase;
+use pyre::server::database_id::DatabaseResolver;
+use pyre::server::http::{
+    Access, Action, ChangeLogConfig, HttpConfig, HttpError, HttpServer, SessionExtractor,
+};
+use pyre::server::manifest::FieldSchema;
+use pyre::sync::SyncPageResult;
+use pyre::sync_encoding::{self, CompactSyncPage, SyncFormat};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+/// A blocking HTTP/1.1 request, run off the test's runtime.
+async fn request(
+    port: u16,
+    method: &'static str,
+    path: String,
+    headers: &'static [(&'static str, &'static str)],
+    body: Option<JsonValue>,
+) -> (u16, String) {
+    let (status, _, body) = request_bytes(port, method, path, headers, body).await;
+    (status, String::from_utf8_lossy(&body).to_string())
+}
+
+/// Like `request`, keeping the response head and the body as bytes.
+async fn request_bytes(
+    port: u16,
+    method: &'static str,
+    path: String,
+    headers: &'static [(&'static str, &'static str)],
+    body: Option<JsonValue>,
+) -> (u16, String, Vec<u8>) {
+    tokio::task::spawn_blocking(move || {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let mut request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+            body.len()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let head_end = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap_or(response.len());
+        let head = String::from_utf8_lossy(&response[..head_end]).to_string();
+        let body = response.get(head_end + 4..).unwrap_or_default().to_vec();
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .unwrap_or(0);
+        (status, head, body)
+    })
+    .await
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn router_mounts_next_to_app_routes_with_custom_sessions_and_authorization(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    body String
+    updatedAt Int
+    @allow(*) { ownerId == Session.userId }
+}
+"#,
+    )
+    .await?;
+    let manifest = manifest_for(
+        &db.context,
+        r#"
+query GetNotes {
+    note {
+        id
+        body
+    }
+}
+
+insert CreateNote($body: String) {
+    note {
+        ownerId = Session.userId
+        body = $body
+        updatedAt = 10
+    }
+}
+"#,
+        false,
+    )?;
+    let get_notes = query_id(&manifest, "query");
+    let create_note = query_id(&manifest, "insert");
+
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let read_only_query = get_notes.clone();
+    let pyre = HttpServer::new(
+        HttpConfig::new(manifest, databases)
+            .with_session(user_session)
+            .with_authorizer(move |access: &Access<'_>| {
+                let guest =
+                    access.headers.get("x-user").map(|value| value.as_bytes()) == Some(b"2");
+                match access.action {
+                    Action::Run { query_id } if guest && query_id != read_only_query => {
+                        Err(HttpError::Forbidden("guests can only read".to_string()))
+                    }
+                    _ => Ok(()),
+                }
+            }),
+    );
+    let app = Router::new()
+        .route("/", get(|| async { "app" }))
+        .nest("/pyre", pyre.router());
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(app.into_make_service()));
+
+    let (status, body) = request(port, "GET", "/".to_string(), &[], None).await;
+    assert_eq!((status, body.as_str()), (200, "app"));
+
+    let (status, body) = request(port, "GET", "/pyre/health".to_string(), &[], None).await;
+    assert_eq!(status, 200, "health body: {}", body);
+
+    let create_path = format!("/pyre/db/{}", create_note);
+    let (status, body) = request(
+        port,
+        "POST",
+        create_path.clone(),
+        &[],
+        Some(json!({ "body": "hi" })),
+    )
+    .await;
+    assert_eq!(status, 401, "anonymous body: {}", body);
+
+    let (status, body) = request(
+        port,
+        "POST",
+        create_path.clone(),
+        &[("x-user", "2")],
+        Some(json!({ "body": "hi" })),
+    )
+    .await;
+    assert_eq!(status, 403, "guest body: {}", body);
+    assert_eq!(
+        serde_json::from_str::<JsonValue>(&body)?["error"],
+        json!("guests can only read")
+    );
+
+    let (status, body) = request(
+        port,
+        "POST",
+        create_path,
+        &[("x-user", "1")],
+        Some(json!({ "body": "hi" })),
+    )
+    .await;
+    assert_eq!(status, 200, "create body: {}", body);
+
+    let get_path = format!("/pyre/db/{}", get_notes);
+    let (status, body) = request(
+        port,
+        "POST",
+        get_path.clone(),
+        &[("x-user", "1")],
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, 200, "owner body: {}", body);
+    assert_eq!(
+        serde_json::from_str::<JsonValue>(&body)?["note"][0]["body"],
+        json!("hi")
+    );
+
+    let (status, body) = request(port, "POST", get_path, &[("x-user", "2")], Some(json!({}))).await;
+    assert_eq!(status, 200, "guest read body: {}", body);
+    assert_eq!(serde_json::from_str::<JsonValue>(&body)?["note"], json!([]));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_catches_up_shapes_on_their_own_cursors() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Task {
+    id Int @id
+    projectId Int
+    title String
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    db.db.connect()?.execute_batch(
+        "insert into tasks (id, projectId, title, updatedAt) values (1, 1, 'one', 10), (2, 2, 'two', 10);",
+    )
+    .await?;
+    let manifest = manifest_for(
+        &db.context,
+        r#"
+query ProjectTasks($projectId: Int) {
+    task {
+        @where { projectId == $projectId }
+        id
+        title
+    }
+}
+
+insert CreateTask($title: String) {
+    task {
+        projectId = 1
+        title = $title
+        updatedAt = 10
+    }
+}
+"#,
+        false,
+    )?;
+    let project_tasks = query_id(&manifest, "query");
+    let create_task = query_id(&manifest, "insert");
+
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let pyre = HttpServer::new(HttpConfig::new(manifest, databases).with_session(user_session));
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let (status, body) = request(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[("x-user", "1")],
+        Some(json!({
+            "shapes": [
+                { "id": "first", "queryId": project_tasks, "params": { "projectId": 1 } },
+                { "id": "second", "queryId": project_tasks, "params": { "projectId": 2 } },
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(status, 200, "shapes body: {}", body);
+    let body = serde_json::from_str::<JsonValue>(&body)?;
+    assert_eq!(
+        body["shapes"]["first"]["tables"]["tasks"]["rows"][0]["id"],
+        json!(1)
+    );
+    assert_eq!(
+        body["shapes"]["second"]["tables"]["tasks"]["rows"][0]["id"],
+        json!(2)
+    );
+    assert_eq!(
+        body["shapes"]["first"]["tables"]["tasks"]["rows"]
+            .as_array()
+            .map(Vec::len),
+        Some(1)
+    );
+
+    let (status, body) = request(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[("x-user", "1")],
+        Some(json!({ "shapes": [{ "id": "tasks", "queryId": create_task }] })),
+    )
+    .await;
+    assert_eq!(status, 400, "mutation shape body: {}", body);
+
+    let (status, body) = request(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[("x-user", "1")],
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, 400, "empty sync body: {}", body);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replay_applies_each_offline_mutation_once() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    slug String @unique
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    let manifest = manifest_for(
+        &db.context,
+        r#"
+insert CreateNote($slug: String) {
+    note {
+        slug = $slug
+        updatedAt = 10
+        id
+    }
+}
+"#,
+        false,
+    )?;
+    let create_note = query_id(&manifest, "insert");
+
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let mut config = HttpConfig::new(manifest, databases).with_session(user_session);
+    // The change log is written in the same transaction as the recorded response.
+    config.change_log = Some(ChangeLogConfig::default());
+    let pyre = HttpServer::new(config);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let log = json!({
+        "mutations": [
+            { "idempotencyKey": "a", "queryId": create_note, "input": { "slug": "a" } },
+            { "idempotencyKey": "b", "queryId": create_note, "input": { "slug": "b" } },
+        ],
+    });
+    let (status, body) = request(
+        port,
+        "POST",
+        "/db/replay".to_string(),
+        &[("x-user", "1")],
+        Some(log.clone()),
+    )
+    .await;
+    assert_eq!(status, 200, "replay body: {}", body);
+    let first = serde_json::from_str::<JsonValue>(&body)?;
+    assert_eq!(first["results"][0]["ok"], json!(true));
+    assert_eq!(first["results"][0]["replayed"], json!(false));
+    assert_eq!(first["results"][1]["serverRevision"], json!(2));
+    assert_eq!(first["deltas"].as_array().map(Vec::len), Some(2));
+    assert_eq!(first["deltas"][1]["data"][0]["rows"][0][1], json!("b"));
+
+    // Retrying the whole log after a lost response changes nothing.
+    let (status, body) = request(
+        port,
+        "POST",
+        "/db/replay".to_string(),
+        &[("x-user", "1")],
+        Some(log),
+    )
+    .await;
+    assert_eq!(status, 200, "retried replay body: {}", body);
+    let retried = serde_json::from_str::<JsonValue>(&body)?;
+    assert_eq!(retried["results"][0]["replayed"], json!(true));
+    assert_eq!(
+        retried["results"][1]["result"],
+        first["results"][1]["result"]
+    );
+    assert_eq!(
+        retried["results"][1]["serverRevision"],
+        first["results"][1]["serverRevision"]
+    );
+
+    let (status, body) = request(
+        port,
+        "POST",
+        "/db/replay".to_string(),
+        &[("x-user", "1")],
+        Some(json!({
+            "mutations": [
+                { "idempotencyKey": "c", "queryId": create_note, "input": { "slug": "a" } },
+                { "idempotencyKey": "d", "queryId": create_note, "input": { "slug": "d" } },
+            ],
+        })),
+    )
+    .await;
+    assert_eq!(status, 200, "failed replay body: {}", body);
+    let failed = serde_json::from_str::<JsonValue>(&body)?;
+    assert_eq!(failed["results"][0]["ok"], json!(false));
+    assert_eq!(failed["results"][1]["ok"], json!(false));
+
+    let create_path = format!("/db/{}", create_note);
+    let (status, first_body) = request(
+        port,
+        "POST",
+        create_path.clone(),
+        &[("x-user", "1"), ("Idempotency-Key", "e")],
+        Some(json!({ "slug": "e" })),
+    )
+    .await;
+    assert_eq!(status, 200, "keyed body: {}", first_body);
+    let (status, retry_body) = request(
+        port,
+        "POST",
+        create_path.clone(),
+        &[("x-user", "1"), ("Idempotency-Key", "e")],
+        Some(json!({ "slug": "e" })),
+    )
+    .await;
+    assert_eq!((status, retry_body), (200, first_body));
+
+    let (status, body) = request(
+        port,
+        "POST",
+        create_path.clone(),
+        &[("x-user", "1"), ("Idempotency-Key", "e")],
+        Some(json!({ "slug": "f" })),
+    )
+    .await;
+    assert_eq!(status, 409, "reused key body: {}", body);
+    assert_eq!(
+        serde_json::from_str::<JsonValue>(&body)?["code"],
+        json!("idempotencyKeyReused")
+    );
+
+    // Keys are scoped to the session, so another user can pick the same one.
+    let (status, body) = request(
+        port,
+        "POST",
+        create_path,
+        &[("x-user", "2"), ("Idempotency-Key", "e")],
+        Some(json!({ "slug": "g" })),
+    )
+    .await;
+    assert_eq!(status, 200, "other user's key body: {}", body);
+
+    let mut rows = db
+        .db
+        .connect()?
+        .query("select slug from notes order by slug", ())
+        .await?;
+    let mut slugs = Vec::new();
+    while let Some(row) = rows.next().await? {
+        slugs.push(row.get::<String>(0)?);
+    }
+    assert_eq!(slugs, vec!["a", "b", "e", "g"]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sync_negotiates_compact_messagepack_pages() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+record Task {
+    id Int @id
+    title String
+    done Bool
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    db.db.connect()?.execute_batch(
+        "insert into tasks (id, title, done, updatedAt) values (1, 'one', 0, 10), (2, 'two', 1, 11);",
+    )
+    .await?;
+    let manifest = manifest_for(
+        &db.context,
+        r#"
+query Tasks {
+    task {
+        id
+    }
+}
+"#,
+        false,
+    )?;
+
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let mut config = HttpConfig::new(manifest, databases);
+    config.cors_origins = vec!["http://app.test".to_string()];
+    let pyre = HttpServer::new(config);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let (status, head, json_body) = request_bytes(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[],
+        Some(json!({ "syncCursor": {} })),
+    )
+    .await;
+    assert_eq!(
+        status,
+        200,
+        "json body: {}",
+        String::from_utf8_lossy(&json_body)
+    );
+    assert!(
+        head.to_lowercase()
+            .contains("content-type: application/json"),
+        "{}",
+        head
+    );
+    let json_page: SyncPageResult = serde_json::from_slice(&json_body)?;
+    assert_eq!(json_page.tables["tasks"].rows.len(), 2);
+
+    let (status, head, compact_body) = request_bytes(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[
+            ("Accept", "application/msgpack"),
+            ("Accept-Encoding", "gzip, br"),
+        ],
+        Some(json!({ "syncCursor": {} })),
+    )
+    .await;
+    assert_eq!(status, 200);
+    let head = head.to_lowercase();
+    assert!(
+        head.contains("content-type: application/msgpack"),
+        "{}",
+        head
+    );
+    assert!(head.contains("content-encoding: gzip"), "{}", head);
+
+    // Column names are sent once per table, not once per row.
+    let compact: CompactSyncPage = sync_encoding::decode(&compact_body, SyncFormat::MessagePack)?;
+    let tasks = &compact.tables["tasks"];
+    assert_eq!(tasks.rows.len(), 2);
+    assert!(tasks
+        .rows
+        .iter()
+        .all(|row| row.len() == tasks.headers.len()));
+    assert!(tasks.headers.contains(&"title".to_string()));
+
+    let decoded = sync_encoding::decode_sync_page(&compact_body, SyncFormat::MessagePack)?;
+    assert_eq!(
+        serde_json::to_value(&decoded)?,
+        serde_json::to_value(&json_page)?
+    );
+
+    // Without gzip in Accept-Encoding the MessagePack body is sent as is.
+    let (status, head, body) = request_bytes(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[("Accept", "application/msgpack")],
+        Some(json!({ "syncCursor": {} })),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert!(
+        !head.to_lowercase().contains("content-encoding"),
+        "{}",
+        head
+    );
+    let decoded: SyncPageResult =
+        rmp_serde::from_slice::<CompactSyncPage>(&body).map(SyncPageResult::from)?;
+    assert_eq!(decoded.tables["tasks"].rows, json_page.tables["tasks"].rows);
+
+    // `q=0` refuses an encoding, and CORS keeps the negotiated `Vary` headers.
+    let (status, head, _) = request_bytes(
+        port,
+        "POST",
+        "/sync".to_string(),
+        &[
+            ("Accept", "application/msgpack"),
+            ("Accept-Encoding", "gzip;q=0, br"),
+            ("Origin", "http://app.test"),
+        ],
+        Some(json!({ "syncCursor": {} })),
+    )
+    .await;
+    assert_eq!(status, 200);
+    let head = head.to_lowercase();
+    assert!(!head.contains("content-encoding"), "{}", head);
+    assert!(head.contains("vary: accept, accept-encoding"), "{}", head);
+    assert!(head.contains("vary: origin"), "{}", head);
+
+    // WebSockets opened with `encoding=msgpack` reply in binary frames.
+    let catchup = tokio::task::spawn_blocking(move || {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let (mut socket, _) = tungstenite::client(
+            format!("ws://127.0.0.1:{}/sync/ws?encoding=msgpack", port),
+            stream,
+        )
+        .unwrap();
+        socket
+            .send(tungstenite::Message::Text(
+                json!({ "type": "catchup", "id": "c", "syncCursor": {} }).to_string(),
+            ))
+            .unwrap();
+        loop {
+            if let tungstenite::Message::Binary(bytes) = socket.read().unwrap() {
+                let message: JsonValue = rmp_serde::from_slice(&bytes).unwrap();
+                if message["type"] == "catchup" {
+                    return message;
+                }
+            }
+        }
+    })
+    .await?;
+    assert_eq!(catchup["id"], json!("c"));
+    assert_eq!(
+        catchup["result"]["tables"]["tasks"]["rows"]
+            .as_array()
+            .map(Vec::len),
+        Some(2)
+    );
+    assert!(catchup["result"]["tables"]["tasks"]["headers"].is_array());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn websockets_that_never_authenticate_are_closed() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    @allow(*) { ownerId == Session.userId }
+}
+"#,
+    )
+    .await?;
+    let manifest = manifest_for(&db.context, "", false)?;
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let mut config = HttpConfig::new(manifest, databases).with_session(user_session);
+    config.auth_timeout = std::time::Duration::from_millis(200);
+    let pyre = HttpServer::new(config);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let (timed_out, close) = tokio::task::spawn_blocking(move || {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let (mut socket, _) =
+            tungstenite::client(format!("ws://127.0.0.1:{}/sync/ws", port), stream).unwrap();
+        let mut timed_out = JsonValue::Null;
+        loop {
+            match socket.read().unwrap() {
+                tungstenite::Message::Text(text) => {
+                    timed_out = serde_json::from_str(&text).unwrap()
+                }
+                tungstenite::Message::Close(frame) => return (timed_out, frame),
+                _ => {}
+            }
+        }
+    })
+    .await?;
+    assert_eq!(timed_out["type"], json!("error"));
+    assert_eq!(timed_out["error"], json!("authentication timed out"));
+    assert_eq!(close.map(|frame| u16::from(frame.code)), Some(1008));
+
+    Ok(())
+}
+
+fn team_session(headers: &HeaderMap) -> Result<JsonValue, HttpError> {
+    let team_id = headers
+        .get("x-team")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or_else(|| HttpError::Unauthorized("sign in first".to_string()))?;
+    Ok(json!({ "userId": 1, "teamId": team_id }))
+}
+
+/// Read an SSE stream until `until` appears in it.
+fn read_sse_until(stream: &mut TcpStream, until: &str) -> String {
+    let mut received = String::new();
+    let mut buffer = [0; 4096];
+    while !received.contains(until) {
+        let read = stream.read(&mut buffer).expect("SSE stream timed out");
+        assert!(read > 0, "SSE stream closed: {}", received);
+        received.push_str(&String::from_utf8_lossy(&buffer[..read]));
+    }
+    received
+}
+
+/// Every request gets user 1, with a credential that expires at `expires_at`.
+struct ExpiringSession {
+    expires_at: i64,
+}
+
+impl SessionExtractor for ExpiringSession {
+    fn session(
+        &self,
+        _headers: &HeaderMap,
+        _session_schema: &HashMap<String, FieldSchema>,
+    ) -> Result<JsonValue, HttpError> {
+        Ok(json!({ "userId": 1 }))
+    }
+
+    fn expires_at(&self, _headers: &HeaderMap) -> Option<i64> {
+        Some(self.expires_at)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn live_connections_end_when_their_session_expires() -> Result<(), Box<dyn std::error::Error>>
+{
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    @allow(*) { ownerId == Session.userId }
+}
+"#,
+    )
+    .await?;
+    let manifest = manifest_for(&db.context, "", false)?;
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+    let pyre = HttpServer::new(HttpConfig::new(manifest, databases).with_session(
+        ExpiringSession {
+            expires_at: now + 1,
+        },
+    ));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let events = tokio::task::spawn_blocking(move || {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET /sync/events HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nAccept: text/event-stream\r\nConnection: close\r\n\r\n"
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        let received = read_sse_until(&mut stream, "sessionExpired");
+        // The response ends after `sessionExpired`, closing the connection.
+        let mut rest = Vec::new();
+        stream
+            .read_to_end(&mut rest)
+            .expect("SSE stream should end");
+        (received, rest)
+    });
+    let socket = tokio::task::spawn_blocking(move || {
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let (mut socket, _) =
+            tungstenite::client(format!("ws://127.0.0.1:{}/sync/ws", port), stream).unwrap();
+        let mut messages = Vec::new();
+        loop {
+            match socket.read().unwrap() {
+                tungstenite::Message::Text(text) => {
+                    messages.push(serde_json::from_str::<JsonValue>(&text).unwrap()["type"].clone())
+                }
+                tungstenite::Message::Close(frame) => return (messages, frame),
+                _ => {}
+            }
+        }
+    });
+
+    let (received, rest) = events.await?;
+    assert!(received.contains("connected"), "{}", received);
+    assert!(!String::from_utf8_lossy(&rest).contains("data:"));
+
+    let (messages, close) = socket.await?;
+    assert_eq!(messages, vec![json!("connected"), json!("sessionExpired")]);
+    assert_eq!(close.map(|frame| u16::from(frame.code)), Some(1008));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn updated_sessions_resync_the_tables_whose_permissions_changed(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+    teamId Int
+}
+
+record Note {
+    id Int @id
+    teamId Int
+    body String
+    @allow(query) { teamId == Session.teamId }
+}
+
+record Profile {
+    id Int @id
+    name String
+    @allow(query) { id == Session.userId }
+}
+"#,
+    )
+    .await?;
+    let manifest = manifest_for(
+        &db.context,
+        r#"
+query GetNotes {
+    note {
+        id
+        body
+    }
+}
+"#,
+        false,
+    )?;
+
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let pyre = HttpServer::new(
+        HttpConfig::new(manifest, databases)
+            .with_session(team_session)
+            .with_admin_token("admin-secret"),
+    );
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let mut events = tokio::task::spawn_blocking(move || {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET /sync/events HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\nAccept: text/event-stream\r\nx-team: 1\r\n\r\n"
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        read_sse_until(&mut stream, "connected");
+        stream
+    })
+    .await?;
+
+    let update = json!({ "match": { "teamId": 1 }, "set": { "teamId": 2 } });
+    let (status, body) = request(
+        port,
+        "POST",
+        "/sync/sessions".to_string(),
+        &[("Authorization", "Bearer wrong")],
+        Some(update.clone()),
+    )
+    .await;
+    assert_eq!(status, 401, "wrong token body: {}", body);
+
+    let (status, body) = request(
+        port,
+        "POST",
+        "/sync/sessions".to_string(),
+        &[("Authorization", "Bearer admin-secret")],
+        Some(update),
+    )
+    .await;
+    assert_eq!(status, 200, "update body: {}", body);
+    assert_eq!(
+        serde_json::from_str::<JsonValue>(&body)?,
+        json!({ "connections": 1, "syncRequired": 1 })
+    );
+
+    // Only the notes permission reads `teamId`, so profiles are left as they are.
+    // The stream is kept open so the connection is still live for the next update.
+    let (received, _events) =
+        tokio::task::spawn_blocking(move || (read_sse_until(&mut events, "syncRequired"), events))
+            .await?;
+    let line = received
+        .lines()
+        .find(|line| line.contains("syncRequired"))
+        .and_then(|line| line.strip_prefix("data:"))
+        .ok_or("syncRequired event should be sent")?;
+    let message: JsonValue = serde_json::from_str(line.trim())?;
+    assert_eq!(message["tables"], json!(["notes"]));
+
+    // A session that is replaced with the same permissions needs no resync.
+    let updated = pyre
+        .update_sessions("default", |session| {
+            (session["teamId"] == json!(2)).then(|| session.clone())
+        })
+        .await?;
+    assert_eq!((updated.connections, updated.sync_required), (1, 0));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn routed_databases_stop_opening_at_the_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+record Task {
+    id Int @id
+    title String
+    @public
+}
+"#,
+    )
+    .await?;
+    let manifest = manifest_for(
+        &db.context,
+        r#"
+query Tasks {
+    task {
+        id
+        title
+    }
+}
+"#,
+        false,
+    )?;
+
+    let directory = db.temp_dir.path().join("databases");
+    std::fs::create_dir(&directory)?;
+    for database_id in ["first", "second", "third"] {
+        std::fs::copy(
+            db.temp_dir.path().join("test.db"),
+            directory.join(format!("{}.db", database_id)),
+        )?;
+    }
+    let databases = DatabaseResolver::from_database_arg(directory.to_str().unwrap(), "default")?;
+    let mut config = HttpConfig::new(manifest, databases);
+    config.max_open_databases = 2;
+    let pyre = HttpServer::new(config);
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    // A database that fails to open isn't kept, so it doesn't use up the limit.
+    let (status, body) = request(
+        port,
+        "GET",
+        "/health?databaseId=missing".to_string(),
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, 404, "missing body: {}", body);
+
+    for database_id in ["first", "second"] {
+        let (status, body) = request(
+            port,
+            "GET",
+            format!("/health?databaseId={}", database_id),
+            &[],
+            None,
+        )
+        .await;
+        assert_eq!(status, 200, "{} body: {}", database_id, body);
+    }
+
+    let (status, body) = request(
+        port,
+        "GET",
+        "/health?databaseId=third".to_string(),
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, 503, "third body: {}", body);
+
+    let (status, body) = request(
+        port,
+        "GET",
+        "/health?databaseId=first".to_string(),
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, 200, "reopened body: {}", body);
+
+    Ok(())
+}
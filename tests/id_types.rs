@@ -710,3 +710,51 @@ record GameDocument {
         sync_status_sql
     );
 }
+
+#[test]
+fn test_composite_primary_keys_generate_key_types() {
+    let schema_source = r#"
+record Org {
+    @public
+    id Id.Int @id
+    name String
+}
+
+record User {
+    @public
+    id Id.Uuid @id
+    name String
+}
+
+record Membership {
+    @public
+    @id(orgId, userId)
+    orgId Org.id
+    userId User.id
+    role String
+}
+"#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("Failed to parse schema");
+    let mut database = ast::Database {
+        schemas: vec![schema],
+    };
+    ast::resolve_id_brands(&mut database);
+
+    let typescript = pyre::generate::server::typescript::schema(&database);
+    assert!(
+        typescript.contains("type MembershipId = { \"orgId\": OrgId; \"userId\": string & User }"),
+        "{}",
+        typescript
+    );
+
+    let elm = pyre::generate::client::elm::write_schema(&database);
+    assert!(
+        elm.contains(
+            "type alias MembershipId =\n    { orgId : Db.Id.Org\n    , userId : Db.Id.User\n    }\n"
+        ),
+        "{}",
+        elm
+    );
+}
@@ -0,0 +1,274 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use pyre::server::jwt::{
+    expires_at, parse_jwks, JwtError, JwtKey, JwtVerifier, JWT_LEEWAY_SECONDS,
+};
+use pyre::server::manifest::{FieldSchema, PyreSession};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde_json::{json, Value as JsonValue};
+use sha2::Sha256;
+use std::collections::HashMap;
+
+const NOW: i64 = 1_700_000_000;
+
+/// A 2048-bit RSA key used only by these tests, as PKCS#8 DER.
+const RSA_PKCS8: &str = concat!(
+    "MIIEvQIBADANBgkqhkiG9w0BAQEFAASCBKcwggSjAgEAAoIBAQChE6sut5pUhTGnrh1igOE7M7UkWgxHiUCIG3d0",
+    "toDG4izh0HDHVjssSZ+GLzXVy9U5NaaZmZNl9o8TNYpHt/qMFiyflZzi7+DW+hvZBmFq31BBNYmEYTKNSr32Z/bo",
+    "EU1JyYYMwcW90ArvcH0cmAiIB2nKp3sH4mbaPQpnLGrMmyIYP/6I9QAYg1SPcS3Xmw9ybhjGcJQkue4Gv0ocME0L",
+    "D4aauhIUrp8HaL9XJ7HdxLCkeIGIDvZ/i5dz2ET/TuAN0OZtDLceVhnypKz87AMcCntnj06b+8fvwQhKb7Rswyb4",
+    "sXc+Om3H2qxmHdy39iRpDwf+2Pb9QkbyNy4ZO/VxAgMBAAECggEAK42ubTDe7XigTwcg0egoPoJCOgSRKmydws+u",
+    "cqQAWQaB/UMHo5aY5GY52KX+SANX8aKknAavGNRcpmSFMdQ4zlUWlsX1CFW1NVumWP3FLIkaYa9wshc3/IVXrP86",
+    "f1BiVg/EC8H/Tk4pDsobQjaHpARhIhc8CIs6pUr4+6HCdPUVp4LqZ7ZaTnwnnfh86MC++Dyom80Erx4wbz1PHxI9",
+    "qpAzmB/pCMOaoxP8hFH0PkRhc6e3oVOhH8V+QQXtbinQGYiKMTxskiTG2vorhlQZwp/I+U+lxxiAKCuchiWUgEou",
+    "9/eEVpoDtRUjcoOh6y+rhqzR1s+4g69AwU6fAN2DWwKBgQDL8/qRwZ44Pgt7r4rG1NhAmcad6KvW84NxIAXhltuM",
+    "3RYFVeiQG5ayFOcPSV9CJJlQFwSZ6Lo1TxvD7amhZNMi9OouMDwr8VrwucXXVfM6I7PI/ZImsJMZHg3zFVWkFqYM",
+    "ZDwcfkG1Njr9t/+zg46G3YqP3kcYCzJ4uuqeDvnZYwKBgQDKLqK1HFTdwbbtMR3xoQ6Gvmy2sKSUzDgIbVG2htQJ",
+    "KL1av+cjnOadHPEIeDB8mz+1H0Jgvdh8dhFF9HqbXhuUIuVwGhro+As/TM5qgmuVTlTAoMaRgX9NcmuzHJWjLh97",
+    "2Kknx+A+eszp/tY0JLM35OSrKGJwK7p+CW0FAbhYGwKBgCj5FMefbBfEby4j7+9N5zvjKMGkcpE2Tpu1YTkWw3ij",
+    "Gb9sqNH6mOWhyWGKzfZNv/cVCLmaxX3cLKnJ3yArTzdgmIM68XuqeyiNSa+e9sQhVAb22hunsYWEQi0phhD289jd",
+    "ci4PN0geYu+BvX7k2QdDDokkkIfGCBe1BVlHjO4dAoGBAI7w1bvx4dY+ZJPhS+hPE7QT4UxwKovArkTW9RR00mf/",
+    "U5BbaJD3FtwxX/+66ZzPLgVhqk43Kl0kJCYvKJe7uwfKfCf7bNcUtu7z4GPQ/Tq7/JOLl5e71ELvCFHDfc+2hi/L",
+    "CBzF5TaHH2S39L08zTBRNZIEpUPkxp7hDyChDLHNAoGAUHgXGTDK6PIw5GYKVF92sQawTltvJpklqaRvQIKxeCSm",
+    "cToWhvaARUVNxib16kZe4eP10A0sgspMHQ/VYkoOseACB9uQVJ3dPnUEnBDsAG0Fu76aQg52xgL/IqCUtsp5fRaM",
+    "jHoOXo2IqP60IArRt+iDVzRAI2DSeeXUedsRIqo=",
+);
+const RSA_N: &str = concat!(
+    "oROrLreaVIUxp64dYoDhOzO1JFoMR4lAiBt3dLaAxuIs4dBwx1Y7LEmfhi811cvVOTWmmZmTZfaPEzWKR7f6jBYs",
+    "n5Wc4u_g1vob2QZhat9QQTWJhGEyjUq99mf26BFNScmGDMHFvdAK73B9HJgIiAdpyqd7B-Jm2j0KZyxqzJsiGD_-",
+    "iPUAGINUj3Et15sPcm4YxnCUJLnuBr9KHDBNCw-GmroSFK6fB2i_Vyex3cSwpHiBiA72f4uXc9hE_07gDdDmbQy3",
+    "HlYZ8qSs_OwDHAp7Z49Om_vH78EISm-0bMMm-LF3Pjptx9qsZh3ct_YkaQ8H_tj2_UJG8jcuGTv1cQ",
+);
+const RSA_E: &str = "AQAB";
+
+fn encode_segment(value: &JsonValue) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(value).unwrap())
+}
+
+fn signing_input(header: JsonValue, claims: &JsonValue) -> String {
+    format!("{}.{}", encode_segment(&header), encode_segment(claims))
+}
+
+fn hs256_token(secret: &[u8], claims: &JsonValue) -> String {
+    let input = signing_input(json!({ "alg": "HS256", "typ": "JWT" }), claims);
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+    mac.update(input.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{}.{}", input, signature)
+}
+
+fn rs256_token(kid: &str, claims: &JsonValue) -> String {
+    let key = RsaKeyPair::from_pkcs8(&STANDARD.decode(RSA_PKCS8).unwrap()).unwrap();
+    let input = signing_input(json!({ "alg": "RS256", "kid": kid }), claims);
+    let mut signature = vec![0; key.public().modulus_len()];
+    key.sign(
+        &ring::signature::RSA_PKCS1_SHA256,
+        &SystemRandom::new(),
+        input.as_bytes(),
+        &mut signature,
+    )
+    .unwrap();
+    format!("{}.{}", input, URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn es256_key() -> EcdsaKeyPair {
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+    EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap()
+}
+
+fn es256_token(key: &EcdsaKeyPair, claims: &JsonValue) -> String {
+    let input = signing_input(json!({ "alg": "ES256" }), claims);
+    let signature = key.sign(&SystemRandom::new(), input.as_bytes()).unwrap();
+    format!("{}.{}", input, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+}
+
+fn hs256_verifier() -> JwtVerifier {
+    JwtVerifier {
+        keys: vec![JwtKey::Hmac {
+            kid: None,
+            secret: b"secret".to_vec(),
+        }],
+        ..JwtVerifier::default()
+    }
+}
+
+#[test]
+fn hs256_tokens_map_claims_onto_the_session() {
+    let verifier = JwtVerifier {
+        claim_paths: HashMap::from([
+            ("userId".to_string(), "sub".to_string()),
+            ("role".to_string(), "app_metadata.role".to_string()),
+        ]),
+        ..hs256_verifier()
+    };
+    let token = hs256_token(
+        b"secret",
+        &json!({ "sub": 42, "exp": NOW + 60, "app_metadata": { "role": "admin" } }),
+    );
+
+    let claims = verifier.verify(&token, NOW).expect("token should verify");
+    let schema = HashMap::from([
+        (
+            "userId".to_string(),
+            FieldSchema {
+                type_: "Int".to_string(),
+                nullable: false,
+                omittable: false,
+            },
+        ),
+        (
+            "role".to_string(),
+            FieldSchema {
+                type_: "String".to_string(),
+                nullable: false,
+                omittable: false,
+            },
+        ),
+    ]);
+    let session = verifier.session_from_claims(&claims, schema.keys());
+    assert_eq!(session, json!({ "userId": 42, "role": "admin" }));
+    PyreSession::new(session, &schema).expect("mapped claims should be a valid session");
+
+    let wrong_secret = hs256_token(b"other", &json!({ "sub": 42, "exp": NOW + 60 }));
+    assert_eq!(
+        verifier.verify(&wrong_secret, NOW),
+        Err(JwtError::InvalidSignature)
+    );
+}
+
+#[test]
+fn rs256_and_es256_tokens_verify_against_a_jwks() {
+    let es256 = es256_key();
+    let point = es256.public_key().as_ref();
+    let jwks = json!({
+        "keys": [
+            { "kty": "RSA", "kid": "rsa-1", "alg": "RS256", "use": "sig", "n": RSA_N, "e": RSA_E },
+            {
+                "kty": "EC",
+                "crv": "P-256",
+                "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+            },
+            { "kty": "RSA", "use": "enc", "n": RSA_N, "e": RSA_E },
+        ]
+    });
+    let verifier = JwtVerifier {
+        keys: parse_jwks(&jwks.to_string()).expect("jwks should parse"),
+        ..JwtVerifier::default()
+    };
+    assert_eq!(verifier.keys.len(), 2);
+
+    let claims = json!({ "userId": 7, "exp": NOW + 60 });
+    let rs256 = rs256_token("rsa-1", &claims);
+    assert_eq!(verifier.verify(&rs256, NOW), Ok(claims.clone()));
+    assert_eq!(
+        verifier.verify(&es256_token(&es256, &claims), NOW),
+        Ok(claims.clone())
+    );
+
+    assert!(matches!(
+        verifier.verify(&rs256_token("rsa-2", &claims), NOW),
+        Err(JwtError::NoMatchingKey { .. })
+    ));
+
+    let mut parts = rs256.split('.').collect::<Vec<_>>();
+    let tampered = encode_segment(&json!({ "userId": 8, "exp": NOW + 60 }));
+    parts[1] = &tampered;
+    assert_eq!(
+        verifier.verify(&parts.join("."), NOW),
+        Err(JwtError::InvalidSignature)
+    );
+}
+
+#[test]
+fn jwt_algorithms_must_match_the_configured_keys() {
+    let verifier = hs256_verifier();
+    let unsigned = format!(
+        "{}.",
+        signing_input(json!({ "alg": "none" }), &json!({ "exp": NOW + 60 }))
+    );
+    assert!(matches!(
+        verifier.verify(&unsigned, NOW),
+        Err(JwtError::NoMatchingKey { .. })
+    ));
+
+    // An HS256 token signed with an RSA public key must not verify against that key.
+    let rsa_only = JwtVerifier {
+        keys: vec![JwtKey::Rsa {
+            kid: None,
+            n: URL_SAFE_NO_PAD.decode(RSA_N).unwrap(),
+            e: URL_SAFE_NO_PAD.decode(RSA_E).unwrap(),
+        }],
+        ..JwtVerifier::default()
+    };
+    let confused = hs256_token(
+        &URL_SAFE_NO_PAD.decode(RSA_N).unwrap(),
+        &json!({ "exp": NOW + 60 }),
+    );
+    assert!(matches!(
+        rsa_only.verify(&confused, NOW),
+        Err(JwtError::NoMatchingKey { .. })
+    ));
+}
+
+#[test]
+fn jwt_registered_claims_are_validated() {
+    let verifier = JwtVerifier {
+        issuer: Some("https://issuer.example".to_string()),
+        audience: Some("pyre".to_string()),
+        ..hs256_verifier()
+    };
+    let valid = json!({
+        "iss": "https://issuer.example",
+        "aud": ["other", "pyre"],
+        "exp": NOW + 60,
+        "nbf": NOW - 10,
+    });
+    assert!(verifier
+        .verify(&hs256_token(b"secret", &valid), NOW)
+        .is_ok());
+
+    let with = |key: &str, value: JsonValue| {
+        let mut claims = valid.clone();
+        claims[key] = value;
+        verifier.verify(&hs256_token(b"secret", &claims), NOW)
+    };
+    assert_eq!(with("exp", json!(NOW - 120)), Err(JwtError::Expired));
+    assert!(
+        with("exp", json!(NOW - 30)).is_ok(),
+        "small clock skew is allowed"
+    );
+    assert_eq!(with("nbf", json!(NOW + 120)), Err(JwtError::NotYetValid));
+    assert_eq!(
+        with("iss", json!("https://evil.example")),
+        Err(JwtError::InvalidClaim("iss".to_string()))
+    );
+    assert_eq!(
+        with("aud", json!("other")),
+        Err(JwtError::InvalidClaim("aud".to_string()))
+    );
+
+    let no_exp = hs256_token(
+        b"secret",
+        &json!({ "iss": "https://issuer.example", "aud": "pyre" }),
+    );
+    assert_eq!(
+        verifier.verify(&no_exp, NOW),
+        Err(JwtError::MissingClaim("exp".to_string()))
+    );
+}
+
+#[test]
+fn jwt_expiry_includes_the_clock_skew_allowance() {
+    let verifier = hs256_verifier();
+    let token = hs256_token(b"secret", &json!({ "exp": NOW }));
+    let expiry = expires_at(&token).expect("token has an exp");
+
+    assert_eq!(expiry, NOW + JWT_LEEWAY_SECONDS);
+    assert!(verifier.verify(&token, expiry - 1).is_ok());
+    assert_eq!(verifier.verify(&token, expiry), Err(JwtError::Expired));
+    assert_eq!(expires_at("not-a-token"), None);
+}
@@ -801,6 +801,44 @@ fn db_status_reports_up_to_date_after_push() {
     assert_eq!(status["pendingMigrations"], json!([]));
 }
 
+#[test]
+fn db_status_fails_when_applied_migration_changed() {
+    let ctx = TestContext::new();
+    write_schema(&ctx);
+
+    ctx.run_command("migration")
+        .arg("--db")
+        .arg(".yak/yak.db")
+        .arg("init")
+        .assert()
+        .success();
+
+    ctx.run_command("migrate")
+        .arg(".yak/yak.db")
+        .assert()
+        .success();
+
+    let migration_root = ctx.workspace_path.join("pyre/migrations");
+    let migration_dir = std::fs::read_dir(&migration_root)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.is_dir())
+        .unwrap();
+    let migration_sql = migration_dir.join("migration.sql");
+    let mut contents = std::fs::read_to_string(&migration_sql).unwrap();
+    contents.push_str("\n-- edited after apply\n");
+    std::fs::write(migration_sql, contents).unwrap();
+
+    let status = call_mcp_tool(&ctx, "pyre_db_status", json!({ "database": ".yak/yak.db" }));
+
+    assert_eq!(status["ok"], false);
+    assert_eq!(status["status"], "applied_migration_changed");
+    assert_eq!(
+        status["changedMigrations"][0]["name"],
+        json!(migration_dir.file_name().unwrap().to_str().unwrap())
+    );
+}
+
 #[test]
 fn pyre_query_executes_selection_and_mutation() {
     let ctx = TestContext::new();
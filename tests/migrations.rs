@@ -130,6 +130,14 @@ async fn create_migration_diff(
     // Create database with old schema
     let db = MigrationDatabase::new(old_schema_source).await?;
 
+    diff_database(&db, new_schema_source).await
+}
+
+/// Diff an existing migration database against a new schema.
+async fn diff_database(
+    db: &MigrationDatabase,
+    new_schema_source: &str,
+) -> Result<diff::Diff, TestError> {
     // Introspect the database to get actual tables
     let introspection_raw = introspect_uninitialized_db(&db.db).await?;
 
@@ -250,6 +258,454 @@ async fn test_introspection_captures_index_metadata() -> Result<(), TestError> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_composite_primary_key_is_created_and_introspected() -> Result<(), TestError> {
+    let schema = r#"record Membership {
+    @id(orgId, userId)
+    orgId  Int
+    userId Int
+    role   String
+    @public
+}"#;
+
+    let db = MigrationDatabase::new(schema).await?;
+    let introspection_raw = introspect_uninitialized_db(&db.db).await?;
+
+    let memberships = introspection_raw
+        .tables
+        .iter()
+        .find(|t| t.name == "memberships")
+        .expect("memberships table should be present");
+
+    assert_eq!(memberships.primary_key, vec!["orgId", "userId"]);
+    assert!(memberships
+        .columns
+        .iter()
+        .filter(|column| column.name == "orgId" || column.name == "userId")
+        .all(|column| column.pk));
+
+    let file = introspect::to_schema::to_schema(&introspect::Introspection {
+        tables: introspection_raw.tables.clone(),
+        migration_state: introspect::MigrationState::NoMigrationTable,
+        schema: introspect::SchemaResult::FailedToParse {
+            source: String::new(),
+            errors: vec![],
+        },
+    });
+    let rendered = pyre::generate::to_string::schema_to_string(
+        "",
+        &ast::Schema {
+            files: vec![file],
+            ..ast::Schema::default()
+        },
+    );
+    assert!(rendered.contains("@id(orgId, userId)"), "{}", rendered);
+
+    let conn = db.db.connect().map_err(TestError::Database)?;
+    conn.execute(
+        "insert into memberships (orgId, userId, role) values (1, 1, 'owner'), (1, 2, 'member')",
+        (),
+    )
+    .await
+    .map_err(TestError::Database)?;
+    assert!(conn
+        .execute(
+            "insert into memberships (orgId, userId, role) values (1, 2, 'owner')",
+            (),
+        )
+        .await
+        .is_err());
+
+    let db_diff = create_migration_diff(schema, schema).await?;
+    assert!(db_diff.added.is_empty());
+    assert!(db_diff.modified_records.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_table_options_and_collations_are_created_and_introspected() -> Result<(), TestError> {
+    let schema = r#"record Account {
+    @strict
+    @withoutRowid
+    @id(orgId, email)
+    orgId Int
+    email String @collate(nocase)
+    @public
+}"#;
+
+    let db = MigrationDatabase::new(schema).await?;
+    let introspection_raw = introspect_uninitialized_db(&db.db).await?;
+
+    let accounts = introspection_raw
+        .tables
+        .iter()
+        .find(|t| t.name == "accounts")
+        .expect("accounts table should be present");
+
+    assert!(accounts.strict);
+    assert!(accounts.without_rowid);
+    let email = accounts
+        .columns
+        .iter()
+        .find(|column| column.name == "email")
+        .expect("email column should be present");
+    assert_eq!(email.collation, Some(ast::Collation::NoCase));
+
+    let conn = db.db.connect().map_err(TestError::Database)?;
+    conn.execute(
+        "insert into accounts (orgId, email) values (1, 'Ada@example.com')",
+        (),
+    )
+    .await
+    .map_err(TestError::Database)?;
+    assert!(
+        conn.execute(
+            "insert into accounts (orgId, email) values (1, 'ada@EXAMPLE.com')",
+            (),
+        )
+        .await
+        .is_err(),
+        "nocase keys should collide regardless of case"
+    );
+    assert!(
+        conn.execute(
+            "insert into accounts (orgId, email) values ('one', 'grace@example.com')",
+            (),
+        )
+        .await
+        .is_err(),
+        "strict tables should reject mistyped values"
+    );
+
+    let db_diff = create_migration_diff(schema, schema).await?;
+    assert!(db_diff.modified_records.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_changing_table_options_rebuilds_the_table() -> Result<(), TestError> {
+    let old_schema = r#"record User {
+    id    Int    @id
+    email String
+    @public
+}"#;
+
+    let new_schema = r#"record User {
+    @strict
+    id    Int    @id
+    email String @collate(nocase) @unique
+    @public
+}"#;
+
+    let db = MigrationDatabase::new(old_schema).await?;
+    let conn = db.db.connect().map_err(TestError::Database)?;
+    conn.execute(
+        "insert into users (id, email) values (1, 'Ada@example.com')",
+        (),
+    )
+    .await
+    .map_err(TestError::Database)?;
+
+    let db_diff = diff_database(&db, new_schema).await?;
+    let users = db_diff
+        .modified_records
+        .iter()
+        .find(|record| record.name == "users")
+        .expect("users should be modified");
+    assert!(users.changes.iter().any(|change| matches!(
+        change,
+        diff::RecordChange::ModifiedTableOptions(options)
+            if options.strict_changed == Some((false, true))
+    )));
+    assert!(users.changes.iter().any(|change| matches!(
+        change,
+        diff::RecordChange::ModifiedField { name, changes }
+            if name == "email"
+                && changes.collation_changed == Some((None, Some(ast::Collation::NoCase)))
+    )));
+    assert!(users.rebuild.is_some());
+
+    for statement in diff::to_sql::to_sql(&db_diff) {
+        if let pyre::generate::sql::to_sql::SqlAndParams::Sql(sql) = statement {
+            conn.execute_batch(&sql)
+                .await
+                .map_err(TestError::Database)?;
+        }
+    }
+
+    let introspection_raw = introspect_uninitialized_db(&db.db).await?;
+    let users = introspection_raw
+        .tables
+        .iter()
+        .find(|t| t.name == "users")
+        .expect("users table should still exist");
+    assert!(users.strict);
+    assert!(users.indexes.iter().any(|index| index.unique));
+
+    let mut rows = conn
+        .query(
+            "select email from users where email = 'ada@EXAMPLE.com'",
+            (),
+        )
+        .await
+        .map_err(TestError::Database)?;
+    let row = rows
+        .next()
+        .await
+        .map_err(TestError::Database)?
+        .expect("existing rows should be copied into the rebuilt table");
+    let email: String = row.get(0).map_err(TestError::Database)?;
+    assert_eq!(email, "Ada@example.com");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_changing_the_primary_key_rebuilds_the_table() -> Result<(), TestError> {
+    let old_schema = r#"record Membership {
+    orgId  Int @id
+    userId Int
+    role   String
+    @public
+}"#;
+
+    let new_schema = r#"record Membership {
+    @id(orgId, userId)
+    orgId  Int
+    userId Int
+    role   String
+    @public
+}"#;
+
+    let db = MigrationDatabase::new(old_schema).await?;
+    let conn = db.db.connect().map_err(TestError::Database)?;
+    conn.execute(
+        "insert into memberships (orgId, userId, role) values (1, 2, 'owner')",
+        (),
+    )
+    .await
+    .map_err(TestError::Database)?;
+
+    let db_diff = diff_database(&db, new_schema).await?;
+    let memberships = db_diff
+        .modified_records
+        .iter()
+        .find(|record| record.name == "memberships")
+        .expect("memberships should be modified");
+    assert!(memberships.changes.iter().any(|change| matches!(
+        change,
+        diff::RecordChange::ModifiedPrimaryKey { old, new }
+            if old == &vec!["orgId".to_string()]
+                && new == &vec!["orgId".to_string(), "userId".to_string()]
+    )));
+    assert!(memberships.rebuild.is_some());
+
+    for statement in diff::to_sql::to_sql(&db_diff) {
+        if let pyre::generate::sql::to_sql::SqlAndParams::Sql(sql) = statement {
+            conn.execute_batch(&sql)
+                .await
+                .map_err(TestError::Database)?;
+        }
+    }
+
+    let introspection_raw = introspect_uninitialized_db(&db.db).await?;
+    let memberships = introspection_raw
+        .tables
+        .iter()
+        .find(|t| t.name == "memberships")
+        .expect("memberships table should still exist");
+    assert_eq!(memberships.primary_key, vec!["orgId", "userId"]);
+
+    // Both key columns are now needed to make a row unique.
+    conn.execute(
+        "insert into memberships (orgId, userId, role) values (1, 3, 'member')",
+        (),
+    )
+    .await
+    .map_err(TestError::Database)?;
+    let mut rows = conn
+        .query("select role from memberships order by userId", ())
+        .await
+        .map_err(TestError::Database)?;
+    let mut roles = Vec::new();
+    while let Some(row) = rows.next().await.map_err(TestError::Database)? {
+        roles.push(row.get::<String>(0).map_err(TestError::Database)?);
+    }
+    assert_eq!(roles, vec!["owner", "member"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_migration_adds_missing_tombstone_triggers() -> Result<(), TestError> {
+    let schema = r#"record Note {
+    id   Int @id
+    body String
+    @public
+}"#;
+
+    let db = MigrationDatabase::new(schema).await?;
+    let conn = db.db.connect().map_err(TestError::Database)?;
+    // Databases migrated before tombstones existed have no trigger.
+    conn.execute("drop trigger \"_pyre_tombstone_notes\"", ())
+        .await
+        .map_err(TestError::Database)?;
+
+    let db_diff = diff_database(&db, schema).await?;
+    let notes = db_diff
+        .modified_records
+        .iter()
+        .find(|record| record.name == "notes")
+        .expect("notes should be modified");
+    assert!(matches!(
+        notes.changes.as_slice(),
+        [diff::RecordChange::AddedTombstoneTrigger { primary_key }] if primary_key == &vec!["id".to_string()]
+    ));
+    assert!(notes.rebuild.is_none());
+
+    for statement in diff::to_sql::to_sql(&db_diff) {
+        if let pyre::generate::sql::to_sql::SqlAndParams::Sql(sql) = statement {
+            conn.execute_batch(&sql)
+                .await
+                .map_err(TestError::Database)?;
+        }
+    }
+
+    let introspection_raw = introspect_uninitialized_db(&db.db).await?;
+    let notes = introspection_raw
+        .tables
+        .iter()
+        .find(|t| t.name == "notes")
+        .expect("notes table should still exist");
+    assert!(notes.tombstone_trigger);
+    assert!(diff_database(&db, schema)
+        .await?
+        .modified_records
+        .is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_computed_columns_are_generated_and_introspected() -> Result<(), TestError> {
+    let schema = r#"record LineItem {
+    id         Int    @id
+    quantity   Int
+    unitCents  Int
+    totalCents Int    @computed(quantity * unitCents) @index
+    label      String
+    shout      String @computed(upper(label) ++ "!")
+    @public
+}"#;
+
+    let db = MigrationDatabase::new(schema).await?;
+    let conn = db.db.connect().map_err(TestError::Database)?;
+    conn.execute(
+        "insert into lineItems (id, quantity, unitCents, label) values (1, 3, 250, 'hat')",
+        (),
+    )
+    .await
+    .map_err(TestError::Database)?;
+
+    {
+        let mut rows = conn
+            .query("select totalCents, shout from lineItems where id = 1", ())
+            .await
+            .map_err(TestError::Database)?;
+        let row = rows
+            .next()
+            .await
+            .map_err(TestError::Database)?
+            .expect("inserted row should be readable");
+        let total: i64 = row.get(0).map_err(TestError::Database)?;
+        let shout: String = row.get(1).map_err(TestError::Database)?;
+        assert_eq!(total, 750);
+        assert_eq!(shout, "HAT!");
+    }
+
+    let introspection_raw = introspect_uninitialized_db(&db.db).await?;
+    let line_items = introspection_raw
+        .tables
+        .iter()
+        .find(|t| t.name == "lineItems")
+        .expect("lineItems table should be present");
+    let total = line_items
+        .columns
+        .iter()
+        .find(|column| column.name == "totalCents")
+        .expect("totalCents column should be present");
+    assert_eq!(
+        total.generated.as_deref(),
+        Some(r#"("quantity" * "unitCents")"#)
+    );
+    assert!(line_items.indexes.iter().any(|index| index
+        .columns
+        .iter()
+        .any(|column| column.name == "totalCents")));
+
+    let file = introspect::to_schema::to_schema(&introspect::Introspection {
+        tables: introspection_raw.tables.clone(),
+        migration_state: introspect::MigrationState::NoMigrationTable,
+        schema: introspect::SchemaResult::FailedToParse {
+            source: String::new(),
+            errors: vec![],
+        },
+    });
+    let rendered = pyre::generate::to_string::schema_to_string(
+        "",
+        &ast::Schema {
+            files: vec![file],
+            ..ast::Schema::default()
+        },
+    );
+    assert!(
+        rendered.contains("@computed(quantity * unitCents)"),
+        "{}",
+        rendered
+    );
+    assert!(
+        rendered.contains(r#"@computed(upper(label) ++ "!")"#),
+        "{}",
+        rendered
+    );
+
+    let db_diff = diff_database(&db, schema).await?;
+    assert!(db_diff.modified_records.is_empty());
+
+    let changed_schema = schema.replace("quantity * unitCents", "quantity * unitCents + 1");
+    let db_diff = diff_database(&db, &changed_schema).await?;
+    let line_items = db_diff
+        .modified_records
+        .iter()
+        .find(|record| record.name == "lineItems")
+        .expect("lineItems should be modified");
+    assert!(line_items.rebuild.is_some());
+
+    for statement in diff::to_sql::to_sql(&db_diff) {
+        if let pyre::generate::sql::to_sql::SqlAndParams::Sql(sql) = statement {
+            conn.execute_batch(&sql)
+                .await
+                .map_err(TestError::Database)?;
+        }
+    }
+
+    let mut rows = conn
+        .query("select totalCents from lineItems where id = 1", ())
+        .await
+        .map_err(TestError::Database)?;
+    let row = rows
+        .next()
+        .await
+        .map_err(TestError::Database)?
+        .expect("existing rows should be copied into the rebuilt table");
+    let total: i64 = row.get(0).map_err(TestError::Database)?;
+    assert_eq!(total, 751);
+
+    Ok(())
+}
+
 // ============================================================================
 // Table Migration Tests
 // ============================================================================
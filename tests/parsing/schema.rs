@@ -253,6 +253,170 @@ record Membership {
     );
 }
 
+#[test]
+fn test_valid_record_with_composite_primary_key() {
+    let schema_source = r#"
+record Membership {
+    @id(orgId, userId)
+    orgId  Int
+    userId Int
+    @public
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema)
+        .expect("Record-level @id should parse successfully");
+
+    let formatted = pyre::generate::to_string::schema_to_string("", &schema);
+    assert!(
+        formatted.contains("@id(orgId, userId)"),
+        "Formatted schema should keep the composite key. Got:\n{}",
+        formatted
+    );
+
+    let mut reparsed = ast::Schema::default();
+    parser::run("schema.pyre", &formatted, &mut reparsed)
+        .expect("Formatted composite key should parse again");
+}
+
+#[test]
+fn test_valid_record_with_table_options_and_collation() {
+    let schema_source = r#"
+record Account {
+    @withoutRowid
+    @strict
+    @id(orgId, email)
+    orgId Int
+    email String @collate(nocase)
+    @public
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema)
+        .expect("Table options and collations should parse successfully");
+
+    let formatted = pyre::generate::to_string::schema_to_string("", &schema);
+    for expected in ["@strict", "@withoutRowid", "@collate(nocase)"] {
+        assert!(
+            formatted.contains(expected),
+            "Formatted schema should keep {}. Got:\n{}",
+            expected,
+            formatted
+        );
+    }
+
+    let mut reparsed = ast::Schema::default();
+    parser::run("schema.pyre", &formatted, &mut reparsed)
+        .expect("Formatted table options should parse again");
+}
+
+#[test]
+fn test_unknown_collation_fails_to_parse() {
+    let schema_source = r#"
+record Account {
+    id    Int @id
+    email String @collate(klingon)
+    @public
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    assert!(parser::run("schema.pyre", schema_source, &mut schema).is_err());
+}
+
+#[test]
+fn test_valid_record_with_computed_columns() {
+    let schema_source = r#"
+record LineItem {
+    id         Int    @id
+    quantity   Int
+    unitCents  Int
+    totalCents Int    @computed(quantity * (unitCents + 1))
+    firstName  String
+    lastName   String
+    fullName   String @computed(firstName ++ " " ++ lastName)
+    @public
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema)
+        .expect("Computed columns should parse successfully");
+
+    let formatted = pyre::generate::to_string::schema_to_string("", &schema);
+    for expected in [
+        "@computed(quantity * (unitCents + 1))",
+        r#"@computed(firstName ++ " " ++ lastName)"#,
+    ] {
+        assert!(
+            formatted.contains(expected),
+            "Formatted schema should keep {}. Got:\n{}",
+            expected,
+            formatted
+        );
+    }
+
+    let mut reparsed = ast::Schema::default();
+    parser::run("schema.pyre", &formatted, &mut reparsed)
+        .expect("Formatted computed columns should parse again");
+}
+
+#[test]
+fn test_computed_string_literals_escape_quotes_and_backslashes() {
+    let schema_source = r#"
+record Label {
+    id    Int    @id
+    name  String
+    quoted String @computed("say \"" ++ name ++ "\" \\ done")
+    @public
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema)
+        .expect("Escaped computed strings should parse");
+
+    let formatted = pyre::generate::to_string::schema_to_string("", &schema);
+    assert!(
+        formatted.contains(r#"@computed("say \"" ++ name ++ "\" \\ done")"#),
+        "Formatted schema should escape the literal. Got:\n{}",
+        formatted
+    );
+
+    let mut reparsed = ast::Schema::default();
+    parser::run("schema.pyre", &formatted, &mut reparsed)
+        .expect("Formatted computed strings should parse again");
+    assert_eq!(
+        pyre::generate::to_string::schema_to_string("", &reparsed),
+        formatted
+    );
+}
+
+#[test]
+fn test_valid_record_with_version_field() {
+    let schema_source = r#"
+record Task {
+    id      Int    @id
+    title   String
+    version Int    @version
+    @public
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema)
+        .expect("Version fields should parse successfully");
+
+    let formatted = pyre::generate::to_string::schema_to_string("", &schema);
+    assert!(
+        formatted.contains("@version"),
+        "Formatted schema should keep @version. Got:\n{}",
+        formatted
+    );
+}
+
 #[test]
 fn test_invalid_table_level_index_with_unknown_field_fails_typecheck() {
     let schema_source = r#"
@@ -285,3 +285,27 @@ fn test_update_with_nullable_params() {
         result.err()
     );
 }
+
+#[test]
+fn test_valid_update_with_if_version() {
+    let update_source = r#"
+update RenameTask($id: Int, $version: Int, $title: String) {
+    task {
+        @where { id == $id }
+        @ifVersion($version)
+        title = $title
+    }
+}
+"#;
+
+    let query_list = parser::parse_query("query.pyre", update_source)
+        .expect("Update with @ifVersion should parse successfully");
+
+    let formatted = pyre::generate::to_string::query(&query_list);
+    assert!(
+        formatted.contains("@ifVersion($version)"),
+        "Formatted update should keep @ifVersion. Got:\n{}",
+        formatted
+    );
+    parser::parse_query("query.pyre", &formatted).expect("Formatted @ifVersion should parse again");
+}
@@ -0,0 +1,204 @@
+use crate::helpers::test_database::TestDatabase;
+use crate::helpers::TestError;
+use std::collections::HashMap;
+
+const MEMBERSHIP_SCHEMA: &str = r#"
+record Membership {
+    @public
+    @id(orgId, userId)
+
+    orgId  Int
+    userId Int
+    role   String
+}
+"#;
+
+fn key_params(org_id: i64, user_id: i64) -> HashMap<String, libsql::Value> {
+    let mut params = HashMap::new();
+    params.insert("orgId".to_string(), libsql::Value::Integer(org_id));
+    params.insert("userId".to_string(), libsql::Value::Integer(user_id));
+    params
+}
+
+#[tokio::test]
+async fn test_composite_key_rows_are_addressed_by_every_key_column() -> Result<(), TestError> {
+    let db = TestDatabase::new(MEMBERSHIP_SCHEMA).await?;
+
+    let insert = r#"
+        insert AddMember($orgId: Int, $userId: Int, $role: String) {
+            membership {
+                orgId = $orgId
+                userId = $userId
+                role = $role
+            }
+        }
+    "#;
+
+    for (org_id, user_id) in [(1, 1), (1, 2), (2, 1)] {
+        let mut params = key_params(org_id, user_id);
+        params.insert(
+            "role".to_string(),
+            libsql::Value::Text("member".to_string()),
+        );
+        db.execute_insert_with_params(insert, params).await?;
+    }
+
+    let update = r#"
+        update PromoteMember($orgId: Int, $userId: Int) {
+            membership {
+                @where { orgId == $orgId && userId == $userId }
+                role = "owner"
+                orgId
+                userId
+            }
+        }
+    "#;
+    db.execute_query_with_params(update, key_params(1, 2))
+        .await?;
+
+    let query = r#"
+        query Members {
+            membership {
+                orgId
+                userId
+                role
+            }
+        }
+    "#;
+    let rows = db.execute_query(query).await?;
+    let results = db.parse_query_results(rows).await?;
+    let members = results.get("membership").expect("membership results");
+
+    let owners = members
+        .iter()
+        .filter(|member| member["role"] == "owner")
+        .map(|member| (member["orgId"].as_i64(), member["userId"].as_i64()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(members.len(), 3);
+    assert_eq!(owners, vec![(Some(1), Some(2))]);
+
+    Ok(())
+}
+
+const GRANT_SCHEMA: &str = r#"
+record Membership {
+    @public
+    @id(orgId, userId)
+
+    orgId  Int
+    userId Int
+    role   String
+
+    grants @link(orgId, userId, Grant.orgId, Grant.userId)
+}
+
+record Grant {
+    @public
+    id     Int @id
+    orgId  Int
+    userId Int
+    scope  String
+
+    membership @link(orgId, userId, Membership.orgId, Membership.userId)
+}
+"#;
+
+#[tokio::test]
+async fn test_composite_links_join_on_every_column() -> Result<(), TestError> {
+    let db = TestDatabase::new(GRANT_SCHEMA).await?;
+
+    let add_member = r#"
+        insert AddMember($orgId: Int, $userId: Int, $role: String) {
+            membership {
+                orgId = $orgId
+                userId = $userId
+                role = $role
+            }
+        }
+    "#;
+    for (org_id, user_id, role) in [(1, 2, "owner"), (2, 1, "member")] {
+        let mut params = key_params(org_id, user_id);
+        params.insert("role".to_string(), libsql::Value::Text(role.to_string()));
+        db.execute_insert_with_params(add_member, params).await?;
+    }
+
+    let add_grant = r#"
+        insert AddGrant($orgId: Int, $userId: Int, $scope: String) {
+            grant {
+                orgId = $orgId
+                userId = $userId
+                scope = $scope
+            }
+        }
+    "#;
+    for (org_id, user_id, scope) in [(1, 2, "billing"), (1, 2, "admin"), (2, 1, "read")] {
+        let mut params = key_params(org_id, user_id);
+        params.insert("scope".to_string(), libsql::Value::Text(scope.to_string()));
+        db.execute_insert_with_params(add_grant, params).await?;
+    }
+
+    // (1, 2) and (2, 1) share every individual value, so a join on a
+    // single column would mix their grants up.
+    let members_query = r#"
+        query MembersWithGrants {
+            membership {
+                @sort(orgId, Asc)
+                orgId
+                userId
+                grants {
+                    scope
+                }
+            }
+        }
+    "#;
+    let rows = db.execute_query(members_query).await?;
+    let results = db.parse_query_results(rows).await?;
+    let members = results.get("membership").expect("membership results");
+
+    let scopes = |member: &serde_json::Value| {
+        let mut scopes = member["grants"]
+            .as_array()
+            .expect("grants array")
+            .iter()
+            .map(|grant| grant["scope"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        scopes.sort();
+        scopes
+    };
+    assert_eq!(members.len(), 2);
+    assert_eq!(scopes(&members[0]), vec!["admin", "billing"]);
+    assert_eq!(scopes(&members[1]), vec!["read"]);
+
+    let grants_query = r#"
+        query GrantsWithMembership {
+            grant {
+                scope
+                membership {
+                    role
+                }
+            }
+        }
+    "#;
+    let rows = db.execute_query(grants_query).await?;
+    let results = db.parse_query_results(rows).await?;
+    let grants = results.get("grant").expect("grant results");
+
+    let roles = grants
+        .iter()
+        .map(|grant| {
+            (
+                grant["scope"].as_str().unwrap_or_default().to_string(),
+                grant["membership"]["role"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    assert_eq!(roles["billing"], "owner");
+    assert_eq!(roles["admin"], "owner");
+    assert_eq!(roles["read"], "member");
+
+    Ok(())
+}
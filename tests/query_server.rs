@@ -1,41 +1,14 @@
 #[allow(dead_code, unused_imports)]
 mod helpers;
 
+use helpers::server::manifest_for;
 use helpers::test_database::TestDatabase;
+use pyre::server::idempotency::{self, Outcome};
 use pyre::server::manifest::{Manifest, PyreSession, QueryManifest};
 use pyre::server::query;
+use pyre::server::sync::{ConnectedSessions, SyncServer, SyncSession};
 use serde_json::json;
 
-fn manifest_for(
-    context: &pyre::typecheck::Context,
-    query_source: &str,
-    include_generated_crud: bool,
-) -> Result<Manifest, Box<dyn std::error::Error>> {
-    let mut query_list = if query_source.trim().is_empty() {
-        pyre::ast::QueryList {
-            queries: Vec::new(),
-        }
-    } else {
-        pyre::parser::parse_query("query.pyre", query_source)
-            .map_err(|err| format!("query parse failed: {:?}", err))?
-    };
-
-    if include_generated_crud {
-        pyre::generated_queries::append_generated_crud_queries(&mut query_list, context);
-    }
-
-    let query_info = pyre::typecheck::check_queries(&query_list, context)
-        .map_err(|errors| format!("query typecheck failed: {:?}", errors))?;
-    let mut files = Vec::new();
-    pyre::generate::manifest::generate_queries(context, &query_list, &query_info, &mut files);
-    let manifest_file = files
-        .into_iter()
-        .find(|file| file.path == std::path::Path::new("manifest.json"))
-        .ok_or("manifest file should be generated")?;
-
-    Ok(serde_json::from_str(&manifest_file.contents)?)
-}
-
 fn only_query(manifest: &Manifest) -> &QueryManifest {
     manifest
         .queries
@@ -639,6 +612,455 @@ query Dashboard {
     Ok(())
 }
 
+const BATCH_SCHEMA: &str = r#"
+record Note {
+    id Int @id
+    slug String @unique
+    body String
+    @public
+}
+"#;
+
+const BATCH_QUERIES: &str = r#"
+insert CreateNote($slug: String, $body: String) {
+    note {
+        slug = $slug
+        body = $body
+        id
+    }
+}
+
+query GetNotes {
+    note {
+        slug
+    }
+}
+"#;
+
+fn batch_item(manifest: &Manifest, operation: &str, input: serde_json::Value) -> query::BatchItem {
+    query::BatchItem {
+        query_id: query_by_operation(manifest, operation).id.clone(),
+        input,
+    }
+}
+
+async fn note_count(conn: &libsql::Connection) -> Result<i64, Box<dyn std::error::Error>> {
+    let mut rows = conn.query("select count(*) from notes", ()).await?;
+    let row = rows.next().await?.ok_or("count should return a row")?;
+    Ok(row.get::<i64>(0)?)
+}
+
+#[tokio::test]
+async fn run_batch_reports_each_item_and_combines_affected_rows(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(BATCH_SCHEMA).await?;
+    let conn = db.db.connect()?;
+    let manifest = manifest_for(&db.context, BATCH_QUERIES, false)?;
+    let session = PyreSession::new(json!({}), &manifest.session_schema)?;
+    let items = vec![
+        batch_item(&manifest, "insert", json!({ "slug": "a", "body": "one" })),
+        batch_item(
+            &manifest,
+            "insert",
+            json!({ "slug": "a", "body": "duplicate" }),
+        ),
+        batch_item(&manifest, "insert", json!({ "slug": "b", "body": "two" })),
+    ];
+
+    let result = query::run_batch(
+        &conn,
+        &manifest,
+        &items,
+        &session,
+        query::BatchOptions {
+            transaction: false,
+            sync: true,
+        },
+    )
+    .await?;
+
+    assert!(result.items[0].is_ok());
+    assert!(matches!(
+        result.items[1],
+        Err(query::BatchItemError::Failed(query::Error::Database(_)))
+    ));
+    assert!(result.items[2].is_ok());
+    assert_eq!(result.committed, None);
+    let affected_rows = result
+        .affected_rows
+        .iter()
+        .map(|group| group.rows.len())
+        .sum::<usize>();
+    assert_eq!(affected_rows, 2);
+    assert_eq!(note_count(&conn).await?, 2);
+
+    let response = result.into_query_result().response;
+    assert_eq!(response["results"][0]["ok"], json!(true));
+    assert_eq!(response["results"][1]["ok"], json!(false));
+    assert!(response.get("committed").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn run_batch_transaction_rolls_back_every_item_on_failure(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(BATCH_SCHEMA).await?;
+    let conn = db.db.connect()?;
+    let manifest = manifest_for(&db.context, BATCH_QUERIES, false)?;
+    let session = PyreSession::new(json!({}), &manifest.session_schema)?;
+    let transaction = query::BatchOptions {
+        transaction: true,
+        sync: false,
+    };
+
+    let failed = query::run_batch(
+        &conn,
+        &manifest,
+        &[
+            batch_item(&manifest, "insert", json!({ "slug": "a", "body": "one" })),
+            batch_item(
+                &manifest,
+                "insert",
+                json!({ "slug": "a", "body": "duplicate" }),
+            ),
+            batch_item(&manifest, "query", json!({})),
+        ],
+        &session,
+        transaction,
+    )
+    .await?;
+
+    assert!(matches!(
+        failed.items[0],
+        Err(query::BatchItemError::RolledBack)
+    ));
+    assert!(matches!(
+        failed.items[1],
+        Err(query::BatchItemError::Failed(_))
+    ));
+    assert!(matches!(
+        failed.items[2],
+        Err(query::BatchItemError::NotRun)
+    ));
+    assert_eq!(failed.committed, Some(false));
+    assert!(failed.affected_rows.is_empty());
+    assert_eq!(note_count(&conn).await?, 0);
+
+    let committed = query::run_batch(
+        &conn,
+        &manifest,
+        &[
+            batch_item(&manifest, "insert", json!({ "slug": "a", "body": "one" })),
+            batch_item(&manifest, "query", json!({})),
+        ],
+        &session,
+        transaction,
+    )
+    .await?;
+
+    assert_eq!(committed.committed, Some(true));
+    let response = committed.into_query_result().response;
+    assert_eq!(response["committed"], json!(true));
+    assert_eq!(
+        response["results"][1]["result"]["note"][0]["slug"],
+        json!("a")
+    );
+    assert_eq!(note_count(&conn).await?, 1);
+
+    let empty = query::run_batch(&conn, &manifest, &[], &session, transaction).await;
+    assert!(matches!(empty, Err(query::Error::InvalidInput(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn idempotency_keys_apply_a_mutation_once() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(BATCH_SCHEMA).await?;
+    let conn = db.db.connect()?;
+    let manifest = manifest_for(&db.context, BATCH_QUERIES, false)?;
+    let session = PyreSession::new(json!({}), &manifest.session_schema)?;
+    let insert = query_by_operation(&manifest, "insert").id.clone();
+    let input = json!({ "slug": "a", "body": "one" });
+
+    let first = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        input.clone(),
+        &session,
+        "key-1",
+        false,
+    )
+    .await?;
+    let Outcome::Applied(first) = first else {
+        panic!("the first request should run");
+    };
+    let retry = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        input.clone(),
+        &session,
+        "key-1",
+        false,
+    )
+    .await?;
+    let Outcome::Replayed(retry) = retry else {
+        panic!("the retry should be replayed");
+    };
+    assert_eq!(retry, first.response);
+    assert_eq!(note_count(&conn).await?, 1);
+
+    let reused = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        json!({ "slug": "b", "body": "two" }),
+        &session,
+        "key-1",
+        false,
+    )
+    .await;
+    assert!(matches!(reused, Err(idempotency::Error::KeyReused(_))));
+
+    // A failed request records nothing, so it can be retried once fixed.
+    let failed = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        input.clone(),
+        &session,
+        "key-2",
+        false,
+    )
+    .await;
+    assert!(matches!(failed, Err(idempotency::Error::Query(_))));
+    conn.execute("delete from notes", ()).await?;
+    let retried =
+        idempotency::run(&conn, &manifest, &insert, input, &session, "key-2", false).await?;
+    assert!(matches!(retried, Outcome::Applied(_)));
+    assert_eq!(note_count(&conn).await?, 1);
+
+    conn.execute(
+        "update _pyre_idempotency set created_at = created_at - 7200",
+        (),
+    )
+    .await?;
+    assert_eq!(idempotency::prune_idempotency(&conn, 3600).await?, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn idempotency_keys_are_scoped_to_the_session() -> Result<(), Box<dyn std::error::Error>> {
+    let schema = format!(
+        r#"
+session {{
+    userId Int
+    teamId Int
+}}
+{}"#,
+        BATCH_SCHEMA
+    );
+    let db = TestDatabase::new(&schema).await?;
+    let conn = db.db.connect()?;
+    let manifest = manifest_for(&db.context, BATCH_QUERIES, false)?;
+    let insert = query_by_operation(&manifest, "insert").id.clone();
+    let session = |value: serde_json::Value| PyreSession::new(value, &manifest.session_schema);
+    let first_user = session(json!({ "userId": 1, "teamId": 1 }))?;
+    let second_user = session(json!({ "userId": 2, "teamId": 1 }))?;
+
+    let first = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        json!({ "slug": "a", "body": "one" }),
+        &first_user,
+        "key-1",
+        false,
+    )
+    .await?;
+    assert!(matches!(first, Outcome::Applied(_)));
+
+    // Another user's request with the same key is a request of its own.
+    let second = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        json!({ "slug": "b", "body": "two" }),
+        &second_user,
+        "key-1",
+        false,
+    )
+    .await?;
+    assert!(matches!(second, Outcome::Applied(_)));
+    assert_eq!(note_count(&conn).await?, 2);
+
+    // Values outside the `session` block, such as a refreshed token's `exp`, are
+    // dropped from the session and leave the scope as it was.
+    let refreshed = session(json!({ "userId": 1, "teamId": 1, "exp": 4102444800i64 }))?;
+    let retry = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        json!({ "slug": "a", "body": "one" }),
+        &refreshed,
+        "key-1",
+        false,
+    )
+    .await?;
+    assert!(matches!(retry, Outcome::Replayed(_)));
+
+    // Every session field is part of the scope, so a retry after the user moved teams
+    // runs again under the new session's permissions.
+    let moved = session(json!({ "userId": 1, "teamId": 2 }))?;
+    conn.execute("delete from notes", ()).await?;
+    let retry = idempotency::run(
+        &conn,
+        &manifest,
+        &insert,
+        json!({ "slug": "a", "body": "one" }),
+        &moved,
+        "key-1",
+        false,
+    )
+    .await?;
+    assert!(matches!(retry, Outcome::Applied(_)));
+    assert_eq!(note_count(&conn).await?, 1);
+
+    Ok(())
+}
+
+const VERSIONED_SCHEMA: &str = r#"
+record Task {
+    id Int @id
+    title String
+    version Int @version
+    @public
+}
+"#;
+
+const VERSIONED_QUERIES: &str = r#"
+insert CreateTask($title: String) {
+    task {
+        title = $title
+        id
+        version
+    }
+}
+
+update RenameTask($id: Int, $version: Int, $title: String) {
+    task {
+        @where { id == $id }
+        @ifVersion($version)
+        title = $title
+        id
+        version
+    }
+}
+"#;
+
+#[tokio::test]
+async fn if_version_updates_report_conflicts_with_the_current_row(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(VERSIONED_SCHEMA).await?;
+    let conn = db.db.connect()?;
+    let manifest = manifest_for(&db.context, VERSIONED_QUERIES, false)?;
+    let session = PyreSession::new(json!({}), &manifest.session_schema)?;
+    let insert = query_by_operation(&manifest, "insert").id.clone();
+    let update = query_by_operation(&manifest, "update").id.clone();
+
+    let created = query::run(&conn, &manifest, &insert, json!({ "title": "a" }), &session).await?;
+    assert_eq!(
+        created.response["task"],
+        json!([{ "id": 1, "title": "a", "version": 1 }])
+    );
+
+    let renamed = query::run(
+        &conn,
+        &manifest,
+        &update,
+        json!({ "id": 1, "version": 1, "title": "b" }),
+        &session,
+    )
+    .await?;
+    assert_eq!(
+        renamed.response,
+        json!({
+            "task": [{ "id": 1, "title": "b", "version": 2 }],
+            "taskConflicts": []
+        })
+    );
+
+    // A second client still holding version 1 loses, and gets the row as it is now.
+    let stale = query::run(
+        &conn,
+        &manifest,
+        &update,
+        json!({ "id": 1, "version": 1, "title": "c" }),
+        &session,
+    )
+    .await?;
+    assert_eq!(
+        stale.response,
+        json!({
+            "task": [],
+            "taskConflicts": [{ "id": 1, "title": "b", "version": 2 }]
+        })
+    );
+
+    let mut stale_sync = query::run_sync(
+        &conn,
+        &manifest,
+        &update,
+        json!({ "id": 1, "version": 1, "title": "c" }),
+        &session,
+    )
+    .await?;
+    assert!(stale_sync
+        .affected_rows
+        .iter()
+        .all(|group| group.rows.is_empty()));
+    assert_eq!(stale_sync.conflicts.len(), 1);
+    assert_eq!(stale_sync.conflicts[0].table_name, "tasks");
+
+    let connected_sessions = ConnectedSessions::from([
+        ("origin".to_string(), SyncSession::new()),
+        ("other".to_string(), SyncSession::new()),
+    ]);
+    let messages = SyncServer::new(&db.context)
+        .calculate_deltas(
+            &conn,
+            &mut stale_sync,
+            &connected_sessions,
+            "main",
+            Some("origin"),
+        )
+        .await?;
+    assert!(messages.is_empty(), "nothing changed for other sessions");
+    let sync = &stale_sync.response["sync"];
+    assert_eq!(sync["type"], "delta");
+    assert_eq!(sync["conflicts"][0]["table_name"], "tasks");
+    let headers = sync["conflicts"][0]["headers"].as_array().unwrap();
+    let version_index = headers
+        .iter()
+        .position(|header| header == "version")
+        .unwrap();
+    assert_eq!(sync["conflicts"][0]["rows"][0][version_index], json!(2));
+
+    let mut title: Option<String> = None;
+    let mut rows = conn
+        .query("select title from tasks where id = 1", ())
+        .await?;
+    if let Some(row) = rows.next().await? {
+        title = Some(row.get(0)?);
+    }
+    assert_eq!(title.as_deref(), Some("b"));
+
+    Ok(())
+}
+
 #[test]
 fn manifest_load_reads_generated_manifest_file() -> Result<(), Box<dyn std::error::Error>> {
     let manifest = Manifest {
@@ -657,3 +1079,24 @@ fn manifest_load_reads_generated_manifest_file() -> Result<(), Box<dyn std::erro
 
     Ok(())
 }
+
+#[test]
+fn manifest_hash_keeps_names_and_types_apart() {
+    let hash = |fields: &[(&'static str, &'static str, bool)], query_ids: &[&'static str]| {
+        pyre::hash::hash_manifest(fields.iter().copied(), query_ids.iter().copied())
+    };
+
+    assert_eq!(
+        hash(&[("userId", "Int", false)], &["a"]),
+        hash(&[("userId", "Int", false)], &["a"])
+    );
+    assert_ne!(
+        hash(&[("userId", "Int", false)], &[]),
+        hash(&[("user", "IdInt", false)], &[])
+    );
+    assert_ne!(hash(&[], &["ab", "c"]), hash(&[], &["a", "bc"]));
+    assert_ne!(
+        hash(&[("userId", "Int", false)], &[]),
+        hash(&[("userId", "Int", true)], &[])
+    );
+}
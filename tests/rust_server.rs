@@ -77,3 +77,52 @@ query GetGame($id: Int, $name: String?, $description: String?) {
         content
     );
 }
+
+#[test]
+fn generated_rust_output_lists_version_conflicts_with_the_row_type() {
+    let schema_source = r#"
+record Task {
+    @public
+
+    id Id.Int @id
+    title String
+    version Int @version
+}
+"#;
+
+    let query_source = r#"
+update RenameTask($id: Int, $version: Int, $title: String) {
+    task {
+        @where { id == $id }
+        @ifVersion($version)
+        title = $title
+        id
+        version
+    }
+}
+"#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema parses");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let context = typecheck::check_schema(&database).expect("schema typechecks");
+    let query_list = parser::parse_query("query.pyre", query_source).expect("query parses");
+
+    let mut files: Vec<GeneratedFile<String>> = Vec::new();
+    rust::generate_queries(&context, &query_list, Path::new("rust"), &mut files);
+    let content = &files
+        .iter()
+        .find(|file| file.path == Path::new("rust/server.rs"))
+        .expect("generated Rust server file")
+        .contents;
+
+    assert!(
+        content.contains("pub task: Vec<Task>,")
+            && content.contains("#[serde(rename = \"taskConflicts\")]")
+            && content.contains("pub task_conflicts: Vec<Task>,"),
+        "Expected conflicts typed like the updated rows. Generated:\n{}",
+        content
+    );
+}
@@ -135,6 +135,52 @@ record Note {
     assert!(result.tables[0].sql[0].contains("LIMIT 5001"));
 }
 
+#[test]
+fn sync_sql_orders_composite_key_tables_by_every_key_column() {
+    let schema_source = r#"
+record Membership {
+    @id(orgId, userId)
+    orgId Int
+    userId Int
+    updatedAt Int
+    @public
+}
+"#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let context = typecheck::check_schema(&database).expect("schema should typecheck");
+    let sync_status = SyncStatusResult {
+        tables: vec![TableSyncStatus {
+            table_name: "memberships".to_string(),
+            sync_layer: 0,
+            needs_sync: true,
+            max_updated_at: None,
+            permission_hash: "perm".to_string(),
+        }],
+    };
+
+    let result = get_sync_sql(
+        &sync_status,
+        &SyncCursor::new(),
+        &context,
+        &Default::default(),
+        100,
+    )
+    .expect("sync sql should generate");
+
+    assert_eq!(
+        result.tables[0].primary_key,
+        vec!["orgId".to_string(), "userId".to_string()]
+    );
+    assert!(result.tables[0].sql[0].contains(
+        "ORDER BY \"memberships\".updatedAt ASC, \"memberships\".\"orgId\" ASC, \"memberships\".\"userId\" ASC"
+    ));
+}
+
 #[test]
 fn sync_cursor_rejects_unknown_tables() {
     let schema_source = r#"
@@ -156,7 +202,9 @@ record Note {
         "not_a_table".to_string(),
         pyre::sync::TableCursor {
             last_seen_updated_at: Some(1),
+            last_seen_key: None,
             permission_hash: "perm".to_string(),
+            last_seen_tombstone: None,
         },
     );
 
@@ -192,7 +240,9 @@ record Note {
         "notes".to_string(),
         pyre::sync::TableCursor {
             last_seen_updated_at: Some(1),
+            last_seen_key: None,
             permission_hash: "x".repeat(pyre::sync::MAX_SYNC_CURSOR_PERMISSION_HASH_BYTES + 1),
+            last_seen_tombstone: None,
         },
     );
 
@@ -207,6 +257,76 @@ record Note {
     }
 }
 
+#[test]
+fn sync_cursor_rejects_malformed_last_seen_keys() {
+    let schema_source = r#"
+record Note {
+    id Int @id
+    updatedAt Int
+    @public
+}
+"#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let context = typecheck::check_schema(&database).expect("schema should typecheck");
+    let cursor_with = |last_seen_updated_at: Option<i64>, key: serde_json::Value| {
+        let mut cursor = SyncCursor::new();
+        cursor.insert(
+            "notes".to_string(),
+            pyre::sync::TableCursor {
+                last_seen_updated_at,
+                last_seen_key: key.as_object().cloned(),
+                permission_hash: "perm".to_string(),
+                last_seen_tombstone: None,
+            },
+        );
+        cursor
+    };
+
+    for (cursor, expected) in [
+        (
+            cursor_with(None, serde_json::json!({ "id": 1 })),
+            "requires last_seen_updated_at",
+        ),
+        (
+            cursor_with(Some(1), serde_json::json!({ "body": 1 })),
+            "primary key columns",
+        ),
+        (
+            cursor_with(Some(1), serde_json::json!({ "id": 1, "body": 1 })),
+            "primary key columns",
+        ),
+        (
+            cursor_with(Some(1), serde_json::json!({ "id": [1] })),
+            "scalar values",
+        ),
+    ] {
+        match pyre::sync::get_sync_status_statement(&cursor, &context, &Default::default()) {
+            Err(pyre::sync::SyncError::InvalidSyncCursor(message)) => {
+                assert!(message.contains(expected), "{}", message);
+            }
+            _ => panic!("expected invalid sync cursor error for {}", expected),
+        }
+    }
+
+    let valid = cursor_with(Some(1), serde_json::json!({ "id": 1 }));
+    let statement = pyre::sync::get_sync_status_statement(&valid, &context, &Default::default())
+        .expect("a key matching the primary key should be accepted");
+    assert!(statement.sql.contains("AS has_new_rows"));
+    assert_eq!(
+        statement.params,
+        vec![
+            pyre::sync::SessionValue::Integer(1),
+            pyre::sync::SessionValue::Integer(1),
+            pyre::sync::SessionValue::Integer(1),
+        ]
+    );
+}
+
 #[test]
 fn query_only_namespaces_are_excluded_from_sync_sql() {
     let main_source = r#"
@@ -310,7 +430,7 @@ record Account {
 
     assert_eq!(
         status_sql,
-        "SELECT NULL AS table_name, NULL AS sync_layer, NULL AS permission_hash, NULL AS last_seen_updated_at, NULL AS max_updated_at WHERE 0"
+        "SELECT NULL AS table_name, NULL AS sync_layer, NULL AS permission_hash, NULL AS last_seen_updated_at, NULL AS max_updated_at, NULL AS has_new_rows WHERE 0"
     );
 }
 
@@ -430,6 +550,7 @@ record Map {
                 json!("Png"),
                 json!(1700000000),
             ]],
+            previous: None,
         }],
         &context,
     );
@@ -0,0 +1,149 @@
+#[allow(dead_code, unused_imports)]
+mod helpers;
+
+use helpers::server::{manifest_for, query_id, user_session};
+use helpers::test_database::TestDatabase;
+use pyre::client::sync::{Error as SyncClientError, SyncClient, SyncClientConfig, SyncEvent};
+use pyre::server::database_id::DatabaseResolver;
+use pyre::server::http::{HttpConfig, HttpServer};
+use pyre::server::manifest::Manifest;
+use serde_json::json;
+
+const SCHEMA: &str = r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    body String
+    updatedAt Int
+    @allow(*) { ownerId == Session.userId }
+}
+"#;
+
+const QUERIES: &str = r#"
+query GetNotes {
+    note {
+        id
+        body
+    }
+}
+
+insert CreateNote($body: String) {
+    note {
+        ownerId = Session.userId
+        body = $body
+        updatedAt = 20
+    }
+}
+"#;
+
+fn client_config(port: u16, manifest: Manifest, user_id: i64) -> SyncClientConfig {
+    SyncClientConfig::new(format!("http://127.0.0.1:{}", port), manifest)
+        .with_header("x-user", user_id.to_string())
+        .with_session(json!({ "userId": user_id }))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replica_catches_up_follows_live_deltas_and_runs_queries_locally(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(SCHEMA).await?;
+    db.db
+        .connect()?
+        .execute_batch(
+            "insert into notes (id, ownerId, body, updatedAt) values (1, 1, 'mine', 10), (2, 2, 'theirs', 10);",
+        )
+        .await?;
+    let manifest = manifest_for(&db.context, QUERIES, false)?;
+    let get_notes = query_id(&manifest, "query");
+    let create_note = query_id(&manifest, "insert");
+
+    let database_path = db.temp_dir.path().join("test.db");
+    let databases =
+        DatabaseResolver::from_database_arg(database_path.to_str().unwrap(), "default")?;
+    let pyre =
+        HttpServer::new(HttpConfig::new(manifest.clone(), databases).with_session(user_session));
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(axum::Server::from_tcp(listener)?.serve(pyre.router().into_make_service()));
+
+    let replica_dir = tempfile::TempDir::new()?;
+    let replica_path = replica_dir.path().join("replica.db");
+    let client = SyncClient::open(
+        &replica_path,
+        SCHEMA,
+        client_config(port, manifest.clone(), 1),
+    )
+    .await?;
+    client.catchup().await?;
+    assert_eq!(
+        client.query(&get_notes, json!({})).await?["note"],
+        json!([{ "id": 1, "body": "mine" }]),
+        "only the session's rows are replicated"
+    );
+    assert!(client.cursor().await?.contains_key("notes"));
+
+    let mut live = client.subscribe().await?;
+    assert!(matches!(
+        live.next().await?,
+        Some(SyncEvent::Connected { .. })
+    ));
+
+    // Another connection of the same user writes, and the delta arrives live.
+    let other = SyncClient::open(
+        replica_dir.path().join("other.db"),
+        SCHEMA,
+        client_config(port, manifest.clone(), 1),
+    )
+    .await?;
+    other
+        .mutate(&create_note, json!({ "body": "from elsewhere" }))
+        .await?;
+    assert!(matches!(live.next().await?, Some(SyncEvent::Delta(_))));
+    assert_eq!(
+        client.query(&get_notes, json!({})).await?["note"]
+            .as_array()
+            .map(Vec::len),
+        Some(2)
+    );
+
+    // The client's own mutation is applied from the response.
+    let created = client
+        .mutate(&create_note, json!({ "body": "from here" }))
+        .await?;
+    assert!(
+        created.is_object() && created.get("sync").is_none(),
+        "got {}",
+        created
+    );
+    let notes = client.query(&get_notes, json!({})).await?;
+    assert_eq!(notes["note"][2]["body"], json!("from here"));
+    drop(live);
+
+    assert!(matches!(
+        client.query(&create_note, json!({ "body": "x" })).await,
+        Err(SyncClientError::NotAQuery(_))
+    ));
+
+    // Reopening keeps the rows and the cursor.
+    drop(client);
+    let reopened =
+        SyncClient::open(&replica_path, SCHEMA, client_config(port, manifest, 1)).await?;
+    assert_eq!(
+        reopened.query(&get_notes, json!({})).await?["note"]
+            .as_array()
+            .map(Vec::len),
+        Some(3)
+    );
+    reopened.catchup().await?;
+    assert_eq!(
+        reopened.query(&get_notes, json!({})).await?["note"]
+            .as_array()
+            .map(Vec::len),
+        Some(3)
+    );
+
+    Ok(())
+}
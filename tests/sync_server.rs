@@ -2,16 +2,20 @@
 mod helpers;
 
 use helpers::test_database::TestDatabase;
+use pyre::server::database_id::{DatabaseIdError, DatabaseResolver};
 use pyre::server::manifest::{FieldSchema, PyreSession};
 use pyre::server::query::QueryResult;
 use pyre::server::schema::{
     load_context_from_database, load_schema_from_database, Error as SchemaError,
 };
 use pyre::server::sync::{
-    catchup, ConnectedSessions, DeltaMessage, SyncServer, SyncSession,
+    catchup, catchup_shape, ensure_tombstones, prune_changes, prune_tombstones, read_changes,
+    ConnectedSessions, DeltaMessage, RecentDeltas, SessionShapes, SyncServer, SyncSession,
     MAX_LIVE_SYNC_DELTA_PAYLOAD_BYTES, MAX_LIVE_SYNC_DELTA_ROWS, MAX_LIVE_SYNC_FANOUT_RECIPIENTS,
 };
-use pyre::sync::{SyncCursor, TableCursor, TableSyncData};
+use pyre::sync::{
+    ShapeDefinition, ShapeFilter, SyncCursor, SyncPageResult, TableCursor, TableSyncData,
+};
 use pyre::sync_deltas::AffectedRowTableGroup;
 use serde_json::json;
 use std::collections::HashMap;
@@ -20,6 +24,7 @@ fn query_result(affected_rows: Vec<AffectedRowTableGroup>) -> QueryResult {
     QueryResult {
         response: json!({}),
         affected_rows,
+        conflicts: Vec::new(),
     }
 }
 
@@ -60,6 +65,59 @@ async fn extract_affected_rows(
     Err("missing _affectedRows result set".into())
 }
 
+fn query_shapes(
+    db: &TestDatabase,
+    query_source: &str,
+) -> Result<Vec<ShapeDefinition>, Box<dyn std::error::Error>> {
+    let query_list =
+        pyre::parser::parse_query("query.pyre", query_source).map_err(|_| "query should parse")?;
+    let query = query_list
+        .queries
+        .iter()
+        .find_map(|query| match query {
+            pyre::ast::QueryDef::Query(query) => Some(query),
+            _ => None,
+        })
+        .ok_or("missing query")?;
+    Ok(pyre::sync::shape_definitions(&db.context, query).ok_or("query should have shapes")?)
+}
+
+fn project_shape(
+    shapes: &[ShapeDefinition],
+    project_id: i64,
+    session: &SyncSession,
+) -> Result<ShapeFilter, Box<dyn std::error::Error>> {
+    let params = json!({ "projectId": project_id });
+    let params = params.as_object().ok_or("params should be an object")?;
+    Ok(ShapeFilter::resolve(shapes, params, session).map_err(pyre::server::sync::Error::Sync)?)
+}
+
+const TASKS_SCHEMA: &str = r#"
+session {
+    userId Int
+}
+
+record Task {
+    id Int @id
+    projectId Int
+    ownerId Int
+    title String
+    updatedAt Int
+    @allow(query) { ownerId == Session.userId }
+    @allow(insert, update) { ownerId == 1 }
+}
+"#;
+
+const PROJECT_TASKS_QUERY: &str = r#"
+query ProjectTasks($projectId: Int) {
+    task {
+        @where { projectId == $projectId }
+        id
+        title
+    }
+}
+"#;
+
 #[tokio::test]
 async fn catchup_paginates_and_advances_cursor() -> Result<(), Box<dyn std::error::Error>> {
     let db = TestDatabase::new(
@@ -98,7 +156,9 @@ insert into notes (id, body, updatedAt) values (3, 'three', 30);
         "notes".to_string(),
         TableCursor {
             last_seen_updated_at: notes.last_seen_updated_at,
+            last_seen_key: notes.last_seen_key.clone(),
             permission_hash: notes.permission_hash.clone(),
+            last_seen_tombstone: notes.last_seen_tombstone,
         },
     );
 
@@ -117,7 +177,9 @@ insert into notes (id, body, updatedAt) values (3, 'three', 30);
         "notes".to_string(),
         TableCursor {
             last_seen_updated_at: notes.last_seen_updated_at,
+            last_seen_key: notes.last_seen_key.clone(),
             permission_hash: notes.permission_hash.clone(),
+            last_seen_tombstone: notes.last_seen_tombstone,
         },
     );
 
@@ -128,12 +190,235 @@ insert into notes (id, body, updatedAt) values (3, 'three', 30);
     Ok(())
 }
 
+#[tokio::test]
+async fn catchup_pages_through_rows_sharing_an_updated_at() -> Result<(), Box<dyn std::error::Error>>
+{
+    let db = TestDatabase::new(
+        r#"
+record Note {
+    id Int @id
+    body String
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    conn.execute_batch(
+        r#"
+insert into notes (id, body, updatedAt) values (1, 'one', 10);
+insert into notes (id, body, updatedAt) values (2, 'two', 10);
+insert into notes (id, body, updatedAt) values (3, 'three', 10);
+insert into notes (id, body, updatedAt) values (4, 'four', 10);
+insert into notes (id, body, updatedAt) values (5, 'five', 20);
+"#,
+    )
+    .await?;
+
+    let session = HashMap::new();
+    let mut cursor = SyncCursor::new();
+    let mut seen = Vec::new();
+    loop {
+        let page = catchup(&conn, &db.context, &cursor, &session, 2).await?;
+        if let Some(notes) = page.tables.get("notes") {
+            seen.extend(notes.rows.iter().map(|row| row["id"].clone()));
+        }
+        cursor = cursor_after(&cursor, &page);
+        if !page.has_more {
+            break;
+        }
+    }
+
+    assert_eq!(seen, vec![json!(1), json!(2), json!(3), json!(4), json!(5)]);
+    assert_eq!(cursor["notes"].last_seen_updated_at, Some(20));
+    assert_eq!(
+        cursor["notes"].last_seen_key,
+        json!({ "id": 5 }).as_object().cloned()
+    );
+
+    // A row written later at an already-seen timestamp is still picked up.
+    conn.execute_batch("insert into notes (id, body, updatedAt) values (6, 'six', 20);")
+        .await?;
+    let page = catchup(&conn, &db.context, &cursor, &session, 2).await?;
+    let notes = page.tables.get("notes").expect("the late row should sync");
+    assert_eq!(notes.rows.len(), 1);
+    assert_eq!(notes.rows[0]["id"], json!(6));
+
+    let cursor = cursor_after(&cursor, &page);
+    let page = catchup(&conn, &db.context, &cursor, &session, 2).await?;
+    assert!(page.tables.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cursors_without_a_key_resume_after_their_timestamp(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+record Note {
+    id Int @id
+    body String
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    conn.execute_batch(
+        r#"
+insert into notes (id, body, updatedAt) values (1, 'one', 10);
+insert into notes (id, body, updatedAt) values (2, 'two', 10);
+insert into notes (id, body, updatedAt) values (3, 'three', 20);
+"#,
+    )
+    .await?;
+
+    let session = HashMap::new();
+    let first = catchup(&conn, &db.context, &SyncCursor::new(), &session, 10).await?;
+    let notes = &first.tables["notes"];
+    let mut legacy = SyncCursor::new();
+    legacy.insert(
+        "notes".to_string(),
+        TableCursor {
+            last_seen_updated_at: Some(10),
+            last_seen_key: None,
+            permission_hash: notes.permission_hash.clone(),
+            last_seen_tombstone: notes.last_seen_tombstone,
+        },
+    );
+
+    let page = catchup(&conn, &db.context, &legacy, &session, 10).await?;
+    let notes = &page.tables["notes"];
+    assert_eq!(
+        notes
+            .rows
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect::<Vec<_>>(),
+        vec![json!(3)]
+    );
+    assert_eq!(notes.last_seen_key, json!({ "id": 3 }).as_object().cloned());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn catchup_shape_syncs_only_rows_the_query_reads() -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(TASKS_SCHEMA).await?;
+    let conn = db.db.connect()?;
+    conn.execute_batch(
+        r#"
+insert into tasks (id, projectId, ownerId, title, updatedAt) values (1, 1, 1, 'one', 10);
+insert into tasks (id, projectId, ownerId, title, updatedAt) values (2, 2, 1, 'two', 10);
+insert into tasks (id, projectId, ownerId, title, updatedAt) values (3, 1, 2, 'hidden', 10);
+insert into tasks (id, projectId, ownerId, title, updatedAt) values (4, 1, 1, 'four', 20);
+"#,
+    )
+    .await?;
+    let shapes = query_shapes(&db, PROJECT_TASKS_QUERY)?;
+    assert_eq!(
+        serde_json::to_value(&shapes)?,
+        json!([{
+            "table_name": "tasks",
+            "where": { "column": { "column": "projectId", "operator": "=", "value": { "arg": "projectId" } } },
+        }])
+    );
+
+    let session = SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(1))]);
+    let project_one = project_shape(&shapes, 1, &session)?;
+    let first = catchup_shape(
+        &conn,
+        &db.context,
+        &project_one,
+        &SyncCursor::new(),
+        &session,
+        1,
+    )
+    .await?;
+    assert!(first.has_more);
+    assert_eq!(first.tables["tasks"].rows[0]["id"], json!(1));
+
+    let second = catchup_shape(
+        &conn,
+        &db.context,
+        &project_one,
+        &cursor_after(&SyncCursor::new(), &first),
+        &session,
+        1,
+    )
+    .await?;
+    assert!(!second.has_more);
+    assert_eq!(
+        second.tables["tasks"]
+            .rows
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect::<Vec<_>>(),
+        vec![json!(4)]
+    );
+
+    // Each shape keeps its own cursor, so a new shape starts from the beginning.
+    let project_two = project_shape(&shapes, 2, &session)?;
+    let other = catchup_shape(
+        &conn,
+        &db.context,
+        &project_two,
+        &SyncCursor::new(),
+        &session,
+        10,
+    )
+    .await?;
+    assert_eq!(
+        other.tables["tasks"]
+            .rows
+            .iter()
+            .map(|row| row["id"].clone())
+            .collect::<Vec<_>>(),
+        vec![json!(2)]
+    );
+
+    let missing = ShapeFilter::resolve(&shapes, &serde_json::Map::new(), &session);
+    assert!(missing.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn shape_in_conditions_need_a_list() -> Result<(), Box<dyn std::error::Error>> {
+    let shapes: Vec<ShapeDefinition> = serde_json::from_value(json!([{
+        "table_name": "tasks",
+        "where": { "column": { "column": "projectId", "operator": "in", "value": { "arg": "projectIds" } } },
+    }]))?;
+    let session = SyncSession::new();
+
+    let params = json!({ "projectIds": [1, 2] });
+    let params = params.as_object().ok_or("params should be an object")?;
+    assert!(ShapeFilter::resolve(&shapes, params, &session).is_ok());
+
+    let params = json!({ "projectIds": 1 });
+    let params = params.as_object().ok_or("params should be an object")?;
+    assert!(matches!(
+        ShapeFilter::resolve(&shapes, params, &session),
+        Err(pyre::sync::SyncError::InvalidShape(_))
+    ));
+
+    Ok(())
+}
+
 #[test]
 fn table_sync_data_serializes_empty_rows() {
     let data = TableSyncData {
         rows: Vec::new(),
         permission_hash: "permission-hash".to_string(),
         last_seen_updated_at: None,
+        last_seen_key: None,
+        primary_key: Vec::new(),
+        deleted: Vec::new(),
+        last_seen_tombstone: None,
+        reset: false,
     };
 
     let serialized = serde_json::to_value(data).expect("table sync data should serialize");
@@ -141,6 +426,199 @@ fn table_sync_data_serializes_empty_rows() {
     assert_eq!(serialized["rows"], json!([]));
     assert_eq!(serialized["permission_hash"], json!("permission-hash"));
     assert_eq!(serialized["last_seen_updated_at"], serde_json::Value::Null);
+    assert!(serialized.get("primary_key").is_none());
+    assert!(serialized.get("deleted").is_none());
+    assert!(serialized.get("last_seen_tombstone").is_none());
+    assert!(serialized.get("reset").is_none());
+}
+
+fn cursor_after(cursor: &SyncCursor, page: &SyncPageResult) -> SyncCursor {
+    let mut cursor = cursor.clone();
+    for (table_name, table) in &page.tables {
+        cursor.insert(
+            table_name.clone(),
+            TableCursor {
+                last_seen_updated_at: table.last_seen_updated_at,
+                last_seen_key: table.last_seen_key.clone(),
+                permission_hash: table.permission_hash.clone(),
+                last_seen_tombstone: table.last_seen_tombstone,
+            },
+        );
+    }
+    cursor
+}
+
+#[tokio::test]
+async fn catchup_sends_keys_deleted_since_the_cursor_including_cascades(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+record Note {
+    id Int @id
+    body String
+    updatedAt Int
+    @public
+}
+
+record Comment {
+    id Int @id
+    noteId Int
+    note @link(noteId, Note.id)
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    // Pyre doesn't declare cascades itself, but hand-written ones still leave tombstones.
+    conn.execute_batch(
+        r#"
+pragma foreign_keys = on;
+drop table comments;
+create table comments (
+    id integer not null primary key,
+    noteId integer not null references notes (id) on delete cascade,
+    updatedAt integer not null
+);
+insert into notes (id, body, updatedAt) values (1, 'one', 10);
+insert into notes (id, body, updatedAt) values (2, 'two', 20);
+insert into comments (id, noteId, updatedAt) values (10, 1, 10);
+insert into comments (id, noteId, updatedAt) values (11, 2, 20);
+"#,
+    )
+    .await?;
+    let trigger = pyre::db::migrate::tombstone_trigger_sql("comments", &["id".to_string()])
+        .expect("comments has a primary key");
+    conn.execute(&trigger, ()).await?;
+    ensure_tombstones(&conn, &db.context).await?;
+
+    let session = HashMap::new();
+    let first = catchup(&conn, &db.context, &SyncCursor::new(), &session, 10).await?;
+    assert_eq!(first.tables["notes"].last_seen_tombstone, Some(0));
+    let cursor = cursor_after(&SyncCursor::new(), &first);
+
+    conn.execute("delete from notes where id = 1", ()).await?;
+
+    let second = catchup(&conn, &db.context, &cursor, &session, 10).await?;
+    let notes = &second.tables["notes"];
+    let comments = &second.tables["comments"];
+    assert!(notes.rows.is_empty());
+    assert_eq!(notes.deleted, vec![json!({ "id": 1 })]);
+    assert_eq!(notes.last_seen_updated_at, Some(20));
+    assert_eq!(comments.deleted, vec![json!({ "id": 10 })]);
+    assert!(!notes.reset && !comments.reset);
+
+    let cursor = cursor_after(&cursor, &second);
+    let third = catchup(&conn, &db.context, &cursor, &session, 10).await?;
+    assert!(third.tables.is_empty());
+
+    // Cursors from clients that predate tombstones keep the old behavior.
+    let mut legacy = cursor_after(&SyncCursor::new(), &first);
+    for table in legacy.values_mut() {
+        table.last_seen_tombstone = None;
+    }
+    let legacy_page = catchup(&conn, &db.context, &legacy, &session, 10).await?;
+    assert!(legacy_page.tables.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ensure_tombstones_reports_tables_without_a_trigger(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+record Note {
+    id Int @id
+    body String
+    @public
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    ensure_tombstones(&conn, &db.context).await?;
+
+    conn.execute("drop trigger \"_pyre_tombstone_notes\"", ())
+        .await?;
+    let error = ensure_tombstones(&conn, &db.context)
+        .await
+        .expect_err("a missing trigger should be reported");
+    assert!(matches!(
+        &error,
+        pyre::server::sync::Error::MissingTombstoneTriggers(tables) if tables == &vec!["notes".to_string()]
+    ));
+    assert!(error.to_string().contains("pyre migrate"));
+
+    // The server reports the gap rather than creating the trigger itself.
+    let mut rows = conn
+        .query(
+            "select count(*) from sqlite_master where type = 'trigger' and name = '_pyre_tombstone_notes'",
+            (),
+        )
+        .await?;
+    let row = rows.next().await?.expect("count row");
+    assert_eq!(row.get::<i64>(0)?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cursor_behind_pruned_tombstones_forces_a_full_resync(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+record Note {
+    id Int @id
+    body String
+    updatedAt Int
+    @public
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    conn.execute_batch(
+        r#"
+insert into notes (id, body, updatedAt) values (1, 'one', 10);
+insert into notes (id, body, updatedAt) values (2, 'two', 20);
+insert into notes (id, body, updatedAt) values (3, 'three', 30);
+"#,
+    )
+    .await?;
+
+    let session = HashMap::new();
+    let first = catchup(&conn, &db.context, &SyncCursor::new(), &session, 10).await?;
+    let stale = cursor_after(&SyncCursor::new(), &first);
+
+    conn.execute("delete from notes where id = 1", ()).await?;
+    let second = catchup(&conn, &db.context, &stale, &session, 10).await?;
+    let recent = cursor_after(&stale, &second);
+    conn.execute("delete from notes where id = 2", ()).await?;
+
+    assert_eq!(prune_tombstones(&conn, 3600).await?, 0);
+    conn.execute(
+        "UPDATE _pyre_tombstones SET deleted_at = deleted_at - 7200 WHERE id = 1",
+        (),
+    )
+    .await?;
+    assert_eq!(prune_tombstones(&conn, 3600).await?, 1);
+
+    let resync = catchup(&conn, &db.context, &stale, &session, 10).await?;
+    let notes = &resync.tables["notes"];
+    assert!(notes.reset);
+    assert!(notes.deleted.is_empty());
+    assert_eq!(notes.rows.len(), 1);
+    assert_eq!(notes.rows[0]["id"], json!(3));
+    assert_eq!(notes.last_seen_tombstone, Some(2));
+
+    let incremental = catchup(&conn, &db.context, &recent, &session, 10).await?;
+    let notes = &incremental.tables["notes"];
+    assert!(!notes.reset);
+    assert_eq!(notes.deleted, vec![json!({ "id": 2 })]);
+
+    Ok(())
 }
 
 #[tokio::test]
@@ -369,6 +847,7 @@ record Note {
             "updatedAt".to_string(),
         ],
         rows: vec![vec![json!(1), json!("one"), json!(10)]],
+        previous: None,
     }];
     let connected_sessions = ConnectedSessions::from([
         ("a".to_string(), SyncSession::new()),
@@ -415,6 +894,7 @@ record Note {
             "updatedAt".to_string(),
         ],
         rows: vec![vec![json!(1), json!("one"), json!(10)]],
+        previous: None,
     }];
     let connected_sessions = ConnectedSessions::from([
         ("a".to_string(), SyncSession::new()),
@@ -452,6 +932,169 @@ record Note {
     Ok(())
 }
 
+fn note_rows(ids: &[(i64, i64)]) -> Vec<AffectedRowTableGroup> {
+    vec![AffectedRowTableGroup {
+        table_name: "notes".to_string(),
+        headers: vec![
+            "id".to_string(),
+            "ownerId".to_string(),
+            "body".to_string(),
+            "updatedAt".to_string(),
+        ],
+        rows: ids
+            .iter()
+            .map(|(id, owner_id)| vec![json!(id), json!(owner_id), json!("note"), json!(10)])
+            .collect(),
+        previous: None,
+    }]
+}
+
+#[test]
+fn recent_deltas_evict_oldest_and_refuse_replays_across_missing_revisions() {
+    let mut recent = RecentDeltas::new(2, 4);
+    recent.record(5, note_rows(&[(1, 1)]));
+    recent.record(6, note_rows(&[(2, 1)]));
+    recent.record(7, note_rows(&[(3, 1)]));
+
+    assert_eq!(recent.covered_through(), 5);
+    assert_eq!(recent.latest_revision(), 7);
+    assert!(recent.since(4).is_none());
+    let revisions: Vec<i64> = recent.since(5).unwrap().map(|(rev, _)| *rev).collect();
+    assert_eq!(revisions, vec![6, 7]);
+    assert_eq!(recent.since(7).unwrap().count(), 0);
+    assert!(recent.since(8).is_none());
+
+    // Revision 8 was allocated elsewhere and has not been read yet.
+    let mut recent = RecentDeltas::new(10, 7);
+    recent.record(9, note_rows(&[(4, 1)]));
+    assert!(recent.since(7).is_none());
+    assert_eq!(recent.since(8).unwrap().count(), 1);
+
+    recent.record(8, note_rows(&[(5, 1)]));
+    let revisions: Vec<i64> = recent.since(7).unwrap().map(|(rev, _)| *rev).collect();
+    assert_eq!(revisions, vec![8, 9]);
+}
+
+#[tokio::test]
+async fn replay_deltas_filters_missed_rows_by_session_permissions(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    body String
+    updatedAt Int
+    @allow(query) { ownerId == Session.userId }
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    let mut recent = RecentDeltas::open(&conn, 10).await?;
+    recent.record(1, note_rows(&[(1, 1), (2, 2)]));
+    recent.record(2, note_rows(&[(3, 2)]));
+    recent.record(3, note_rows(&[(4, 1)]));
+    let server = SyncServer::new(&db.context);
+    let user_1 = SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(1))]);
+
+    let missed = server.replay_deltas(&recent, 1, &user_1, "main")?;
+    assert_eq!(missed.len(), 1);
+    assert_eq!(missed[0].type_, "delta");
+    assert_eq!(missed[0].server_revision, Some(3));
+    assert_eq!(missed[0].data[0].rows[0][0], json!(4));
+
+    let all = server.replay_deltas(&recent, 0, &user_1, "main")?;
+    assert_eq!(
+        all.iter()
+            .map(|message| message.server_revision)
+            .collect::<Vec<_>>(),
+        vec![Some(1), Some(3)]
+    );
+    assert_eq!(all[0].data[0].rows.len(), 1);
+
+    let mut evicted = RecentDeltas::new(1, 0);
+    evicted.record(1, note_rows(&[(1, 1)]));
+    evicted.record(2, note_rows(&[(2, 1)]));
+    let gap = server.replay_deltas(&evicted, 0, &user_1, "main")?;
+    assert_eq!(gap.len(), 1);
+    assert_eq!(gap[0].type_, "syncRequired");
+    assert_eq!(gap[0].server_revision, Some(2));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn change_log_records_revisions_for_other_processes() -> Result<(), Box<dyn std::error::Error>>
+{
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    body String
+    updatedAt Int
+    @allow(query) { ownerId == Session.userId }
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    let writer = SyncServer::new(&db.context).with_change_log("instance-a");
+
+    let mut first = query_result(note_rows(&[(1, 1)]));
+    let mut second = query_result(note_rows(&[(2, 2)]));
+    writer
+        .calculate_deltas(&conn, &mut first, &ConnectedSessions::new(), "main", None)
+        .await?;
+    writer
+        .calculate_deltas(&conn, &mut second, &ConnectedSessions::new(), "main", None)
+        .await?;
+    assert_eq!(second.server_revision(), Some(2));
+
+    let changes = read_changes(&conn, 0, 10).await?;
+    assert_eq!(
+        changes
+            .iter()
+            .map(|change| (change.server_revision, change.instance_id.as_str()))
+            .collect::<Vec<_>>(),
+        vec![(1, "instance-a"), (2, "instance-a")]
+    );
+    assert_eq!(read_changes(&conn, 1, 10).await?.len(), 1);
+
+    let user_2 = ConnectedSessions::from([(
+        "user-2".to_string(),
+        SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(2))]),
+    )]);
+    let reader = SyncServer::new(&db.context);
+    assert!(reader
+        .change_deltas(&changes[0], &user_2, "main")?
+        .is_empty());
+    let messages = reader.change_deltas(&changes[1], &user_2, "main")?;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].message.server_revision, Some(2));
+    assert_eq!(messages[0].message.data[0].rows[0][0], json!(2));
+
+    assert_eq!(prune_changes(&conn, 3600).await?, 0);
+    conn.execute(
+        "UPDATE _pyre_changes SET created_at = created_at - 7200",
+        (),
+    )
+    .await?;
+    assert_eq!(prune_changes(&conn, 3600).await?, 2);
+    assert!(read_changes(&conn, 0, 10).await?.is_empty());
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn calculate_deltas_filters_rows_by_session_permissions(
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -484,6 +1127,7 @@ record Note {
             vec![json!(1), json!(1), json!("one"), json!(10)],
             vec![json!(2), json!(2), json!("two"), json!(20)],
         ],
+        previous: None,
     }];
     let connected_sessions = ConnectedSessions::from([
         (
@@ -554,6 +1198,7 @@ record Map {
             json!("Png"),
             json!(10),
         ]],
+        previous: None,
     }];
     let connected_sessions = ConnectedSessions::from([("a".to_string(), SyncSession::new())]);
 
@@ -607,6 +1252,7 @@ record Note {
             "updatedAt".to_string(),
         ],
         rows: vec![vec![json!(1), json!("one"), json!(10)]],
+        previous: None,
     }];
     let connected_sessions = ConnectedSessions::from([("a".to_string(), SyncSession::new())]);
 
@@ -710,6 +1356,7 @@ record Note {
         rows: (0..=MAX_LIVE_SYNC_DELTA_ROWS)
             .map(|index| vec![json!(index), json!("one"), json!(10)])
             .collect(),
+        previous: None,
     }];
     let connected_sessions = ConnectedSessions::from([("a".to_string(), SyncSession::new())]);
 
@@ -752,6 +1399,7 @@ record Note {
             json!("x".repeat(MAX_LIVE_SYNC_DELTA_PAYLOAD_BYTES)),
             json!(10),
         ]],
+        previous: None,
     }];
     let server = SyncServer::new(&db.context);
     let connected_sessions = ConnectedSessions::from([("a".to_string(), SyncSession::new())]);
@@ -792,6 +1440,7 @@ record Note {
             "updatedAt".to_string(),
         ],
         rows: vec![vec![json!(1), json!("one"), json!(10)]],
+        previous: None,
     }];
     let connected_sessions = (0..=MAX_LIVE_SYNC_FANOUT_RECIPIENTS)
         .map(|index| (format!("s{}", index), SyncSession::new()))
@@ -876,6 +1525,160 @@ update UpdateNote {
     Ok(())
 }
 
+#[tokio::test]
+async fn generated_update_sends_removals_to_sessions_that_lose_sight_of_a_row(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(
+        r#"
+session {
+    userId Int
+}
+
+record Note {
+    id Int @id
+    ownerId Int
+    body String
+    updatedAt Int
+    @allow(query) { ownerId == Session.userId }
+    @allow(insert, update) { ownerId == 1 }
+}
+"#,
+    )
+    .await?;
+    let conn = db.db.connect()?;
+    conn.execute_batch(
+        "insert into notes (id, ownerId, body, updatedAt) values (1, 1, 'mine', 10), (2, 1, 'kept', 10);",
+    )
+    .await?;
+    let update_query = r#"
+update HandOverNote {
+    note {
+        @where { id == 1 }
+        ownerId = 2
+        updatedAt = 20
+        id
+    }
+}
+"#;
+    let result_sets = db.execute_query(update_query).await?;
+    let affected_rows = extract_affected_rows(result_sets).await?;
+    let connected_sessions = ConnectedSessions::from([
+        (
+            "user-1".to_string(),
+            SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(1))]),
+        ),
+        (
+            "user-2".to_string(),
+            SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(2))]),
+        ),
+        (
+            "user-3".to_string(),
+            SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(3))]),
+        ),
+    ]);
+
+    let mut result = query_result(affected_rows.clone());
+    let messages = SyncServer::new(&db.context)
+        .calculate_deltas(&conn, &mut result, &connected_sessions, "main", None)
+        .await?;
+
+    assert_eq!(affected_rows[0].rows.len(), 1);
+    assert_eq!(messages.len(), 2);
+
+    assert_eq!(messages[0].session_id, "user-1");
+    assert!(messages[0].message.data.is_empty());
+    assert_eq!(messages[0].message.removed.len(), 1);
+    assert_eq!(messages[0].message.removed[0].table_name, "notes");
+    assert_eq!(
+        messages[0].message.removed[0].keys,
+        vec![json!({ "id": 1 })]
+    );
+
+    assert_eq!(messages[1].session_id, "user-2");
+    assert!(messages[1].message.removed.is_empty());
+    assert_eq!(messages[1].message.data[0].rows.len(), 1);
+    assert!(messages[1].message.data[0].previous.is_none());
+
+    let serialized = serde_json::to_value(&messages[0].message)?;
+    assert_eq!(
+        serialized["removed"],
+        json!([{ "table_name": "notes", "keys": [{ "id": 1 }] }])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn calculate_deltas_routes_rows_to_the_shapes_that_cover_them(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = TestDatabase::new(TASKS_SCHEMA).await?;
+    let conn = db.db.connect()?;
+    conn.execute_batch(
+        "insert into tasks (id, projectId, ownerId, title, updatedAt) values (1, 1, 1, 'one', 10);",
+    )
+    .await?;
+    let update_query = r#"
+update MoveTask {
+    task {
+        @where { id == 1 }
+        projectId = 2
+        updatedAt = 20
+        id
+    }
+}
+"#;
+    let result_sets = db.execute_query(update_query).await?;
+    let affected_rows = extract_affected_rows(result_sets).await?;
+
+    let session = SyncSession::from([("userId".to_string(), pyre::sync::SessionValue::Integer(1))]);
+    let connected_sessions = ConnectedSessions::from([
+        ("project-1".to_string(), session.clone()),
+        ("project-2".to_string(), session.clone()),
+        ("project-3".to_string(), session.clone()),
+        ("everything".to_string(), session.clone()),
+    ]);
+    let shapes = query_shapes(&db, PROJECT_TASKS_QUERY)?;
+    let session_shapes = SessionShapes::from([
+        (
+            "project-1".to_string(),
+            project_shape(&shapes, 1, &session)?,
+        ),
+        (
+            "project-2".to_string(),
+            project_shape(&shapes, 2, &session)?,
+        ),
+        (
+            "project-3".to_string(),
+            project_shape(&shapes, 3, &session)?,
+        ),
+    ]);
+
+    let mut result = query_result(affected_rows);
+    let messages = SyncServer::new(&db.context)
+        .with_session_shapes(session_shapes)
+        .calculate_deltas(&conn, &mut result, &connected_sessions, "main", None)
+        .await?;
+    let messages = messages
+        .into_iter()
+        .map(|message| (message.session_id, message.message))
+        .collect::<HashMap<_, _>>();
+
+    assert_eq!(messages.len(), 3);
+    assert!(!messages.contains_key("project-3"));
+
+    let left = &messages["project-1"];
+    assert!(left.data.is_empty());
+    assert_eq!(left.removed[0].keys, vec![json!({ "id": 1 })]);
+
+    for session_id in ["project-2", "everything"] {
+        let message = &messages[session_id];
+        assert!(message.removed.is_empty());
+        assert_eq!(message.data[0].rows.len(), 1);
+    }
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn generated_delete_affected_rows_feed_permission_filtered_native_deltas(
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -975,7 +1778,9 @@ insert into notes (id, ownerId, body, updatedAt) values (2, 2, 'two', 20);
         "notes".to_string(),
         TableCursor {
             last_seen_updated_at: first_notes.last_seen_updated_at,
+            last_seen_key: None,
             permission_hash: first_notes.permission_hash.clone(),
+            last_seen_tombstone: first_notes.last_seen_tombstone,
         },
     )]);
     let session_two =
@@ -1235,6 +2040,7 @@ record Note {
         table_name: "notes".to_string(),
         headers: vec!["id".to_string(), "body".to_string()],
         rows: vec![vec![json!(1), json!("one")]],
+        previous: None,
     }]);
     let message_json = serde_json::to_value(&message)?;
 
@@ -1388,3 +2194,54 @@ async fn pyre_session_rejects_missing_or_invalid_fields() -> Result<(), Box<dyn
 
     Ok(())
 }
+
+#[test]
+fn database_resolver_routes_templates_directories_and_maps(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempfile::TempDir::new()?;
+    std::fs::write(temp_dir.path().join("acme.db"), "")?;
+    let directory = temp_dir.path().to_string_lossy().to_string();
+
+    let template = DatabaseResolver::from_database_arg(
+        &format!("{}/{{databaseId}}.db", directory),
+        "default",
+    )?;
+    assert_eq!(template.default_database_id(), None);
+    assert_eq!(template.resolve("acme")?, format!("{}/acme.db", directory));
+    assert!(matches!(
+        template.resolve("globex"),
+        Err(DatabaseIdError::Unknown { .. })
+    ));
+    assert!(matches!(
+        template.resolve("../acme"),
+        Err(DatabaseIdError::Invalid { .. })
+    ));
+    assert!(matches!(
+        template.database_id_for(None),
+        Err(DatabaseIdError::Missing { .. })
+    ));
+
+    let from_directory = DatabaseResolver::from_database_arg(&directory, "default")?;
+    assert!(matches!(from_directory, DatabaseResolver::Directory(_)));
+    assert!(from_directory.resolve("acme")?.ends_with("acme.db"));
+
+    let fixed = DatabaseResolver::from_database_arg("app.db", "main")?;
+    assert_eq!(fixed.database_id_for(None)?, "main");
+    assert_eq!(fixed.resolve("main")?, "app.db");
+    assert!(matches!(
+        fixed.resolve("other"),
+        Err(DatabaseIdError::Mismatch { .. })
+    ));
+
+    let map = DatabaseResolver::Map(HashMap::from([(
+        "tenant:acme".to_string(),
+        "libsql://acme.example.io".to_string(),
+    )]));
+    assert_eq!(map.resolve("tenant:acme")?, "libsql://acme.example.io");
+    assert!(matches!(
+        map.resolve("tenant:globex"),
+        Err(DatabaseIdError::Unknown { .. })
+    ));
+
+    Ok(())
+}
@@ -139,6 +139,556 @@ record Note {
     assert_eq!(arg_names, vec!["body"]);
 }
 
+const COMPOSITE_KEY_SCHEMA: &str = r#"
+record Organization {
+    @public
+    id Id.Int @id
+    name String
+
+    memberships @link(Membership.orgId)
+}
+
+record User {
+    @public
+    id Id.Int @id
+    name String
+}
+
+record Membership {
+    @public
+    @id(orgId, userId)
+
+    orgId Organization.id
+    userId User.id
+    role String
+
+    organization @link(orgId, Organization.id)
+    grants       @link(orgId, userId, Grant.orgId, Grant.userId)
+}
+
+record Grant {
+    @public
+    id Id.Int @id
+    orgId Organization.id
+    userId User.id
+    scope String
+
+    membership @link(orgId, userId, Membership.orgId, Membership.userId)
+}
+"#;
+
+#[test]
+fn composite_primary_keys_typecheck() {
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", COMPOSITE_KEY_SCHEMA, &mut schema).expect("Failed to parse schema");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let context = typecheck::check_schema(&database).expect("Schema should typecheck");
+
+    let membership = context.tables.get("membership").expect("membership table");
+    assert_eq!(
+        ast::get_primary_key_field_names(&membership.record.fields),
+        vec!["orgId".to_string(), "userId".to_string()]
+    );
+    assert_eq!(
+        ast::get_primary_id_field_name(&membership.record.fields),
+        None
+    );
+
+    // A link that targets every key column identifies exactly one membership.
+    let grant = context.tables.get("grant").expect("grant table");
+    let link = ast::collect_links(&grant.record.fields)
+        .into_iter()
+        .find(|link| link.link_name == "membership")
+        .expect("membership link");
+    assert_eq!(link.local_ids, vec!["orgId", "userId"]);
+    assert_eq!(link.foreign.fields, vec!["orgId", "userId"]);
+    assert!(!ast::ids_are_primary_key(
+        &link.local_ids,
+        &grant.record.fields
+    ));
+    assert!(ast::linked_to_unique_field_with_record(
+        &link,
+        &membership.record
+    ));
+
+    let mut files: Vec<pyre::filesystem::GeneratedFile<String>> = Vec::new();
+    pyre::generate::typescript::core::generate_schema(
+        &context,
+        &database,
+        std::path::Path::new("core"),
+        &mut files,
+    );
+    let schema_ts = &files
+        .iter()
+        .find(|file| file.path.ends_with("schema.ts"))
+        .expect("schema.ts is generated")
+        .contents;
+
+    assert!(schema_ts.contains(
+        r#"        "membership": {
+          type: "many-to-one",
+          from: "orgId",
+          columns: [{ from: "orgId", to: "orgId" }, { from: "userId", to: "userId" }],
+          to: {
+            table: "memberships",
+            column: "orgId"
+          }
+        }"#
+    ));
+    assert!(schema_ts.contains(
+        r#"        "grants": {
+          type: "one-to-many",
+          from: "orgId",
+          columns: [{ from: "orgId", to: "orgId" }, { from: "userId", to: "userId" }],
+          to: {
+            table: "grants",
+            column: "orgId"
+          }
+        }"#
+    ));
+}
+
+#[test]
+fn composite_links_need_a_foreign_column_per_local_column() {
+    let schema_source = r#"
+record Membership {
+    @public
+    @id(orgId, userId)
+    orgId Int
+    userId Int
+}
+
+record Grant {
+    @public
+    id Int @id
+    orgId Int
+    userId Int
+
+    membership @link(orgId, userId, Membership.orgId)
+}
+"#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("Failed to parse schema");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+
+    let errors = typecheck::check_schema(&database).expect_err("link should be rejected");
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::LinkColumnCountMismatch { link_name, local_ids, foreign_fields }
+            if link_name == "membership" && local_ids.len() == 2 && foreign_fields.len() == 1
+    )));
+}
+
+#[test]
+fn generated_update_and_delete_use_every_composite_key_column() {
+    let context = checked_context(COMPOSITE_KEY_SCHEMA);
+    let mut query_list = ast::QueryList { queries: vec![] };
+
+    pyre::generated_queries::append_generated_crud_queries(&mut query_list, &context);
+
+    let arg_names = |name: &str| {
+        query_list
+            .queries
+            .iter()
+            .find_map(|query| match query {
+                ast::QueryDef::Query(query) if query.name == name => Some(
+                    query
+                        .args
+                        .iter()
+                        .map(|arg| arg.name.clone())
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+            .expect("generated query exists")
+    };
+
+    assert!(arg_names("MembershipCreate").starts_with(&["orgId".to_string(), "userId".to_string()]));
+    assert!(arg_names("MembershipUpdate").starts_with(&["orgId".to_string(), "userId".to_string()]));
+    assert_eq!(
+        arg_names("MembershipUpdate")
+            .iter()
+            .filter(|name| name.as_str() == "orgId" || name.as_str() == "userId")
+            .count(),
+        2
+    );
+    assert_eq!(arg_names("MembershipDelete"), vec!["orgId", "userId"]);
+    typecheck::check_queries(&query_list, &context).expect("generated CRUD typechecks");
+}
+
+#[test]
+fn composite_primary_key_rejects_unknown_and_repeated_fields() {
+    let schema_source = r#"
+record Membership {
+    @public
+    @id(orgId, orgId, teamId)
+    orgId Int
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let errors = typecheck::check_schema(&database).expect_err("schema should fail typecheck");
+
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::InvalidRecordIndexField { directive, field, .. }
+            if directive == "@id" && field == "teamId"
+    )));
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::DuplicateRecordIndexField { directive, field, .. }
+            if directive == "@id" && field == "orgId"
+    )));
+}
+
+#[test]
+fn composite_primary_key_cannot_be_combined_with_column_id() {
+    let schema_source = r#"
+record Membership {
+    @public
+    @id(orgId, userId)
+    id Int @id
+    orgId Int
+    userId Int
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let errors = typecheck::check_schema(&database).expect_err("schema should fail typecheck");
+
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::MultiplePrimaryKeys { record, field }
+            if record == "Membership" && field == "id"
+    )));
+}
+
+#[test]
+fn composite_primary_key_after_column_id_reports_the_directive() {
+    let schema_source = r#"
+record Membership {
+    @public
+    id Int @id
+    orgId Int
+    userId Int
+    @id(orgId, userId)
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let errors = typecheck::check_schema(&database).expect_err("schema should fail typecheck");
+
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::MultiplePrimaryKeys { field, .. } if field == "@id(orgId, userId)"
+    )));
+}
+
+#[test]
+fn without_rowid_rejects_auto_increment_keys() {
+    let schema_source = r#"
+record Note {
+    @public
+    @withoutRowid
+    id Id.Int @id
+    body String
+}
+    "#;
+
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let errors = typecheck::check_schema(&database).expect_err("schema should fail typecheck");
+
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::WithoutRowidAutoIncrementKey { field, .. } if field == "id"
+    )));
+}
+
+fn computed_column_errors(schema_source: &str) -> Vec<String> {
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let errors = typecheck::check_schema(&database).expect_err("schema should fail typecheck");
+    errors
+        .iter()
+        .filter_map(|error| match &error.error_type {
+            ErrorType::InvalidComputedColumn { message, .. } => Some(message.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn computed_columns_are_checked_against_the_record() {
+    let unknown_field = computed_column_errors(
+        r#"
+record LineItem {
+    @public
+    id Int @id
+    quantity Int
+    totalCents Int @computed(quantity * unitCents)
+}
+    "#,
+    );
+    assert!(unknown_field
+        .iter()
+        .any(|message| message.contains("unitCents")));
+
+    let mismatch = computed_column_errors(
+        r#"
+record LineItem {
+    @public
+    id Int @id
+    label String
+    total Int @computed(label ++ "!")
+}
+    "#,
+    );
+    assert_eq!(mismatch.len(), 1);
+
+    let non_deterministic = computed_column_errors(
+        r#"
+record LineItem {
+    @public
+    id Int @id
+    stamp DateTime @computed(now())
+}
+    "#,
+    );
+    assert_eq!(non_deterministic.len(), 1);
+}
+
+#[test]
+fn computed_columns_cannot_be_set_in_mutations() {
+    let context = checked_context(
+        r#"
+record LineItem {
+    @public
+    id Int @id
+    quantity Int
+    unitCents Int
+    totalCents Int @computed(quantity * unitCents)
+}
+    "#,
+    );
+
+    let query_list = parser::parse_query(
+        "query.pyre",
+        r#"
+insert CreateLineItem($quantity: Int, $unitCents: Int, $totalCents: Int) {
+    lineItem {
+        quantity = $quantity
+        unitCents = $unitCents
+        totalCents = $totalCents
+    }
+}
+
+update SetTotal($id: Int, $totalCents: Int) {
+    lineItem {
+        @where { id == $id }
+        totalCents = $totalCents
+    }
+}
+    "#,
+    )
+    .expect("query parses");
+
+    let errors = match typecheck::check_queries(&query_list, &context) {
+        Ok(_) => panic!("computed writes should fail"),
+        Err(errors) => errors,
+    };
+    let managed_fields = errors
+        .iter()
+        .filter(|error| {
+            matches!(
+                &error.error_type,
+                ErrorType::ManagedColumnCannotBeSet { field, .. } if field == "totalCents"
+            )
+        })
+        .count();
+    assert_eq!(managed_fields, 2);
+}
+
+#[test]
+fn computed_columns_are_not_required_on_insert() {
+    let context = checked_context(
+        r#"
+record LineItem {
+    @public
+    id Int @id
+    quantity Int
+    unitCents Int
+    totalCents Int @computed(quantity * unitCents)
+}
+    "#,
+    );
+
+    let query_list = parser::parse_query(
+        "query.pyre",
+        r#"
+insert CreateLineItem($quantity: Int, $unitCents: Int) {
+    lineItem {
+        quantity = $quantity
+        unitCents = $unitCents
+    }
+}
+    "#,
+    )
+    .expect("query parses");
+
+    typecheck::check_queries(&query_list, &context)
+        .expect("computed columns should be left out of inserts");
+}
+
+fn version_field_errors(schema_source: &str) -> Vec<String> {
+    let mut schema = ast::Schema::default();
+    parser::run("schema.pyre", schema_source, &mut schema).expect("schema should parse");
+    let database = ast::Database {
+        schemas: vec![schema],
+    };
+    let errors = typecheck::check_schema(&database).expect_err("schema should fail typecheck");
+    errors
+        .iter()
+        .filter_map(|error| match &error.error_type {
+            ErrorType::InvalidVersionField { message, .. } => Some(message.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn version_fields_must_be_a_single_int() {
+    let nullable = version_field_errors(
+        r#"
+record Task {
+    @public
+    id Int @id
+    version Int? @version
+}
+    "#,
+    );
+    assert_eq!(nullable.len(), 1);
+
+    let repeated = version_field_errors(
+        r#"
+record Task {
+    @public
+    id Int @id
+    version Int @version
+    revision Int @version
+}
+    "#,
+    );
+    assert_eq!(repeated.len(), 1);
+    assert!(repeated[0].contains("version"));
+}
+
+#[test]
+fn if_version_checks_the_updated_record_version() {
+    let context = checked_context(
+        r#"
+record Task {
+    @public
+    id Int @id
+    title String
+    version Int @version
+}
+
+record Tag {
+    @public
+    id Int @id
+    name String
+}
+    "#,
+    );
+
+    let query_list = parser::parse_query(
+        "query.pyre",
+        r#"
+insert CreateTask($title: String) {
+    task {
+        title = $title
+    }
+}
+
+update RenameTask($id: Int, $version: Int, $title: String) {
+    task {
+        @where { id == $id }
+        @ifVersion($version)
+        title = $title
+    }
+}
+    "#,
+    )
+    .expect("query parses");
+    typecheck::check_queries(&query_list, &context)
+        .expect("version fields are left out of inserts and checked in updates");
+
+    let query_list = parser::parse_query(
+        "query.pyre",
+        r#"
+update RenameTag($id: Int, $version: Int, $name: String) {
+    tag {
+        @where { id == $id }
+        @ifVersion($version)
+        name = $name
+    }
+}
+
+query GetTask($version: Int) {
+    task {
+        @ifVersion($version)
+        id
+    }
+}
+
+update SetVersion($id: Int, $version: Int) {
+    task {
+        @where { id == $id }
+        version = $version
+    }
+}
+    "#,
+    )
+    .expect("query parses");
+    let errors = match typecheck::check_queries(&query_list, &context) {
+        Ok(_) => panic!("misplaced version checks should fail"),
+        Err(errors) => errors,
+    };
+    let version_checks = errors
+        .iter()
+        .filter(|error| matches!(&error.error_type, ErrorType::InvalidVersionCheck { .. }))
+        .count();
+    assert_eq!(version_checks, 2);
+    assert!(errors.iter().any(|error| matches!(
+        &error.error_type,
+        ErrorType::ManagedColumnCannotBeSet { field, .. } if field == "version"
+    )));
+}
+
 #[test]
 fn uuid_primary_ids_remain_settable_on_insert() {
     let context = checked_context(
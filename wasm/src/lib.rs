@@ -147,6 +147,17 @@ pub fn reshape_sync_table_groups(table_groups: JsValue) -> JsValue {
     }
 }
 
+#[wasm_bindgen]
+pub fn decode_sync_page(bytes: &[u8]) -> JsValue {
+    match sync_shape::decode_sync_page_wasm(bytes) {
+        Ok(page) => {
+            let json_str = serde_json::to_string(&page).unwrap();
+            js_sys::JSON::parse(&json_str).unwrap()
+        }
+        Err(e) => serde_wasm_bindgen::to_value(&("Error: ".to_string() + &e)).unwrap(),
+    }
+}
+
 #[wasm_bindgen]
 pub fn seed_database(schema_source: String, options: JsValue) -> JsValue {
     let options: Option<seed::SeedOptions> = if options.is_undefined() || options.is_null() {
@@ -15,7 +15,11 @@ pub struct SyncCursorWasm {
 #[derive(Serialize, Deserialize)]
 pub struct TableCursorWasm {
     pub last_seen_updated_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_key: Option<serde_json::Map<String, serde_json::Value>>,
     pub permission_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen_tombstone: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -29,6 +33,14 @@ pub struct TableSyncDataWasm {
     pub rows: Vec<serde_json::Value>,
     pub permission_hash: String,
     pub last_seen_updated_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen_key: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen_tombstone: Option<i64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reset: bool,
 }
 
 pub type SessionWasm = HashMap<String, SessionValueWasm>;
@@ -71,7 +83,9 @@ fn convert_cursor_wasm_to_rust(cursor: &SyncCursorWasm) -> sync::SyncCursor {
                 k.clone(),
                 sync::TableCursor {
                     last_seen_updated_at: v.last_seen_updated_at,
+                    last_seen_key: v.last_seen_key.clone(),
                     permission_hash: v.permission_hash.clone(),
+                    last_seen_tombstone: v.last_seen_tombstone,
                 },
             )
         })
@@ -90,6 +104,10 @@ fn convert_result_rust_to_wasm(result: sync::SyncPageResult) -> SyncPageResultWa
                         rows: v.rows,
                         permission_hash: v.permission_hash,
                         last_seen_updated_at: v.last_seen_updated_at,
+                        last_seen_key: v.last_seen_key,
+                        deleted: v.deleted,
+                        last_seen_tombstone: v.last_seen_tombstone,
+                        reset: v.reset,
                     },
                 )
             })
@@ -187,6 +205,8 @@ pub struct TableSyncSqlWasm {
     pub params: Vec<Vec<SessionValueWasm>>,
     pub headers: Vec<String>,
     pub json_columns: Vec<String>,
+    /// Primary key columns, for building each table's `last_seen_key`.
+    pub primary_key: Vec<String>,
 }
 
 impl From<sync::SessionValue> for SessionValueWasm {
@@ -229,6 +249,7 @@ pub fn get_sync_status_sql_wasm(
             sync::SyncError::PermissionError(msg) => "Permission error: ".to_string() + &msg,
             sync::SyncError::InvalidPageSize => "Invalid page size".to_string(),
             sync::SyncError::InvalidSyncCursor(msg) => "Invalid sync cursor: ".to_string() + &msg,
+            sync::SyncError::InvalidShape(msg) => "Invalid sync shape: ".to_string() + &msg,
         },
     )?;
 
@@ -279,6 +300,7 @@ pub fn get_sync_sql_wasm(
             sync::SyncError::PermissionError(msg) => "Permission error: ".to_string() + &msg,
             sync::SyncError::InvalidPageSize => "Invalid page size".to_string(),
             sync::SyncError::InvalidSyncCursor(msg) => "Invalid sync cursor: ".to_string() + &msg,
+            sync::SyncError::InvalidShape(msg) => "Invalid sync shape: ".to_string() + &msg,
         })?;
 
     // Generate sync SQL
@@ -295,6 +317,7 @@ pub fn get_sync_sql_wasm(
         sync::SyncError::PermissionError(msg) => "Permission error: ".to_string() + &msg,
         sync::SyncError::InvalidPageSize => "Invalid page size".to_string(),
         sync::SyncError::InvalidSyncCursor(msg) => "Invalid sync cursor: ".to_string() + &msg,
+        sync::SyncError::InvalidShape(msg) => "Invalid sync shape: ".to_string() + &msg,
     })?;
 
     Ok(SyncSqlResultWasm {
@@ -312,6 +335,7 @@ pub fn get_sync_sql_wasm(
                     .collect(),
                 headers: t.headers,
                 json_columns: t.json_columns,
+                primary_key: t.primary_key,
             })
             .collect(),
     })
@@ -61,6 +61,7 @@ pub fn convert_table_group_wasm_to_rust(
         table_name: group.table_name.clone(),
         headers: group.headers.clone(),
         rows: group.rows.clone(),
+        previous: None,
     }
 }
 
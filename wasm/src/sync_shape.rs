@@ -3,6 +3,7 @@ use crate::sync_deltas::{
     convert_table_group_rust_to_wasm, convert_table_group_wasm_to_rust, AffectedRowTableGroupWasm,
 };
 use pyre::db::introspect;
+use pyre::sync_encoding::{self, SyncFormat};
 use pyre::sync_shape;
 use serde_wasm_bindgen;
 use wasm_bindgen::prelude::*;
@@ -15,9 +16,19 @@ pub fn reshape_sync_table_groups_wasm(
         None => return Err("No schema found".to_string()),
     };
 
+    // Table groups arrive as plain objects, or as MessagePack bytes (optionally gzip
+    // compressed) from a server asked for the compact encoding.
     let table_groups_wasm: Vec<AffectedRowTableGroupWasm> =
-        serde_wasm_bindgen::from_value(table_groups)
-            .map_err(|_e| "Failed to parse sync table groups".to_string())?;
+        if table_groups.is_instance_of::<js_sys::Uint8Array>() {
+            sync_encoding::decode(
+                &js_sys::Uint8Array::new(&table_groups).to_vec(),
+                SyncFormat::MessagePack,
+            )
+            .map_err(|e| format!("Failed to decode sync table groups: {}", e))?
+        } else {
+            serde_wasm_bindgen::from_value(table_groups)
+                .map_err(|_e| "Failed to parse sync table groups".to_string())?
+        };
 
     match &introspection.schema {
         introspect::SchemaResult::Success { context, .. } => Ok(sync_shape::reshape_table_groups(
@@ -33,3 +44,10 @@ pub fn reshape_sync_table_groups_wasm(
         _ => Err("No schema found".to_string()),
     }
 }
+
+/// Decode a MessagePack catchup page, in the compact layout, into a page with one
+/// object per row.
+pub fn decode_sync_page_wasm(bytes: &[u8]) -> Result<pyre::sync::SyncPageResult, String> {
+    sync_encoding::decode_sync_page(bytes, SyncFormat::MessagePack)
+        .map_err(|e| format!("Failed to decode sync page: {}", e))
+}